@@ -0,0 +1,10 @@
+pub mod store;
+
+// The backup model, for crates that only want to read one.
+pub use store::{
+    backup::{
+        Account, AssetUri, Backup, Component, Contact, Error, Group, LoadIssue, LoadOptions,
+        Manifest, Message, MessageContent, RawAsset, Record, RecordType, Slot, WellKnownAssetKind,
+    },
+    RcStr,
+};
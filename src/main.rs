@@ -1,52 +1,2300 @@
-use std::{fs, io::Write, path::PathBuf, str::FromStr, rc::Rc};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
-use bson::Bson;
-use serde::de::DeserializeOwned;
-use store::backup::SZBson;
+use clap::{Args, Parser, Subcommand};
+use neos_full_statbox::store::{
+    self,
+    aliases::Aliases,
+    backup::{
+        AssetUri, Backup, LoadOptions, Manifest, Record, RecordType, SZBson, UnknownDirPolicy,
+        WellKnownAssetKind,
+    },
+    config::Config,
+    conflicts::Resolution,
+    convert::{Converters, ExternalConverter},
+    debug_dump::DebugBundle,
+    edit::{self, RecordChange},
+    export::{
+        asset_links, bundle, calendar,
+        contact_cards::{self, CardFormat},
+        dashboard,
+        inventory_structure::{self, StructureFormat},
+        inventory_tree, media,
+        messages::{self, TranscriptFormat},
+        openmetrics, queue,
+        slot_graph::{GraphFormat, SlotGraph},
+        text_corpus, voice,
+    },
+    features,
+    filter::RecordFilter,
+    handle::RecordHandle,
+    hash,
+    identity::IdentityMap,
+    ignore::IgnoreRules,
+    inventory::Inventory,
+    journal::{self, Journal},
+    labels::Labels,
+    limits::ManifestLimits,
+    locale::{self, Catalog},
+    lock, memory, merge,
+    metrics::MetricDef,
+    phash::phash,
+    recovery,
+    redact::{Redact, RedactionProfile, Redactor},
+    render::{self, Zone},
+    report::{
+        self, broken_refs, compression, contacts, copies, devices, disabled, groups, headless,
+        link_cycles, population, relays,
+        screenshots::{self, PeopleIndex},
+        shapes, similar_images, stranded, strings, threads, ReportFormat,
+    },
+    sample::Sample,
+    schema::{self, SCHEMA_VERSION},
+    stats::Stats,
+    text, timings,
+    validate::{self, ValidationProfile},
+    verify::VerifyReport,
+    warnings::Warnings,
+    watch::{Change, Fingerprint},
+    RcStr,
+};
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::store::backup::{AssetUri, Backup, Manifest, WellKnownAssetKind};
+#[derive(Parser)]
+#[command(about = "A storage visualizer for an offline dump of a neos account")]
+struct Cli {
+    /// Config file, defaults to neos-full-statbox/config.toml in the user config directory.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Timezone reports render times in: utc, local or an IANA name like Europe/Berlin.
+    #[arg(long, global = true)]
+    timezone: Option<Zone>,
+    /// Extra asset directory to search when the backup lacks an asset, may be repeated.
+    #[arg(long = "asset-root", global = true)]
+    asset_roots: Vec<PathBuf>,
+    /// Contact labels file, defaults to neos-full-statbox/labels.toml in the user config directory.
+    #[arg(long, global = true)]
+    labels: Option<PathBuf>,
+    /// Only load this account folder (like U-alice), may be repeated.
+    #[arg(long = "include-account", value_name = "ACCOUNT", global = true)]
+    include_accounts: Vec<String>,
+    /// Skip this account folder when loading, may be repeated.
+    #[arg(long = "exclude-account", value_name = "ACCOUNT", global = true)]
+    exclude_accounts: Vec<String>,
+    /// Skip files and folders matching this glob while loading, like "*.bak", may be
+    /// repeated. Added to the config's and the built-in ones for OS and sync tool litter.
+    #[arg(long, value_name = "GLOB", global = true)]
+    ignore: Vec<String>,
+    /// Don't skip Thumbs.db, .DS_Store, sync conflict copies and the like by default.
+    #[arg(long, global = true)]
+    no_default_ignores: bool,
+    /// Don't load messages.
+    #[arg(long, global = true)]
+    skip_messages: bool,
+    /// Don't load records.
+    #[arg(long, global = true)]
+    skip_records: bool,
+    /// Open the backup read-only, so commands that change it refuse to run.
+    #[arg(long, global = true)]
+    read_only: bool,
+    /// Stop at the first file that doesn't parse, instead of leaving it out with a warning.
+    #[arg(long, global = true)]
+    strict: bool,
+    /// What to do with a folder in an account this version doesn't know: error, warn
+    /// (skip it with a warning, the default) or capture (keep its JSON files as they are).
+    #[arg(long, value_name = "POLICY", global = true)]
+    unknown_dirs: Option<UnknownDirPolicy>,
+    /// Sniff the assets records refer to without an extension in their URL, so they're
+    /// treated as the kind their bytes say.
+    #[arg(long, global = true)]
+    sniff_assets: bool,
+    /// Only open a sample of the manifests in stats --components and the compression and
+    /// strings reports, as a percentage like 5% or a count, scaling their totals up to
+    /// estimates marked as such.
+    #[arg(long, value_name = "PERCENT|COUNT", global = true)]
+    sample: Option<Sample>,
+    /// Open at most this many manifests, the same as --sample COUNT.
+    #[arg(long, value_name = "COUNT", global = true, conflicts_with = "sample")]
+    limit: Option<u64>,
+    /// Print how long each phase took (directory walk, parsing, decompression, report
+    /// compute) with counts and throughput to stderr when the command finishes.
+    #[arg(long, global = true)]
+    timings: bool,
+    /// Language of report text: en, a catalog name in neos-full-statbox/locales in the user
+    /// config directory (like ja for locales/ja.toml), or a path to a catalog.
+    #[arg(long, global = true)]
+    locale: Option<String>,
+    /// Write the report text shown that the locale's catalog lacks to this file, as a
+    /// catalog to translate.
+    #[arg(long, value_name = "PATH", global = true)]
+    untranslated: Option<PathBuf>,
+    /// List what loading and scans tolerated (unknown fields, unreadable timestamps and
+    /// file names, manifests left out) when the command finishes, instead of a count.
+    /// Checking for unknown fields makes loading slower.
+    #[arg(long, global = true)]
+    warnings: bool,
+    /// Write stats and report JSON in this schema version, for dashboards not yet updated
+    /// for the current one. 0 is the unversioned layout from before versioning.
+    #[arg(long, value_name = "N", global = true, default_value_t = SCHEMA_VERSION,
+        value_parser = clap::value_parser!(u32).range(0..=SCHEMA_VERSION as i64))]
+    schema_version: u32,
+    /// Refuse manifests that decompress to more than this many megabytes (default 2048),
+    /// to open manifests from untrusted backups without exhausting memory.
+    #[arg(long, value_name = "MB", global = true)]
+    max_manifest_mb: Option<u64>,
+    /// Refuse manifests with slots nested deeper than this (default 512).
+    #[arg(long, value_name = "DEPTH", global = true)]
+    max_slot_depth: Option<usize>,
+    /// Refuse manifests with more components than this (default 5000000).
+    #[arg(long, value_name = "COUNT", global = true)]
+    max_components: Option<u64>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Parse every manifest in a backup, listing the ones that fail and where.
+    Scan {
+        backup: PathBuf,
+        /// Also walk each failing manifest piece by piece, reporting how much of it is
+        /// still readable and every part that isn't.
+        #[arg(long)]
+        recover: bool,
+    },
+    /// Write a bundle per failing manifest (raw BSON, decoded tree, failing field path).
+    DebugDump {
+        backup: PathBuf,
+        #[arg(long, default_value = "debug-dump")]
+        out: PathBuf,
+        /// Only dump this record's manifest.
+        #[arg(long)]
+        record: Option<String>,
+        /// none, contacts-only or full-anonymize.
+        #[arg(long, default_value = "none")]
+        redact: RedactionProfile,
+    },
+    /// Decode a single 7zbson asset and write it out as RON.
+    DumpAsset {
+        assets_dir: PathBuf,
+        hash: String,
+        #[arg(long, default_value = "dump.ron")]
+        out: PathBuf,
+    },
+    /// Print the hash Neos stores each file's bytes under, with its neosdb URL.
+    Hash {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Print statistics about a backup.
+    Stats {
+        backup: PathBuf,
+        /// Also open every manifest to count component usage (slow).
+        #[arg(long)]
+        components: bool,
+        /// Also break assets down by kind, sniffing files without an extension.
+        #[arg(long)]
+        assets: bool,
+        /// Save the computed stats as JSON, for use as a later baseline.
+        #[arg(long)]
+        json: Option<PathBuf>,
+        /// Stats JSON from a previous run to compare against.
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+        /// Continue an interrupted --components scan instead of starting over.
+        #[arg(long)]
+        resume: bool,
+        /// Also run a named report after the stats, may be repeated.
+        #[arg(long = "report", value_name = "NAME", value_parser = report_by_name)]
+        reports: Vec<&'static dyn report::Report>,
+        /// Count records and messages in one pass over the files without loading the
+        /// backup, using far less memory. Labels and custom metrics are left out.
+        #[arg(long, conflicts_with_all = ["components", "assets", "reports"])]
+        streaming: bool,
+        /// Also estimate how much memory the loaded backup takes, by subsystem.
+        #[arg(long, conflicts_with = "streaming")]
+        memory: bool,
+    },
+    /// Check referenced assets exist and manifests parse.
+    ///
+    /// Exits 3 when assets are missing, 4 when manifests fail to parse, 6 when
+    /// --deep or --integrity find corrupt assets and 5 when the backup itself cannot
+    /// be loaded; the most severe wins, in that order.
+    #[command(visible_alias = "doctor")]
+    Verify {
+        backup: PathBuf,
+        /// Write the findings as JSON.
+        #[arg(long)]
+        report: Option<PathBuf>,
+        /// Continue an interrupted check instead of starting over.
+        #[arg(long)]
+        resume: bool,
+        /// Also hash every asset and fully decode manifests, grading each account.
+        /// Results are cached, so later runs only check new or changed assets.
+        #[arg(long)]
+        deep: bool,
+        /// Also hash every file in Assets/ against its name and the sizes records list it
+        /// at, reporting corrupted, truncated and missing assets.
+        #[arg(long)]
+        integrity: bool,
+    },
+    /// List the files in the asset folder no record or message references, with sizes.
+    Orphans {
+        backup: PathBuf,
+        /// Delete them. Refused when accounts, records or messages were left out of the
+        /// load, since what they reference would look orphaned.
+        #[arg(long)]
+        prune: bool,
+        /// Move them into this folder instead of deleting them; implies --prune.
+        #[arg(long, value_name = "DIR")]
+        quarantine: Option<PathBuf>,
+        /// Show what --prune would remove without removing anything.
+        #[arg(long)]
+        dry_run: bool,
+        /// Write the orphans as JSON.
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// List sync tool conflict copies of records and messages, like Syncthing's
+    /// .sync-conflict- files, and which version of each is newer.
+    Conflicts {
+        backup: PathBuf,
+        /// Keep the newer version of each, by globalVersion for records and last update
+        /// time for messages, deleting the other. Conflicts where neither is newer, or
+        /// either side can't be read, are left for manual review.
+        #[arg(long)]
+        resolve: bool,
+        /// Move the losing versions into this folder instead of deleting them; implies
+        /// --resolve.
+        #[arg(long, value_name = "DIR")]
+        quarantine: Option<PathBuf>,
+        /// Show what --resolve would do without changing anything.
+        #[arg(long)]
+        dry_run: bool,
+        /// Write the conflicts as JSON.
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// Compare two snapshots of a backup: records added, removed or modified (by their
+    /// versions), contacts changed and messages new in the later one.
+    Diff {
+        before: PathBuf,
+        after: PathBuf,
+        /// Write the changes as JSON.
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// Combine several backups, like ones taken on different dates or machines, into one
+    /// folder laid out the same way. Each record is taken from the backup with its highest
+    /// version, other files from the last backup that has them, and each asset once.
+    Merge {
+        /// The backups, oldest first.
+        #[arg(required = true, num_args = 2..)]
+        backups: Vec<PathBuf>,
+        /// Where to write the merged backup, which has to be missing or empty.
+        #[arg(long)]
+        out: PathBuf,
+        /// Show what the merge would contain without writing anything.
+        #[arg(long)]
+        dry_run: bool,
+        /// Write the summary as JSON.
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// Check every manifest's components against a profile's allow list, exiting with 1
+    /// if any record uses a disallowed one.
+    ///
+    /// Built in are vanilla-neos and resonite-compatible, more can be added as
+    /// [[profiles]] in the config file.
+    Validate {
+        backup: PathBuf,
+        #[arg(long)]
+        profile: String,
+        /// Write the violations as JSON.
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// Serve assets over HTTP, with /preview/<hash> converting images and audio on the fly
+    /// and /record/<account>/<id> giving a record's JSON.
+    ///
+    /// Needs the `server` feature.
+    Serve {
+        backup: PathBuf,
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
+    /// List the optional features and whether this build has them.
+    Features,
+    /// Convert one asset to another format with a built-in or configured converter.
+    Convert {
+        backup: PathBuf,
+        hash: String,
+        /// Extension of the format to convert to, like png or wav.
+        #[arg(long)]
+        to: String,
+        /// Defaults to <hash>.<to> in the current directory.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// List the available asset converters.
+    Converters,
+    /// Estimate what `export bundle` would copy for an account or one of its folders,
+    /// with assets shared between records counted once, without writing anything.
+    Estimate {
+        backup: PathBuf,
+        /// An account folder, optionally followed by a folder path, like
+        /// U-alice/Inventory/Stuff.
+        target: String,
+    },
+    /// Find the records whose thumbnail looks like an image, such as a screenshot of an
+    /// item found online.
+    Identify {
+        backup: PathBuf,
+        image: PathBuf,
+        /// Perceptual hashes at most this many bits apart count as a match.
+        #[arg(long, default_value_t = similar_images::IDENTIFY_THRESHOLD)]
+        threshold: u32,
+    },
+    /// List an account's records with their folder, type and size.
+    ListRecords {
+        backup: PathBuf,
+        account: String,
+    },
+    /// List the records matching every filter given.
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Find {
+        #[command(subcommand)]
+        what: Option<FindWhat>,
+        #[arg(required = true)]
+        backup: Option<PathBuf>,
+        #[command(flatten)]
+        filter: FindArgs,
+    },
+    /// Hand records over to another account: their owner is rewritten, their files move
+    /// to its folder and links to them are repointed. Records are picked by ID, by the
+    /// find filters, or both, where both must match.
+    Reown {
+        backup: PathBuf,
+        /// The account folder taking the records.
+        #[arg(long)]
+        to: String,
+        /// Defaults to the owner name on the records the account already has.
+        #[arg(long)]
+        owner_name: Option<String>,
+        #[arg(long = "record")]
+        records: Vec<String>,
+        #[command(flatten)]
+        filter: FindArgs,
+        /// Print the changes without making them.
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        history: HistoryArgs,
+    },
+    /// Tidy an account's inventory, changing the record files in place.
+    #[command(subcommand)]
+    Inv(Inv),
+    /// Add or remove tags on the records picked, changing the record files in place.
+    #[command(subcommand)]
+    Tag(Tag),
+    /// Run several stages over one loaded backup, separated by |, like
+    /// "find --type world | verify | export bundle --out worlds".
+    ///
+    /// find narrows the records every later stage sees. The other stages are stats,
+    /// verify, report NAME and export bundle.
+    Pipeline {
+        backup: PathBuf,
+        pipeline: String,
+    },
+    /// Load a backup once and answer pipelines sent with query over a local socket.
+    ///
+    /// Changes to the backup on disk are picked up before the next query: new or removed
+    /// files reload it, new assets only reset the asset index. Send "reload" to force one.
+    Daemon {
+        backup: PathBuf,
+        /// Defaults to neos-full-statbox/daemon.sock in the user runtime directory.
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Run a pipeline on a running daemon. Paths are the daemon's, not the caller's.
+    Query {
+        pipeline: String,
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    Export(ExportArgs),
+    #[command(subcommand)]
+    Report(Report),
+}
+
+#[derive(Subcommand)]
+enum FindWhat {
+    /// List in-game photos, oldest first.
+    Photos {
+        backup: PathBuf,
+        /// Only photos this user ID was in, may be repeated to need all of them.
+        #[arg(long = "with", value_name = "USER")]
+        with: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Inv {
+    /// Make a folder and any missing parents, like Inventory\Worlds\Old or
+    /// Inventory/Worlds/Old.
+    Mkdir {
+        backup: PathBuf,
+        #[arg(long)]
+        account: String,
+        path: String,
+        /// Print the changes without making them.
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        history: HistoryArgs,
+    },
+    /// Move a record into a folder, a folder taking everything in it along.
+    Move {
+        backup: PathBuf,
+        record: String,
+        path: String,
+        /// Print the changes without making them.
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        history: HistoryArgs,
+    },
+    /// Give an account the folders of an inventory structure exported as JSON or YAML,
+    /// moving its records with the same IDs into the folders the structure has them in.
+    Import {
+        backup: PathBuf,
+        structure: PathBuf,
+        #[arg(long)]
+        account: String,
+        /// The account in the structure to copy, needed when it has several and none
+        /// is named like the one changed.
+        #[arg(long)]
+        from: Option<String>,
+        /// Print the changes without making them.
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        history: HistoryArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum Tag {
+    /// Add the tags to every record picked that doesn't have them.
+    Add(TagArgs),
+    /// Remove the tags from every record picked that has them.
+    Remove(TagArgs),
+}
+
+#[derive(Args)]
+struct TagArgs {
+    backup: PathBuf,
+    #[arg(required = true)]
+    tags: Vec<String>,
+    #[arg(long = "record")]
+    records: Vec<String>,
+    #[command(flatten)]
+    filter: FindArgs,
+    /// Print the changes without making them.
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    history: HistoryArgs,
+}
+
+#[derive(Args, Clone)]
+struct HistoryArgs {
+    /// Raise the localVersion of every record changed.
+    #[arg(long)]
+    bump_version: bool,
+    /// Set lastModificationTime on every record changed to now.
+    #[arg(long)]
+    touch: bool,
+    /// Append each change to a .Edits.json file next to the record.
+    #[arg(long)]
+    edit_log: bool,
+    /// All of --bump-version, --touch and --edit-log.
+    #[arg(long)]
+    preserve_history: bool,
+}
+
+impl From<HistoryArgs> for edit::History {
+    fn from(args: HistoryArgs) -> Self {
+        if args.preserve_history {
+            return edit::History::all();
+        }
+        edit::History {
+            bump_version: args.bump_version,
+            touch: args.touch,
+            log: args.edit_log,
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+struct FindArgs {
+    /// audio, directory, link, object, texture or world.
+    #[arg(long = "type", value_parser = record_type)]
+    record_type: Option<RecordType>,
+    #[arg(long)]
+    tag: Option<String>,
+    /// Case-insensitive part of the record name.
+    #[arg(long)]
+    name: Option<String>,
+    #[arg(long)]
+    public: Option<bool>,
+    /// The account folder, a user or group ID.
+    #[arg(long)]
+    account: Option<String>,
+}
 
-mod store;
+impl From<FindArgs> for RecordFilter {
+    fn from(args: FindArgs) -> Self {
+        RecordFilter {
+            record_type: args.record_type,
+            tag: args.tag,
+            name_contains: args.name,
+            public: args.public,
+            account: args.account,
+        }
+    }
+}
 
-fn main() {
-    let bson: Manifest = read_7zbson("fed049610a4bd07198d82367bb16c106217e240fa21c34256a9454e824a0cc5a").unwrap();
-    writeln!(fs::File::create("dump.ron").unwrap(), "{:#?}", bson).unwrap();
+#[derive(Parser)]
+#[command(no_binary_name = true, name = "pipeline")]
+struct StageLine {
+    #[command(subcommand)]
+    stage: Stage,
+}
+
+#[derive(Subcommand)]
+enum Stage {
+    Find(FindArgs),
+    Stats,
+    Verify,
+    Report {
+        #[arg(value_parser = report_by_name)]
+        name: &'static dyn report::Report,
+    },
+    #[command(subcommand)]
+    Export(StageExport),
+}
+
+#[derive(Subcommand)]
+enum StageExport {
+    Bundle {
+        #[arg(long)]
+        out: PathBuf,
+        /// none, contacts-only or full-anonymize.
+        #[arg(long, default_value = "none")]
+        redact: RedactionProfile,
+    },
+}
+
+#[derive(Subcommand)]
+enum Report {
+    /// List the inventory folders holding the most records and asset bytes.
+    Directories {
+        backup: PathBuf,
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+    },
+    /// List contacts by when you last interacted with them.
+    Contacts {
+        backup: PathBuf,
+        /// Only show contacts with inconsistent or blocked friendship states.
+        #[arg(long)]
+        flagged: bool,
+        /// Only show contacts whose ID, current or former name contains this.
+        #[arg(long)]
+        search: Option<String>,
+        /// Only show contacts under this label.
+        #[arg(long)]
+        label: Option<String>,
+    },
+    /// List users with their current and former names.
+    Aliases {
+        backup: PathBuf,
+        /// Only show users whose ID, current or former name contains this.
+        #[arg(long)]
+        search: Option<String>,
+    },
+    /// Summarize the hardware contacts and session users were seen on.
+    Devices { backup: PathBuf },
+    /// List records carrying inactive slots or disabled components, and the asset
+    /// bytes only they use.
+    Disabled {
+        backup: PathBuf,
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+    },
+    /// List headless hosts seen in captured sessions, with the worlds they ran.
+    Headless { backup: PathBuf },
+    /// Group records saved from the same object by their root Persistent-ID.
+    Copies { backup: PathBuf },
+    /// Compare two records' manifests slot by slot, matched on Persistent-ID.
+    Compare {
+        backup: PathBuf,
+        /// A record ID, or account/id for one account's copy of a group record.
+        before: String,
+        after: String,
+    },
+    /// List how captured sessions could be reached: relays, direct addresses, NAT
+    /// punchthrough and Steam, with how often and when each was seen.
+    Relays { backup: PathBuf },
+    /// Peak and average attendance per world, across snapshots of the sessions you
+    /// hosted or were invited to.
+    Population { backup: PathBuf },
+    /// Match up the messages between accounts in the backup that are each other's
+    /// contacts, listing messages only one side has or that the sides disagree on.
+    Threads {
+        backup: PathBuf,
+        /// Show the combined conversation between two accounts, with each side's read
+        /// receipts.
+        #[arg(long, num_args = 2, value_names = ["A", "B"])]
+        between: Option<Vec<String>>,
+    },
+    /// List folder links that lead back to the folder holding them or an ancestor.
+    LinkCycles { backup: PathBuf },
+    /// Storage per group: its records wherever a member exported them, against the
+    /// usage the cloud reported, and linked group records the backup is missing.
+    Groups { backup: PathBuf },
+    /// List records no inventory folder leads to, which the game can't show.
+    Stranded { backup: PathBuf },
+    /// List records referring to assets no asset root has, through their own fields or
+    /// inside their manifests, by owner. Records missing their own asset can't be
+    /// recovered from this backup.
+    BrokenRefs {
+        backup: PathBuf,
+        /// Write the broken records as JSON.
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+    /// Cluster image assets that look the same under different content hashes, like
+    /// recompressed copies of one texture, with what keeping one of each would save.
+    SimilarImages {
+        backup: PathBuf,
+        /// Perceptual hashes at most this many bits apart count as the same image.
+        #[arg(long, default_value_t = similar_images::DEFAULT_THRESHOLD)]
+        threshold: u32,
+    },
+    /// Group in-game photos by world, with the days they were taken and who was in them.
+    Screenshots { backup: PathBuf },
+    /// Compare each record's manifest as stored against decompressed and recompressed
+    /// with brotli, to see what a brson conversion would save before running one.
+    Compression { backup: PathBuf },
+    /// List the BSON shapes of manifest fields the typed model doesn't know yet, the
+    /// ones in the most records first.
+    Shapes {
+        backup: PathBuf,
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+    },
+    /// Find the most duplicated strings across backup JSON and manifest BSON.
+    Strings {
+        backup: PathBuf,
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+    },
+}
 
-    scan_for_invalid();
+#[derive(Args)]
+struct ExportArgs {
+    /// none, contacts-only or full-anonymize.
+    #[arg(long, global = true, default_value = "none")]
+    redact: RedactionProfile,
+    #[command(subcommand)]
+    kind: Export,
 }
 
-fn read_7zbson<Output : DeserializeOwned>(asset: &str) -> Result<Output, store::backup::Error> {
-    let b = Backup {
-        assets_dir: "F:\\neos backup 2\\Assets".into(),
-        ..Default::default()
+#[derive(Subcommand)]
+enum Export {
+    /// Write a self-contained HTML page charting the backup's stats.
+    Dashboard {
+        backup: PathBuf,
+        #[arg(long, default_value = "dashboard.html")]
+        out: PathBuf,
+        /// Also open every manifest to chart component usage (slow).
+        #[arg(long)]
+        components: bool,
+        /// Also include a named report, may be repeated.
+        #[arg(long = "report", value_name = "NAME", value_parser = report_by_name)]
+        reports: Vec<&'static dyn report::Report>,
+    },
+    /// Write backup health metrics in OpenMetrics text format, for scheduled syncs.
+    Metrics {
+        backup: PathBuf,
+        #[arg(long, default_value = "statbox.prom")]
+        out: PathBuf,
+        /// Also parse every manifest to report parse errors (slow).
+        #[arg(long)]
+        manifests: bool,
+    },
+    /// Copy the records and the assets they use into a new backup folder.
+    Bundle {
+        backup: PathBuf,
+        #[arg(long, default_value = "bundle")]
+        out: PathBuf,
+    },
+    /// Make a folder per record of symlinks into the asset store, named by role.
+    ///
+    /// Paths follow naming.asset-links in the config, {account}/{name}/{role}.{ext} by
+    /// default.
+    AssetLinks {
+        backup: PathBuf,
+        #[arg(long, default_value = "asset-links")]
+        out: PathBuf,
+    },
+    /// Write each account's inventory as folders, every item a folder of its own with its
+    /// manifest as JSON, its thumbnail and copies of its assets, to browse in a file manager.
+    InventoryTree {
+        backup: PathBuf,
+        #[arg(long, default_value = "inventory")]
+        out: PathBuf,
+        /// Continue an interrupted export from its manifest instead of starting over.
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Write every image and audio asset as PNG or OGG, tagged with the record it came from.
+    ///
+    /// Images need the `images` feature and audio the `audio` feature. Paths follow
+    /// naming.media in the config, {account}/{record}/{hash}.{ext} by default.
+    Media {
+        backup: PathBuf,
+        #[arg(long, default_value = "media")]
+        out: PathBuf,
+        /// Continue an interrupted export from its manifest instead of starting over.
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Write the text and links found in well-known components (comments, text
+    /// renderers, URL fields) as JSON lines, one per field.
+    Text {
+        backup: PathBuf,
+        #[arg(long, default_value = "text.jsonl")]
+        out: PathBuf,
+    },
+    /// Write each conversation as a Markdown or HTML transcript by account and contact,
+    /// with voice messages and sent items copied alongside and linked.
+    Messages {
+        backup: PathBuf,
+        #[arg(long, default_value = "messages")]
+        out: PathBuf,
+        /// md or html.
+        #[arg(long, default_value = "md")]
+        format: TranscriptFormat,
+    },
+    /// Extract voice messages as ogg files by account and contact, optionally with
+    /// transcripts from whisper-cli, configured under [whisper] in the config.
+    Voice {
+        backup: PathBuf,
+        #[arg(long, default_value = "voice")]
+        out: PathBuf,
+        /// Transcribe each message, keeping transcripts an earlier run already made.
+        #[arg(long)]
+        transcribe: bool,
+    },
+    /// Write accounts, records, messages, contacts, groups and variables into a SQLite
+    /// database, for ad-hoc SQL over the backup. Needs the `sqlite` feature.
+    Sqlite {
+        backup: PathBuf,
+        #[arg(long, default_value = "backup.sqlite")]
+        out: PathBuf,
+        /// Stream messages into databases of at most this many rows beside the main one,
+        /// listed in an index file, instead of holding them all in memory.
+        #[arg(long, value_name = "ROWS")]
+        chunk_rows: Option<u64>,
+        /// Like --chunk-rows, by roughly how many megabytes of text a database holds.
+        #[arg(long, value_name = "MB")]
+        chunk_mb: Option<u64>,
+    },
+    /// Write the in-game photos as JSON, by the world they were taken in, with when
+    /// and who was there.
+    Screenshots {
+        backup: PathBuf,
+        #[arg(long, default_value = "screenshots.json")]
+        out: PathBuf,
+    },
+    /// Write session invites, submissions, features and first publishes as an iCalendar
+    /// file, to overlay Neos history on a calendar app.
+    Calendar {
+        backup: PathBuf,
+        #[arg(long, default_value = "history.ics")]
+        out: PathBuf,
+    },
+    /// Write a card per contact (username, user ID, profile icon, tagline, first and
+    /// last interaction) to carry the contact list into other tools.
+    Contacts {
+        backup: PathBuf,
+        /// json or vcard.
+        #[arg(long, default_value = "json")]
+        format: CardFormat,
+        /// Defaults to contacts.json or contacts.vcf.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Write each account's folders with the names and IDs of what's in them, no assets,
+    /// to plan a re-upload that keeps the organization.
+    InventoryStructure {
+        backup: PathBuf,
+        /// json or yaml.
+        #[arg(long, default_value = "json")]
+        format: StructureFormat,
+        /// Defaults to inventory-structure.json or inventory-structure.yaml.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Write a record's slot and component hierarchy as a graph.
+    SlotGraph {
+        backup: PathBuf,
+        /// A record ID, or account/id for one account's copy of a group record.
+        record: String,
+        /// dot or mermaid.
+        #[arg(long, default_value = "dot")]
+        format: GraphFormat,
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    if cli.timings {
+        timings::enable();
+    }
+    let config = Config::load_or_default(cli.config.as_deref())?;
+    config.naming.check()?;
+    if let Some(locale) = cli.locale.as_ref().or(config.locale.as_ref()) {
+        locale::install(Catalog::find(locale)?);
+    }
+    if cli.untranslated.is_some() {
+        locale::record_untranslated();
+    }
+    let zone = match (cli.timezone, &config.timezone) {
+        (Some(zone), _) => zone,
+        (None, Some(zone)) => zone.parse()?,
+        (None, None) => Zone::default(),
+    };
+    let ignore = if cli.no_default_ignores {
+        IgnoreRules::none()
+    } else {
+        IgnoreRules::default()
+    };
+    let ignore = ignore.with(config.ignore.iter().chain(&cli.ignore))?;
+    let mut asset_roots = cli.asset_roots;
+    asset_roots.extend(config.asset_roots.iter().cloned());
+    let defaults = ManifestLimits::default();
+    let limits = ManifestLimits {
+        max_decompressed_bytes: cli
+            .max_manifest_mb
+            .map_or(defaults.max_decompressed_bytes, |mb| mb << 20),
+        max_slot_depth: cli.max_slot_depth.unwrap_or(defaults.max_slot_depth),
+        max_components: cli.max_components.unwrap_or(defaults.max_components),
+    };
+    let load_options = LoadOptions {
+        include_accounts: cli.include_accounts,
+        exclude_accounts: cli.exclude_accounts,
+        skip_messages: cli.skip_messages,
+        skip_records: cli.skip_records,
+        asset_roots,
+        read_only: cli.read_only,
+        detect_asset_kinds: cli.sniff_assets,
+        limits,
+        strict: cli.strict,
+        unknown_dirs: cli.unknown_dirs.or(config.unknown_dirs).unwrap_or_default(),
+        sample: cli.sample.or(cli.limit.map(Sample::Limit)),
+        ignore,
+        warnings: if cli.warnings {
+            Warnings::default().with_unknown_fields()
+        } else {
+            Warnings::default()
+        },
     };
+    let globals = Globals {
+        untranslated: cli.untranslated,
+        warnings: load_options.warnings.clone(),
+        list_warnings: cli.warnings,
+    };
+    let labels = Labels::load_or_default(cli.labels.as_deref())?;
+    let schema_version = cli.schema_version;
 
-    let asset = SZBson(Rc::new(asset.into()));
+    match cli.command {
+        Command::Scan { backup, recover } => {
+            let backup = load(backup, &load_options)?;
+            println!("Scanning assets...");
+            let mut failures = 0;
+            let mut unreadable = 0;
+            scan_for_invalid(&backup, None, |rec, bundle| {
+                match bundle {
+                    Ok(bundle) => {
+                        failures += 1;
+                        println!(
+                            "{} ({}): {} at {}",
+                            rec.id,
+                            bundle.asset,
+                            bundle.error,
+                            bundle.path.as_deref().unwrap_or("<document>")
+                        );
+                        if let Some(tree) = bundle.tree.as_ref().filter(|_| recover) {
+                            let recovered = recovery::recover(tree);
+                            println!(
+                                "  recovered {} slots and {} components, {} parts unreadable",
+                                recovered.slots,
+                                recovered.components,
+                                recovered.failures.len()
+                            );
+                            for failure in &recovered.failures {
+                                println!("    {}: {}", failure.path, failure.error);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        unreadable += 1;
+                        println!("{}: {}", rec.id, e);
+                    }
+                }
+                Ok(())
+            })?;
+            println!("{} manifests failed to parse", failures);
+            if unreadable > 0 {
+                println!("{} manifests could not be read", unreadable);
+            }
+        }
+        Command::DebugDump {
+            backup,
+            out,
+            record,
+            redact,
+        } => {
+            let backup = load(backup, &load_options)?;
+            let redactor = redactor(redact, &backup);
+            scan_for_invalid(&backup, record.as_deref(), |rec, bundle| {
+                match bundle {
+                    Ok(mut bundle) => {
+                        bundle.redact(&redactor);
+                        println!("{}: wrote {:?}", rec.id, bundle.write(&out)?);
+                    }
+                    Err(e) => println!("{}: {}", rec.id, e),
+                }
+                Ok(())
+            })?;
+        }
+        Command::DumpAsset {
+            assets_dir,
+            hash,
+            out,
+        } => {
+            let bson: Manifest = read_7zbson(assets_dir, &hash, load_options.limits)?;
+            writeln!(fs::File::create(out)?, "{:#?}", bson)?;
+        }
+        Command::Hash { files } => {
+            for file in files {
+                let id = hash::hash_asset(io::BufReader::new(fs::File::open(&file)?))?;
+                let ext = file.extension().map(|e| e.to_string_lossy());
+                println!("{}  {}  {}", id, id.url(ext.as_deref()), file.display());
+            }
+        }
+        Command::Stats {
+            backup,
+            components,
+            assets,
+            json,
+            baseline,
+            resume,
+            reports,
+            streaming,
+            memory,
+        } => {
+            // Reports need the loaded backup, which a streaming scan never has.
+            let (stats, backup) = if streaming {
+                println!("Scanning backup...");
+                (Stats::compute_streaming(&backup, &load_options)?, None)
+            } else {
+                let backup = load(backup, &load_options)?;
+                let mut stats = Stats::compute(&backup);
+                stats.count_labels(&backup, &labels);
+                stats.evaluate_metrics(&backup, &config.metrics);
+                if components {
+                    println!("Scanning manifests...");
+                    let mut journal = journal("components", &backup, resume)?;
+                    stats.scan_manifests_journaled(&backup, &mut journal)?;
+                    journal.finish()?;
+                }
+                if assets {
+                    println!("Scanning assets...");
+                    stats.scan_assets(&backup);
+                }
+                (stats, Some(backup))
+            };
+            print!("{}", render::stats(&stats));
+            if let Some(backup) = &backup {
+                if memory {
+                    print!("{}", render::memory(&memory::memory_usage(backup)));
+                }
+                for report in reports {
+                    print!("{}", report.run(backup).render(ReportFormat::Text));
+                }
+            }
+            if let Some(baseline) = baseline {
+                let baseline: Stats = schema::read(&baseline, "stats")?;
+                print!("{}", render::delta(&stats.diff(&baseline)));
+            }
+            if let Some(json) = json {
+                schema::write(&json, "stats", &stats, schema_version)?;
+            }
+        }
+        Command::Verify {
+            backup,
+            report,
+            resume,
+            deep,
+            integrity,
+        } => {
+            let result = match load(backup, &load_options) {
+                Ok(backup) => {
+                    let mut journal = journal("verify", &backup, resume)?;
+                    let mut result = VerifyReport::check_journaled(&backup, &mut journal)?;
+                    journal.finish()?;
+                    if deep {
+                        println!("Verifying asset contents...");
+                        let mut cache = match journal::path_for("deep-verify", &backup.assets_dir) {
+                            Some(path) => Journal::open(path, true)?,
+                            None => Journal::disabled(),
+                        };
+                        result.deep_check(&backup, &mut cache)?;
+                    }
+                    if integrity {
+                        let checked = backup.verify_assets_with(|p| {
+                            if p.done % 100 == 0 || p.done == p.total {
+                                eprint!(
+                                    "\rHashed {}/{} assets, {}",
+                                    p.done,
+                                    p.total,
+                                    render::human_bytes(p.bytes)
+                                );
+                            }
+                        })?;
+                        eprintln!();
+                        result.add_integrity(checked);
+                    }
+                    result
+                }
+                Err(e) => VerifyReport::load_failed(&e),
+            };
+            print!("{}", render::verify(&result));
+            if let Some(report) = report {
+                schema::write(&report, "verify", &result, schema_version)?;
+            }
+            finish(&globals)?;
+            std::process::exit(result.exit_code());
+        }
+        Command::Orphans {
+            backup,
+            prune,
+            quarantine,
+            dry_run,
+            report,
+        } => {
+            let backup = load(backup, &load_options)?;
+            let orphans = if prune || quarantine.is_some() {
+                backup.prune(quarantine.as_deref(), dry_run)?
+            } else {
+                backup.find_orphans()?
+            };
+            print!("{}", render::orphans(&orphans));
+            if prune || quarantine.is_some() {
+                let verb = match (dry_run, &quarantine) {
+                    (true, _) => "Would prune",
+                    (false, Some(_)) => "Quarantined",
+                    (false, None) => "Deleted",
+                };
+                println!(
+                    "{} {} orphaned assets, {}",
+                    verb,
+                    orphans.assets.len(),
+                    render::human_bytes(orphans.bytes())
+                );
+            }
+            if let Some(report) = report {
+                schema::write(&report, "orphans", &orphans, schema_version)?;
+            }
+        }
+        Command::Conflicts {
+            backup,
+            resolve,
+            quarantine,
+            dry_run,
+            report,
+        } => {
+            let backup = load(backup, &load_options)?;
+            let resolve = resolve || quarantine.is_some();
+            let conflicts = if resolve {
+                backup.resolve_conflicts(quarantine.as_deref(), dry_run)?
+            } else {
+                backup.find_conflicts()?
+            };
+            print!("{}", render::conflicts(&conflicts));
+            if resolve {
+                let resolved = conflicts
+                    .iter()
+                    .filter(|c| c.resolution != Resolution::Manual)
+                    .count();
+                let verb = if dry_run { "Would resolve" } else { "Resolved" };
+                println!("{} {} of {} conflicts", verb, resolved, conflicts.len());
+            }
+            if let Some(report) = report {
+                schema::write(&report, "conflicts", &conflicts, schema_version)?;
+            }
+        }
+        Command::Diff {
+            before,
+            after,
+            report,
+        } => {
+            let before = load(before, &load_options)?;
+            let after = load(after, &load_options)?;
+            let diff = before.diff(&after);
+            print!("{}", render::backup_diff(&diff, zone));
+            if let Some(report) = report {
+                schema::write(&report, "diff", &diff, schema_version)?;
+            }
+        }
+        Command::Merge {
+            backups,
+            out,
+            dry_run,
+            report,
+        } => {
+            let sources = backups
+                .into_iter()
+                .map(|root| load(root, &load_options))
+                .collect::<Result<Vec<_>, _>>()?;
+            let plan = merge::plan(&sources, &load_options.ignore)?;
+            print!("{}", render::merge_summary(&plan.summary));
+            if !dry_run {
+                plan.write(&out)?;
+                println!("Merged into {}", out.display());
+            }
+            if let Some(report) = report {
+                schema::write(&report, "merge", &plan.summary, schema_version)?;
+            }
+        }
+        Command::Validate {
+            backup,
+            profile,
+            report,
+        } => {
+            let Some(profile) = ValidationProfile::find(&config.profiles, &profile) else {
+                let configured = config.profiles.iter().map(|p| p.name.clone());
+                let known: Vec<_> = ValidationProfile::built_in()
+                    .into_iter()
+                    .map(|p| p.name)
+                    .chain(configured)
+                    .collect();
+                return Err(format!(
+                    "unknown profile {}, expected one of: {}",
+                    profile,
+                    known.join(", ")
+                )
+                .into());
+            };
+            let backup = load(backup, &load_options)?;
+            let result = validate::validate(&backup, &profile);
+            print!("{}", render::validation(&result));
+            if let Some(report) = report {
+                schema::write(&report, "validation", &result, schema_version)?;
+            }
+            finish(&globals)?;
+            std::process::exit(result.exit_code());
+        }
+        #[cfg(feature = "server")]
+        Command::Serve { backup, addr } => {
+            let backup = load(backup, &load_options)?;
+            println!("Serving on http://{}", addr);
+            store::serve::serve(&backup, &addr, &store::preview::PreviewCache::new())?;
+        }
+        #[cfg(not(feature = "server"))]
+        Command::Serve { .. } => unavailable("server"),
+        Command::Features => print!("{}", render::features(features::FEATURES)),
+        Command::Convert {
+            backup,
+            hash,
+            to,
+            out,
+        } => {
+            let backup = load(backup, &load_options)?;
+            let out = out.unwrap_or_else(|| PathBuf::from(format!("{}.{}", hash, to)));
+            let converters = converters(&config.converters);
+            let converter = converters.convert_asset(&backup, &hash, &to, &out)?;
+            println!("Wrote {:?} with {}", out, converter.name());
+        }
+        Command::Converters => print!("{}", render::converters(&converters(&config.converters))),
+        Command::Estimate { backup, target } => {
+            let backup = load(backup, &load_options)?;
+            let mut parts = target.split(['/', '\\']).filter(|p| !p.is_empty());
+            let account_name = parts.next().unwrap_or_default();
+            let path: Vec<RcStr> = parts.map(RcStr::from).collect();
+            let Some(account) = backup.accounts.get(account_name) else {
+                return Err(format!("no account {} in the backup", account_name).into());
+            };
+            let estimate = if path.is_empty() {
+                bundle::estimate_bundle(&backup, account.records.values())
+            } else {
+                let inventory = Inventory::build(account);
+                let Some(folder) = inventory.folder(&path) else {
+                    return Err(format!("no folder {} in {}", path.join("\\"), account_name).into());
+                };
+                bundle::estimate_bundle(&backup, folder.records(account))
+            };
+            println!(
+                "A bundle of {} would hold {} records and {} assets ({}), {} assets missing",
+                target,
+                estimate.records,
+                estimate.assets,
+                render::human_bytes(estimate.bytes),
+                estimate.missing
+            );
+        }
+        Command::Identify {
+            backup,
+            image,
+            threshold,
+        } => {
+            features::require("images")?;
+            let hash = phash(&fs::read(&image)?)?;
+            let backup = load(backup, &load_options)?;
+            let matches = similar_images::identify(&backup, hash, threshold);
+            print!("{}", render::thumbnail_matches(&matches));
+        }
+        Command::ListRecords { backup, account } => {
+            // Only the one account's records are read.
+            let backup = Backup::open_with(backup, &load_options)?;
+            let Some(acc) = backup.account(&account) else {
+                let accounts = backup.account_names()?;
+                let accounts: Vec<_> = accounts.iter().map(|a| a.as_str()).collect();
+                return Err(format!(
+                    "no account {}, expected one of: {}",
+                    account,
+                    accounts.join(", ")
+                )
+                .into());
+            };
+            let records = acc
+                .records_iter()?
+                .map(|rec| rec.map(|(_, rec)| rec))
+                .collect::<Result<Vec<_>, _>>()?;
+            print!("{}", render::account_records(&records));
+        }
+        Command::Find {
+            what,
+            backup,
+            filter,
+        } => match (what, backup) {
+            (Some(FindWhat::Photos { backup, with }), _) => {
+                let backup = load(backup, &load_options)?;
+                let photos = screenshots::photos(&backup);
+                let index = PeopleIndex::build(&photos);
+                print!(
+                    "{}",
+                    render::photos(&index.photos_with(&photos, &with), zone)
+                );
+            }
+            (None, Some(backup)) => {
+                let backup = load(backup, &load_options)?;
+                let filter = RecordFilter::from(filter);
+                print!("{}", render::records(&filter.find(&backup)));
+            }
+            (None, None) => unreachable!("clap requires a backup without a subcommand"),
+        },
+        Command::Reown {
+            backup: root,
+            to,
+            owner_name,
+            records,
+            filter,
+            dry_run,
+            history,
+        } => {
+            let filter = RecordFilter::from(filter);
+            if records.is_empty() && filter.is_empty() {
+                return Err("pick the records to reown with --record or a find filter".into());
+            }
+            let backup = load(root.clone(), &load_options)?;
+            let to = RcStr::new(to);
+            let owner_name = owner_name.map(RcStr::new).or_else(|| {
+                let account = backup.accounts.get(&to)?;
+                account
+                    .records
+                    .values()
+                    .next()
+                    .map(|r| r.owner_name.clone())
+            });
+            let Some(owner_name) = owner_name else {
+                return Err(format!(
+                    "{} has no records to take its name from, pass --owner-name",
+                    to
+                )
+                .into());
+            };
+            let selected = select_records(&backup, &records, &filter);
+            let changes = edit::reown_changes(&backup, &selected, &to, &owner_name);
+            for change in &changes {
+                println!("{}", change);
+            }
+            if !dry_run {
+                let _lock = backup.lock()?;
+                edit::apply(&root, &changes, &history.into())?;
+            }
+            let moved = changes
+                .iter()
+                .filter(|c| matches!(c, RecordChange::Move { .. }))
+                .count();
+            println!(
+                "{} {} records to {}",
+                if dry_run { "Would move" } else { "Moved" },
+                moved,
+                to
+            );
+            let taken = selected
+                .iter()
+                .filter(|(account, _)| account != &to)
+                .count()
+                - moved;
+            if taken > 0 {
+                println!("{} records {} already has were left alone", taken, to);
+            }
+        }
+        Command::Pipeline { backup, pipeline } => {
+            // Every stage parses before the load, so a typo doesn't cost a full load.
+            let stages = parse_pipeline(&pipeline).unwrap_or_else(|e| {
+                eprint!("{}", e);
+                std::process::exit(2);
+            });
+            let mut backup = load(backup, &load_options)?;
+            let mut dropped = Vec::new();
+            for (line, stage) in stages {
+                println!("== {}", line);
+                let out = &mut io::stdout();
+                run_stage(
+                    stage,
+                    &mut backup,
+                    &labels,
+                    &config.metrics,
+                    out,
+                    &mut dropped,
+                )?;
+            }
+        }
+        #[cfg(unix)]
+        Command::Daemon { backup, socket } => {
+            let socket = socket.or_else(default_socket).ok_or("no --socket given")?;
+            daemon(backup, &socket, &load_options, &labels, &config.metrics)?;
+        }
+        #[cfg(unix)]
+        Command::Query { pipeline, socket } => {
+            let socket = socket.or_else(default_socket).ok_or("no --socket given")?;
+            let mut stream = std::os::unix::net::UnixStream::connect(&socket)
+                .map_err(|e| format!("no daemon at {:?}: {}", socket, e))?;
+            writeln!(stream, "{}", pipeline)?;
+            io::copy(&mut stream, &mut io::stdout())?;
+        }
+        #[cfg(not(unix))]
+        Command::Daemon { .. } | Command::Query { .. } => {
+            eprintln!("the daemon needs unix sockets, which this platform lacks");
+            std::process::exit(2);
+        }
+        Command::Inv(op) => inv(op, &load_options)?,
+        Command::Tag(op) => tag(op, &load_options)?,
+        Command::Report(kind) => report(
+            kind,
+            zone,
+            &load_options,
+            &config.aliases,
+            &labels,
+            schema_version,
+        )?,
+        Command::Export(ExportArgs { redact, kind }) => {
+            export(redact, kind, zone, &load_options, &config, &labels)?
+        }
+    }
+    finish(&globals)?;
+    Ok(())
+}
 
-    asset.open(&b)
+// What the global flags ask for once a command is done.
+struct Globals {
+    untranslated: Option<PathBuf>,
+    warnings: Warnings,
+    list_warnings: bool,
 }
 
-fn scan_for_invalid() {
+fn finish(globals: &Globals) -> Result<(), Box<dyn std::error::Error>> {
+    if timings::enabled() {
+        eprint!("{}", render::timings(&timings::take()));
+    }
+    let warnings = globals.warnings.take();
+    if globals.list_warnings {
+        eprint!("{}", render::warnings(&warnings));
+    } else if !warnings.is_empty() {
+        eprintln!(
+            "{} warnings while loading or scanning, --warnings lists them",
+            warnings.len()
+        );
+    }
+    if let Some(path) = &globals.untranslated {
+        let catalog: BTreeMap<_, _> = locale::take_untranslated()
+            .into_iter()
+            .map(|msg| (msg.clone(), msg))
+            .collect();
+        fs::write(path, toml::to_string(&catalog)?)?;
+    }
+    Ok(())
+}
+
+// Falls back to an unjournaled scan when there is no cache directory.
+fn journal<T>(kind: &str, backup: &Backup, resume: bool) -> Result<Journal<T>, store::backup::Error>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    let Some(path) = journal::path_for(kind, &backup.assets_dir) else {
+        return Ok(Journal::disabled());
+    };
+    let journal = Journal::open(path, resume)?;
+    if journal.resumed() > 0 {
+        println!("Resuming, {} manifests already done", journal.resumed());
+    }
+    Ok(journal)
+}
+
+fn report(
+    kind: Report,
+    zone: Zone,
+    options: &LoadOptions,
+    aliases: &BTreeMap<String, String>,
+    labels: &Labels,
+    schema_version: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match kind {
+        Report::Directories { backup, top } => {
+            let backup = load(backup, options)?;
+            for (name, account) in &backup.accounts {
+                let mut sizes = Inventory::build(account).folder_sizes(account);
+                sizes.sort_by_key(|size| std::cmp::Reverse(size.bytes));
+                sizes.truncate(top);
+                print!("{}", render::folder_sizes(name, &sizes));
+            }
+        }
+        Report::Contacts {
+            backup,
+            flagged,
+            search,
+            label,
+        } => {
+            let backup = load(backup, options)?;
+            let aliases = Aliases::build(&backup, aliases);
+            let mut interactions = contacts::last_interactions(&backup, &aliases, labels);
+            if flagged {
+                interactions.retain(|c| !c.flags.is_empty());
+            }
+            if let Some(search) = search {
+                interactions.retain(|c| aliases.matches(&c.contact_id, &search));
+            }
+            if let Some(label) = label {
+                interactions.retain(|c| labels.has(&c.contact_id, &label));
+            }
+            print!("{}", render::contacts(&interactions, zone));
+        }
+        Report::Devices { backup } => {
+            let backup = load(backup, options)?;
+            print!("{}", render::devices(&devices::device_stats(&backup)));
+        }
+        Report::Disabled { backup, top } => {
+            let backup = load(backup, options)?;
+            let mut records = disabled::disabled_content(&backup);
+            records.truncate(top);
+            print!("{}", render::disabled(&records));
+        }
+        Report::Headless { backup } => {
+            let backup = load(backup, options)?;
+            print!(
+                "{}",
+                render::headless(
+                    &headless::headless_hosts(&backup, &Aliases::build(&backup, aliases)),
+                    zone
+                )
+            );
+        }
+        Report::Aliases { backup, search } => {
+            let backup = load(backup, options)?;
+            let aliases = Aliases::build(&backup, aliases);
+            let table = match &search {
+                Some(search) => render::aliases(aliases.search(search)),
+                None => render::aliases(&aliases.users),
+            };
+            print!("{}", table);
+        }
+        Report::Copies { backup } => {
+            let backup = load(backup, options)?;
+            print!("{}", render::copies(&copies::copies(&backup)));
+        }
+        Report::Compare {
+            backup,
+            before,
+            after,
+        } => {
+            let backup = load(backup, options)?;
+            let before = IdentityMap::build(&record_manifest(&backup, &before)?);
+            let after = IdentityMap::build(&record_manifest(&backup, &after)?);
+            print!("{}", render::identity_diff(&before.diff(&after)));
+        }
+        Report::LinkCycles { backup } => {
+            let backup = load(backup, options)?;
+            print!(
+                "{}",
+                render::link_cycles(&link_cycles::link_cycles(&backup))
+            );
+        }
+        Report::Groups { backup } => {
+            let backup = load(backup, options)?;
+            print!("{}", render::group_storage(&groups::group_storage(&backup)));
+        }
+        Report::SimilarImages { backup, threshold } => {
+            features::require("images")?;
+            let backup = load(backup, options)?;
+            let similar = similar_images::similar_images(&backup, threshold);
+            print!("{}", render::similar_images(&similar));
+        }
+        Report::Compression { backup } => {
+            let backup = load(backup, options)?;
+            print!(
+                "{}",
+                render::compression(&compression::compression(&backup))
+            );
+        }
+        Report::Screenshots { backup } => {
+            let backup = load(backup, options)?;
+            let worlds = screenshots::photos_by_world(screenshots::photos(&backup));
+            print!("{}", render::photos_by_world(&worlds, zone));
+        }
+        Report::Stranded { backup } => {
+            let backup = load(backup, options)?;
+            print!("{}", render::stranded(&stranded::stranded_records(&backup)));
+        }
+        Report::BrokenRefs { backup, json } => {
+            let backup = load(backup, options)?;
+            let broken = broken_refs::broken_refs(&backup);
+            print!("{}", render::broken_refs(&broken));
+            if let Some(json) = json {
+                schema::write(&json, "broken-refs", &broken, schema_version)?;
+            }
+        }
+        Report::Relays { backup } => {
+            let backup = load(backup, options)?;
+            print!(
+                "{}",
+                render::session_routes(&relays::session_routes(&backup), zone)
+            );
+        }
+        Report::Population { backup } => {
+            let backup = load(backup, options)?;
+            print!(
+                "{}",
+                render::world_population(&population::world_population(&backup), zone)
+            );
+        }
+        Report::Threads { backup, between } => {
+            let backup = load(backup, options)?;
+            let threads = threads::stitched_threads(&backup);
+            match between.as_deref() {
+                Some([a, b]) => {
+                    let thread = threads
+                        .iter()
+                        .find(|t| {
+                            (t.a.as_str(), t.b.as_str()) == (a, b)
+                                || (t.a.as_str(), t.b.as_str()) == (b, a)
+                        })
+                        .ok_or_else(|| {
+                            format!("no messages between {} and {} in the backup", a, b)
+                        })?;
+                    print!("{}", render::conversation(thread, zone));
+                }
+                _ => print!("{}", render::threads(&threads, zone)),
+            }
+        }
+        Report::Shapes { backup, top } => {
+            let backup = load(backup, options)?;
+            let mut shapes = shapes::leftover_shapes(&backup);
+            shapes.truncate(top);
+            print!("{}", render::shapes(&shapes));
+        }
+        Report::Strings { backup, top } => {
+            let backup = load(backup, options)?;
+            print!("{}", render::strings(&strings::string_report(&backup, top)));
+        }
+    }
+    Ok(())
+}
+
+fn export(
+    redact: RedactionProfile,
+    kind: Export,
+    zone: Zone,
+    options: &LoadOptions,
+    config: &Config,
+    labels: &Labels,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (aliases, metrics, naming) = (&config.aliases, &config.metrics, &config.naming);
+    match kind {
+        Export::Dashboard {
+            backup,
+            out,
+            components,
+            reports,
+        } => {
+            let backup = load(backup, options)?;
+            let mut stats = Stats::compute(&backup);
+            stats.count_labels(&backup, labels);
+            stats.evaluate_metrics(&backup, metrics);
+            if components {
+                println!("Scanning manifests...");
+                stats.scan_manifests(&backup);
+            }
+            let reports: Vec<_> = reports
+                .into_iter()
+                .map(|report| (report, report.run(&backup)))
+                .collect();
+            dashboard::write_dashboard(&stats, &reports, &mut fs::File::create(&out)?)?;
+            println!("Wrote {:?}", out);
+        }
+        Export::Metrics {
+            backup,
+            out,
+            manifests,
+        } => {
+            let backup = load(backup, options)?;
+            let mut stats = Stats::compute(&backup);
+            stats.count_labels(&backup, labels);
+            stats.evaluate_metrics(&backup, metrics);
+            if manifests {
+                println!("Scanning manifests...");
+                stats.scan_manifests(&backup);
+            }
+            openmetrics::write_metrics(&stats, &mut fs::File::create(&out)?)?;
+            println!("Wrote {:?}", out);
+        }
+        Export::Bundle { backup, out } => {
+            let backup = load(backup, options)?;
+            let redactor = redactor(redact, &backup);
+            println!(
+                "{}",
+                bundle_summary(&bundle::write_bundle(&backup, &out, &redactor)?, &out)
+            );
+        }
+        Export::AssetLinks { backup, out } => {
+            let backup = load(backup, options)?;
+            let redactor = redactor(redact, &backup);
+            let links = asset_links::link_assets(&backup, &out, naming, &redactor)?;
+            println!(
+                "Linked {} assets for {} records in {:?}, {} missing",
+                links.links, links.records, out, links.missing
+            );
+        }
+        Export::InventoryTree {
+            backup,
+            out,
+            resume,
+        } => {
+            let backup = load(backup, options)?;
+            let summary = inventory_tree::export_inventory_tree(
+                &backup,
+                &out,
+                &redactor(redact, &backup),
+                resume,
+            )?;
+            print!("{}", render::export_summary(&summary));
+            println!("Manifest in {:?}", out.join(queue::MANIFEST));
+        }
+        Export::Media {
+            backup,
+            out,
+            resume,
+        } => {
+            for feature in ["images", "audio"] {
+                if let Err(e) = features::require(feature) {
+                    println!("Skipping {}: {}", feature, e);
+                }
+            }
+            let backup = load(backup, options)?;
+            let summary =
+                media::export_media(&backup, &out, &redactor(redact, &backup), naming, resume)?;
+            print!("{}", render::export_summary(&summary));
+            println!("Manifest in {:?}", out.join(queue::MANIFEST));
+        }
+        Export::Text { backup, out } => {
+            let backup = load(backup, options)?;
+            let fields = text::text_fields(&backup);
+            let mut file = io::BufWriter::new(fs::File::create(&out)?);
+            text_corpus::write_corpus(&fields, &redactor(redact, &backup), &mut file)?;
+            file.flush()?;
+            println!("Wrote {} text fields to {:?}", fields.len(), out);
+        }
+        Export::Messages {
+            backup,
+            out,
+            format,
+        } => {
+            let backup = load(backup, options)?;
+            let summary =
+                messages::export_messages(&backup, &out, &redactor(redact, &backup), format, zone)?;
+            println!(
+                "Wrote {} conversations with {} messages to {:?}, copying {} assets ({} missing)",
+                summary.transcripts, summary.messages, out, summary.assets, summary.missing
+            );
+        }
+        Export::Voice {
+            backup,
+            out,
+            transcribe,
+        } => {
+            let transcriber = match (transcribe, &config.whisper) {
+                (false, _) => None,
+                (true, None) => {
+                    return Err(
+                        "transcribing needs a [whisper] section with a model in the config".into(),
+                    )
+                }
+                (true, Some(whisper)) => Some(whisper.transcriber()?),
+            };
+            let backup = load(backup, options)?;
+            let summary =
+                voice::export_voice(&backup, &out, &redactor(redact, &backup), transcriber)?;
+            print!("{}", render::voice_export(&summary));
+        }
+        #[cfg(feature = "sqlite")]
+        Export::Sqlite {
+            backup,
+            out,
+            chunk_rows,
+            chunk_mb,
+        } => {
+            use store::export::sqlite::{self, Chunking};
+            let summary = if chunk_rows.is_some() || chunk_mb.is_some() {
+                let chunking = Chunking {
+                    max_rows: chunk_rows,
+                    max_bytes: chunk_mb.map(|mb| mb << 20),
+                };
+                let messages = Backup::open_with(backup.clone(), options)?;
+                let rest = LoadOptions {
+                    skip_messages: true,
+                    ..options.clone()
+                };
+                let backup = load(backup, &rest)?;
+                let redactor = redactor(redact, &backup);
+                let (summary, index) =
+                    sqlite::export_sqlite_chunked(&backup, &messages, &out, &redactor, chunking)?;
+                println!("Wrote {} message chunks", index.chunks.len());
+                summary
+            } else {
+                let backup = load(backup, options)?;
+                sqlite::export_sqlite(&backup, &out, &redactor(redact, &backup))?
+            };
+            println!(
+                "Wrote {:?} with {} accounts, {} records, {} messages, {} contacts, {} groups and {} variables",
+                out,
+                summary.accounts,
+                summary.records,
+                summary.messages,
+                summary.contacts,
+                summary.groups,
+                summary.variables
+            );
+        }
+        #[cfg(not(feature = "sqlite"))]
+        Export::Sqlite { .. } => unavailable("sqlite"),
+        Export::Screenshots { backup, out } => {
+            let backup = load(backup, options)?;
+            let redactor = redactor(redact, &backup);
+            let mut worlds = screenshots::photos_by_world(screenshots::photos(&backup));
+            for world in &mut worlds {
+                world.redact(&redactor);
+            }
+            let mut file = io::BufWriter::new(fs::File::create(&out)?);
+            serde_json::to_writer_pretty(&mut file, &worlds)?;
+            file.flush()?;
+            let photos: usize = worlds.iter().map(|w| w.photos.len()).sum();
+            println!(
+                "Wrote {} photos in {} worlds to {:?}",
+                photos,
+                worlds.len(),
+                out
+            );
+        }
+        Export::Calendar { backup, out } => {
+            let backup = load(backup, options)?;
+            let aliases = Aliases::build(&backup, aliases);
+            let redactor = redactor(redact, &backup);
+            let mut events = calendar::history_events(&backup, &aliases);
+            for event in &mut events {
+                event.redact(&redactor);
+            }
+            let mut file = io::BufWriter::new(fs::File::create(&out)?);
+            calendar::write_ics(&events, &mut file)?;
+            file.flush()?;
+            println!("Wrote {} events to {:?}", events.len(), out);
+        }
+        Export::Contacts {
+            backup,
+            format,
+            out,
+        } => {
+            let backup = load(backup, options)?;
+            let aliases = Aliases::build(&backup, aliases);
+            let redactor = redactor(redact, &backup);
+            let mut cards = contact_cards::contact_cards(&backup, &aliases);
+            for card in &mut cards {
+                card.redact(&redactor);
+            }
+            let out = out.unwrap_or_else(|| format!("contacts.{}", format.extension()).into());
+            let mut file = io::BufWriter::new(fs::File::create(&out)?);
+            contact_cards::write_cards(&cards, format, &mut file)?;
+            file.flush()?;
+            println!("Wrote {} contacts to {:?}", cards.len(), out);
+        }
+        Export::InventoryStructure {
+            backup,
+            format,
+            out,
+        } => {
+            let backup = load(backup, options)?;
+            let structures =
+                inventory_structure::inventory_structures(&backup, &redactor(redact, &backup));
+            let out =
+                out.unwrap_or_else(|| format!("inventory-structure.{}", format.extension()).into());
+            let mut file = io::BufWriter::new(fs::File::create(&out)?);
+            inventory_structure::write_structures(&structures, format, &mut file)?;
+            file.flush()?;
+            println!("Wrote {} inventories to {:?}", structures.len(), out);
+        }
+        Export::SlotGraph {
+            backup,
+            record,
+            format,
+            out,
+        } => {
+            let backup = load(backup, options)?;
+            let manifest = record_manifest(&backup, &record)?;
+            let mut graph = SlotGraph::build(&manifest);
+            graph.redact(&redactor(redact, &backup));
+            graph.write(format, &mut fs::File::create(&out)?)?;
+            println!("Wrote {:?}", out);
+        }
+    }
+    Ok(())
+}
+
+// A command whose feature this build lacks says so, rather than failing with a Debug dump.
+#[cfg(any(not(feature = "server"), not(feature = "sqlite")))]
+fn unavailable(feature: &'static str) -> ! {
+    eprintln!("{}", features::MissingFeature(feature));
+    std::process::exit(2);
+}
+
+// The account and file of every record given by ID, or all of them when none are, that
+// the filter lets through.
+fn select_records(
+    backup: &Backup,
+    records: &[String],
+    filter: &RecordFilter,
+) -> Vec<(RcStr, RcStr)> {
+    backup
+        .accounts
+        .iter()
+        .flat_map(|(name, account)| account.records.iter().map(move |r| (name, r)))
+        .filter(|(name, (_, rec))| {
+            (records.is_empty() || records.iter().any(|id| id == rec.id.as_str()))
+                && filter.matches(name, rec)
+        })
+        .map(|(name, (file, _))| (name.clone(), file.clone()))
+        .collect()
+}
+
+fn tag(op: Tag, options: &LoadOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let (adding, args) = match op {
+        Tag::Add(args) => (true, args),
+        Tag::Remove(args) => (false, args),
+    };
+    let filter = RecordFilter::from(args.filter);
+    if args.records.is_empty() && filter.is_empty() {
+        return Err("pick the records to tag with --record or a find filter".into());
+    }
+    let backup = load(args.backup, options)?;
+    let selected = select_records(&backup, &args.records, &filter);
+    let tags: Vec<_> = args.tags.into_iter().map(RcStr::new).collect();
+    let changes = if adding {
+        edit::tag_changes(&backup, &selected, &tags, &[])
+    } else {
+        edit::tag_changes(&backup, &selected, &[], &tags)
+    };
+    for change in &changes {
+        println!("{}", change);
+    }
+    if !args.dry_run && !changes.is_empty() {
+        let _lock = backup.lock()?;
+        edit::apply(&backup.root, &changes, &args.history.into())?;
+    }
+    println!(
+        "{} {} of {} records picked",
+        if args.dry_run {
+            "Would change"
+        } else {
+            "Changed"
+        },
+        changes.len(),
+        selected.len()
+    );
+    Ok(())
+}
+
+fn inv(op: Inv, options: &LoadOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let (backup, dry_run, history, changes) = match op {
+        Inv::Mkdir {
+            backup: root,
+            account,
+            path,
+            dry_run,
+            history,
+        } => {
+            let backup = load(root, options)?;
+            let changes = edit::mkdir_changes(&backup, &account, &edit::parse_path(&path))?;
+            (backup, dry_run, history, changes)
+        }
+        Inv::Move {
+            backup: root,
+            record,
+            path,
+            dry_run,
+            history,
+        } => {
+            let backup = load(root, options)?;
+            let changes = edit::move_changes(&backup, &record, &edit::parse_path(&path))?;
+            (backup, dry_run, history, changes)
+        }
+        Inv::Import {
+            backup: root,
+            structure,
+            account,
+            from,
+            dry_run,
+            history,
+        } => {
+            let structures =
+                inventory_structure::read_structures(&fs::read_to_string(&structure)?)?;
+            let from = from.as_ref().unwrap_or(&account);
+            let picked = match structures.as_slice() {
+                [only] => Some(only),
+                all => all.iter().find(|s| s.account.as_str() == from),
+            };
+            let Some(picked) = picked else {
+                return Err(format!(
+                    "no account {} in {:?}, pick one with --from",
+                    from, structure
+                )
+                .into());
+            };
+            let backup = load(root, options)?;
+            let import = inventory_structure::import_changes(&backup, &account, picked)?;
+            if !import.missing.is_empty() {
+                println!(
+                    "{} records of the structure aren't in {}",
+                    import.missing.len(),
+                    account
+                );
+            }
+            (backup, dry_run, history, import.changes)
+        }
+    };
+    for change in &changes {
+        println!("{}", change);
+    }
+    if changes.is_empty() {
+        println!("Nothing to change");
+    } else if !dry_run {
+        let _lock = backup.lock()?;
+        edit::apply(&backup.root, &changes, &history.into())?;
+    }
+    Ok(())
+}
+
+fn report_by_name(name: &str) -> Result<&'static dyn report::Report, String> {
+    report::find(name).ok_or_else(|| {
+        format!(
+            "unknown report, expected one of: {}",
+            report::names().collect::<Vec<_>>().join(", ")
+        )
+    })
+}
+
+fn record_manifest(backup: &Backup, record: &str) -> Result<Manifest, Box<dyn std::error::Error>> {
+    // account/id picks one copy of a group record, a bare ID the first account's.
+    let rec = match record.parse::<RecordHandle>() {
+        Ok(handle) => handle.resolve(backup),
+        Err(_) => backup.record(record),
+    };
+    let rec = rec.ok_or_else(|| format!("no record {} in the backup", record))?;
+    let Some(AssetUri::SZBson(asset)) = &rec.asset_uri else {
+        return Err(format!("record {} has no manifest", record).into());
+    };
+    Ok(asset.open(backup)?)
+}
+
+// Records a find drops are added to dropped, so a caller reusing the backup can put them back.
+fn run_stage(
+    stage: Stage,
+    backup: &mut Backup,
+    labels: &Labels,
+    metrics: &[MetricDef],
+    out: &mut dyn Write,
+    dropped: &mut Vec<(RcStr, RcStr, Record)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match stage {
+        Stage::Find(filter) => {
+            let filter = RecordFilter::from(filter);
+            dropped.extend(backup.retain_records(|account, rec| filter.matches(account, rec)));
+            let kept: usize = backup.accounts.values().map(|a| a.records.len()).sum();
+            writeln!(out, "{} records selected", kept)?;
+        }
+        Stage::Stats => {
+            let mut stats = Stats::compute(backup);
+            stats.count_labels(backup, labels);
+            stats.evaluate_metrics(backup, metrics);
+            write!(out, "{}", render::stats(&stats))?;
+        }
+        Stage::Verify => write!(out, "{}", render::verify(&VerifyReport::check(backup)))?,
+        Stage::Report { name } => write!(out, "{}", name.run(backup).render(ReportFormat::Text))?,
+        Stage::Export(StageExport::Bundle { out: dir, redact }) => {
+            let redactor = redactor(redact, backup);
+            let bundle = bundle::write_bundle(backup, &dir, &redactor)?;
+            writeln!(out, "{}", bundle_summary(&bundle, &dir))?
+        }
+    }
+    Ok(())
+}
+
+fn default_socket() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .map(|d| d.join("neos-full-statbox").join("daemon.sock"))
+}
+
+#[cfg(unix)]
+fn daemon(
+    root: PathBuf,
+    socket: &Path,
+    options: &LoadOptions,
+    labels: &Labels,
+    metrics: &[MetricDef],
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Left behind by a daemon that didn't shut down cleanly.
+    if socket.exists() {
+        fs::remove_file(socket)?;
+    }
+    if let Some(dir) = socket.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut backup = load(root.clone(), options)?;
+    let mut fingerprint = Fingerprint::of(&root)?;
+    let listener = std::os::unix::net::UnixListener::bind(socket)?;
+    println!("Listening on {:?}", socket);
+
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let mut line = String::new();
+        if io::BufRead::read_line(&mut io::BufReader::new(&stream), &mut line).is_err() {
+            continue;
+        }
+        let line = line.trim();
+
+        let mut now = Fingerprint::of(&root)?;
+        let change = match now.change_since(&fingerprint) {
+            _ if line == "reload" => Change::Backup,
+            change => change,
+        };
+        match change {
+            Change::None => {}
+            Change::Assets => backup.invalidate_asset_index(),
+            // A backup being written mid-sync may not load, keep answering from the old one
+            // and try again next time.
+            Change::Backup => match load(root.clone(), options) {
+                Ok(reloaded) => backup = reloaded,
+                Err(e) => {
+                    writeln!(stream, "reload failed, answering from the last load: {}", e).ok();
+                    // Left stale so the next query tries again.
+                    now = fingerprint.clone();
+                }
+            },
+        }
+        fingerprint = now;
+        if line == "reload" {
+            writeln!(stream, "reloaded").ok();
+            continue;
+        }
+        // One client hanging up or a failing stage only loses that answer.
+        if let Err(e) = answer(line, &mut backup, labels, metrics, &mut stream) {
+            writeln!(stream, "error: {}", e).ok();
+        }
+    }
+    Ok(())
+}
+
+// Runs a pipeline against the shared backup, putting back whatever its finds dropped.
+#[cfg(unix)]
+fn answer(
+    pipeline: &str,
+    backup: &mut Backup,
+    labels: &Labels,
+    metrics: &[MetricDef],
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stages = match parse_pipeline(pipeline) {
+        Ok(stages) => stages,
+        Err(e) => return Ok(out.write_all(e.as_bytes())?),
+    };
+    let mut dropped = Vec::new();
+    let result = stages.into_iter().try_for_each(|(line, stage)| {
+        writeln!(out, "== {}", line)?;
+        run_stage(stage, backup, labels, metrics, out, &mut dropped)
+    });
+    backup.restore_records(dropped);
+    result
+}
+
+fn parse_pipeline(pipeline: &str) -> Result<Vec<(String, Stage)>, String> {
+    pipeline
+        .split('|')
+        .map(|line| {
+            let words = split_words(line)?;
+            let stage = StageLine::try_parse_from(&words).map_err(|e| e.to_string())?;
+            Ok((line.trim().to_owned(), stage.stage))
+        })
+        .collect()
+}
+
+// Whitespace separated, with double quotes around words that contain spaces.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("error: unclosed quote in {:?}\n", line.trim()));
+    }
+    words.extend(word);
+    if words.is_empty() {
+        return Err("error: empty pipeline stage\n".to_owned());
+    }
+    Ok(words)
+}
+
+fn bundle_summary(bundle: &bundle::Bundle, out: &Path) -> String {
+    format!(
+        "Copied {} records and {} assets ({}) to {:?}, {} assets missing",
+        bundle.records,
+        bundle.assets,
+        render::human_bytes(bundle.bytes),
+        out,
+        bundle.missing
+    )
+}
+
+fn record_type(s: &str) -> Result<RecordType, String> {
+    serde_json::from_value(serde_json::Value::String(s.to_owned()))
+        .map_err(|_| format!("unknown record type {:?}", s))
+}
+
+fn converters(external: &[ExternalConverter]) -> Converters {
+    let mut converters = Converters::builtin();
+    for converter in external {
+        converters.register(Box::new(converter.clone()));
+    }
+    converters
+}
+
+// The dashboard and metrics only carry aggregates, so only exports with
+// per-user or free text content need to consult this.
+fn redactor(profile: RedactionProfile, backup: &Backup) -> Redactor {
+    Redactor::new(profile, backup.accounts.keys().cloned())
+}
+
+fn load(root: PathBuf, options: &LoadOptions) -> Result<Backup, store::backup::Error> {
+    if let Some(lock) = lock::current(&root) {
+        eprintln!(
+            "Warning: {} is writing to the backup, what's read may be half updated",
+            lock.holder
+        );
+    }
     println!("Parsing backup...");
-    let backup = Backup::load(PathBuf::from_str("F:\\neos backup 2").unwrap()).unwrap();
-    println!("Parsing backup. done!");
+    let backup = Backup::load_with(root, options)?;
+    println!("Parsing backup. done! ({})", backup.format);
+    Ok(backup)
+}
 
-    println!("Scanning assets...");
+fn read_7zbson<Output: serde::de::DeserializeOwned>(
+    assets_dir: PathBuf,
+    asset: &str,
+    limits: ManifestLimits,
+) -> Result<Output, store::backup::Error> {
+    let mut b = Backup::default();
+    b.assets_dir = assets_dir;
+    b.limits = limits;
+
+    let asset = SZBson(RcStr::new(asset.into()));
+
+    asset.open(&b)
+}
+
+// Calls back with a debug bundle for every manifest that fails to parse, or the
+// error that stopped one from being captured.
+fn scan_for_invalid<F>(
+    backup: &Backup,
+    only: Option<&str>,
+    mut f: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(&Record, Result<DebugBundle, store::backup::Error>) -> io::Result<()>,
+{
     for val in backup.accounts.values() {
         for rec in val.records.values() {
+            if only.is_some_and(|id| id != rec.id.as_str()) {
+                continue;
+            }
             if let Some(AssetUri::SZBson(asset)) = &rec.asset_uri {
-                println!("Opening {:?}", asset);
-                let res: Result<Manifest, _> = asset.open(&backup);
-                if let Err(e) = res {
-                    println!(
-                        "Error parsing {:?}, dumping to dump.ron: {:#?}",
-                        rec.asset_uri, e
-                    );
-                    let res: Bson = asset.open(&backup).unwrap();
-                    writeln!(fs::File::create("dump.ron").unwrap(), "{:#?}", res).unwrap();
-                    return;
+                match DebugBundle::capture(backup, asset, Some(rec.id.clone())) {
+                    Ok(None) => {}
+                    Ok(Some(bundle)) => f(rec, Ok(bundle))?,
+                    Err(e) => f(rec, Err(e))?,
                 }
             }
         }
     }
-}
\ No newline at end of file
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use super::{backup::Backup, report::sessions::captured_sessions, RcStr};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+// User IDs are stable, usernames are not. Everything naming a user goes through here so
+// reports agree on one current name and searches match any name the user went by.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UserNames {
+    pub current: RcStr,
+    pub former: BTreeSet<RcStr>,
+    // The current name came from the user's overrides rather than the backup.
+    pub overridden: bool,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Aliases {
+    pub users: BTreeMap<RcStr, UserNames>,
+}
+
+#[derive(Default)]
+struct Sightings {
+    // A contact's username is as of the backup, so it beats anything timestamped.
+    contact: Option<RcStr>,
+    latest: Option<(Option<DateTime<Utc>>, RcStr)>,
+    all: BTreeSet<RcStr>,
+}
+
+impl Sightings {
+    fn saw(&mut self, name: &RcStr, time: Option<DateTime<Utc>>) {
+        if name.is_empty() {
+            return;
+        }
+        self.all.insert(name.clone());
+        if self.latest.as_ref().is_none_or(|(seen, _)| time > *seen) {
+            self.latest = Some((time, name.clone()));
+        }
+    }
+}
+
+impl Aliases {
+    // Names come from contacts, captured sessions (including invites in messages), record
+    // owners and submissions. Overrides map user IDs to the name to show.
+    pub fn build(backup: &Backup, overrides: &BTreeMap<String, String>) -> Self {
+        let mut sightings = BTreeMap::<RcStr, Sightings>::new();
+        let mut saw = |id: &RcStr, name: &RcStr, time| {
+            sightings.entry(id.clone()).or_default().saw(name, time)
+        };
+
+        for account in backup.accounts.values() {
+            for rec in account.records.values() {
+                saw(&rec.owner_id, &rec.owner_name, rec.last_modification_time);
+                for submission in &rec.submissions {
+                    saw(
+                        &submission.submitted_by_id,
+                        &submission.submitted_by_name,
+                        Some(submission.submission_time),
+                    );
+                }
+            }
+        }
+        for session in captured_sessions(backup).values() {
+            saw(
+                &session.host_user_id,
+                &session.host_username,
+                Some(session.last_update),
+            );
+            for user in &session.session_users {
+                saw(&user.user_id, &user.username, Some(session.last_update));
+            }
+        }
+        for account in backup.accounts.values() {
+            for contact in account.contacts.values() {
+                let user = sightings.entry(contact.id.clone()).or_default();
+                user.saw(&contact.friend_username, None);
+                user.contact = Some(contact.friend_username.clone());
+                for name in contact
+                    .alternate_usernames
+                    .iter()
+                    .flat_map(|n| n.split(','))
+                {
+                    user.saw(&name.trim().to_owned().into(), None);
+                }
+            }
+        }
+
+        let mut users: BTreeMap<RcStr, UserNames> = sightings
+            .into_iter()
+            .filter_map(|(id, seen)| {
+                let current = seen.contact.or(seen.latest.map(|(_, name)| name))?;
+                let mut former = seen.all;
+                former.remove(&current);
+                Some((
+                    id,
+                    UserNames {
+                        current,
+                        former,
+                        overridden: false,
+                    },
+                ))
+            })
+            .collect();
+        for (id, name) in overrides {
+            let user = users.entry(id.clone().into()).or_default();
+            let name: RcStr = name.clone().into();
+            if !user.current.is_empty() && user.current != name {
+                user.former.insert(user.current.clone());
+            }
+            user.former.remove(&name);
+            user.current = name;
+            user.overridden = true;
+        }
+        Self { users }
+    }
+
+    pub fn current(&self, id: &RcStr) -> Option<&RcStr> {
+        self.users.get(id).map(|u| &u.current)
+    }
+
+    // The current name, or what the caller had when the user is unknown.
+    pub fn display(&self, id: &RcStr, fallback: &RcStr) -> RcStr {
+        self.current(id).unwrap_or(fallback).clone()
+    }
+
+    // Case-insensitive substring match against the ID and every name the user had.
+    pub fn matches(&self, id: &RcStr, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let hit = |s: &str| s.to_lowercase().contains(&query);
+        hit(id)
+            || self
+                .users
+                .get(id)
+                .is_some_and(|u| hit(&u.current) || u.former.iter().any(|name| hit(name)))
+    }
+
+    pub fn search<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> impl Iterator<Item = (&'a RcStr, &'a UserNames)> {
+        self.users
+            .iter()
+            .filter(move |(id, _)| self.matches(id, query))
+    }
+}
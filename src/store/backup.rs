@@ -1,16 +1,26 @@
-use super::RcStr;
+use super::{
+    format::{self, FileKind, FormatVersion},
+    ignore::IgnoreRules,
+    limits::{LimitExceeded, ManifestLimits},
+    lock::{BackupLock, LockError},
+    sample::{ManifestSample, Sample},
+    timings::{self, Phase},
+    warnings::{WarningKind, Warnings},
+    RcStr,
+};
 use chrono::{DateTime, Utc};
-use core::panic;
+use rayon::prelude::*;
 use serde::{
     de::{DeserializeOwned, Visitor},
     Deserialize, Serialize,
 };
 use std::{
-    collections::BTreeMap,
-    ffi::OsStr,
-    fs::File,
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
     io::{self, BufReader, Read, Write},
     path::{Path, PathBuf},
+    str::FromStr,
+    sync::{PoisonError, RwLock},
 };
 use thiserror::Error;
 
@@ -24,96 +34,437 @@ pub enum Error {
     SerdeBson(bson::de::Error, RcStr),
     #[error("Serde_bson_raw: {0} ({1})")]
     SerdeBsonRaw(bson::raw::Error, RcStr),
+    #[error("Serde_bson encoding: {0}")]
+    SerdeBsonEncode(#[from] bson::ser::Error),
     #[error("Lzma: {0}")]
     Lzma(#[from] lzma_rs::error::Error),
+    #[error("Config: {0} ({1})")]
+    Config(toml::de::Error, PathBuf),
+    #[error(transparent)]
+    LimitExceeded(#[from] LimitExceeded),
+    #[error("unknown folder in an account: {}", .0.display())]
+    UnknownFolder(PathBuf),
+    #[error("bad record ID {0:?}, it has to be a file name in the Records folder")]
+    BadRecordId(String),
 }
 
-fn os_to_cow(s: &OsStr) -> RcStr {
-    s.to_string_lossy().into_owned().into()
+#[derive(Error, Debug)]
+#[error("asset store unavailable, the backup has no Assets folder")]
+pub struct AssetStoreUnavailable;
+
+#[derive(Error, Debug)]
+#[error("no asset {0}")]
+pub struct MissingAsset(pub RcStr);
+
+// What kind of problem an error is, for tools that need to branch on it rather than on
+// its text.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCategory {
+    // Reading or writing failed, whatever was being read.
+    Io,
+    // Well-formed data that doesn't fit the model, like a record missing a field.
+    Schema,
+    // Data that isn't well-formed, like truncated JSON or an asset that won't decompress.
+    Corruption,
+    MissingAsset,
+    // Something this build or this tool doesn't handle, or won't, like a manifest past
+    // the limits it was opened with.
+    Unsupported,
 }
 
-trait FromDisk: Sized {
-    fn from_disk(p: PathBuf) -> Result<Self, Error>;
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    // Something is missing, the rest is unaffected.
+    Warning,
+    // Something couldn't be read at all.
+    Error,
 }
 
-impl<T: FromDisk> FromDisk for BTreeMap<RcStr, T> {
-    fn from_disk(p: PathBuf) -> Result<Self, Error> {
-        let dir = p.read_dir()?;
-        let mut map = BTreeMap::<RcStr, T>::default();
-        for dir in dir.into_iter() {
-            let dir = dir?;
-            if !dir
-                .path()
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-                .ends_with(".Storage.json")
-            {
-                let name = os_to_cow(&dir.path().file_stem().unwrap());
-                let item = T::from_disk(dir.path())?;
-                map.insert(name, item);
+impl ErrorCategory {
+    pub fn severity(self) -> Severity {
+        match self {
+            ErrorCategory::MissingAsset | ErrorCategory::Unsupported => Severity::Warning,
+            ErrorCategory::Io | ErrorCategory::Schema | ErrorCategory::Corruption => {
+                Severity::Error
             }
         }
-        Ok(map)
     }
 }
 
-impl<T: FromDisk> FromDisk for Vec<T> {
-    fn from_disk(p: PathBuf) -> Result<Self, Error> {
-        let dir = p.read_dir()?;
-        let mut vec = Vec::<T>::default();
-        for dir in dir.into_iter() {
+impl Error {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::Io(e) => io_category(e),
+            Error::SerdeJson(e, _) => match e.classify() {
+                serde_json::error::Category::Io => ErrorCategory::Io,
+                serde_json::error::Category::Data => ErrorCategory::Schema,
+                _ => ErrorCategory::Corruption,
+            },
+            Error::SerdeBson(..) | Error::SerdeBsonEncode(_) | Error::Config(..) => {
+                ErrorCategory::Schema
+            }
+            Error::SerdeBsonRaw(..) | Error::Lzma(_) => ErrorCategory::Corruption,
+            Error::LimitExceeded(_) | Error::UnknownFolder(_) | Error::BadRecordId(_) => {
+                ErrorCategory::Unsupported
+            }
+        }
+    }
+
+    // Stable across versions and never translated, unlike the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Io(e) => io_code(e),
+            Error::SerdeJson(e, _) => match e.classify() {
+                serde_json::error::Category::Io => "io",
+                serde_json::error::Category::Data => "json-schema",
+                _ => "json-syntax",
+            },
+            Error::SerdeBson(..) => "bson-schema",
+            Error::SerdeBsonRaw(..) => "bson-malformed",
+            Error::SerdeBsonEncode(_) => "bson-encode",
+            Error::Lzma(_) => "lzma-corrupt",
+            Error::Config(..) => "config-invalid",
+            Error::LimitExceeded(_) => "limit-exceeded",
+            Error::UnknownFolder(_) => "unknown-folder",
+            Error::BadRecordId(_) => "bad-record-id",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.category().severity()
+    }
+}
+
+fn io_category(e: &io::Error) -> ErrorCategory {
+    match e.get_ref() {
+        Some(inner) if inner.is::<MissingAsset>() || inner.is::<AssetStoreUnavailable>() => {
+            ErrorCategory::MissingAsset
+        }
+        _ if e.kind() == io::ErrorKind::Unsupported => ErrorCategory::Unsupported,
+        _ => ErrorCategory::Io,
+    }
+}
+
+fn io_code(e: &io::Error) -> &'static str {
+    match e.get_ref() {
+        Some(inner) if inner.is::<MissingAsset>() => "asset-missing",
+        Some(inner) if inner.is::<AssetStoreUnavailable>() => "asset-store-unavailable",
+        _ => match e.kind() {
+            io::ErrorKind::Unsupported => "unsupported",
+            io::ErrorKind::NotFound => "io-not-found",
+            io::ErrorKind::PermissionDenied => "io-permission-denied",
+            _ => "io",
+        },
+    }
+}
+
+// An error as its category, code and message, which unlike Error can be serialized into
+// reports and journals.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorInfo {
+    pub category: ErrorCategory,
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl ErrorInfo {
+    pub fn new(category: ErrorCategory, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            category,
+            code: code.to_owned(),
+            severity: category.severity(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<&Error> for ErrorInfo {
+    fn from(e: &Error) -> Self {
+        Self::new(e.category(), e.code(), e.to_string())
+    }
+}
+
+trait FromDisk: Sized + Send {
+    fn from_disk(p: PathBuf, version: FormatVersion, options: &LoadOptions) -> Result<Self, Error>;
+}
+
+// The files kept next to a record: the game's .Storage.json and the edit log.
+pub(crate) const SIDECARS: [&str; 2] = [".Storage.json", ".Edits.json"];
+
+pub(crate) fn is_sidecar(name: &str) -> bool {
+    SIDECARS.iter().any(|suffix| name.ends_with(suffix))
+}
+
+// The data files or folders in a directory, leaving out the sidecars and ignored files.
+pub(crate) fn data_entries(p: &Path, ignore: &IgnoreRules) -> Result<Vec<PathBuf>, Error> {
+    timings::time(Phase::DirectoryWalk, 0, || {
+        let mut entries = Vec::new();
+        for dir in p.read_dir()? {
             let dir = dir?;
-            if !dir
-                .path()
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-                .ends_with(".Storage.json")
-            {
-                let item = T::from_disk(dir.path())?;
-                vec.push(item);
+            let name = dir.file_name();
+            let name = name.to_string_lossy();
+            if !is_sidecar(&name) && !ignore.is_ignored(&name) {
+                entries.push(dir.path());
             }
         }
-        Ok(vec)
+        Ok(entries)
+    })
+}
+
+// Entries are parsed in parallel, and collected in file name order whatever order they
+// finish or the directory lists them in.
+impl<T: FromDisk> FromDisk for BTreeMap<RcStr, T> {
+    fn from_disk(p: PathBuf, version: FormatVersion, options: &LoadOptions) -> Result<Self, Error> {
+        data_entries(&p, &options.ignore)?
+            .into_par_iter()
+            .map(|path| {
+                let name = options.warnings.name(path.file_stem().unwrap(), &path);
+                let item = T::from_disk(path.clone(), version, options);
+                Ok(options.tolerate(&path, item)?.map(|item| (name, item)))
+            })
+            .filter_map(Result::transpose)
+            .collect()
     }
 }
 
-trait FromFile: DeserializeOwned {}
+impl<T: FromDisk> FromDisk for Vec<T> {
+    fn from_disk(p: PathBuf, version: FormatVersion, options: &LoadOptions) -> Result<Self, Error> {
+        let mut entries = data_entries(&p, &options.ignore)?;
+        entries.sort();
+        entries
+            .into_par_iter()
+            .map(|path| {
+                let item = T::from_disk(path.clone(), version, options);
+                options.tolerate(&path, item)
+            })
+            .filter_map(Result::transpose)
+            .collect()
+    }
+}
+
+pub(crate) trait FromFile: DeserializeOwned + Serialize + Default + Send {
+    const KIND: FileKind;
+}
 
 impl<T> FromDisk for T
 where
     T: FromFile,
 {
-    fn from_disk(p: PathBuf) -> Result<Self, Error> {
-        from_file(p)
+    fn from_disk(p: PathBuf, version: FormatVersion, options: &LoadOptions) -> Result<Self, Error> {
+        from_file(p, version, &options.warnings)
     }
 }
 
-fn from_file<T>(p: PathBuf) -> Result<T, Error>
+// The top-level fields the model has, those of its default serialized.
+fn unknown_fields<T: FromFile>(value: &serde_json::Value) -> Vec<String> {
+    let Some(object) = value.as_object() else {
+        return Vec::new();
+    };
+    let known = serde_json::to_value(T::default()).unwrap_or_default();
+    object
+        .keys()
+        .filter(|key| known.get(key.as_str()).is_none())
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn from_file<T>(
+    p: PathBuf,
+    version: FormatVersion,
+    warnings: &Warnings,
+) -> Result<T, Error>
 where
-    T: DeserializeOwned,
+    T: FromFile,
 {
     let content = std::fs::File::open(&p)?;
+    let bytes = if timings::enabled() {
+        content.metadata()?.len()
+    } else {
+        0
+    };
     let buf_content = std::io::BufReader::new(content);
-    let result = serde_json::from_reader(buf_content).map_err(|e| Error::SerdeJson(e, p))?;
-    Ok(result)
+    super::de::take_dropped();
+    let item = timings::time(Phase::JsonParse, bytes, || {
+        if !version.migrates(T::KIND) && !warnings.checks_unknown_fields() {
+            return serde_json::from_reader(buf_content)
+                .map_err(|e| Error::SerdeJson(e, p.clone()));
+        }
+        let mut value =
+            serde_json::from_reader(buf_content).map_err(|e| Error::SerdeJson(e, p.clone()))?;
+        for dropped in version.migrate(T::KIND, &mut value) {
+            warnings.push(&p, dropped);
+        }
+        if warnings.checks_unknown_fields() {
+            for field in unknown_fields::<T>(&value) {
+                warnings.push(&p, WarningKind::UnknownField { field });
+            }
+        }
+        serde_json::from_value(value).map_err(|e| Error::SerdeJson(e, p.clone()))
+    })?;
+    // A timestamp was dropped without the fix-ups, which are what name it, so the rare file
+    // this happens to is read again for the warning.
+    if super::de::take_dropped() {
+        if let Ok(mut value) = serde_json::from_slice(&std::fs::read(&p)?) {
+            for dropped in format::dropped_timestamps(T::KIND, &mut value) {
+                warnings.push(&p, dropped);
+            }
+        }
+    }
+    Ok(item)
 }
 
-impl FromDisk for Backup {
-    fn from_disk(p: PathBuf) -> Result<Self, Error> {
-        let mut backup = Self::default();
+// Which parts of a backup to read. Accounts are matched by folder name, and an empty
+// include list means all of them; exclusions win over inclusions.
+#[derive(Debug, Default, Clone)]
+pub struct LoadOptions {
+    pub include_accounts: Vec<String>,
+    pub exclude_accounts: Vec<String>,
+    pub skip_messages: bool,
+    pub skip_records: bool,
+    // Searched in order when an asset isn't in the backup's own Assets folder.
+    pub asset_roots: Vec<PathBuf>,
+    // Refuse to take the backup's lock, so nothing opened this way can write to it.
+    pub read_only: bool,
+    // Only open this much of the manifests in manifest scans, extrapolating their totals.
+    pub sample: Option<Sample>,
+    // Files and folders to skip wherever the backup is walked, like sync conflict copies.
+    pub ignore: IgnoreRules,
+    // Where what loading tolerates goes, shared with the loaded Backup.
+    pub warnings: Warnings,
+    // Sniff every asset records refer to without an extension, giving it a proper kind.
+    pub detect_asset_kinds: bool,
+    // How much of a manifest the loaded Backup opens before refusing it.
+    pub limits: ManifestLimits,
+    // Fail on the first file that doesn't parse, instead of leaving it out and listing it
+    // in the Backup's load_issues.
+    pub strict: bool,
+    pub unknown_dirs: UnknownDirPolicy,
+}
+
+// What loading does with a folder in an account it doesn't know, like one a newer Neos
+// added.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnknownDirPolicy {
+    // Fail the load.
+    Error,
+    // Leave it out, with a warning.
+    #[default]
+    Warn,
+    // Keep the JSON files in it as they are, in the Account's unknown_files.
+    Capture,
+}
+
+impl FromStr for UnknownDirPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(UnknownDirPolicy::Error),
+            "warn" => Ok(UnknownDirPolicy::Warn),
+            "capture" => Ok(UnknownDirPolicy::Capture),
+            _ => Err(format!(
+                "unknown folder policy {:?}, expected error, warn or capture",
+                s
+            )),
+        }
+    }
+}
+
+impl LoadOptions {
+    // Leaves out part of what references assets, so a backup loaded this way can't say
+    // what nothing uses.
+    pub fn is_partial(&self) -> bool {
+        !self.include_accounts.is_empty()
+            || !self.exclude_accounts.is_empty()
+            || self.skip_messages
+            || self.skip_records
+    }
 
-        for dir in p.read_dir()?.into_iter() {
+    pub fn wants_account(&self, name: &str) -> bool {
+        (self.include_accounts.is_empty() || self.include_accounts.iter().any(|a| a == name))
+            && !self.exclude_accounts.iter().any(|a| a == name)
+    }
+
+    // None for a file a lenient load leaves out, with a warning. Only parse errors are
+    // tolerated, a file that can't be read still fails the load.
+    pub(crate) fn tolerate<T>(
+        &self,
+        path: &Path,
+        item: Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        match item {
+            Err(e @ Error::SerdeJson(..)) if !self.strict && e.category() != ErrorCategory::Io => {
+                let error = ErrorInfo::from(&e);
+                self.warnings.push(path, WarningKind::SkippedFile { error });
+                Ok(None)
+            }
+            item => item.map(Some),
+        }
+    }
+}
+
+// A file a lenient load left out because it didn't parse.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadIssue {
+    pub path: PathBuf,
+    pub error: ErrorInfo,
+}
+
+impl Backup {
+    fn from_disk(p: PathBuf, version: FormatVersion, options: &LoadOptions) -> Result<Self, Error> {
+        let mut backup = Self {
+            root: p.clone(),
+            format: version,
+            read_only: options.read_only,
+            partial: options.is_partial(),
+            warnings: options.warnings.clone(),
+            limits: options.limits,
+            ..Default::default()
+        };
+        let warned = options.warnings.len();
+
+        for dir in p.read_dir()? {
             let dir = dir?;
 
             if dir.file_name() == "Assets" {
                 backup.assets_dir = dir.path();
-            } else {
-                let (name, acc) = Account::load(dir.path())?;
+            } else if !dir.file_type()?.is_dir()
+                || options
+                    .ignore
+                    .is_ignored(&dir.file_name().to_string_lossy())
+            {
+                // The lock file, or anything else that isn't an account folder.
+                continue;
+            } else if options.wants_account(&dir.file_name().to_string_lossy()) {
+                let (name, acc) = Account::load(dir.path(), version, options)?;
                 backup.accounts.insert(name, acc);
             }
         }
+        backup.metadata_only = backup.assets_dir.as_os_str().is_empty();
+        for dir in &options.asset_roots {
+            backup.add_asset_root(dir.clone());
+        }
+        if let Some(sample) = options.sample {
+            backup.manifest_sample = ManifestSample::new(&backup, sample);
+        }
+        if options.detect_asset_kinds {
+            backup.detect_asset_kinds();
+        }
+        for warning in options.warnings.since(warned) {
+            if let WarningKind::SkippedFile { error } = warning.kind {
+                backup.load_issues.push(LoadIssue {
+                    path: warning.path,
+                    error,
+                });
+            }
+        }
+        backup.load_issues.sort_by(|a, b| a.path.cmp(&b.path));
         Ok(backup)
     }
 }
@@ -121,21 +472,190 @@ impl FromDisk for Backup {
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Backup {
+    // The folder the backup was loaded from, empty for one built in memory.
+    #[serde(skip)]
+    pub root: PathBuf,
     #[serde(skip_serializing)]
     pub assets_dir: PathBuf,
+    // Loaded without an Assets folder, so only fallback roots can supply assets.
+    #[serde(skip)]
+    pub metadata_only: bool,
+    #[serde(skip)]
+    pub format: FormatVersion,
+    #[serde(skip)]
+    pub read_only: bool,
+    // Loaded with accounts, records or messages left out.
+    #[serde(skip)]
+    pub partial: bool,
+    // What loading tolerated, and scans of it add to.
+    #[serde(skip)]
+    pub warnings: Warnings,
+    // Other snapshots or cache folders searched in order when an asset isn't in assets_dir.
+    #[serde(skip)]
+    pub fallback_asset_dirs: Vec<PathBuf>,
+    // Applied to every manifest opened from the backup.
+    #[serde(skip)]
+    pub limits: ManifestLimits,
+    // The files loading left out, empty unless it was lenient.
+    #[serde(skip)]
+    pub load_issues: Vec<LoadIssue>,
+    // Which root (0 being assets_dir) each looked up hash was found in, None if none had it.
+    #[serde(skip)]
+    asset_index: RwLock<BTreeMap<String, Option<usize>>>,
+    // The manifests scans open, all of them unless loaded with a sample.
+    #[serde(skip)]
+    pub manifest_sample: ManifestSample,
     pub accounts: BTreeMap<RcStr, Account>,
 }
 
 impl Backup {
     pub fn load(root: PathBuf) -> Result<Self, Error> {
-        Self::from_disk(root)
+        Self::load_with(root, &LoadOptions::default())
     }
 
-    fn open_asset<P>(&self, id: P) -> Result<File, io::Error>
+    pub fn load_with(root: PathBuf, options: &LoadOptions) -> Result<Self, Error> {
+        let version = FormatVersion::detect(&root)?;
+        Self::from_disk(root, version, options)
+    }
+
+    // Takes the backup's lock for writing to it, failing if it was opened read-only or
+    // something else, like a sync tool, holds the lock.
+    pub fn lock(&self) -> Result<BackupLock, LockError> {
+        if self.read_only {
+            return Err(LockError::ReadOnly);
+        }
+        BackupLock::acquire(&self.root)
+    }
+
+    // Drops every record the predicate rejects, leaving contacts and messages alone.
+    // Returns what was dropped as (account, file name, record) for restore_records.
+    pub fn retain_records<F>(&mut self, mut keep: F) -> Vec<(RcStr, RcStr, Record)>
     where
-        P: AsRef<Path>,
+        F: FnMut(&RcStr, &Record) -> bool,
     {
-        File::open(self.assets_dir.join(id))
+        let mut dropped = Vec::new();
+        for (account_name, account) in &mut self.accounts {
+            let (kept, gone) = std::mem::take(&mut account.records)
+                .into_iter()
+                .partition(|(_, rec)| keep(account_name, rec));
+            account.records = kept;
+            dropped.extend(
+                gone.into_iter()
+                    .map(|(file, rec)| (account_name.clone(), file, rec)),
+            );
+        }
+        dropped
+    }
+
+    pub fn restore_records(&mut self, records: Vec<(RcStr, RcStr, Record)>) {
+        for (account, file, rec) in records {
+            self.accounts
+                .entry(account)
+                .or_default()
+                .records
+                .insert(file, rec);
+        }
+    }
+
+    // Entries in the asset index and their estimated heap bytes, for memory reports.
+    pub fn asset_index_size(&self) -> (usize, usize) {
+        let index = self
+            .asset_index
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let entry = std::mem::size_of::<String>() + std::mem::size_of::<Option<usize>>();
+        let bytes = index.keys().map(|k| entry * 3 / 2 + k.capacity()).sum();
+        (index.len(), bytes)
+    }
+
+    // Forgets where every asset was found, for when the asset directories change.
+    pub fn invalidate_asset_index(&mut self) {
+        self.asset_index
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    pub fn add_asset_root(&mut self, dir: PathBuf) {
+        self.fallback_asset_dirs.push(dir);
+        // Assets missing so far may be in the new root.
+        self.asset_index
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|_, root| root.is_some());
+    }
+
+    pub fn asset_roots(&self) -> impl Iterator<Item = &Path> {
+        let own = (!self.metadata_only).then_some(self.assets_dir.as_path());
+        own.into_iter()
+            .chain(self.fallback_asset_dirs.iter().map(PathBuf::as_path))
+    }
+
+    // False for a metadata-only backup with no fallback roots, where every asset lookup
+    // would fail the same way.
+    pub fn has_asset_store(&self) -> bool {
+        self.asset_roots().next().is_some()
+    }
+
+    // The error for an asset no root holds, telling a missing file apart from there being
+    // nowhere to look.
+    pub fn missing_asset(&self, id: &str) -> io::Error {
+        if self.has_asset_store() {
+            io::Error::new(io::ErrorKind::NotFound, MissingAsset(id.into()))
+        } else {
+            io::Error::new(io::ErrorKind::NotFound, AssetStoreUnavailable)
+        }
+    }
+
+    // The first root holding the asset, remembered in the asset index.
+    pub fn asset_root(&self, id: &str) -> Option<&Path> {
+        let cached = self
+            .asset_index
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(id)
+            .copied();
+        let root = match cached {
+            Some(root) => root,
+            None => {
+                let root = self.asset_roots().position(|dir| dir.join(id).is_file());
+                self.asset_index
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(id.to_owned(), root);
+                root
+            }
+        };
+        root.and_then(|root| self.asset_roots().nth(root))
+    }
+
+    pub fn asset_path(&self, id: &str) -> Option<PathBuf> {
+        self.asset_root(id).map(|dir| dir.join(id))
+    }
+
+    pub(crate) fn open_asset(&self, id: &str) -> Result<File, io::Error> {
+        match self.asset_path(id) {
+            Some(path) => File::open(path),
+            None => Err(self.missing_asset(id)),
+        }
+    }
+
+    pub fn record(&self, id: &str) -> Option<&Record> {
+        self.accounts
+            .values()
+            .find_map(|account| account.records.get(&RcStr::new(id.to_owned())))
+    }
+
+    pub fn sniff_asset(&self, id: &str) -> Result<Option<&'static str>, io::Error> {
+        let mut header = Vec::with_capacity(super::sniff::SNIFF_LEN);
+        self.open_asset(id)?
+            .take(super::sniff::SNIFF_LEN as u64)
+            .read_to_end(&mut header)?;
+        Ok(super::sniff::sniff(&header))
+    }
+
+    pub fn has_asset(&self, id: &str) -> bool {
+        self.asset_root(id).is_some()
     }
 }
 
@@ -149,73 +669,192 @@ pub struct Account {
     pub records: BTreeMap<RcStr, Record>,
     pub variable_definitions: BTreeMap<RcStr, VariableDefinition>,
     pub variables: BTreeMap<RcStr, Variable>,
+    // What was in folders loading didn't know, when told to capture them, by their path
+    // in the account folder like Storage/abc.json.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub unknown_files: BTreeMap<String, serde_json::Value>,
 }
 
 impl Account {
-    fn load(root: PathBuf) -> Result<(RcStr, Self), Error> {
-        let name = os_to_cow(root.file_name().unwrap());
+    pub(crate) fn load(
+        root: PathBuf,
+        version: FormatVersion,
+        options: &LoadOptions,
+    ) -> Result<(RcStr, Self), Error> {
+        let name = options.warnings.name(root.file_name().unwrap(), &root);
         let mut acc = Self::default();
-        for dir in root.read_dir()?.into_iter() {
+        let ignore = &options.ignore;
+        for dir in root.read_dir()? {
             let dir = dir?;
-            match dir.file_name().to_str().unwrap() {
-                "Contacts" => acc.contacts = BTreeMap::<RcStr, Contact>::from_disk(dir.path())?,
+            if ignore.is_ignored(&dir.file_name().to_string_lossy()) {
+                continue;
+            }
+            match options
+                .warnings
+                .name(&dir.file_name(), &dir.path())
+                .as_str()
+            {
+                "Contacts" => {
+                    acc.contacts =
+                        BTreeMap::<RcStr, Contact>::from_disk(dir.path(), version, options)?
+                }
                 "GroupMembers" => {
-                    acc.group_members =
-                        BTreeMap::<RcStr, BTreeMap<RcStr, GroupMember>>::from_disk(dir.path())?
+                    acc.group_members = BTreeMap::<RcStr, BTreeMap<RcStr, GroupMember>>::from_disk(
+                        dir.path(),
+                        version,
+                        options,
+                    )?
                 }
-                "Groups" => acc.groups = BTreeMap::<RcStr, Group>::from_disk(dir.path())?,
+                "Groups" => {
+                    acc.groups = BTreeMap::<RcStr, Group>::from_disk(dir.path(), version, options)?
+                }
+                "Messages" if options.skip_messages => {}
                 "Messages" => {
-                    acc.messages = BTreeMap::<RcStr, Vec<Message>>::from_disk(dir.path())?
+                    acc.messages =
+                        BTreeMap::<RcStr, Vec<Message>>::from_disk(dir.path(), version, options)?
+                }
+                "Records" if options.skip_records => {}
+                "Records" => {
+                    acc.records =
+                        BTreeMap::<RcStr, Record>::from_disk(dir.path(), version, options)?
                 }
-                "Records" => acc.records = BTreeMap::<RcStr, Record>::from_disk(dir.path())?,
                 "VariableDefinitions" => {
-                    acc.variable_definitions =
-                        BTreeMap::<RcStr, VariableDefinition>::from_disk(dir.path())?
+                    acc.variable_definitions = BTreeMap::<RcStr, VariableDefinition>::from_disk(
+                        dir.path(),
+                        version,
+                        options,
+                    )?
+                }
+                "Variables" => {
+                    acc.variables =
+                        BTreeMap::<RcStr, Variable>::from_disk(dir.path(), version, options)?
                 }
-                "Variables" => acc.variables = BTreeMap::<RcStr, Variable>::from_disk(dir.path())?,
-                _ => panic!("Unknown folder in backup area!"),
+                _ => match options.unknown_dirs {
+                    UnknownDirPolicy::Error => return Err(Error::UnknownFolder(dir.path())),
+                    UnknownDirPolicy::Warn => options
+                        .warnings
+                        .push(&dir.path(), WarningKind::UnknownFolder),
+                    UnknownDirPolicy::Capture => {
+                        acc.capture(&root, dir.path(), options)?;
+                    }
+                },
             }
         }
         Ok((name, acc))
     }
+
+    // Reads every file under the path as JSON into unknown_files. Those that aren't are
+    // load issues like any other file that doesn't parse.
+    fn capture(&mut self, root: &Path, path: PathBuf, options: &LoadOptions) -> Result<(), Error> {
+        let mut pending = vec![path];
+        while let Some(path) = pending.pop() {
+            if path.is_dir() {
+                for entry in path.read_dir()? {
+                    let entry = entry?;
+                    if !options
+                        .ignore
+                        .is_ignored(&entry.file_name().to_string_lossy())
+                    {
+                        pending.push(entry.path());
+                    }
+                }
+                continue;
+            }
+            let value = serde_json::from_slice(&fs::read(&path)?)
+                .map_err(|e| Error::SerdeJson(e, path.clone()));
+            if let Some(value) = options.tolerate(&path, value)? {
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                self.unknown_files.insert(key, value);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Contact {
-    id: RcStr,
-    owner_id: RcStr,
-    friend_username: RcStr,
-    alternate_usernames: Option<RcStr>,
-    friend_status: RcStr,
-    is_accepted: bool,
-    user_status: ContactStatus,
-    #[serde(deserialize_with = "super::de::err_to_none")]
-    latest_message_time: Option<DateTime<Utc>>,
-    profile: Option<Profile>,
+    pub id: RcStr,
+    pub owner_id: RcStr,
+    pub friend_username: RcStr,
+    pub alternate_usernames: Option<RcStr>,
+    pub friend_status: FriendStatus,
+    pub is_accepted: bool,
+    pub user_status: ContactStatus,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
+    pub latest_message_time: Option<DateTime<Utc>>,
+    pub profile: Option<Profile>,
 }
 
-impl FromFile for Contact {}
+impl FromFile for Contact {
+    const KIND: FileKind = FileKind::Contact;
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(from = "RcStr", into = "RcStr")]
+pub enum FriendStatus {
+    #[default]
+    None,
+    SearchResult,
+    Requested,
+    Ignored,
+    Blocked,
+    Accepted,
+    Other(RcStr),
+}
+
+impl From<RcStr> for FriendStatus {
+    fn from(s: RcStr) -> Self {
+        match s.as_str() {
+            "None" => FriendStatus::None,
+            "SearchResult" => FriendStatus::SearchResult,
+            "Requested" => FriendStatus::Requested,
+            "Ignored" => FriendStatus::Ignored,
+            "Blocked" => FriendStatus::Blocked,
+            "Accepted" => FriendStatus::Accepted,
+            _ => FriendStatus::Other(s),
+        }
+    }
+}
+
+impl From<FriendStatus> for RcStr {
+    fn from(s: FriendStatus) -> Self {
+        match s {
+            FriendStatus::None => "None".to_owned().into(),
+            FriendStatus::SearchResult => "SearchResult".to_owned().into(),
+            FriendStatus::Requested => "Requested".to_owned().into(),
+            FriendStatus::Ignored => "Ignored".to_owned().into(),
+            FriendStatus::Blocked => "Blocked".to_owned().into(),
+            FriendStatus::Accepted => "Accepted".to_owned().into(),
+            FriendStatus::Other(s) => s,
+        }
+    }
+}
 
 #[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ContactStatus {
-    online_status: RcStr,
-    #[serde(deserialize_with = "super::de::err_to_none")]
-    last_status_change: Option<DateTime<Utc>>,
-    current_session_id: Option<RcStr>,
-    current_session_access_level: i32,
-    current_session_hidden: bool,
-    current_hosting: bool,
-    compatibility_hash: Option<RcStr>,
-    neos_version: Option<RcStr>,
+    pub online_status: RcStr,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
+    pub last_status_change: Option<DateTime<Utc>>,
+    pub current_session_id: Option<RcStr>,
+    pub current_session_access_level: i32,
+    pub current_session_hidden: bool,
+    pub current_hosting: bool,
+    pub compatibility_hash: Option<RcStr>,
+    pub neos_version: Option<RcStr>,
     #[serde(rename = "publicRSAKey")]
-    public_rsa_key: Option<RsaKey>,
-    output_device: RcStr,
-    is_mobile: bool,
+    pub public_rsa_key: Option<RsaKey>,
+    pub output_device: RcStr,
+    pub is_mobile: bool,
     #[serde(rename = "CurrentSession")]
-    current_session: Option<Session>,
-    active_sessions: Option<Vec<Session>>,
+    pub current_session: Option<Session>,
+    pub active_sessions: Option<Vec<Session>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Default)]
@@ -233,7 +872,7 @@ pub struct RsaKey {
     pub d: Option<RcStr>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Session {
     pub name: RcStr,
@@ -263,8 +902,11 @@ pub struct Session {
     pub total_active_users: i32,
     pub max_users: i32,
     pub mobile_friendly: bool,
+    #[serde(deserialize_with = "super::de::required_lenient_time")]
     pub session_begin_time: DateTime<Utc>,
+    #[serde(deserialize_with = "super::de::required_lenient_time")]
     pub last_update: DateTime<Utc>,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
     pub away_since: Option<DateTime<Utc>>,
     pub access_level: RcStr,
     #[serde(rename = "HasEnded")]
@@ -274,14 +916,14 @@ pub struct Session {
     // There are more :D
 }
 
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CorrespondingWorldId {
-    record_id: RcStr,
-    owner_id: RcStr,
+    pub record_id: RcStr,
+    pub owner_id: RcStr,
 }
 
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionUsers {
     pub username: RcStr,
@@ -294,27 +936,29 @@ pub struct SessionUsers {
 #[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Profile {
-    icon_url: RcStr,
-    background_url: Option<RcStr>,
-    tagline: Option<RcStr>,
-    description: Option<RcStr>,
-    profile_world_url: Option<RcStr>,
+    pub icon_url: RcStr,
+    pub background_url: Option<RcStr>,
+    pub tagline: Option<RcStr>,
+    pub description: Option<RcStr>,
+    pub profile_world_url: Option<RcStr>,
     #[serde(deserialize_with = "super::de::null_to_default")]
-    showcase_items: Vec<RcStr>,
+    pub showcase_items: Vec<RcStr>,
     #[serde(deserialize_with = "super::de::null_to_default")]
-    token_opt_out: Vec<RcStr>,
+    pub token_opt_out: Vec<RcStr>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct GroupMember {
-    id: RcStr,
-    owner_id: RcStr,
-    quota_bytes: i64,
-    used_bytes: u64,
+    pub id: RcStr,
+    pub owner_id: RcStr,
+    pub quota_bytes: i64,
+    pub used_bytes: u64,
 }
 
-impl FromFile for GroupMember {}
+impl FromFile for GroupMember {
+    const KIND: FileKind = FileKind::GroupMember;
+}
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
@@ -326,7 +970,40 @@ pub struct Group {
     pub used_bytes: u64,
 }
 
-impl FromFile for Group {}
+impl FromFile for Group {
+    const KIND: FileKind = FileKind::Group;
+}
+
+impl Group {
+    // The group's records wherever they were exported, each once, with the account
+    // folder the first copy was found in. Every member can export the same group record.
+    pub fn records<'a>(&self, backup: &'a Backup) -> Vec<(&'a RcStr, &'a Record)> {
+        let mut seen = BTreeSet::new();
+        let mut records = Vec::new();
+        for (account_name, account) in &backup.accounts {
+            for rec in account.records.values() {
+                if rec.owner_id == self.id && seen.insert(&rec.id) {
+                    records.push((account_name, rec));
+                }
+            }
+        }
+        records
+    }
+}
+
+impl Backup {
+    // Every group an account in the backup belongs to, by ID, taking the first account's
+    // copy when several members exported it.
+    pub fn groups(&self) -> BTreeMap<&RcStr, &Group> {
+        let mut groups = BTreeMap::new();
+        for account in self.accounts.values() {
+            for (id, group) in &account.groups {
+                groups.entry(id).or_insert(group);
+            }
+        }
+        groups
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
@@ -336,14 +1013,87 @@ pub struct Message {
     pub recipient_id: RcStr,
     pub message_type: MessageType,
     pub content: RcStr,
+    #[serde(deserialize_with = "super::de::required_lenient_time")]
     pub send_time: DateTime<Utc>,
+    #[serde(deserialize_with = "super::de::required_lenient_time")]
     pub last_update_time: DateTime<Utc>,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
     pub read_time: Option<DateTime<Utc>>,
 }
 
-impl FromFile for Message {}
+impl FromFile for Message {
+    const KIND: FileKind = FileKind::Message;
+}
+
+impl Message {
+    // The copy's sender, which the message only records by who it went to.
+    pub fn sender<'a>(&self, owner: &'a RcStr, contact: &'a RcStr) -> &'a RcStr {
+        if self.recipient_id == *owner {
+            contact
+        } else {
+            owner
+        }
+    }
 
+    // The content read as what the message's type says it holds, or as it was sent when
+    // it doesn't parse as that.
+    pub fn parsed_content(&self) -> MessageContent {
+        let parsed = match self.message_type {
+            MessageType::Text => return MessageContent::Text(self.content.clone()),
+            MessageType::Object => serde_json::from_str(&self.content).map(MessageContent::Object),
+            MessageType::Sound => serde_json::from_str(&self.content).map(MessageContent::Sound),
+            MessageType::SessionInvite => serde_json::from_str(&self.content)
+                .map(|session| MessageContent::SessionInvite(Box::new(session))),
+            MessageType::CreditTransfer => {
+                serde_json::from_str(&self.content).map(MessageContent::CreditTransfer)
+            }
+        };
+        parsed.unwrap_or_else(|_| MessageContent::Raw(self.content.clone()))
+    }
+}
+
+#[derive(Debug)]
+pub enum MessageContent {
+    Text(RcStr),
+    Object(SentRecord),
+    // A voice message, the record of an ogg asset.
+    Sound(SentRecord),
+    SessionInvite(Box<Session>),
+    CreditTransfer(CreditTransfer),
+    // Content that doesn't parse as what the message's type says it is.
+    Raw(RcStr),
+}
+
+// A record sent in an Object or Sound message. Only what the recipient needs to spawn it
+// is certain to be there, and even that has changed over Neos versions.
 #[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SentRecord {
+    pub id: Option<RcStr>,
+    pub owner_id: Option<RcStr>,
+    pub name: Option<RcStr>,
+    pub asset_uri: Option<AssetUri>,
+    pub thumbnail_uri: Option<AssetUri>,
+    #[serde(
+        rename = "neosDBmanifest",
+        alias = "neosDbManifest",
+        deserialize_with = "super::de::null_to_default"
+    )]
+    pub neos_db_manifest: Vec<AssetRef>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditTransfer {
+    // What was sent, like KFC or NCR.
+    pub token: RcStr,
+    pub amount: f64,
+    pub recipient_id: Option<RcStr>,
+    pub comment: Option<RcStr>,
+    pub transaction_type: Option<RcStr>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 pub enum MessageType {
     #[default]
     Object,
@@ -365,7 +1115,9 @@ pub struct VariableDefinition {
     pub list_permissions: Vec<RcStr>,
 }
 
-impl FromFile for VariableDefinition {}
+impl FromFile for VariableDefinition {
+    const KIND: FileKind = FileKind::VariableDefinition;
+}
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
@@ -375,7 +1127,9 @@ pub struct Variable {
     pub value: RcStr,
 }
 
-impl FromFile for Variable {}
+impl FromFile for Variable {
+    const KIND: FileKind = FileKind::Variable;
+}
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "camelCase")]
@@ -396,30 +1150,112 @@ impl<Output: DeserializeOwned> WellKnownAssetKind<Output> for SZBson {
     type ParserError = Error;
 
     fn open(&self, b: &Backup) -> Result<Output, Self::ParserError> {
-        let asset = b.open_asset(self.0.as_ref())?;
+        let raw = self.read_raw(b)?;
+        timings::time(Phase::BsonParse, raw.len() as u64, || {
+            let bson = self.parse_document(raw, &b.limits)?;
+            bson::de::from_document(bson).map_err(|e| Error::SerdeBson(e, self.0.clone()))
+        })
+    }
+}
+
+impl SZBson {
+    pub fn read_raw(&self, b: &Backup) -> Result<Vec<u8>, Error> {
+        let asset = b.open_asset(self.0.as_str())?;
+        let bytes = if timings::enabled() {
+            asset.metadata()?.len()
+        } else {
+            0
+        };
         let mut content = Vec::new();
-        uncompress_7z(asset, &mut content)?;
-        let bson = bson::RawDocumentBuf::from_bytes(content)
-            .map_err(|e| Error::SerdeBsonRaw(e, self.0.clone()))?;
-        let bson = bson
-            .to_document()
+        timings::time(Phase::Decompress, bytes, || {
+            b.limits.decompress(&self.0, asset, &mut content)
+        })?;
+        Ok(content)
+    }
+
+    // A 7zbson asset's bytes, as read from disk or about to be written.
+    pub fn decode_document(
+        &self,
+        data: &[u8],
+        limits: &ManifestLimits,
+    ) -> Result<bson::Document, Error> {
+        let mut raw = Vec::new();
+        limits.decompress(&self.0, data, &mut raw)?;
+        self.parse_document(raw, limits)
+    }
+
+    // The document as a 7zbson asset, ready to be stored under the hash of these bytes.
+    // Only literals are written, so it comes out a little larger than the raw BSON, but
+    // any LZMA decoder reads it.
+    pub fn encode_document(document: &bson::Document) -> Result<Vec<u8>, Error> {
+        let mut raw = Vec::new();
+        document.to_writer(&mut raw)?;
+        Ok(compress_7z(&raw)?)
+    }
+
+    // Encodes a Manifest or anything else serializing to a document. The model drops what
+    // it doesn't know about, so to patch an object, edit the Document from read_document
+    // instead of a Manifest opened from it.
+    pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+        Ok(compress_7z(&bson::to_vec(value)?)?)
+    }
+
+    pub fn read_document(&self, b: &Backup) -> Result<bson::Document, Error> {
+        let raw = self.read_raw(b)?;
+        timings::time(Phase::BsonParse, raw.len() as u64, || {
+            self.parse_document(raw, &b.limits)
+        })
+    }
+
+    fn parse_document(
+        &self,
+        raw: Vec<u8>,
+        limits: &ManifestLimits,
+    ) -> Result<bson::Document, Error> {
+        let bson = bson::RawDocumentBuf::from_bytes(raw)
             .map_err(|e| Error::SerdeBsonRaw(e, self.0.clone()))?;
-        Ok(bson::de::from_document(bson).map_err(|e| Error::SerdeBson(e, self.0.clone()))?)
+        limits.check(&self.0, &bson)?;
+        bson.to_document()
+            .map_err(|e| Error::SerdeBsonRaw(e, self.0.clone()))
+    }
+
+    // Read from the container header, without decompressing anything.
+    pub fn uncompressed_size(&self, b: &Backup) -> Result<u64, Error> {
+        let mut status = [0; 1 + 4 + 8];
+        b.open_asset(self.0.as_str())?.read_exact(&mut status)?;
+        Ok(u64::from_le_bytes(status[5..].try_into().unwrap()))
     }
 }
 
-fn uncompress_7z<W>(file: File, out: &mut W) -> Result<(), lzma_rs::error::Error>
+pub(crate) fn uncompress_7z<R, W>(file: R, out: &mut W) -> Result<(), lzma_rs::error::Error>
 where
+    R: Read,
     W: Write,
 {
     let mut file = BufReader::new(file);
 
     let mut status = [0; 1 + 4 + 8]; // flag, dict size, and uncompressed size
-    file.read(&mut status)?;
-    file.read(&mut [0; 8])?; // discard the compressed size (it is not expected).
+    file.read_exact(&mut status)?;
+    file.read_exact(&mut [0; 8])?; // discard the compressed size (it is not expected).
     let mut file = status.chain(file);
 
-    Ok(lzma_rs::lzma_decompress(&mut file, out)?)
+    lzma_rs::lzma_decompress(&mut file, out)
+}
+
+// The reverse of uncompress_7z: an LZMA header with the size filled in, then the
+// compressed size Neos adds after it, then the stream without an end marker.
+pub(crate) fn compress_7z(raw: &[u8]) -> io::Result<Vec<u8>> {
+    let options = lzma_rs::compress::Options {
+        unpacked_size: lzma_rs::compress::UnpackedSize::WriteToHeader(Some(raw.len() as u64)),
+    };
+    let mut lzma = Vec::new();
+    lzma_rs::lzma_compress_with_options(&mut &raw[..], &mut lzma, &options)?;
+    let (header, stream) = lzma.split_at(1 + 4 + 8);
+    let mut out = Vec::with_capacity(lzma.len() + 8);
+    out.extend_from_slice(header);
+    out.extend_from_slice(&(stream.len() as u64).to_le_bytes());
+    out.extend_from_slice(stream);
+    Ok(out)
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -434,16 +1270,67 @@ pub struct Unknown {
     pub id: RcStr,
 }
 
+// An asset whose URL had no extension, of a kind sniffed from its bytes that has no
+// variant of its own, like png or mp4.
+#[derive(Serialize, Debug, Clone)]
+pub struct Detected {
+    pub kind: &'static str,
+    pub id: RcStr,
+}
+
 pub trait WellKnownAssetKind<Output> {
     type ParserError;
     fn open(&self, b: &Backup) -> Result<Output, Self::ParserError>;
 }
 
+// What every asset kind can do without reading its format.
+pub trait RawAsset {
+    fn id(&self) -> &RcStr;
+
+    // Copies the asset's bytes as stored, returning how many there were.
+    fn extract_to(&self, b: &Backup, path: &Path) -> io::Result<u64> {
+        match b.asset_path(self.id()) {
+            Some(asset) => fs::copy(asset, path),
+            None => Err(b.missing_asset(self.id())),
+        }
+    }
+}
+
+impl RawAsset for SZBson {
+    fn id(&self) -> &RcStr {
+        &self.0
+    }
+}
+
+impl RawAsset for Webp {
+    fn id(&self) -> &RcStr {
+        &self.0
+    }
+}
+
+impl RawAsset for Ogg {
+    fn id(&self) -> &RcStr {
+        &self.0
+    }
+}
+
+impl RawAsset for Unknown {
+    fn id(&self) -> &RcStr {
+        &self.id
+    }
+}
+
+impl RawAsset for Detected {
+    fn id(&self) -> &RcStr {
+        &self.id
+    }
+}
+
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct NeosRecAsset {
-    group_id: RcStr,
-    asset_id: RcStr,
+    pub group_id: RcStr,
+    pub asset_id: RcStr,
 }
 
 #[derive(Serialize, Debug)]
@@ -453,9 +1340,35 @@ pub enum AssetUri {
     Webp(Webp),
     Ogg(Ogg),
     Unknown(Unknown),
+    Detected(Detected),
     NeosRec(NeosRecAsset),
 }
 
+impl AssetUri {
+    pub fn asset_id(&self) -> Option<&RcStr> {
+        match self {
+            AssetUri::SZBson(SZBson(id)) | AssetUri::Webp(Webp(id)) | AssetUri::Ogg(Ogg(id)) => {
+                Some(id)
+            }
+            AssetUri::Unknown(Unknown { id, .. }) | AssetUri::Detected(Detected { id, .. }) => {
+                Some(id)
+            }
+            AssetUri::NeosRec(_) => None,
+        }
+    }
+
+    pub fn kind(&self) -> Option<&str> {
+        match self {
+            AssetUri::SZBson(_) => Some("7zbson"),
+            AssetUri::Webp(_) => Some("webp"),
+            AssetUri::Ogg(_) => Some("ogg"),
+            AssetUri::Unknown(Unknown { kind, .. }) => kind.as_deref(),
+            AssetUri::Detected(Detected { kind, .. }) => Some(kind),
+            AssetUri::NeosRec(_) => None,
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for AssetUri {
     fn deserialize<D>(d: D) -> Result<Self, D::Error>
     where
@@ -536,9 +1449,11 @@ pub struct Record {
     #[serde(deserialize_with = "super::de::option_split_backslashes")]
     pub path: Vec<RcStr>,
     pub thumbnail_uri: Option<AssetUri>,
-    #[serde(deserialize_with = "super::de::err_to_none")]
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
     pub last_modification_time: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
     pub creation_time: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
     pub first_publish_time: Option<DateTime<Utc>>,
     pub is_public: bool,
     pub is_for_patrons: bool,
@@ -552,7 +1467,17 @@ pub struct Record {
     pub neos_db_manifest: Vec<AssetRef>,
 }
 
-impl FromFile for Record {}
+impl FromFile for Record {
+    const KIND: FileKind = FileKind::Record;
+}
+
+impl Record {
+    // Group records are saved into whichever member's folder exported them, this is how
+    // to tell them apart from the member's own.
+    pub fn is_group_owned(&self) -> bool {
+        self.owner_id.starts_with("G-")
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct AssetRef {
@@ -616,6 +1541,25 @@ pub struct Component {
     pub data: Data,
 }
 
+impl Component {
+    // FrooxEngine.ValueField`1[[System.Single, mscorlib, ...]] -> ValueField<Single>
+    pub fn short_type(&self) -> String {
+        let cs_type = self.cs_type.as_str();
+        let (outer, generics) = match cs_type.split_once("`") {
+            Some((outer, rest)) => (outer, rest.split_once("[[").map(|(_, g)| g)),
+            None => (cs_type, None),
+        };
+        let outer = outer.rsplit('.').next().unwrap_or(outer);
+        match generics {
+            Some(generics) => {
+                let arg = generics.split(',').next().unwrap_or_default();
+                format!("{}<{}>", outer, arg.rsplit('.').next().unwrap_or(arg))
+            }
+            None => outer.to_owned(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "PascalCase")]
 pub struct Data {
@@ -642,12 +1586,110 @@ pub enum DataField {
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+// Driven and hooked fields carry the ID of the link controlling them next to the value.
+#[derive(Deserialize, Debug)]
+#[serde(from = "RawField<T>")]
+pub enum Field<T> {
+    Value {
+        id: RcStr,
+        data: T,
+    },
+    Driven {
+        id: RcStr,
+        data: T,
+        drive_ref: RcStr,
+    },
+    Hooked {
+        id: RcStr,
+        data: T,
+        hook_ref: RcStr,
+    },
+}
+
+impl<T> Field<T> {
+    pub fn id(&self) -> &RcStr {
+        match self {
+            Field::Value { id, .. } | Field::Driven { id, .. } | Field::Hooked { id, .. } => id,
+        }
+    }
+
+    pub fn data(&self) -> &T {
+        match self {
+            Field::Value { data, .. } | Field::Driven { data, .. } | Field::Hooked { data, .. } => {
+                data
+            }
+        }
+    }
+
+    pub fn data_mut(&mut self) -> &mut T {
+        match self {
+            Field::Value { data, .. } | Field::Driven { data, .. } | Field::Hooked { data, .. } => {
+                data
+            }
+        }
+    }
+
+    pub fn drive_ref(&self) -> Option<&RcStr> {
+        match self {
+            Field::Driven { drive_ref, .. } => Some(drive_ref),
+            _ => None,
+        }
+    }
+
+    pub fn hook_ref(&self) -> Option<&RcStr> {
+        match self {
+            Field::Hooked { hook_ref, .. } => Some(hook_ref),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Default> Default for Field<T> {
+    fn default() -> Self {
+        Field::Value {
+            id: Default::default(),
+            data: Default::default(),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Field<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        RawField {
+            id: self.id().clone(),
+            data: self.data(),
+            drive: self.drive_ref().cloned(),
+            hook: self.hook_ref().cloned(),
+        }
+        .serialize(s)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct Field<T> {
+struct RawField<T> {
     #[serde(rename = "ID")]
-    pub id: RcStr,
-    pub data: T,
+    id: RcStr,
+    data: T,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    drive: Option<RcStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hook: Option<RcStr>,
+}
+
+impl<T> From<RawField<T>> for Field<T> {
+    fn from(raw: RawField<T>) -> Self {
+        let RawField { id, data, .. } = raw;
+        match (raw.drive, raw.hook) {
+            (Some(drive_ref), _) => Field::Driven {
+                id,
+                data,
+                drive_ref,
+            },
+            (None, Some(hook_ref)) => Field::Hooked { id, data, hook_ref },
+            (None, None) => Field::Value { id, data },
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -677,3 +1719,263 @@ pub struct SimulationSpace {
     pub use_parent_space: Field<bool>,
     pub override_root_space: Field<Option<RcStr>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::{doc, spec::BinarySubtype, Binary, Bson, Document};
+
+    fn asset() -> SZBson {
+        SZBson("test".into())
+    }
+
+    #[test]
+    fn header_has_both_sizes() {
+        let document = doc! { "Object": { "ID": "root" } };
+        let raw = bson::to_vec(&document).unwrap();
+        let encoded = SZBson::encode_document(&document).unwrap();
+        let size = |at: usize| u64::from_le_bytes(encoded[at..at + 8].try_into().unwrap());
+        assert_eq!(size(5), raw.len() as u64);
+        assert_eq!(size(13), encoded.len() as u64 - 21);
+    }
+
+    #[test]
+    fn documents_round_trip() {
+        let documents = [
+            Document::new(),
+            doc! {
+                "Object": {
+                    "ID": "4f0c",
+                    "Name": { "ID": "4f0d", "Data": "Ünïcödé ✓" },
+                    "Children": [{ "ID": "a" }, { "ID": "b", "Tag": Bson::Null }],
+                },
+                "Assets": [],
+                "TypeVersions": { "FrooxEngine.Slot": 1_i32, "Big": 1_i64 << 40 },
+                "Position": [1.5, -0.0, f64::MAX],
+                "Blob": Binary { subtype: BinarySubtype::Generic, bytes: (0..=255).collect() },
+            },
+            // Long runs, past what a literal-only encoder's probabilities settle into.
+            doc! { "Data": "ab".repeat(100_000) },
+        ];
+        for document in documents {
+            let encoded = SZBson::encode_document(&document).unwrap();
+            assert_eq!(
+                asset()
+                    .decode_document(&encoded, &ManifestLimits::default())
+                    .unwrap(),
+                document
+            );
+        }
+    }
+
+    #[test]
+    fn manifest_round_trips() {
+        let json: serde_json::Value =
+            serde_json::from_str(include_str!("../../dump.json")).unwrap();
+        let mut document = bson::to_document(&json).unwrap();
+        // The dump was saved without it.
+        document.insert("TypeVersions", doc! { "FrooxEngine.Slot": 1_i64 });
+        let encoded = SZBson::encode_document(&document).unwrap();
+        let decoded = asset()
+            .decode_document(&encoded, &ManifestLimits::default())
+            .unwrap();
+        assert_eq!(decoded, document);
+
+        // What the model keeps survives being encoded from it and read back.
+        let manifest: Manifest = bson::from_document(decoded).unwrap();
+        let encoded = SZBson::encode(&manifest).unwrap();
+        let reread: Manifest = bson::from_document(
+            asset()
+                .decode_document(&encoded, &ManifestLimits::default())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            bson::to_document(&reread).unwrap(),
+            bson::to_document(&manifest).unwrap()
+        );
+    }
+
+    #[test]
+    fn truncated_asset_is_corrupt() {
+        let encoded = SZBson::encode_document(&doc! { "Data": "x".repeat(1000) }).unwrap();
+        for len in [0, 12, 20, encoded.len() / 2] {
+            let e = asset()
+                .decode_document(&encoded[..len], &ManifestLimits::default())
+                .unwrap_err();
+            assert!(matches!(e, Error::Lzma(_)), "{} bytes: {:?}", len, e);
+        }
+    }
+
+    // Detection samples a few files per folder, the early format's timestamps have to
+    // load in the files it didn't look at too.
+    #[test]
+    fn mixed_format_timestamps_load() {
+        let root = std::env::temp_dir().join(format!("statbox-mixed-{}", std::process::id()));
+        let records = root.join("U-test").join("Records");
+        fs::create_dir_all(&records).unwrap();
+        let record = |id: &str, time: &str| {
+            serde_json::json!({
+                "id": id, "ownerId": "U-test", "assetUri": null, "globalVersion": 1,
+                "localVersion": 1, "lastModifyingUserId": "U-test", "name": id,
+                "recordType": "directory", "ownerName": "test", "tags": null,
+                "path": "Inventory", "lastModificationTime": time, "creationTime": time,
+                "firstPublishTime": null, "isPublic": false, "isForPatrons": false,
+                "visits": 0, "rating": 0, "randomOrder": 0, "submissions": null,
+                "neosDBmanifest": null,
+            })
+        };
+        let mut files: Vec<_> = (0..40)
+            .map(|i| (format!("R-{}", i), "2021-03-04T05:06:07Z"))
+            .collect();
+        files.push(("R-offsetless".to_owned(), "2019-01-02T03:04:05.5"));
+        files.push(("R-garbage".to_owned(), "last tuesday"));
+        for (id, time) in &files {
+            let path = records.join(format!("{}.json", id));
+            fs::write(path, serde_json::to_vec(&record(id, time)).unwrap()).unwrap();
+        }
+
+        let options = || LoadOptions {
+            strict: true,
+            ..Default::default()
+        };
+        // Whatever detection made of it, and as if it had missed the early files.
+        let loads = [
+            Backup::load_with(root.clone(), &options()),
+            Backup::from_disk(root.clone(), FormatVersion::NeosLate, &options()),
+        ];
+        fs::remove_dir_all(&root).unwrap();
+        for backup in loads {
+            let backup = backup.unwrap();
+            let records = &backup.accounts["U-test"].records;
+            assert_eq!(records.len(), files.len());
+            assert_eq!(
+                records["R-offsetless"].last_modification_time,
+                Some("2019-01-02T03:04:05.5Z".parse().unwrap())
+            );
+            assert_eq!(records["R-garbage"].last_modification_time, None);
+            assert!(records["R-0"].creation_time.is_some());
+            // Both times of the garbage one, and nothing for the one that was fixed up.
+            let dropped: Vec<_> = backup
+                .warnings
+                .take()
+                .into_iter()
+                .map(|w| (w.path.file_name().unwrap().to_owned(), w.kind.to_string()))
+                .collect();
+            assert_eq!(
+                dropped,
+                ["lastModificationTime", "creationTime"].map(|field| (
+                    "R-garbage.json".into(),
+                    format!("{} \"last tuesday\" isn't a time, dropped", field)
+                ))
+            );
+        }
+    }
+
+    // Messages and the sessions in contacts' statuses were written offsetless by the same
+    // tool, and are loaded whatever detection made of the backup too.
+    #[test]
+    fn early_message_and_session_timestamps_load() {
+        let temp = crate::store::testing::TempBackup::new("early-messages");
+        let account = temp.root.join("U-test");
+        let message = |id: &str, sent: &str| {
+            serde_json::json!({
+                "id": id, "ownerId": "U-test", "recipientId": "U-friend",
+                "messageType": "Text", "content": "hi", "sendTime": sent,
+                "lastUpdateTime": sent, "readTime": "never",
+            })
+        };
+        let messages = account.join("Messages").join("U-friend");
+        fs::create_dir_all(&messages).unwrap();
+        for (id, sent) in [
+            ("M-1", "2019-01-02T03:04:05.5"),
+            ("M-2", "2021-03-04T05:06:07Z"),
+        ] {
+            let json = serde_json::to_vec(&message(id, sent)).unwrap();
+            fs::write(messages.join(format!("{}.json", id)), json).unwrap();
+        }
+        let mut contact = serde_json::to_value(Contact::default()).unwrap();
+        contact["id"] = "U-friend".into();
+        let mut session = serde_json::to_value(Session::default()).unwrap();
+        session["sessionBeginTime"] = "2019-01-02T03:04:05".into();
+        contact["userStatus"]["CurrentSession"] = session;
+        fs::create_dir_all(account.join("Contacts")).unwrap();
+        let json = serde_json::to_vec(&contact).unwrap();
+        fs::write(account.join("Contacts").join("U-friend.json"), json).unwrap();
+
+        let options = LoadOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let loads = [
+            Backup::load_with(temp.root.clone(), &options),
+            Backup::from_disk(temp.root.clone(), FormatVersion::NeosLate, &options),
+        ];
+        for backup in loads {
+            let backup = backup.unwrap();
+            let account = &backup.accounts["U-test"];
+            let sent: Vec<_> = account.messages["U-friend"]
+                .iter()
+                .map(|m| (m.send_time.to_rfc3339(), m.read_time))
+                .collect();
+            assert!(sent.contains(&("2019-01-02T03:04:05.500+00:00".to_owned(), None)));
+            assert_eq!(sent.len(), 2);
+            let session = account.contacts["U-friend"]
+                .user_status
+                .current_session
+                .as_ref();
+            assert_eq!(
+                session.unwrap().session_begin_time,
+                "2019-01-02T03:04:05Z".parse::<DateTime<Utc>>().unwrap()
+            );
+        }
+    }
+
+    // A chain of slots depth deep, each with one component.
+    fn nested(depth: usize) -> Document {
+        let mut slot = doc! { "ID": "leaf", "Components": { "Data": [{ "Type": "A" }] } };
+        for _ in 1..depth {
+            slot = doc! {
+                "ID": "slot",
+                "Components": { "Data": [{ "Type": "A" }] },
+                "Children": [slot],
+            };
+        }
+        doc! { "Object": slot, "Assets": [{ "Type": "B" }] }
+    }
+
+    #[test]
+    fn limits_refuse_oversized_manifests() {
+        let decode = |document: &Document, limits: ManifestLimits| {
+            asset().decode_document(&SZBson::encode_document(document).unwrap(), &limits)
+        };
+        let document = nested(10);
+        let size = bson::to_vec(&document).unwrap().len() as u64;
+        let limits = ManifestLimits {
+            max_decompressed_bytes: size,
+            max_slot_depth: 10,
+            max_components: 11,
+        };
+        assert_eq!(decode(&document, limits).unwrap(), document);
+
+        let refused = [
+            ManifestLimits {
+                max_decompressed_bytes: size - 1,
+                ..limits
+            },
+            ManifestLimits {
+                max_slot_depth: 9,
+                ..limits
+            },
+            ManifestLimits {
+                max_components: 10,
+                ..limits
+            },
+        ];
+        for limits in refused {
+            let e = decode(&document, limits).unwrap_err();
+            assert!(matches!(e, Error::LimitExceeded(_)), "{:?}", e);
+            assert_eq!(e.code(), "limit-exceeded");
+        }
+    }
+}
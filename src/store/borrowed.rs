@@ -0,0 +1,145 @@
+use super::{
+    backup::{is_sidecar, Error, LoadOptions, RecordType},
+    timings::{self, Phase},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+// Views over a record or message file that borrow their strings from the read buffer,
+// for one-pass scans that look at each file once and throw it away. Strings with escapes
+// in them can't be borrowed and fall back to owned ones.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordView<'a> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub name: Cow<'a, str>,
+    #[serde(default)]
+    pub record_type: RecordType,
+    #[serde(borrow, default)]
+    pub tags: Option<Vec<Cow<'a, str>>>,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
+    pub creation_time: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
+    pub last_modification_time: Option<DateTime<Utc>>,
+    #[serde(borrow, default, rename = "neosDBmanifest", alias = "assetManifest")]
+    pub neos_db_manifest: Option<Vec<AssetRefView<'a>>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AssetRefView<'a> {
+    #[serde(borrow)]
+    pub hash: Cow<'a, str>,
+    pub bytes: u64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageView<'a> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(default, deserialize_with = "super::de::lenient_time")]
+    pub send_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub enum View<'a> {
+    Record(RecordView<'a>),
+    Message {
+        contact: &'a str,
+        message: MessageView<'a>,
+    },
+}
+
+// Calls back with each account's records and messages, one file at a time, reusing a
+// single read buffer so nothing from the backup outlives the callback. The options' asset
+// roots play no part here.
+pub fn scan<F>(root: &Path, options: &LoadOptions, mut visit: F) -> Result<(), Error>
+where
+    F: FnMut(&str, View<'_>),
+{
+    let mut buf = Vec::new();
+    for account in root.read_dir()? {
+        let account = account?;
+        if account.file_name() == "Assets" || !account.file_type()?.is_dir() {
+            continue;
+        }
+        let name = account.file_name().to_string_lossy().into_owned();
+        if !options.wants_account(&name) || options.ignore.is_ignored(&name) {
+            continue;
+        }
+
+        let records = if options.skip_records {
+            Vec::new()
+        } else {
+            data_files(&account.path().join("Records"), options)?
+        };
+        for file in records {
+            read(&file, &mut buf)?;
+            let record = timings::time(Phase::JsonParse, buf.len() as u64, || {
+                serde_json::from_slice(&buf)
+            })
+            .map_err(|e| Error::SerdeJson(e, file))?;
+            visit(&name, View::Record(record));
+        }
+
+        let messages = account.path().join("Messages");
+        if options.skip_messages || !messages.is_dir() {
+            continue;
+        }
+        for contact in messages.read_dir()? {
+            let contact = contact?;
+            let contact_name = contact.file_name().to_string_lossy().into_owned();
+            if options.ignore.is_ignored(&contact_name) {
+                continue;
+            }
+            for file in data_files(&contact.path(), options)? {
+                read(&file, &mut buf)?;
+                let message = timings::time(Phase::JsonParse, buf.len() as u64, || {
+                    serde_json::from_slice(&buf)
+                })
+                .map_err(|e| Error::SerdeJson(e, file))?;
+                visit(
+                    &name,
+                    View::Message {
+                        contact: &contact_name,
+                        message,
+                    },
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn data_files(dir: &Path, options: &LoadOptions) -> Result<Vec<PathBuf>, Error> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    timings::time(Phase::DirectoryWalk, 0, || {
+        let mut files = Vec::new();
+        for file in dir.read_dir()? {
+            let file = file?;
+            let name = file.file_name();
+            let name = name.to_string_lossy();
+            if !is_sidecar(&name) && !options.ignore.is_ignored(&name) {
+                files.push(file.path());
+            }
+        }
+        files.sort();
+        Ok(files)
+    })
+}
+
+fn read(path: &Path, buf: &mut Vec<u8>) -> Result<(), Error> {
+    buf.clear();
+    fs::File::open(path)?.read_to_end(buf)?;
+    Ok(())
+}
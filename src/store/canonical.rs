@@ -0,0 +1,185 @@
+use super::hash::{hash_bytes, AssetId};
+use bson::Document;
+use serde::Serialize;
+use serde_json::{Number, Value};
+use std::io::Write;
+
+// One byte-for-byte form of a record, manifest or anything else serializable, so two
+// runs, machines or versions of this tool agree on whether two of them are the same:
+//
+// - no whitespace, and object keys sorted by their UTF-8 bytes, however the model orders
+//   its fields or the file ordered them;
+// - numbers by their value: floats holding a whole number up to 2^53 as that integer, so
+//   1.0 and 1, or -0.0 and 0, agree, and other floats in the shortest form that reads
+//   back as the same f64;
+// - strings escaped only where JSON requires, so non-ASCII text is written as is.
+//
+// Times need nothing more, the model holds them as DateTime<Utc>, which always serializes
+// as RFC 3339 in UTC with a Z. Manifests are best canonicalized from their Document, which
+// keeps what the Manifest model drops and tells an int32 from an int64.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    write_value(&mut out, &serde_json::to_value(value)?)?;
+    Ok(out)
+}
+
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<String> {
+    // Only valid UTF-8 is ever written.
+    Ok(String::from_utf8(to_vec(value)?).expect("canonical JSON is UTF-8"))
+}
+
+// The document as canonical extended JSON, where every value carries its BSON type.
+pub fn document_to_vec(document: &Document) -> serde_json::Result<Vec<u8>> {
+    let value = bson::Bson::Document(document.clone()).into_canonical_extjson();
+    let mut out = Vec::new();
+    write_value(&mut out, &value)?;
+    Ok(out)
+}
+
+// The SHA-256 of the canonical form, for telling records apart by content.
+pub fn digest<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<AssetId> {
+    Ok(hash_bytes(&to_vec(value)?))
+}
+
+pub fn document_digest(document: &Document) -> serde_json::Result<AssetId> {
+    Ok(hash_bytes(&document_to_vec(document)?))
+}
+
+fn write_value<W: Write>(out: &mut W, value: &Value) -> serde_json::Result<()> {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.write_all(b"{").map_err(serde_json::Error::io)?;
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.write_all(b",").map_err(serde_json::Error::io)?;
+                }
+                serde_json::to_writer(&mut *out, key)?;
+                out.write_all(b":").map_err(serde_json::Error::io)?;
+                write_value(out, value)?;
+            }
+            out.write_all(b"}").map_err(serde_json::Error::io)
+        }
+        Value::Array(items) => {
+            out.write_all(b"[").map_err(serde_json::Error::io)?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.write_all(b",").map_err(serde_json::Error::io)?;
+                }
+                write_value(out, item)?;
+            }
+            out.write_all(b"]").map_err(serde_json::Error::io)
+        }
+        Value::Number(n) => write_number(out, n),
+        _ => serde_json::to_writer(out, value),
+    }
+}
+
+// Past this not every whole number is a distinct f64.
+const MAX_EXACT_INTEGER: f64 = (1u64 << 53) as f64;
+
+fn write_number<W: Write>(out: &mut W, n: &Number) -> serde_json::Result<()> {
+    match n.as_f64() {
+        Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() <= MAX_EXACT_INTEGER => {
+            write!(out, "{}", f as i64).map_err(serde_json::Error::io)
+        }
+        _ => serde_json::to_writer(out, n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::doc;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Unsorted {
+        name: &'static str,
+        id: &'static str,
+        global_version: i32,
+    }
+
+    #[test]
+    fn keys_are_sorted_whatever_the_input_order() {
+        let from_struct = Unsorted {
+            name: "Box",
+            id: "R-1",
+            global_version: 2,
+        };
+        let expected = r#"{"globalVersion":2,"id":"R-1","name":"Box"}"#;
+        assert_eq!(to_string(&from_struct).unwrap(), expected);
+        for text in [
+            r#"{"name": "Box", "id": "R-1", "globalVersion": 2}"#,
+            r#"{"globalVersion": 2, "name": "Box", "id": "R-1"}"#,
+        ] {
+            let value: Value = serde_json::from_str(text).unwrap();
+            assert_eq!(to_string(&value).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn numbers_are_written_by_value() {
+        let cases = [
+            (json!(-0.0), "0"),
+            (json!(0.0), "0"),
+            (json!(0), "0"),
+            (json!(1.0), "1"),
+            (json!(-3), "-3"),
+            (json!(1.5), "1.5"),
+            (json!(0.1), "0.1"),
+            (json!(-2.5e-8), "-2.5e-8"),
+            (json!(9007199254740992.0), "9007199254740992"),
+            (json!(1e300), "1e+300"),
+            (json!(u64::MAX), "18446744073709551615"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(to_string(&value).unwrap(), expected, "{}", value);
+        }
+        assert_eq!(to_vec(&json!(-0.0)).unwrap(), to_vec(&json!(0)).unwrap());
+    }
+
+    #[test]
+    fn nested_values_are_canonical_throughout() {
+        let value = json!({
+            "z": [{ "b": 1.0, "a": [] }, [true, null, "ünï \"q\"\n"]],
+            "a": { "y": {}, "x": [[-0.0]] },
+        });
+        assert_eq!(
+            to_string(&value).unwrap(),
+            r#"{"a":{"x":[[0]],"y":{}},"z":[{"a":[],"b":1},[true,null,"ünï \"q\"\n"]]}"#
+        );
+    }
+
+    #[test]
+    fn digests_are_fixed() {
+        let record = json!({
+            "id": "R-1",
+            "ownerId": "U-test",
+            "name": "Box",
+            "globalVersion": 2,
+            "tags": ["a", "b"],
+        });
+        assert_eq!(
+            to_string(&record).unwrap(),
+            r#"{"globalVersion":2,"id":"R-1","name":"Box","ownerId":"U-test","tags":["a","b"]}"#
+        );
+        assert_eq!(
+            digest(&record).unwrap().as_str(),
+            "ba34ccf2fb946e90f8fcbba3b688a0d1cf4419ddc09de48bb20fa4c8a5947c65"
+        );
+
+        let document =
+            doc! { "Object": { "ID": "root", "Scale": 1.5 }, "Assets": [], "Version": 3_i64 };
+        assert_eq!(
+            document_to_vec(&document).unwrap(),
+            br#"{"Assets":[],"Object":{"ID":"root","Scale":{"$numberDouble":"1.5"}},"Version":{"$numberLong":"3"}}"#
+        );
+        assert_eq!(
+            document_digest(&document).unwrap().as_str(),
+            "0aa5b15b009c10d32504e1d2be45305e983adf6f6e620c4cccdf820261b864bd"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use super::{
+    backup::{Error, UnknownDirPolicy},
+    convert::ExternalConverter,
+    metrics::MetricDef,
+    naming::NamingPolicy,
+    validate::ValidationProfile,
+    voice::WhisperCli,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Config {
+    // An IANA name like "Europe/Berlin", or "local"; reports use UTC without one.
+    pub timezone: Option<String>,
+    // Extra asset directories searched after a backup's own Assets folder.
+    pub asset_roots: Vec<PathBuf>,
+    // User ID to the name reports should show, for when the backup's is stale or missing.
+    pub aliases: BTreeMap<String, String>,
+    // Extra metrics stats evaluates next to the built-in ones.
+    pub metrics: Vec<MetricDef>,
+    // Programs that convert between asset formats this crate has no converter for.
+    pub converters: Vec<ExternalConverter>,
+    // Component allow lists for validate, next to the built-in ones.
+    pub profiles: Vec<ValidationProfile>,
+    // Extensions and path templates for the files exports extract.
+    pub naming: NamingPolicy,
+    // How to run whisper-cli for voice message transcripts.
+    pub whisper: Option<WhisperCli>,
+    // What --locale takes, for when it isn't given.
+    pub locale: Option<String>,
+    // Globs for files the loader skips, next to the built-in ones.
+    pub ignore: Vec<String>,
+    // What --unknown-dirs takes, for when it isn't given.
+    pub unknown_dirs: Option<UnknownDirPolicy>,
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("neos-full-statbox").join("config.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| Error::Config(e, path.to_owned()))
+    }
+
+    // An explicit path must exist, the default location is optional.
+    pub fn load_or_default(path: Option<&Path>) -> Result<Self, Error> {
+        match path {
+            Some(path) => Self::load(path),
+            None => match Self::default_path() {
+                Some(path) if path.is_file() => Self::load(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use super::{
+    backup::{from_file, is_sidecar, Backup, Error, ErrorInfo, FromFile, Message, Record},
+    format::FormatVersion,
+    lock::LockError,
+    orphans::move_file,
+    warnings::Warnings,
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    cmp::Ordering,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConflictError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Lock(#[from] LockError),
+    #[error("quarantine {} is inside the backup, where it would load as an account", .0.display())]
+    QuarantineInBackup(PathBuf),
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictKind {
+    Record,
+    Message,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Resolution {
+    // The original is newer, or the copy says the same.
+    KeepOriginal,
+    // The copy is newer or the original is gone, and replaces the original.
+    KeepCopy,
+    // Neither is newer and they differ, or either can't be read, which may be a file
+    // cut short mid-sync just as well as one from a newer schema.
+    Manual,
+}
+
+// One side of a conflict: the version it claims, or why it couldn't be read.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictSide {
+    pub path: PathBuf,
+    // globalVersion for records, lastUpdateTime for messages.
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorInfo>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub account: RcStr,
+    pub kind: ConflictKind,
+    // The file the copy conflicts with, which may no longer exist.
+    pub original: ConflictSide,
+    pub copy: ConflictSide,
+    pub resolution: Resolution,
+}
+
+// The name a sync tool's conflict copy was made from, like R-1.json for Syncthing's
+// R-1.sync-conflict-20240102-030405-ABCDEFG.json or Dropbox's
+// R-1 (alice's conflicted copy 2024-01-02).json.
+pub fn original_name(name: &str) -> Option<String> {
+    if let Some(start) = name.find(".sync-conflict-") {
+        let rest = &name[start + 1..];
+        let ext = rest.rfind('.').map_or("", |dot| &rest[dot..]);
+        return Some(format!("{}{}", &name[..start], ext));
+    }
+    let start = name.find(" (")?;
+    let end = start + name[start..].find(')')?;
+    if !name[start..end].contains("conflicted copy") {
+        return None;
+    }
+    Some(format!("{}{}", &name[..start], &name[end + 1..]))
+}
+
+// How one version of a file compares to another, so the newer one can win.
+trait Versioned: FromFile {
+    type Version: Ord + ToString;
+
+    fn version(&self) -> Self::Version;
+}
+
+impl Versioned for Record {
+    type Version = i32;
+
+    fn version(&self) -> i32 {
+        self.global_version
+    }
+}
+
+impl Versioned for Message {
+    type Version = DateTime<Utc>;
+
+    fn version(&self) -> DateTime<Utc> {
+        self.last_update_time
+    }
+}
+
+impl Backup {
+    // Every sync conflict copy of a record or message in the loaded accounts, whether or
+    // not the ignore rules kept it out of the load, with how it would be resolved.
+    pub fn find_conflicts(&self) -> io::Result<Vec<SyncConflict>> {
+        let mut conflicts = Vec::new();
+        for account in self.accounts.keys() {
+            let dir = self.root.join(account.as_str());
+            let records = dir.join("Records");
+            if records.is_dir() {
+                find_in::<Record>(account, &records, self.format, &mut conflicts)?;
+            }
+            let messages = dir.join("Messages");
+            if !messages.is_dir() {
+                continue;
+            }
+            for contact in messages.read_dir()? {
+                let contact = contact?;
+                if contact.file_type()?.is_dir() {
+                    find_in::<Message>(account, &contact.path(), self.format, &mut conflicts)?;
+                }
+            }
+        }
+        conflicts.sort_by(|a, b| (&a.account, &a.copy.path).cmp(&(&b.account, &b.copy.path)));
+        Ok(conflicts)
+    }
+
+    // Applies every automatic resolution, moving the losing version into quarantine when
+    // given one and deleting it otherwise. Conflicts needing manual review are left alone.
+    // Returns every conflict found, resolved or not.
+    pub fn resolve_conflicts(
+        &self,
+        quarantine: Option<&Path>,
+        dry_run: bool,
+    ) -> Result<Vec<SyncConflict>, ConflictError> {
+        if let Some(quarantine) = quarantine {
+            if std::path::absolute(quarantine)?.starts_with(std::path::absolute(&self.root)?) {
+                return Err(ConflictError::QuarantineInBackup(quarantine.to_owned()));
+            }
+        }
+        let conflicts = self.find_conflicts()?;
+        if dry_run {
+            return Ok(conflicts);
+        }
+        let _lock = self.lock()?;
+        let mut resolved = Vec::with_capacity(conflicts.len());
+        for conflict in conflicts {
+            // Compared again, since an earlier copy of the same file may have replaced it.
+            let (account, original, copy) = (
+                &conflict.account,
+                conflict.original.path,
+                conflict.copy.path,
+            );
+            let conflict = match conflict.kind {
+                ConflictKind::Record => {
+                    compare::<Record>(account, conflict.kind, original, copy, self.format)
+                }
+                ConflictKind::Message => {
+                    compare::<Message>(account, conflict.kind, original, copy, self.format)
+                }
+            };
+            let (original, copy) = (&conflict.original.path, &conflict.copy.path);
+            let loser = match conflict.resolution {
+                Resolution::Manual => {
+                    resolved.push(conflict);
+                    continue;
+                }
+                Resolution::KeepOriginal => copy,
+                Resolution::KeepCopy if quarantine.is_none() || !original.exists() => {
+                    fs::rename(copy, original)?;
+                    resolved.push(conflict);
+                    continue;
+                }
+                Resolution::KeepCopy => original,
+            };
+            match quarantine {
+                // Under the copy's name either way, which says which conflict it lost.
+                Some(quarantine) => {
+                    let to = quarantine.join(copy.strip_prefix(&self.root).unwrap_or(copy));
+                    fs::create_dir_all(to.parent().unwrap_or(quarantine))?;
+                    move_file(loser, &to)?;
+                }
+                None => fs::remove_file(loser)?,
+            }
+            if conflict.resolution == Resolution::KeepCopy {
+                fs::rename(copy, original)?;
+            }
+            resolved.push(conflict);
+        }
+        Ok(resolved)
+    }
+}
+
+fn find_in<T: Versioned>(
+    account: &RcStr,
+    dir: &Path,
+    format: FormatVersion,
+    conflicts: &mut Vec<SyncConflict>,
+) -> io::Result<()> {
+    let kind = if dir.ends_with("Records") {
+        ConflictKind::Record
+    } else {
+        ConflictKind::Message
+    };
+    for entry in dir.read_dir()? {
+        let copy = entry?.path();
+        let name = copy.file_name().unwrap_or_default().to_string_lossy();
+        let Some(original) = original_name(&name) else {
+            continue;
+        };
+        if is_sidecar(&original) {
+            continue;
+        }
+        let original = dir.join(original);
+        conflicts.push(compare::<T>(account, kind, original, copy, format));
+    }
+    Ok(())
+}
+
+fn compare<T: Versioned>(
+    account: &RcStr,
+    kind: ConflictKind,
+    original: PathBuf,
+    copy: PathBuf,
+    format: FormatVersion,
+) -> SyncConflict {
+    let read = |path: &PathBuf| -> Result<T, Error> {
+        if !path.exists() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no such file").into());
+        }
+        // What loading would tolerate is in the comparison already, no need to warn.
+        from_file(path.clone(), format, &Warnings::default())
+    };
+    let (ours, theirs) = (read(&original), read(&copy));
+    let resolution = match (&ours, &theirs) {
+        (Ok(ours), Ok(theirs)) => match theirs.version().cmp(&ours.version()) {
+            Ordering::Greater => Resolution::KeepCopy,
+            Ordering::Less => Resolution::KeepOriginal,
+            Ordering::Equal if same_json(&original, &copy) => Resolution::KeepOriginal,
+            Ordering::Equal => Resolution::Manual,
+        },
+        (Err(Error::Io(e)), Ok(_)) if e.kind() == io::ErrorKind::NotFound => Resolution::KeepCopy,
+        _ => Resolution::Manual,
+    };
+    let side = |path: PathBuf, parsed: Result<T, Error>| match parsed {
+        Ok(parsed) => ConflictSide {
+            path,
+            version: Some(parsed.version().to_string()),
+            error: None,
+        },
+        Err(e) => ConflictSide {
+            path,
+            version: None,
+            error: Some(ErrorInfo::from(&e)),
+        },
+    };
+    SyncConflict {
+        account: account.clone(),
+        kind,
+        original: side(original, ours),
+        copy: side(copy, theirs),
+        resolution,
+    }
+}
+
+// Whether the two files hold the same JSON, however it's laid out.
+fn same_json(a: &Path, b: &Path) -> bool {
+    let parse =
+        |p: &Path| -> Option<serde_json::Value> { serde_json::from_slice(&fs::read(p).ok()?).ok() };
+    matches!((parse(a), parse(b)), (Some(a), Some(b)) if a == b)
+}
@@ -0,0 +1,202 @@
+use super::{
+    backup::{AssetStoreUnavailable, Backup},
+    preview::PreviewError,
+};
+use serde::Deserialize;
+use std::{io, path::Path, process};
+#[cfg(any(feature = "images", feature = "audio"))]
+use {super::preview, std::fs};
+
+// Turns an asset of one sniffed kind into a file of another format. The built-in ones sit
+// behind the same features as their codecs; others can be registered by crates embedding
+// this one, or declared in the config as external commands.
+pub trait Converter: Send + Sync {
+    fn name(&self) -> &str;
+    // Sniffed asset kinds this reads, as sniff::sniff names them.
+    fn inputs(&self) -> Vec<&str>;
+    // Extension of what it writes.
+    fn output(&self) -> &str;
+    fn convert(&self, input: &Path, output: &Path) -> Result<(), ConvertError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConvertError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Preview(#[from] PreviewError),
+    #[error("no asset {0}")]
+    Missing(String),
+    #[error(transparent)]
+    Unavailable(#[from] AssetStoreUnavailable),
+    #[error("no converter from {0} to {1}")]
+    NoConverter(String, String),
+    #[error("{0} failed: {1}")]
+    Command(String, String),
+}
+
+pub struct Converters {
+    converters: Vec<Box<dyn Converter>>,
+}
+
+impl Converters {
+    pub fn empty() -> Self {
+        Self {
+            converters: Vec::new(),
+        }
+    }
+
+    // The converters compiled into this build.
+    pub fn builtin() -> Self {
+        #[allow(unused_mut)]
+        let mut converters = Self::empty();
+        #[cfg(feature = "images")]
+        converters.register(Box::new(ImageToPng));
+        #[cfg(feature = "audio")]
+        converters.register(Box::new(OggToWav));
+        converters
+    }
+
+    // Later registrations win, so a plugin can replace a built-in converter.
+    pub fn register(&mut self, converter: Box<dyn Converter>) {
+        self.converters.push(converter);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Converter> {
+        self.converters.iter().map(|c| c.as_ref())
+    }
+
+    pub fn find(&self, input: &str, output: &str) -> Option<&dyn Converter> {
+        self.converters
+            .iter()
+            .rev()
+            .map(|c| c.as_ref())
+            .find(|c| c.output() == output && c.inputs().contains(&input))
+    }
+
+    // Converts one asset from the backup, returning the converter that did it.
+    pub fn convert_asset(
+        &self,
+        backup: &Backup,
+        hash: &str,
+        output: &str,
+        out: &Path,
+    ) -> Result<&dyn Converter, ConvertError> {
+        if !backup.has_asset_store() {
+            return Err(AssetStoreUnavailable.into());
+        }
+        let path = backup
+            .asset_path(hash)
+            .ok_or_else(|| ConvertError::Missing(hash.to_owned()))?;
+        let kind = backup.sniff_asset(hash)?.unwrap_or("unknown");
+        let converter = self
+            .find(kind, output)
+            .ok_or_else(|| ConvertError::NoConverter(kind.to_owned(), output.to_owned()))?;
+        converter.convert(&path, out)?;
+        Ok(converter)
+    }
+}
+
+impl Default for Converters {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(feature = "images")]
+struct ImageToPng;
+
+#[cfg(feature = "images")]
+impl Converter for ImageToPng {
+    fn name(&self) -> &str {
+        "image-to-png"
+    }
+
+    fn inputs(&self) -> Vec<&str> {
+        vec!["webp", "png", "jpg", "gif"]
+    }
+
+    fn output(&self) -> &str {
+        "png"
+    }
+
+    fn convert(&self, input: &Path, output: &Path) -> Result<(), ConvertError> {
+        let png = preview::resize_to_png(&fs::read(input)?, None)?;
+        Ok(fs::write(output, png)?)
+    }
+}
+
+#[cfg(feature = "audio")]
+struct OggToWav;
+
+#[cfg(feature = "audio")]
+impl Converter for OggToWav {
+    fn name(&self) -> &str {
+        "ogg-to-wav"
+    }
+
+    fn inputs(&self) -> Vec<&str> {
+        vec!["ogg"]
+    }
+
+    fn output(&self) -> &str {
+        "wav"
+    }
+
+    fn convert(&self, input: &Path, output: &Path) -> Result<(), ConvertError> {
+        let wav = preview::ogg_to_wav(fs::File::open(input)?)?;
+        Ok(fs::write(output, wav)?)
+    }
+}
+
+// A converter that is some other program, from the config:
+//
+//   [[converters]]
+//   name = "meshx-to-fbx"
+//   inputs = ["meshx"]
+//   output = "fbx"
+//   command = ["meshx2fbx", "{input}", "{output}"]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExternalConverter {
+    pub name: String,
+    pub inputs: Vec<String>,
+    pub output: String,
+    // Program and arguments, with {input} and {output} replaced by the file paths.
+    pub command: Vec<String>,
+}
+
+impl Converter for ExternalConverter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn inputs(&self) -> Vec<&str> {
+        self.inputs.iter().map(String::as_str).collect()
+    }
+
+    fn output(&self) -> &str {
+        &self.output
+    }
+
+    fn convert(&self, input: &Path, output: &Path) -> Result<(), ConvertError> {
+        let Some((program, args)) = self.command.split_first() else {
+            return Err(ConvertError::Command(
+                self.name.clone(),
+                "no command given".to_owned(),
+            ));
+        };
+        let args = args.iter().map(|arg| {
+            arg.replace("{input}", &input.to_string_lossy())
+                .replace("{output}", &output.to_string_lossy())
+        });
+        let result = process::Command::new(program).args(args).output()?;
+        if !result.status.success() {
+            return Err(ConvertError::Command(
+                self.name.clone(),
+                String::from_utf8_lossy(&result.stderr).trim().to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
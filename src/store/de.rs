@@ -1,6 +1,22 @@
-use serde::{Deserialize, Deserializer};
+use chrono::{DateTime, Utc};
+use serde::{de::Error, Deserialize, Deserializer};
+use std::{borrow::Cow, cell::Cell};
 
-use super::RcStr;
+use super::{
+    format::{is_never, parse_timestamp},
+    RcStr,
+};
+
+thread_local! {
+    // Set when lenient_time drops a timestamp, for the file it was in to be looked at
+    // again for which one it was.
+    static DROPPED: Cell<bool> = const { Cell::new(false) };
+}
+
+// Whether lenient_time dropped a timestamp since this was last asked, on this thread.
+pub(crate) fn take_dropped() -> bool {
+    DROPPED.with(|dropped| dropped.replace(false))
+}
 
 pub fn null_to_default<'de, D, T>(d: D) -> Result<T, D::Error>
 where
@@ -10,12 +26,29 @@ where
     Deserialize::deserialize(d).map(|o: Option<T>| o.unwrap_or_default())
 }
 
-pub fn err_to_none<'de, D, T>(d: D) -> Result<Option<T>, D::Error>
+// Every backup format's timestamps, whichever format the backup was detected as, since
+// detection only samples a few files. What still doesn't parse is None, and noted for
+// from_file to warn about.
+pub fn lenient_time<'de, D>(d: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let time: Option<Cow<'de, str>> = Deserialize::deserialize(d)?;
+    let parsed = time.as_deref().and_then(parse_timestamp);
+    if parsed.is_none() && time.is_some_and(|time| !is_never(&time)) {
+        DROPPED.with(|dropped| dropped.set(true));
+    }
+    Ok(parsed)
+}
+
+// lenient_time for the times a file can't do without, one that still doesn't parse fails
+// the file like any other bad field.
+pub fn required_lenient_time<'de, D>(d: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: Deserializer<'de>,
-    T: Deserialize<'de>,
 {
-    Ok(Deserialize::deserialize(d).ok())
+    let time: Cow<'de, str> = Deserialize::deserialize(d)?;
+    parse_timestamp(&time).ok_or_else(|| D::Error::custom(format!("{:?} isn't a time", time)))
 }
 
 pub fn option_split_backslashes<'de, D>(d: D) -> Result<Vec<RcStr>, D::Error>
@@ -0,0 +1,128 @@
+use super::{
+    backup::{Backup, Error, Manifest, SZBson},
+    redact::{Redact, Redactor},
+    RcStr,
+};
+use bson::{Bson, Document};
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+// Everything needed to reproduce a manifest parse failure outside of the backup.
+#[derive(Debug)]
+pub struct DebugBundle {
+    pub asset: RcStr,
+    pub record: Option<RcStr>,
+    pub error: String,
+    // Dotted path to the field the typed model choked on, when the BSON itself was readable.
+    pub path: Option<String>,
+    pub raw: Vec<u8>,
+    pub tree: Option<Document>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary<'a> {
+    asset: &'a RcStr,
+    record: &'a Option<RcStr>,
+    error: &'a str,
+    path: &'a Option<String>,
+}
+
+impl DebugBundle {
+    // Returns None when the manifest parses fine.
+    pub fn capture(
+        backup: &Backup,
+        asset: &SZBson,
+        record: Option<RcStr>,
+    ) -> Result<Option<Self>, Error> {
+        let raw = asset.read_raw(backup)?;
+        let mut bundle = Self {
+            asset: asset.0.clone(),
+            record,
+            error: String::new(),
+            path: None,
+            raw,
+            tree: None,
+        };
+
+        let tree = match bson::RawDocumentBuf::from_bytes(bundle.raw.clone())
+            .and_then(|doc| doc.to_document())
+        {
+            Ok(tree) => tree,
+            Err(e) => {
+                bundle.error = e.to_string();
+                return Ok(Some(bundle));
+            }
+        };
+
+        let de = bson::Deserializer::new(Bson::Document(tree.clone()));
+        let res: Result<Manifest, _> = serde_path_to_error::deserialize(de);
+        match res {
+            Ok(_) => Ok(None),
+            Err(e) => {
+                bundle.path = Some(e.path().to_string());
+                bundle.error = e.into_inner().to_string();
+                bundle.tree = Some(tree);
+                Ok(Some(bundle))
+            }
+        }
+    }
+
+    // Writes raw.bson, tree.ron and error.json into <dir>/<asset>/.
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        let dir = dir.join(self.asset.as_str());
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("raw.bson"), &self.raw)?;
+        if let Some(tree) = &self.tree {
+            writeln!(fs::File::create(dir.join("tree.ron"))?, "{:#?}", tree)?;
+        }
+        let summary = Summary {
+            asset: &self.asset,
+            record: &self.record,
+            error: &self.error,
+            path: &self.path,
+        };
+        serde_json::to_writer_pretty(fs::File::create(dir.join("error.json"))?, &summary)?;
+        Ok(dir)
+    }
+}
+
+impl Redact for DebugBundle {
+    // Free text in the tree is blanked and the raw bytes re-encoded from it. Type names
+    // and keys are kept since they are what the bundle is for. Undecodable BSON can't be
+    // scrubbed, so it is dropped rather than shared.
+    fn redact(&mut self, r: &Redactor) {
+        if !r.is_active() {
+            return;
+        }
+        self.record = self.record.as_ref().map(|id| r.text(id));
+        match &mut self.tree {
+            Some(tree) => {
+                redact_document(tree, r);
+                self.raw = bson::to_vec(tree).unwrap_or_default();
+            }
+            None => self.raw.clear(),
+        }
+    }
+}
+
+fn redact_document(doc: &mut Document, r: &Redactor) {
+    for (key, value) in doc.iter_mut() {
+        if key != "Type" && key != "ID" {
+            redact_bson(value, r);
+        }
+    }
+}
+
+fn redact_bson(value: &mut Bson, r: &Redactor) {
+    match value {
+        Bson::String(s) => *s = r.message(&s.clone().into()).to_string(),
+        Bson::Array(values) => values.iter_mut().for_each(|v| redact_bson(v, r)),
+        Bson::Document(doc) => redact_document(doc, r),
+        _ => {}
+    }
+}
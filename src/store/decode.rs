@@ -0,0 +1,115 @@
+use super::{
+    backup::{Backup, Ogg, Webp, WellKnownAssetKind},
+    features::MissingFeature,
+};
+#[cfg(feature = "images")]
+use image::{ImageDecoder, ImageFormat, ImageReader};
+use serde::Serialize;
+use std::io;
+#[cfg(any(feature = "images", feature = "audio"))]
+use std::io::BufReader;
+
+#[derive(thiserror::Error, Debug)]
+pub enum DecodeError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Disabled(#[from] MissingFeature),
+    #[cfg(feature = "images")]
+    #[error("Image: {0}")]
+    Image(#[from] image::ImageError),
+    #[cfg(feature = "audio")]
+    #[error("Vorbis: {0}")]
+    Vorbis(#[from] lewton::VorbisError),
+    #[cfg(feature = "audio")]
+    #[error("Ogg: {0}")]
+    Ogg(#[from] ogg::OggReadError),
+}
+
+// What a texture is, read from its header without decoding the pixels.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    pub has_alpha: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioInfo {
+    pub sample_rate: u32,
+    pub channels: u8,
+    // From the last page's granule position, None when no audio page has one.
+    pub duration_secs: Option<f64>,
+}
+
+impl WellKnownAssetKind<ImageInfo> for Webp {
+    type ParserError = DecodeError;
+
+    #[cfg(feature = "images")]
+    fn open(&self, b: &Backup) -> Result<ImageInfo, DecodeError> {
+        let asset = BufReader::new(b.open_asset(&self.0)?);
+        // Guessed, since Neos stores some textures as PNG or JPEG behind a .webp URL.
+        let decoder = ImageReader::new(asset)
+            .with_guessed_format()?
+            .into_decoder()?;
+        let (width, height) = decoder.dimensions();
+        Ok(ImageInfo {
+            width,
+            height,
+            has_alpha: decoder.color_type().has_alpha(),
+        })
+    }
+
+    #[cfg(not(feature = "images"))]
+    fn open(&self, _: &Backup) -> Result<ImageInfo, DecodeError> {
+        Err(MissingFeature("images").into())
+    }
+}
+
+#[cfg(feature = "images")]
+impl WellKnownAssetKind<image::DynamicImage> for Webp {
+    type ParserError = DecodeError;
+
+    fn open(&self, b: &Backup) -> Result<image::DynamicImage, DecodeError> {
+        let asset = BufReader::new(b.open_asset(&self.0)?);
+        let reader = ImageReader::with_format(asset, ImageFormat::WebP).with_guessed_format()?;
+        Ok(reader.decode()?)
+    }
+}
+
+impl WellKnownAssetKind<AudioInfo> for Ogg {
+    type ParserError = DecodeError;
+
+    #[cfg(feature = "audio")]
+    fn open(&self, b: &Backup) -> Result<AudioInfo, DecodeError> {
+        let asset = BufReader::new(b.open_asset(&self.0)?);
+        let stream = lewton::inside_ogg::OggStreamReader::new(asset)?;
+        let (sample_rate, channels) = (
+            stream.ident_hdr.audio_sample_rate,
+            stream.ident_hdr.audio_channels,
+        );
+        let serial = stream.stream_serial();
+        // Only the pages' positions are needed, so the packets aren't decoded.
+        let mut packets = stream.into_inner();
+        let mut samples = None;
+        while let Some(packet) = packets.read_packet()? {
+            if packet.stream_serial() == serial && packet.absgp_page() != u64::MAX {
+                samples = Some(packet.absgp_page());
+            }
+        }
+        Ok(AudioInfo {
+            sample_rate,
+            channels,
+            duration_secs: samples
+                .filter(|_| sample_rate > 0)
+                .map(|samples| samples as f64 / sample_rate as f64),
+        })
+    }
+
+    #[cfg(not(feature = "audio"))]
+    fn open(&self, _: &Backup) -> Result<AudioInfo, DecodeError> {
+        Err(MissingFeature("audio").into())
+    }
+}
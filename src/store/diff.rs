@@ -0,0 +1,332 @@
+use super::{
+    backup::{Backup, Contact, MessageType, Record},
+    canonical, RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+// What changed from one snapshot of a backup to a later one.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupDiff {
+    pub unchanged_records: u64,
+    pub added_records: Vec<RecordVersion>,
+    pub removed_records: Vec<RecordVersion>,
+    pub modified_records: Vec<ModifiedRecord>,
+    pub contacts: Vec<ContactChange>,
+    // Oldest first.
+    pub new_messages: Vec<NewMessage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordVersion {
+    pub account: RcStr,
+    pub id: RcStr,
+    pub name: RcStr,
+    pub global_version: i32,
+    pub local_version: i32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifiedRecord {
+    pub before: RecordVersion,
+    pub after: RecordVersion,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "change")]
+pub enum ContactChangeKind {
+    Added,
+    Removed,
+    // The fields that differ. How the contact was last seen online changes with every
+    // snapshot, so it isn't compared.
+    Changed { fields: Vec<&'static str> },
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactChange {
+    pub account: RcStr,
+    pub id: RcStr,
+    pub username: RcStr,
+    #[serde(flatten)]
+    pub kind: ContactChangeKind,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NewMessage {
+    pub account: RcStr,
+    pub contact: RcStr,
+    pub id: RcStr,
+    pub sender_id: RcStr,
+    pub message_type: MessageType,
+    pub send_time: DateTime<Utc>,
+}
+
+impl BackupDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_records.is_empty()
+            && self.removed_records.is_empty()
+            && self.modified_records.is_empty()
+            && self.contacts.is_empty()
+            && self.new_messages.is_empty()
+    }
+}
+
+impl Backup {
+    // Records are matched by account and ID and count as modified when either version
+    // differs, which is what Neos bumps on every save.
+    pub fn diff(&self, after: &Backup) -> BackupDiff {
+        let mut diff = BackupDiff::default();
+        let before_records = records(self);
+        let after_records = records(after);
+        for (key, before) in &before_records {
+            match after_records.get(key) {
+                Some(now)
+                    if now.global_version == before.global_version
+                        && now.local_version == before.local_version =>
+                {
+                    diff.unchanged_records += 1
+                }
+                Some(now) => diff.modified_records.push(ModifiedRecord {
+                    before: version(key.0, before),
+                    after: version(key.0, now),
+                }),
+                None => diff.removed_records.push(version(key.0, before)),
+            }
+        }
+        diff.added_records = after_records
+            .iter()
+            .filter(|(key, _)| !before_records.contains_key(*key))
+            .map(|((account, _), rec)| version(account, rec))
+            .collect();
+
+        let before_contacts = contacts(self);
+        let after_contacts = contacts(after);
+        for (key, before) in &before_contacts {
+            let kind = match after_contacts.get(key) {
+                Some(now) => {
+                    let fields = changed_fields(before, now);
+                    if fields.is_empty() {
+                        continue;
+                    }
+                    ContactChangeKind::Changed { fields }
+                }
+                None => ContactChangeKind::Removed,
+            };
+            diff.contacts.push(contact_change(key.0, before, kind));
+        }
+        for (key, now) in &after_contacts {
+            if !before_contacts.contains_key(key) {
+                let change = contact_change(key.0, now, ContactChangeKind::Added);
+                diff.contacts.push(change);
+            }
+        }
+
+        let mut seen = BTreeSet::new();
+        for (account_name, account) in &self.accounts {
+            for (contact, messages) in &account.messages {
+                seen.extend(messages.iter().map(|msg| (account_name, contact, &msg.id)));
+            }
+        }
+        for (account_name, account) in &after.accounts {
+            for (contact, messages) in &account.messages {
+                for msg in messages {
+                    if seen.contains(&(account_name, contact, &msg.id)) {
+                        continue;
+                    }
+                    diff.new_messages.push(NewMessage {
+                        account: account_name.clone(),
+                        contact: contact.clone(),
+                        id: msg.id.clone(),
+                        sender_id: msg.sender(account_name, contact).clone(),
+                        message_type: msg.message_type.clone(),
+                        send_time: msg.send_time,
+                    });
+                }
+            }
+        }
+        diff.new_messages.sort_by_key(|msg| msg.send_time);
+        diff
+    }
+}
+
+fn records(backup: &Backup) -> BTreeMap<(&RcStr, &RcStr), &Record> {
+    let mut records = BTreeMap::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            records.insert((account_name, &rec.id), rec);
+        }
+    }
+    records
+}
+
+fn contacts(backup: &Backup) -> BTreeMap<(&RcStr, &RcStr), &Contact> {
+    let mut contacts = BTreeMap::new();
+    for (account_name, account) in &backup.accounts {
+        for contact in account.contacts.values() {
+            contacts.insert((account_name, &contact.id), contact);
+        }
+    }
+    contacts
+}
+
+fn version(account: &RcStr, rec: &Record) -> RecordVersion {
+    RecordVersion {
+        account: account.clone(),
+        id: rec.id.clone(),
+        name: rec.name.clone(),
+        global_version: rec.global_version,
+        local_version: rec.local_version,
+    }
+}
+
+fn contact_change(account: &RcStr, contact: &Contact, kind: ContactChangeKind) -> ContactChange {
+    ContactChange {
+        account: account.clone(),
+        id: contact.id.clone(),
+        username: contact.friend_username.clone(),
+        kind,
+    }
+}
+
+fn changed_fields(before: &Contact, after: &Contact) -> Vec<&'static str> {
+    // Compared in canonical form, so a profile differs only when its content does.
+    let same_profile =
+        canonical::to_vec(&before.profile).ok() == canonical::to_vec(&after.profile).ok();
+    let fields = [
+        (
+            "friendUsername",
+            before.friend_username == after.friend_username,
+        ),
+        (
+            "alternateUsernames",
+            before.alternate_usernames == after.alternate_usernames,
+        ),
+        ("friendStatus", before.friend_status == after.friend_status),
+        ("isAccepted", before.is_accepted == after.is_accepted),
+        ("profile", same_profile),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, unchanged)| !unchanged)
+        .map(|(field, _)| field)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::testing::{record, TempBackup};
+    use serde_json::json;
+    use std::fs;
+
+    fn snapshot(
+        name: &str,
+        records: &[(&str, i32, i32)],
+        contacts: &[(&str, &str)],
+        messages: &[&str],
+    ) -> Backup {
+        let temp = TempBackup::new(name);
+        for (id, global, local) in records {
+            let mut rec = record(id, "U-owner", "Inventory");
+            rec["globalVersion"] = json!(global);
+            rec["localVersion"] = json!(local);
+            temp.write_record("U-owner", &rec);
+        }
+        let account = temp.root.join("U-owner");
+        fs::create_dir_all(account.join("Contacts")).unwrap();
+        for (id, username) in contacts {
+            let mut contact = serde_json::to_value(Contact::default()).unwrap();
+            contact["id"] = json!(id);
+            contact["friendUsername"] = json!(username);
+            let path = account.join("Contacts").join(format!("{}.json", id));
+            fs::write(path, serde_json::to_vec(&contact).unwrap()).unwrap();
+        }
+        let thread = account.join("Messages").join("U-friend");
+        fs::create_dir_all(&thread).unwrap();
+        for (i, id) in messages.iter().enumerate() {
+            let time = format!("2021-03-0{}T05:06:07Z", i + 1);
+            let message = json!({
+                "id": id, "ownerId": "U-owner", "recipientId": "U-owner",
+                "messageType": "Text", "content": "hi", "sendTime": time,
+                "lastUpdateTime": time,
+            });
+            let path = thread.join(format!("{}.json", id));
+            fs::write(path, serde_json::to_vec(&message).unwrap()).unwrap();
+        }
+        temp.load()
+    }
+
+    #[test]
+    fn diff_classifies_changes() {
+        let before = snapshot(
+            "diff-before",
+            &[
+                ("R-same", 1, 1),
+                ("R-local", 1, 1),
+                ("R-global", 1, 1),
+                ("R-gone", 1, 1),
+            ],
+            &[("U-friend", "friend"), ("U-left", "left")],
+            &["M-1"],
+        );
+        let after = snapshot(
+            "diff-after",
+            &[
+                ("R-same", 1, 1),
+                ("R-local", 1, 2),
+                ("R-global", 2, 1),
+                ("R-new", 1, 1),
+            ],
+            &[("U-friend", "renamed"), ("U-met", "met")],
+            &["M-1", "M-2"],
+        );
+        let diff = before.diff(&after);
+
+        assert_eq!(diff.unchanged_records, 1);
+        let ids = |versions: &[RecordVersion]| -> Vec<String> {
+            versions.iter().map(|v| v.id.to_string()).collect()
+        };
+        assert_eq!(ids(&diff.added_records), ["R-new"]);
+        assert_eq!(ids(&diff.removed_records), ["R-gone"]);
+        let modified: Vec<_> = diff
+            .modified_records
+            .iter()
+            .map(|m| {
+                (
+                    m.after.id.as_str(),
+                    m.before.local_version,
+                    m.after.local_version,
+                )
+            })
+            .collect();
+        assert_eq!(modified, [("R-global", 1, 1), ("R-local", 1, 2)]);
+
+        let contacts: Vec<_> = diff
+            .contacts
+            .iter()
+            .map(|c| (c.id.as_str(), c.kind.clone()))
+            .collect();
+        assert_eq!(
+            contacts,
+            [
+                (
+                    "U-friend",
+                    ContactChangeKind::Changed {
+                        fields: vec!["friendUsername"]
+                    }
+                ),
+                ("U-left", ContactChangeKind::Removed),
+                ("U-met", ContactChangeKind::Added),
+            ]
+        );
+        let new: Vec<_> = diff.new_messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(new, ["M-2"]);
+        assert_eq!(diff.new_messages[0].sender_id.as_str(), "U-friend");
+    }
+}
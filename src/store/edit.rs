@@ -0,0 +1,739 @@
+use super::{
+    backup::{AssetUri, Backup, Error, NeosRecAsset, RecordType, SIDECARS},
+    inventory::Inventory,
+    RcStr,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EditError {
+    #[error("no account {0} in the backup")]
+    NoAccount(String),
+    #[error("no record {0} in the backup")]
+    NoRecord(String),
+    #[error("no folder {0} in {1}")]
+    NoFolder(String, RcStr),
+    #[error("{0} is not below a root folder like Inventory")]
+    AtRoot(String),
+    #[error("can't move folder {0} into itself")]
+    IntoItself(RcStr),
+}
+
+// A change to a backup's record files. Records are edited as the JSON on disk rather
+// than written back from the typed model, so fields the model doesn't know survive.
+#[derive(Debug, Clone)]
+pub enum RecordChange {
+    // Sets a top-level field of the record.
+    Set {
+        account: RcStr,
+        file: RcStr,
+        field: &'static str,
+        value: Value,
+    },
+    // Moves the record file, with its .Storage.json sidecar, to another account.
+    Move {
+        account: RcStr,
+        file: RcStr,
+        to: RcStr,
+    },
+    Create {
+        account: RcStr,
+        file: RcStr,
+        record: Value,
+    },
+}
+
+impl fmt::Display for RecordChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordChange::Set {
+                account,
+                file,
+                field,
+                value,
+            } => write!(f, "set {} of {}/{} to {}", field, account, file, value),
+            RecordChange::Move { account, file, to } => {
+                write!(f, "move {}/{} to {}", account, file, to)
+            }
+            RecordChange::Create { account, file, .. } => write!(f, "create {}/{}", account, file),
+        }
+    }
+}
+
+// What an edit leaves behind on the records it changes, so an edited backup can be told
+// apart from the capture it started as. None of it by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct History {
+    // Raise localVersion by one per apply, like a save from the game would.
+    pub bump_version: bool,
+    // Set lastModificationTime to when the edit was applied.
+    pub touch: bool,
+    // Append what changed to the record's .Edits.json sidecar.
+    pub log: bool,
+}
+
+impl History {
+    pub fn all() -> Self {
+        Self {
+            bump_version: true,
+            touch: true,
+            log: true,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !(self.bump_version || self.touch || self.log)
+    }
+}
+
+// One change in a record's .Edits.json, which holds a list of them oldest first.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EditLogEntry {
+    pub time: DateTime<Utc>,
+    // The program and version that made the change.
+    pub tool: String,
+    pub change: String,
+    // The field's value before a set, null if it had none.
+    pub previous: Option<Value>,
+}
+
+pub fn record_path(root: &Path, account: &str, file: &str) -> PathBuf {
+    root.join(account)
+        .join("Records")
+        .join(format!("{}.json", file))
+}
+
+fn sidecar_path(root: &Path, account: &str, file: &str, suffix: &str) -> PathBuf {
+    root.join(account)
+        .join("Records")
+        .join(format!("{}{}", file, suffix))
+}
+
+pub fn edit_log_path(root: &Path, account: &str, file: &str) -> PathBuf {
+    sidecar_path(root, account, file, ".Edits.json")
+}
+
+// The record's edit log, empty if it was never edited with one kept.
+pub fn edit_log(root: &Path, account: &str, file: &str) -> Result<Vec<EditLogEntry>, Error> {
+    let path = edit_log_path(root, account, file);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_value(read(&path)?).map_err(|e| Error::SerdeJson(e, path))
+}
+
+fn read(path: &Path) -> Result<Value, Error> {
+    let content = fs::read(path)?;
+    serde_json::from_slice(&content).map_err(|e| Error::SerdeJson(e, path.to_owned()))
+}
+
+// Through a temporary file, so an interrupted write never leaves half a record.
+fn write(path: &Path, record: &Value) -> Result<(), Error> {
+    let content = serde_json::to_vec(record).map_err(|e| Error::SerdeJson(e, path.to_owned()))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn already_exists(path: &Path) -> Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+    .into()
+}
+
+// Applies the changes in order, then marks every record they touched as history asks.
+// Files that would be overwritten are caught before anything changes, other failures stop
+// at the change that failed with the ones before it applied.
+pub fn apply(root: &Path, changes: &[RecordChange], history: &History) -> Result<(), Error> {
+    let mut taken = BTreeSet::new();
+    for change in changes {
+        let dest = match change {
+            RecordChange::Set { .. } => continue,
+            RecordChange::Move { file, to, .. } => record_path(root, to, file),
+            RecordChange::Create { account, file, .. } => record_path(root, account, file),
+        };
+        if dest.exists() || !taken.insert(dest.clone()) {
+            return Err(already_exists(&dest));
+        }
+    }
+
+    let now = Utc::now();
+    let entry = |change: &RecordChange, previous| EditLogEntry {
+        time: now,
+        tool: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        change: change.to_string(),
+        previous,
+    };
+    // Where each touched record is now, with what happened to it.
+    let mut touched = BTreeMap::<(RcStr, RcStr), Vec<EditLogEntry>>::new();
+    let mut created = BTreeSet::new();
+    for change in changes {
+        match change {
+            RecordChange::Set {
+                account,
+                file,
+                field,
+                value,
+            } => {
+                let path = record_path(root, account, file);
+                let mut record = read(&path)?;
+                let previous = record
+                    .as_object_mut()
+                    .and_then(|fields| fields.insert((*field).to_owned(), value.clone()));
+                write(&path, &record)?;
+                touched
+                    .entry((account.clone(), file.clone()))
+                    .or_default()
+                    .push(entry(change, Some(previous.unwrap_or(Value::Null))));
+            }
+            RecordChange::Move { account, file, to } => {
+                let from = record_path(root, account, file);
+                let dest = record_path(root, to, file);
+                fs::create_dir_all(dest.parent().unwrap())?;
+                fs::rename(from, dest)?;
+                for suffix in SIDECARS {
+                    let sidecar = sidecar_path(root, account, file, suffix);
+                    if sidecar.exists() {
+                        fs::rename(sidecar, sidecar_path(root, to, file, suffix))?;
+                    }
+                }
+                let mut entries = touched
+                    .remove(&(account.clone(), file.clone()))
+                    .unwrap_or_default();
+                entries.push(entry(change, None));
+                touched.insert((to.clone(), file.clone()), entries);
+            }
+            RecordChange::Create {
+                account,
+                file,
+                record,
+            } => {
+                let path = record_path(root, account, file);
+                fs::create_dir_all(path.parent().unwrap())?;
+                write(&path, record)?;
+                created.insert((account.clone(), file.clone()));
+                touched
+                    .entry((account.clone(), file.clone()))
+                    .or_default()
+                    .push(entry(change, None));
+            }
+        }
+    }
+
+    if history.is_empty() {
+        return Ok(());
+    }
+    let stamp = Value::String(now.to_rfc3339_opts(SecondsFormat::Millis, true));
+    for (key, entries) in touched {
+        let (account, file) = &key;
+        // A record made by this edit starts out at its own version and time.
+        if !created.contains(&key) && (history.bump_version || history.touch) {
+            let path = record_path(root, account, file);
+            let mut record = read(&path)?;
+            if let Some(fields) = record.as_object_mut() {
+                if history.bump_version {
+                    let version = fields.get("localVersion").and_then(Value::as_i64);
+                    fields.insert("localVersion".to_owned(), json!(version.unwrap_or(0) + 1));
+                }
+                if history.touch {
+                    fields.insert("lastModificationTime".to_owned(), stamp.clone());
+                }
+            }
+            write(&path, &record)?;
+        }
+        if history.log {
+            let mut log = edit_log(root, account, file)?;
+            log.extend(entries);
+            let log = serde_json::to_value(log)
+                .map_err(|e| Error::SerdeJson(e, edit_log_path(root, account, file)))?;
+            write(&edit_log_path(root, account, file), &log)?;
+        }
+    }
+    Ok(())
+}
+
+// Sets the tags of each record that doesn't already have them as asked, adding before
+// removing, and keeping the order of those it had.
+pub fn tag_changes(
+    backup: &Backup,
+    records: &[(RcStr, RcStr)],
+    add: &[RcStr],
+    remove: &[RcStr],
+) -> Vec<RecordChange> {
+    let mut changes = Vec::new();
+    for (account, file) in records {
+        let Some(rec) = backup
+            .accounts
+            .get(account)
+            .and_then(|a| a.records.get(file))
+        else {
+            continue;
+        };
+        let mut tags = rec.tags.clone();
+        for tag in add {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags.retain(|tag| !remove.contains(tag));
+        if tags == rec.tags {
+            continue;
+        }
+        changes.push(RecordChange::Set {
+            account: account.clone(),
+            file: file.clone(),
+            field: "tags",
+            value: tags
+                .iter()
+                .map(|tag| Value::String(tag.to_string()))
+                .collect(),
+        });
+    }
+    changes
+}
+
+// Hands the records, as (account, file), over to another owner: their owner fields are
+// rewritten, their files move to the new owner's folder, and every link to them is
+// pointed at the new owner. Records the new owner already has, its own or ones with the
+// same ID it has a copy of, are left alone rather than failing the whole move.
+pub fn reown_changes(
+    backup: &Backup,
+    records: &[(RcStr, RcStr)],
+    to: &RcStr,
+    owner_name: &RcStr,
+) -> Vec<RecordChange> {
+    let mut sets = Vec::new();
+    let mut moves = Vec::new();
+    let mut moved = BTreeSet::new();
+    let taken = backup.accounts.get(to).map(|a| &a.records);
+    for (account, file) in records {
+        if account == to || taken.is_some_and(|taken| taken.contains_key(file)) {
+            continue;
+        }
+        let Some(rec) = backup
+            .accounts
+            .get(account)
+            .and_then(|a| a.records.get(file))
+        else {
+            continue;
+        };
+        for (field, value) in [("ownerId", to), ("ownerName", owner_name)] {
+            sets.push(RecordChange::Set {
+                account: account.clone(),
+                file: file.clone(),
+                field,
+                value: Value::String(value.to_string()),
+            });
+        }
+        moves.push(RecordChange::Move {
+            account: account.clone(),
+            file: file.clone(),
+            to: to.clone(),
+        });
+        moved.insert((rec.owner_id.clone(), rec.id.clone()));
+    }
+
+    // Links are rewritten before any file moves, moving links included.
+    for (account, acc) in &backup.accounts {
+        for (file, rec) in &acc.records {
+            let Some(AssetUri::NeosRec(NeosRecAsset { group_id, asset_id })) = &rec.asset_uri
+            else {
+                continue;
+            };
+            if moved.contains(&(group_id.clone(), asset_id.clone())) {
+                sets.push(RecordChange::Set {
+                    account: account.clone(),
+                    file: file.clone(),
+                    field: "assetUri",
+                    value: Value::String(format!("neosrec:///{}/{}", to, asset_id)),
+                });
+            }
+        }
+    }
+    sets.extend(moves);
+    sets
+}
+
+// An inventory path as typed, split on either slash.
+pub fn parse_path(path: &str) -> Vec<RcStr> {
+    path.split(['\\', '/'])
+        .filter(|name| !name.is_empty())
+        .map(|name| RcStr::new(name.to_owned()))
+        .collect()
+}
+
+fn join_path(path: &[RcStr]) -> Value {
+    let names: Vec<&str> = path.iter().map(|name| name.as_str()).collect();
+    Value::String(names.join("\\"))
+}
+
+// A GUID-shaped record ID derived from where the folder goes, so making the same folder
+// twice can't create two of it.
+fn directory_id(account: &str, path: &[RcStr]) -> RcStr {
+    let mut hasher = Sha256::new();
+    hasher.update(account);
+    for name in path {
+        hasher.update([0]);
+        hasher.update(name.as_bytes());
+    }
+    let hex: String = hasher.finalize()[..16]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    RcStr::new(format!(
+        "R-{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+// The Directory records for every folder along the path the account doesn't have yet.
+pub fn mkdir_changes(
+    backup: &Backup,
+    account_name: &str,
+    path: &[RcStr],
+) -> Result<Vec<RecordChange>, EditError> {
+    let (account_name, account) = backup
+        .accounts
+        .get_key_value(&RcStr::new(account_name.to_owned()))
+        .ok_or_else(|| EditError::NoAccount(account_name.to_owned()))?;
+    if path.len() < 2 {
+        return Err(EditError::AtRoot(path_string(path)));
+    }
+    let owner_name = account
+        .records
+        .values()
+        .next()
+        .map_or_else(|| account_name.clone(), |rec| rec.owner_name.clone());
+    let inventory = Inventory::build(account);
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let mut changes = Vec::new();
+    for end in 2..=path.len() {
+        if inventory
+            .folder(&path[..end])
+            .is_some_and(|f| f.record.is_some())
+        {
+            continue;
+        }
+        let id = directory_id(account_name, &path[..end]);
+        changes.push(RecordChange::Create {
+            account: account_name.clone(),
+            file: id.clone(),
+            record: json!({
+                "id": id.as_str(),
+                "ownerId": account_name.as_str(),
+                "assetUri": null,
+                "globalVersion": 0,
+                "localVersion": 0,
+                "lastModifyingUserId": account_name.as_str(),
+                "lastModifyingMachineId": null,
+                "name": path[end - 1].as_str(),
+                "description": null,
+                "recordType": "directory",
+                "ownerName": owner_name.as_str(),
+                "tags": [],
+                "path": join_path(&path[..end - 1]),
+                "thumbnailUri": null,
+                "lastModificationTime": now,
+                "creationTime": now,
+                "firstPublishTime": null,
+                "isPublic": false,
+                "isForPatrons": false,
+                "visits": 0,
+                "rating": 0,
+                "randomOrder": 0,
+                "submissions": null,
+                "neosDBmanifest": [],
+            }),
+        });
+    }
+    Ok(changes)
+}
+
+fn path_string(path: &[RcStr]) -> String {
+    join_path(path).as_str().unwrap_or_default().to_owned()
+}
+
+// Puts a record in another folder of its account. Like every edit, it's only marked as
+// modified when History asks for it.
+pub fn path_change(account: &str, file: &RcStr, path: &[RcStr]) -> RecordChange {
+    RecordChange::Set {
+        account: RcStr::new(account.to_owned()),
+        file: file.clone(),
+        field: "path",
+        value: join_path(path),
+    }
+}
+
+// Moves a record into an existing folder of its account. A folder takes everything
+// beneath it along, their paths rewritten to the new place.
+pub fn move_changes(
+    backup: &Backup,
+    record: &str,
+    dest: &[RcStr],
+) -> Result<Vec<RecordChange>, EditError> {
+    let (account_name, account, file, rec) = backup
+        .accounts
+        .iter()
+        .find_map(|(name, account)| {
+            account
+                .records
+                .iter()
+                .find(|(_, rec)| rec.id.as_str() == record)
+                .map(|(file, rec)| (name, account, file, rec))
+        })
+        .ok_or_else(|| EditError::NoRecord(record.to_owned()))?;
+    let inventory = Inventory::build(account);
+    let is_folder = inventory
+        .folder(dest)
+        .is_some_and(|f| f.record.is_some() || dest.len() == 1);
+    if !is_folder {
+        return Err(EditError::NoFolder(path_string(dest), account_name.clone()));
+    }
+
+    let mut changes = vec![path_change(account_name, file, dest)];
+    if rec.record_type == RecordType::Directory {
+        let mut old = rec.path.clone();
+        old.push(rec.name.clone());
+        if dest.starts_with(&old) {
+            return Err(EditError::IntoItself(rec.name.clone()));
+        }
+        let mut new = dest.to_vec();
+        new.push(rec.name.clone());
+        for (file, child) in &account.records {
+            if let Some(rest) = child.path.strip_prefix(old.as_slice()) {
+                changes.push(path_change(
+                    account_name,
+                    file,
+                    &[new.as_slice(), rest].concat(),
+                ));
+            }
+        }
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::testing::{record, TempBackup};
+
+    // U-owner's Inventory with a Trips folder, and an item outside it.
+    fn inventory(name: &str) -> TempBackup {
+        let backup = TempBackup::new(name);
+        let mut folder = record("R-trips", "U-owner", "Inventory");
+        folder["recordType"] = json!("directory");
+        folder["name"] = json!("Trips");
+        backup.write_record("U-owner", &folder);
+        backup.write_record("U-owner", &record("R-beach", "U-owner", "Inventory"));
+        backup
+    }
+
+    #[test]
+    fn moves_only_mark_records_modified_when_asked() {
+        let temp = inventory("edit-move");
+        let backup = temp.load();
+        let before = &backup.accounts["U-owner"].records["R-beach"];
+        let trips = parse_path("Inventory/Trips");
+        let changes = move_changes(&backup, "R-beach", &trips).unwrap();
+        assert_eq!(changes.len(), 1);
+        apply(&temp.root, &changes, &History::default()).unwrap();
+
+        let moved = temp.load();
+        let moved = &moved.accounts["U-owner"].records["R-beach"];
+        assert_eq!(moved.path, trips);
+        assert_eq!(moved.last_modification_time, before.last_modification_time);
+        assert_eq!(moved.local_version, before.local_version);
+
+        let touch = History {
+            touch: true,
+            ..Default::default()
+        };
+        let changes = move_changes(&temp.load(), "R-beach", &parse_path("Inventory")).unwrap();
+        apply(&temp.root, &changes, &touch).unwrap();
+        let moved = temp.load();
+        let moved = &moved.accounts["U-owner"].records["R-beach"];
+        assert!(moved.last_modification_time > before.last_modification_time);
+        assert_eq!(moved.local_version, before.local_version);
+    }
+
+    #[test]
+    fn reown_leaves_records_the_new_owner_has() {
+        let temp = inventory("edit-reown-taken");
+        temp.write_record("U-new", &record("R-beach", "U-new", "Inventory"));
+        let backup = temp.load();
+        let selected = [
+            ("U-owner".into(), "R-beach".into()),
+            ("U-owner".into(), "R-trips".into()),
+        ];
+        let changes = reown_changes(&backup, &selected, &"U-new".into(), &"new".into());
+        assert!(changes.iter().all(|c| !c.to_string().contains("R-beach")));
+        apply(&temp.root, &changes, &History::default()).unwrap();
+
+        let backup = temp.load();
+        assert!(backup.accounts["U-owner"].records.contains_key("R-beach"));
+        assert_eq!(
+            backup.accounts["U-new"].records["R-trips"]
+                .owner_id
+                .as_str(),
+            "U-new"
+        );
+    }
+
+    #[test]
+    fn reown_round_trip() {
+        let temp = inventory("edit-reown");
+        let mut link = record("R-link", "U-owner", "Inventory");
+        link["recordType"] = json!("link");
+        link["assetUri"] = json!("neosrec:///U-owner/R-beach");
+        temp.write_record("U-owner", &link);
+        let storage = temp.root.join("U-owner/Records/R-beach.Storage.json");
+        fs::write(&storage, "{}").unwrap();
+        let backup = temp.load();
+
+        let selected = [("U-owner".into(), "R-beach".into())];
+        let changes = reown_changes(&backup, &selected, &"U-new".into(), &"new".into());
+        apply(&temp.root, &changes, &History::all()).unwrap();
+
+        let backup = temp.load();
+        assert!(!backup.accounts["U-owner"].records.contains_key("R-beach"));
+        let moved = &backup.accounts["U-new"].records["R-beach"];
+        assert_eq!(
+            (moved.owner_id.as_str(), moved.owner_name.as_str()),
+            ("U-new", "new")
+        );
+        assert_eq!((moved.global_version, moved.local_version), (1, 2));
+        assert!(!storage.exists());
+        assert!(temp
+            .root
+            .join("U-new/Records/R-beach.Storage.json")
+            .exists());
+
+        let link = &backup.accounts["U-owner"].records["R-link"];
+        let Some(AssetUri::NeosRec(target)) = &link.asset_uri else {
+            panic!("{:?} isn't a record link", link.asset_uri);
+        };
+        assert_eq!(
+            (target.group_id.as_str(), target.asset_id.as_str()),
+            ("U-new", "R-beach")
+        );
+        assert_eq!(link.local_version, 2);
+        // Untouched records keep their version.
+        assert_eq!(
+            backup.accounts["U-owner"].records["R-trips"].local_version,
+            1
+        );
+
+        let log = edit_log(&temp.root, "U-new", "R-beach").unwrap();
+        let changes: Vec<_> = log.iter().map(|entry| entry.change.as_str()).collect();
+        assert_eq!(
+            changes,
+            [
+                "set ownerId of U-owner/R-beach to \"U-new\"",
+                "set ownerName of U-owner/R-beach to \"new\"",
+                "move U-owner/R-beach to U-new",
+            ]
+        );
+        assert_eq!(log[0].previous, Some(json!("U-owner")));
+    }
+
+    #[test]
+    fn mkdir_and_folder_moves_round_trip() {
+        let temp = inventory("edit-mkdir");
+        temp.write_record("U-owner", &record("R-shell", "U-owner", "Inventory\\Trips"));
+        let summer = parse_path("Inventory/Trips/2021/Summer");
+        let changes = mkdir_changes(&temp.load(), "U-owner", &summer).unwrap();
+        assert_eq!(changes.len(), 2);
+        apply(&temp.root, &changes, &History::default()).unwrap();
+
+        let backup = temp.load();
+        let account = &backup.accounts["U-owner"];
+        let folder = Inventory::build(account)
+            .folder(&summer)
+            .unwrap()
+            .record
+            .clone();
+        let folder = &account.records[&folder.unwrap()];
+        assert_eq!(folder.record_type, RecordType::Directory);
+        assert_eq!(folder.path, parse_path("Inventory/Trips/2021"));
+        assert!(mkdir_changes(&backup, "U-owner", &summer)
+            .unwrap()
+            .is_empty());
+
+        // Trips goes into a new Archive folder, with everything beneath it.
+        let archive = parse_path("Inventory/Archive");
+        let changes = mkdir_changes(&backup, "U-owner", &archive).unwrap();
+        apply(&temp.root, &changes, &History::default()).unwrap();
+        let backup = temp.load();
+        let changes = move_changes(&backup, "R-trips", &archive).unwrap();
+        assert_eq!(changes.len(), 4);
+        apply(&temp.root, &changes, &History::default()).unwrap();
+
+        let backup = temp.load();
+        let account = &backup.accounts["U-owner"];
+        let path = |id: &str| account.records[id].path.join("/");
+        assert_eq!(path("R-trips"), "Inventory/Archive");
+        assert_eq!(path("R-shell"), "Inventory/Archive/Trips");
+        assert_eq!(path(folder.id.as_str()), "Inventory/Archive/Trips/2021");
+        assert_eq!(path("R-beach"), "Inventory");
+        assert!(Inventory::build(account)
+            .folder(&parse_path("Inventory/Archive/Trips/2021/Summer"))
+            .is_some());
+
+        let into_itself = move_changes(&backup, "R-trips", &parse_path("Inventory/Archive/Trips"));
+        assert!(matches!(into_itself, Err(EditError::IntoItself(_))));
+    }
+
+    #[test]
+    fn tags_round_trip() {
+        let temp = inventory("edit-tags");
+        let mut tagged = record("R-tagged", "U-owner", "Inventory");
+        tagged["tags"] = json!(["keep", "old"]);
+        temp.write_record("U-owner", &tagged);
+        let backup = temp.load();
+
+        let selected: Vec<_> = ["R-tagged", "R-beach"]
+            .map(|file| ("U-owner".into(), file.into()))
+            .into();
+        let history = History {
+            bump_version: true,
+            ..Default::default()
+        };
+        let changes = tag_changes(&backup, &selected, &["new".into()], &["old".into()]);
+        assert_eq!(changes.len(), 2);
+        apply(&temp.root, &changes, &history).unwrap();
+
+        let backup = temp.load();
+        let records = &backup.accounts["U-owner"].records;
+        let tags = |id: &str| {
+            records[id]
+                .tags
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tags("R-tagged"), ["keep", "new"]);
+        assert_eq!(tags("R-beach"), ["new"]);
+        assert_eq!(records["R-tagged"].local_version, 2);
+        assert_eq!(records["R-trips"].local_version, 1);
+
+        // Asking again changes nothing, so nothing is bumped twice.
+        assert!(tag_changes(&backup, &selected, &["new".into()], &["old".into()]).is_empty());
+    }
+}
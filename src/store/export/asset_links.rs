@@ -0,0 +1,143 @@
+use crate::store::{
+    backup::{AssetUri, Backup, Record},
+    naming::{self, file_name, NameParts, NamingPolicy},
+    redact::Redactor,
+    RcStr,
+};
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+#[derive(Debug, Default)]
+pub struct AssetLinks {
+    pub records: u64,
+    pub links: u64,
+    // Referenced assets found in no asset root, so there was nothing to link to.
+    pub missing: u64,
+}
+
+// A browsable view of the flat asset store, each file at the naming policy's asset link
+// path a symlink to the asset, so nothing is copied. Accounts and names in the paths go
+// through the redactor, the assets they point at are named by their hash.
+pub fn link_assets(
+    backup: &Backup,
+    out: &Path,
+    naming: &NamingPolicy,
+    redactor: &Redactor,
+) -> io::Result<AssetLinks> {
+    let mut summary = AssetLinks::default();
+    let template = naming.asset_links_template();
+    let mut used_paths = BTreeSet::new();
+    for (account_name, account) in &backup.accounts {
+        let account_name = redactor.user_id(account_name);
+        let mut used = BTreeSet::new();
+        for rec in account.records.values() {
+            let roles = roles(rec);
+            if roles.is_empty() {
+                continue;
+            }
+            // Names aren't unique, the record ID tells same-named records apart.
+            let mut name = file_name(&redactor.text(&rec.name));
+            if !used.insert(name.clone()) {
+                name = format!("{} ({})", name, file_name(&rec.id));
+                used.insert(name.clone());
+            }
+            summary.records += 1;
+            for (role, hash) in roles {
+                let Some(target) = backup.asset_path(&hash) else {
+                    summary.missing += 1;
+                    continue;
+                };
+                let kind = backup.sniff_asset(&hash).ok().flatten().unwrap_or("bin");
+                let parts = NameParts::default()
+                    .with("account", &account_name)
+                    .with("record", &rec.id)
+                    .with("name", &name)
+                    .with("hash", &hash)
+                    .with("kind", kind)
+                    .with("ext", naming.extension(kind))
+                    .with("role", role);
+                let link = naming::render(template, &parts, out);
+                let link = naming::unique(link, &hash, &mut used_paths);
+                fs::create_dir_all(link.parent().unwrap_or(out))?;
+                if link.symlink_metadata().is_ok() {
+                    fs::remove_file(&link)?;
+                }
+                symlink(&target.canonicalize()?, &link)?;
+                summary.links += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+// The manifest and thumbnail by name, the rest of the record's assets by hash prefix.
+fn roles(rec: &Record) -> Vec<(String, RcStr)> {
+    let mut roles = Vec::new();
+    let mut seen = BTreeSet::new();
+    if let Some(AssetUri::SZBson(asset)) = &rec.asset_uri {
+        seen.insert(asset.0.clone());
+        roles.push(("manifest".to_owned(), asset.0.clone()));
+    }
+    if let Some(hash) = rec.thumbnail_uri.as_ref().and_then(AssetUri::asset_id) {
+        if seen.insert(hash.clone()) {
+            roles.push(("thumbnail".to_owned(), hash.clone()));
+        }
+    }
+    for asset in &rec.neos_db_manifest {
+        if seen.insert(asset.hash.clone()) {
+            let prefix = asset.hash.get(..12).unwrap_or(&asset.hash);
+            roles.push((format!("asset-{}", prefix), asset.hash.clone()));
+        }
+    }
+    roles
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::store::{
+        redact::RedactionProfile,
+        testing::{record, TempBackup},
+    };
+
+    #[test]
+    fn redacted_links_name_no_one() {
+        let source = TempBackup::new("links-source");
+        let mut rec = record("R-1", "U-owner", "Inventory");
+        rec["name"] = serde_json::json!("My house");
+        rec["thumbnailUri"] = serde_json::json!("neosdb:///thumb.webp");
+        source.write_record("U-owner", &rec);
+        source.write_asset("thumb", b"RIFF\0\0\0\0WEBPVP8 ");
+        let backup = source.load();
+
+        let redactor = Redactor::new(RedactionProfile::Full, backup.accounts.keys().cloned());
+        let out = TempBackup::new("links-out");
+        let links = link_assets(&backup, &out.root, &NamingPolicy::default(), &redactor).unwrap();
+        assert_eq!((links.records, links.links, links.missing), (1, 1, 0));
+
+        let mut paths = Vec::new();
+        let mut pending = vec![out.root.clone()];
+        while let Some(dir) = pending.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() && !path.is_symlink() {
+                    pending.push(path.clone());
+                }
+                paths.push(path.strip_prefix(&out.root).unwrap().display().to_string());
+            }
+        }
+        assert!(paths.iter().any(|p| p.contains("thumbnail")));
+        assert!(paths
+            .iter()
+            .all(|p| !p.contains("U-owner") && !p.contains("house")));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::store::{
+    backup::{AssetUri, Backup, Record},
+    redact::Redactor,
+    RcStr,
+};
+use serde_json::{Map, Value};
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+#[derive(Debug, Default)]
+pub struct Bundle {
+    pub records: u64,
+    pub assets: u64,
+    pub bytes: u64,
+    // Referenced assets found in no asset root.
+    pub missing: u64,
+}
+
+// Copies the backup's records and the assets they use into a new backup folder with the
+// same layout, so a filtered backup can be handed around and loaded like any other.
+// Record files are copied as they are, never re-serialized, unless redacting, when the
+// people and free text in them are rewritten and everything else is kept. Assets are
+// named by their hash, so they're copied as they are either way.
+pub fn write_bundle(backup: &Backup, out: &Path, redactor: &Redactor) -> io::Result<Bundle> {
+    let mut bundle = Bundle::default();
+    let assets_dir = out.join("Assets");
+    fs::create_dir_all(&assets_dir)?;
+    let mut seen = BTreeSet::new();
+    for (account_name, account) in &backup.accounts {
+        if account.records.is_empty() {
+            continue;
+        }
+        let records_dir = out
+            .join(redactor.user_id(account_name).as_str())
+            .join("Records");
+        fs::create_dir_all(&records_dir)?;
+        for (file, rec) in &account.records {
+            let name = format!("{}.json", file);
+            let from = backup
+                .root
+                .join(account_name.as_str())
+                .join("Records")
+                .join(&name);
+            if redactor.is_active() {
+                let mut json: Value = serde_json::from_slice(&fs::read(from)?)?;
+                if let Value::Object(record) = &mut json {
+                    redact_record(record, redactor);
+                }
+                fs::write(records_dir.join(&name), serde_json::to_vec(&json)?)?;
+            } else {
+                fs::copy(from, records_dir.join(&name))?;
+            }
+            bundle.records += 1;
+
+            for hash in asset_hashes(rec) {
+                if !seen.insert(hash.clone()) {
+                    continue;
+                }
+                match backup.asset_path(hash) {
+                    Some(path) => {
+                        bundle.bytes += fs::copy(path, assets_dir.join(hash.as_str()))?;
+                        bundle.assets += 1;
+                    }
+                    None => bundle.missing += 1,
+                }
+            }
+        }
+    }
+    Ok(bundle)
+}
+
+// The record's fields naming people go through the redactor's user IDs and usernames,
+// its names and inventory path through its text. Photos also name people and the world
+// they were taken in in their tags.
+fn redact_record(record: &mut Map<String, Value>, r: &Redactor) {
+    let owner = str_field(record, "ownerId");
+    redact_field(record, "ownerName", |name| r.username(&owner, name));
+    for key in ["ownerId", "lastModifyingUserId"] {
+        redact_field(record, key, |id| r.user_id(id));
+    }
+    for key in ["name", "description"] {
+        redact_field(record, key, |text| r.text(text));
+    }
+    redact_field(record, "path", |path| {
+        let folders: Vec<_> = path.split('\\').map(|f| r.text(&f.into())).collect();
+        folders.join("\\").into()
+    });
+    if let Some(Value::Array(tags)) = record.get_mut("tags") {
+        tags.iter_mut()
+            .for_each(|tag| redact_value(tag, |t| redact_tag(t, r)));
+    }
+    if let Some(Value::Array(submissions)) = record.get_mut("submissions") {
+        for submission in submissions.iter_mut().filter_map(Value::as_object_mut) {
+            let by = str_field(submission, "submittedById");
+            redact_field(submission, "submittedByName", |name| r.username(&by, name));
+            for key in ["ownerId", "submittedById", "featuredByUserId"] {
+                redact_field(submission, key, |id| r.user_id(id));
+            }
+            if let Some(Value::Object(target)) = submission.get_mut("targetRecordId") {
+                redact_field(target, "ownerId", |id| r.user_id(id));
+            }
+        }
+    }
+}
+
+fn redact_tag(tag: &RcStr, r: &Redactor) -> RcStr {
+    let Some((key, value)) = tag.split_once(':') else {
+        return tag.clone();
+    };
+    let value = RcStr::from(value.trim());
+    let value = match key {
+        "location_host" | "user" | "present_user" => r.user_id(&value),
+        "location_name" | "location_url" => r.text(&value),
+        _ => return tag.clone(),
+    };
+    format!("{}:{}", key, value).into()
+}
+
+fn str_field(object: &Map<String, Value>, key: &str) -> String {
+    object
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn redact_field<F>(object: &mut Map<String, Value>, key: &str, f: F)
+where
+    F: Fn(&RcStr) -> RcStr,
+{
+    if let Some(value) = object.get_mut(key) {
+        redact_value(value, f);
+    }
+}
+
+fn redact_value<F>(value: &mut Value, f: F)
+where
+    F: Fn(&RcStr) -> RcStr,
+{
+    if let Value::String(s) = value {
+        *s = f(&s.as_str().into()).to_string();
+    }
+}
+
+// What write_bundle would copy for these records, deduplicated the same way, without
+// writing anything. Asset bytes are the files' sizes in whichever root has them.
+pub fn estimate_bundle<'a, I>(backup: &Backup, records: I) -> Bundle
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut bundle = Bundle::default();
+    let mut seen = BTreeSet::new();
+    for rec in records {
+        bundle.records += 1;
+        for hash in asset_hashes(rec) {
+            if !seen.insert(hash) {
+                continue;
+            }
+            match backup.asset_path(hash).and_then(|p| fs::metadata(p).ok()) {
+                Some(meta) => {
+                    bundle.bytes += meta.len();
+                    bundle.assets += 1;
+                }
+                None => bundle.missing += 1,
+            }
+        }
+    }
+    bundle
+}
+
+// The asset itself, its thumbnail and everything its manifest lists.
+fn asset_hashes(rec: &Record) -> impl Iterator<Item = &RcStr> {
+    rec.asset_uri
+        .iter()
+        .chain(&rec.thumbnail_uri)
+        .filter_map(AssetUri::asset_id)
+        .chain(rec.neos_db_manifest.iter().map(|a| &a.hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{
+        redact::RedactionProfile,
+        testing::{record, TempBackup},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn redacted_bundles_rewrite_people_and_text() {
+        let source = TempBackup::new("bundle-source");
+        let mut photo = record("R-photo", "U-owner", "Inventory\\Trips");
+        photo["recordType"] = json!("texture");
+        photo["name"] = json!("At the beach");
+        photo["tags"] = json!(["camera", "user:U-friend", "location_name:Home", "photo"]);
+        photo["unknownField"] = json!(7);
+        source.write_record("U-owner", &photo);
+        let backup = source.load();
+
+        let redactor = Redactor::new(RedactionProfile::Full, backup.accounts.keys().cloned());
+        let out = TempBackup::new("bundle-redacted");
+        fs::remove_dir_all(&out.root).unwrap();
+        let bundle = write_bundle(&backup, &out.root, &redactor).unwrap();
+        assert_eq!(bundle.records, 1);
+
+        let owner = redactor.user_id(&"U-owner".into());
+        assert!(!out.root.join("U-owner").exists());
+        let file = out.root.join(owner.as_str()).join("Records/R-photo.json");
+        let text = fs::read_to_string(file).unwrap();
+        assert!(!text.contains("U-owner") && !text.contains("beach"));
+        let written: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(written["ownerId"], json!(owner.as_str()));
+        assert_eq!(written["lastModifyingUserId"], json!(owner.as_str()));
+        assert_eq!(written["name"], json!("[redacted]"));
+        assert_eq!(written["path"], json!("[redacted]\\[redacted]"));
+        let friend = redactor.user_id(&"U-friend".into());
+        assert_eq!(
+            written["tags"],
+            json!([
+                "camera",
+                format!("user:{}", friend),
+                "location_name:[redacted]",
+                "photo"
+            ])
+        );
+        assert_eq!(written["unknownField"], json!(7));
+        // Still a backup like any other.
+        assert_eq!(out.load().accounts[&owner].records.len(), 1);
+    }
+
+    #[test]
+    fn unredacted_bundles_copy_records_as_they_are() {
+        let source = TempBackup::new("bundle-plain-source");
+        source.write_record("U-owner", &record("R-1", "U-owner", "Inventory"));
+        let backup = source.load();
+        let out = TempBackup::new("bundle-plain");
+        write_bundle(&backup, &out.root, &Redactor::default()).unwrap();
+        let path = Path::new("U-owner").join("Records").join("R-1.json");
+        assert_eq!(
+            fs::read(out.root.join(&path)).unwrap(),
+            fs::read(source.root.join(&path)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use super::{escape, fold};
+use crate::store::{
+    aliases::Aliases,
+    backup::{Backup, MessageContent},
+    redact::{Redact, Redactor},
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::{self, Write};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EventKind {
+    SessionInvite,
+    Submission,
+    Featured,
+    Published,
+}
+
+impl EventKind {
+    fn category(&self) -> &'static str {
+        match self {
+            EventKind::SessionInvite => "Session invite",
+            EventKind::Submission => "Submission",
+            EventKind::Featured => "Featured",
+            EventKind::Published => "Published",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEvent {
+    pub kind: EventKind,
+    // The message, submission or record the event comes from.
+    pub source_id: RcStr,
+    pub account: RcStr,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    // The session or record name.
+    pub title: RcStr,
+    // Who invited or was invited, submitted or featured.
+    pub user_id: Option<RcStr>,
+    pub username: Option<RcStr>,
+}
+
+// Session invites, submissions, features and first publishes across every account,
+// oldest first.
+pub fn history_events(backup: &Backup, aliases: &Aliases) -> Vec<HistoryEvent> {
+    let mut events = Vec::new();
+    for (account_name, account) in &backup.accounts {
+        for (contact, messages) in &account.messages {
+            let username = account
+                .contacts
+                .get(contact)
+                .map(|c| aliases.display(contact, &c.friend_username))
+                .or_else(|| aliases.current(contact).cloned());
+            for msg in messages {
+                let MessageContent::SessionInvite(session) = msg.parsed_content() else {
+                    continue;
+                };
+                events.push(HistoryEvent {
+                    kind: EventKind::SessionInvite,
+                    source_id: msg.id.clone(),
+                    account: account_name.clone(),
+                    start: msg.send_time,
+                    // The snapshot only says the session lasted at least this long.
+                    end: (session.last_update > msg.send_time).then_some(session.last_update),
+                    title: session.name,
+                    user_id: Some(contact.clone()),
+                    username: username.clone(),
+                });
+            }
+        }
+
+        for rec in account.records.values() {
+            let event =
+                |kind, source_id: &RcStr, start, user: Option<(&RcStr, &RcStr)>| HistoryEvent {
+                    kind,
+                    source_id: source_id.clone(),
+                    account: account_name.clone(),
+                    start,
+                    end: None,
+                    title: rec.name.clone(),
+                    user_id: user.map(|(id, _)| id.clone()),
+                    username: user.map(|(id, name)| aliases.display(id, name)),
+                };
+            if let Some(published) = rec.first_publish_time {
+                events.push(event(EventKind::Published, &rec.id, published, None));
+            }
+            for submission in &rec.submissions {
+                let submitter = (&submission.submitted_by_id, &submission.submitted_by_name);
+                events.push(event(
+                    EventKind::Submission,
+                    &submission.id,
+                    submission.submission_time,
+                    Some(submitter),
+                ));
+                if let (true, Some(featured)) = (submission.featured, submission.featured_timestamp)
+                {
+                    let featurer = submission.featured_by_user_id.as_ref();
+                    let name = featurer.and_then(|id| aliases.current(id));
+                    events.push(event(
+                        EventKind::Featured,
+                        &submission.id,
+                        featured,
+                        featurer.zip(name),
+                    ));
+                }
+            }
+        }
+    }
+    events.sort_by_key(|e| e.start);
+    events
+}
+
+impl HistoryEvent {
+    pub fn summary(&self) -> String {
+        let who = self.username.as_ref().or(self.user_id.as_ref());
+        match (self.kind, who) {
+            (EventKind::SessionInvite, Some(who)) => {
+                format!("Session invite: {} (with {})", self.title, who)
+            }
+            (EventKind::Submission, Some(who)) => format!("{} submitted {}", who, self.title),
+            (EventKind::Featured, Some(who)) => format!("{} featured by {}", self.title, who),
+            (kind, _) => format!("{}: {}", kind.category(), self.title),
+        }
+    }
+}
+
+impl Redact for HistoryEvent {
+    fn redact(&mut self, r: &Redactor) {
+        self.title = r.text(&self.title);
+        if let Some(id) = &self.user_id {
+            self.username = self.username.as_ref().map(|name| r.username(id, name));
+            self.user_id = Some(r.user_id(id));
+        }
+    }
+}
+
+// RFC 5545. DTSTAMP is the event's own time rather than now, so reruns over the same
+// backup write the same file and calendar apps see no changes.
+pub fn write_ics<W>(events: &[HistoryEvent], out: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    let stamp = |time: &DateTime<Utc>| time.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//neos-full-statbox//history//EN".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+    ];
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_owned());
+        lines.push(format!(
+            "UID:{:?}-{}-{}@neos-full-statbox",
+            event.kind, event.account, event.source_id
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp(&event.start)));
+        lines.push(format!("DTSTART:{}", stamp(&event.start)));
+        if let Some(end) = &event.end {
+            lines.push(format!("DTEND:{}", stamp(end)));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&event.summary())));
+        lines.push(format!("CATEGORIES:{}", escape(event.kind.category())));
+        lines.push("END:VEVENT".to_owned());
+    }
+    lines.push("END:VCALENDAR".to_owned());
+    for line in lines {
+        write!(out, "{}\r\n", fold(&line))?;
+    }
+    Ok(())
+}
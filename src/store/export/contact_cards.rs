@@ -0,0 +1,175 @@
+use super::{escape, fold};
+use crate::store::{
+    aliases::Aliases,
+    backup::Backup,
+    redact::{Redact, Redactor},
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardFormat {
+    Json,
+    VCard,
+}
+
+impl FromStr for CardFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(CardFormat::Json),
+            "vcard" | "vcf" => Ok(CardFormat::VCard),
+            _ => Err(format!(
+                "unknown card format {:?}, expected json or vcard",
+                s
+            )),
+        }
+    }
+}
+
+impl CardFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CardFormat::Json => "json",
+            CardFormat::VCard => "vcf",
+        }
+    }
+}
+
+// What's worth carrying of a contact into an address book or another platform.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactCard {
+    pub account: RcStr,
+    pub user_id: RcStr,
+    pub username: RcStr,
+    pub former_usernames: Vec<RcStr>,
+    pub icon_url: Option<RcStr>,
+    pub tagline: Option<RcStr>,
+    // From the messages exchanged and the contact's status changes.
+    pub first_interaction: Option<DateTime<Utc>>,
+    pub last_interaction: Option<DateTime<Utc>>,
+}
+
+// One card per contact of every account, in account then username order.
+pub fn contact_cards(backup: &Backup, aliases: &Aliases) -> Vec<ContactCard> {
+    let mut cards = Vec::new();
+    for (account_name, account) in &backup.accounts {
+        for contact in account.contacts.values() {
+            let messages = account.messages.get(&contact.id).into_iter().flatten();
+            let sent: Vec<_> = messages.map(|m| m.send_time).collect();
+            let status_change = contact.user_status.last_status_change;
+            let profile = contact.profile.as_ref();
+            let username = aliases.display(&contact.id, &contact.friend_username);
+            cards.push(ContactCard {
+                account: account_name.clone(),
+                user_id: contact.id.clone(),
+                former_usernames: aliases
+                    .users
+                    .get(&contact.id)
+                    .map(|u| {
+                        u.former
+                            .iter()
+                            .filter(|n| **n != username)
+                            .cloned()
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                username,
+                icon_url: profile
+                    .map(|p| p.icon_url.clone())
+                    .filter(|url| !url.is_empty()),
+                tagline: profile
+                    .and_then(|p| p.tagline.clone())
+                    .filter(|t| !t.trim().is_empty()),
+                first_interaction: sent.iter().copied().chain(status_change).min(),
+                last_interaction: sent
+                    .iter()
+                    .copied()
+                    .chain(status_change)
+                    .chain(contact.latest_message_time)
+                    .max(),
+            });
+        }
+    }
+    cards.sort_by(|a, b| (&a.account, &a.username).cmp(&(&b.account, &b.username)));
+    cards
+}
+
+impl Redact for ContactCard {
+    // A hidden user loses everything that could identify them, not just their names.
+    fn redact(&mut self, r: &Redactor) {
+        let id = self.user_id.clone();
+        self.user_id = r.user_id(&id);
+        self.username = r.username(&id, &self.username);
+        if self.user_id != id {
+            self.former_usernames.clear();
+            self.icon_url = None;
+            self.tagline = None;
+        }
+        self.tagline = self.tagline.as_ref().map(|t| r.text(t));
+    }
+}
+
+pub fn write_cards<W>(cards: &[ContactCard], format: CardFormat, out: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    match format {
+        CardFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, cards)?;
+            writeln!(out)
+        }
+        CardFormat::VCard => cards.iter().try_for_each(|card| write_vcard(card, out)),
+    }
+}
+
+// vCard 4.0 (RFC 6350). The Neos specifics ride along as X- properties, which address
+// books keep without showing.
+fn write_vcard<W>(card: &ContactCard, out: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut lines = vec![
+        "BEGIN:VCARD".to_owned(),
+        "VERSION:4.0".to_owned(),
+        format!("FN:{}", escape(&card.username)),
+        format!("UID:urn:neos:user:{}", escape(&card.user_id)),
+        format!("X-NEOS-USERID:{}", escape(&card.user_id)),
+    ];
+    if !card.former_usernames.is_empty() {
+        let names: Vec<_> = card.former_usernames.iter().map(|n| escape(n)).collect();
+        lines.push(format!("NICKNAME:{}", names.join(",")));
+    }
+    if let Some(icon) = &card.icon_url {
+        // neosdb:/// URLs only mean something to a Neos client.
+        let property = if icon.starts_with("http") {
+            "PHOTO"
+        } else {
+            "X-NEOS-ICON"
+        };
+        lines.push(format!("{}:{}", property, icon));
+    }
+    if let Some(tagline) = &card.tagline {
+        lines.push(format!("NOTE:{}", escape(tagline)));
+    }
+    for (property, time) in [
+        ("X-NEOS-FIRST-INTERACTION", card.first_interaction),
+        ("X-NEOS-LAST-INTERACTION", card.last_interaction),
+    ] {
+        if let Some(time) = time {
+            lines.push(format!("{}:{}", property, time.format("%Y%m%dT%H%M%SZ")));
+        }
+    }
+    lines.push("END:VCARD".to_owned());
+    for line in lines {
+        write!(out, "{}\r\n", fold(&line))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use crate::store::{
+    locale::tr,
+    report::{escape_html, Report, ReportData, ReportFormat},
+    stats::Stats,
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+const TEMPLATE: &str = include_str!("dashboard.html");
+
+pub fn write_dashboard<W>(
+    stats: &Stats,
+    reports: &[(&dyn Report, ReportData)],
+    out: &mut W,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    // "</" would end the script block early if it showed up in a component or tag name.
+    let data = serde_json::to_string(stats)?.replace("</", "<\\/");
+    // The chart series names, which the script can't look up itself.
+    let text: BTreeMap<_, _> = ["Records", "Messages"]
+        .into_iter()
+        .map(|msg| (msg, tr(msg)))
+        .collect();
+    let text = serde_json::to_string(&text)?.replace("</", "<\\/");
+    let sections: String = reports
+        .iter()
+        .map(|(report, data)| {
+            format!(
+                "<section><h2>{}</h2>{}</section>\n",
+                escape_html(tr(report.title())),
+                data.render(ReportFormat::Html)
+            )
+        })
+        .collect();
+    // The other markers sit above the stats, so stats data can't be mistaken for them.
+    let page = translate(TEMPLATE)
+        .replacen("/*TEXT*/", &text, 1)
+        .replacen("/*STATS*/", &data, 1)
+        .replacen("<!--REPORTS-->\n", &sections, 1);
+    out.write_all(page.as_bytes())
+}
+
+// Fills in the template's {{text}} markers, translated.
+fn translate(template: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some((before, after)) = rest.split_once("{{") {
+        let Some((msg, after)) = after.split_once("}}") else {
+            break;
+        };
+        out += before;
+        out += &escape_html(tr(msg));
+        rest = after;
+    }
+    out + rest
+}
@@ -0,0 +1,487 @@
+use crate::store::{
+    backup::{Account, Backup, RecordType},
+    edit::{self, EditError, RecordChange},
+    inventory::{Folder, Inventory},
+    redact::Redactor,
+    RcStr,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StructureError {
+    #[error("line {0}: {1}")]
+    Yaml(usize, &'static str),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructureFormat {
+    Json,
+    Yaml,
+}
+
+impl FromStr for StructureFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(StructureFormat::Json),
+            "yaml" | "yml" => Ok(StructureFormat::Yaml),
+            _ => Err(format!(
+                "unknown structure format {:?}, expected json or yaml",
+                s
+            )),
+        }
+    }
+}
+
+impl StructureFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StructureFormat::Json => "json",
+            StructureFormat::Yaml => "yaml",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StructureItem {
+    pub id: RcStr,
+    pub name: RcStr,
+    pub record_type: RecordType,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StructureFolder {
+    pub name: RcStr,
+    // The Directory record, roots like Inventory have none.
+    pub id: Option<RcStr>,
+    pub folders: Vec<StructureFolder>,
+    pub items: Vec<StructureItem>,
+}
+
+// One account's folders and what's in them, names and IDs only.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryStructure {
+    pub account: RcStr,
+    pub roots: Vec<StructureFolder>,
+}
+
+impl StructureFolder {
+    fn of(folder: &Folder, account: &Account, redactor: &Redactor) -> Self {
+        StructureFolder {
+            name: redactor.text(&folder.name),
+            id: folder.record.clone(),
+            folders: folder
+                .folders
+                .values()
+                .map(|f| StructureFolder::of(f, account, redactor))
+                .collect(),
+            items: folder
+                .items
+                .iter()
+                .filter_map(|id| account.records.get(id))
+                .map(|rec| StructureItem {
+                    id: rec.id.clone(),
+                    name: redactor.text(&rec.name),
+                    record_type: rec.record_type.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Names go through the redactor's text and the account through its user IDs, record IDs
+// are kept so a structure can still be imported.
+pub fn inventory_structures(backup: &Backup, redactor: &Redactor) -> Vec<InventoryStructure> {
+    backup
+        .accounts
+        .iter()
+        .map(|(name, account)| InventoryStructure {
+            account: redactor.user_id(name),
+            roots: Inventory::build(account)
+                .root
+                .folders
+                .values()
+                .map(|f| StructureFolder::of(f, account, redactor))
+                .collect(),
+        })
+        .collect()
+}
+
+pub fn write_structures<W>(
+    structures: &[InventoryStructure],
+    format: StructureFormat,
+    out: &mut W,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    match format {
+        StructureFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, structures)?;
+            writeln!(out)
+        }
+        StructureFormat::Yaml => {
+            for structure in structures {
+                writeln!(out, "- account: {}", scalar(&structure.account))?;
+                writeln!(out, "  roots:")?;
+                for root in &structure.roots {
+                    write_yaml_folder(root, 2, out)?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+// JSON strings are valid double quoted YAML scalars.
+fn scalar(s: &str) -> String {
+    Value::String(s.to_owned()).to_string()
+}
+
+fn write_yaml_folder<W>(folder: &StructureFolder, depth: usize, out: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    let indent = "  ".repeat(depth);
+    writeln!(out, "{}- name: {}", indent, scalar(&folder.name))?;
+    if let Some(id) = &folder.id {
+        writeln!(out, "{}  id: {}", indent, scalar(id))?;
+    }
+    if folder.folders.is_empty() {
+        writeln!(out, "{}  folders: []", indent)?;
+    } else {
+        writeln!(out, "{}  folders:", indent)?;
+        for sub in &folder.folders {
+            write_yaml_folder(sub, depth + 2, out)?;
+        }
+    }
+    if folder.items.is_empty() {
+        writeln!(out, "{}  items: []", indent)?;
+    } else {
+        writeln!(out, "{}  items:", indent)?;
+        for item in &folder.items {
+            writeln!(out, "{}    - id: {}", indent, scalar(&item.id))?;
+            writeln!(out, "{}      name: {}", indent, scalar(&item.name))?;
+            let record_type = serde_json::to_string(&item.record_type)?;
+            writeln!(out, "{}      recordType: {}", indent, record_type)?;
+        }
+    }
+    Ok(())
+}
+
+// Structures as written by write_structures in either format, told apart by JSON's
+// opening bracket. YAML is read as far as the block style it's written in goes, so it can
+// be edited by hand, with comments and plain or single quoted scalars, but not flow
+// collections, anchors or multi-line strings.
+pub fn read_structures(text: &str) -> Result<Vec<InventoryStructure>, StructureError> {
+    if text.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(text)?);
+    }
+    let mut lines: Vec<_> = text
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let content = line.trim_start();
+            let comment = content.is_empty() || content.starts_with('#');
+            (!comment).then(|| YamlLine {
+                number: i + 1,
+                indent: line.len() - content.len(),
+                content: content.trim_end().to_owned(),
+            })
+        })
+        .collect();
+    if lines.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut at = 0;
+    let indent = lines[0].indent;
+    let value = yaml_node(&mut lines, &mut at, indent)?;
+    if let Some(line) = lines.get(at) {
+        return Err(StructureError::Yaml(line.number, "unexpected indentation"));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+struct YamlLine {
+    number: usize,
+    indent: usize,
+    content: String,
+}
+
+impl YamlLine {
+    fn is_item(&self) -> bool {
+        self.content == "-" || self.content.starts_with("- ")
+    }
+}
+
+fn yaml_node(
+    lines: &mut [YamlLine],
+    at: &mut usize,
+    indent: usize,
+) -> Result<Value, StructureError> {
+    if lines[*at].is_item() {
+        yaml_sequence(lines, at, indent)
+    } else {
+        yaml_mapping(lines, at, indent)
+    }
+}
+
+fn yaml_sequence(
+    lines: &mut [YamlLine],
+    at: &mut usize,
+    indent: usize,
+) -> Result<Value, StructureError> {
+    let mut items = Vec::new();
+    while *at < lines.len() && lines[*at].indent == indent && lines[*at].is_item() {
+        let line = &mut lines[*at];
+        let rest = line.content[1..].trim_start();
+        if rest.is_empty() {
+            *at += 1;
+            items.push(yaml_nested(lines, at, indent)?);
+            continue;
+        }
+        // What follows the dash is parsed as a line of its own, indented to where it starts.
+        let offset = line.content.len() - rest.len();
+        line.content = rest.to_owned();
+        line.indent += offset;
+        let indent = line.indent;
+        items.push(yaml_node(lines, at, indent)?);
+    }
+    Ok(Value::Array(items))
+}
+
+fn yaml_mapping(
+    lines: &mut [YamlLine],
+    at: &mut usize,
+    indent: usize,
+) -> Result<Value, StructureError> {
+    let mut map = Map::new();
+    while *at < lines.len() && lines[*at].indent == indent && !lines[*at].is_item() {
+        let line = &lines[*at];
+        let (key, value) = match line.content.split_once(": ") {
+            Some((key, value)) => (key, value.trim()),
+            None => match line.content.strip_suffix(':') {
+                Some(key) => (key, ""),
+                None => return Err(StructureError::Yaml(line.number, "expected a key")),
+            },
+        };
+        let (key, number) = (yaml_scalar(key.trim(), line.number)?, line.number);
+        let Value::String(key) = key else {
+            return Err(StructureError::Yaml(number, "keys have to be strings"));
+        };
+        let value = match value {
+            "" => {
+                *at += 1;
+                // A sequence can sit at its key's indentation.
+                match lines.get(*at) {
+                    Some(next) if next.indent == indent && next.is_item() => {
+                        yaml_sequence(lines, at, indent)?
+                    }
+                    _ => yaml_nested(lines, at, indent)?,
+                }
+            }
+            value => {
+                *at += 1;
+                yaml_scalar(value, number)?
+            }
+        };
+        if map.insert(key, value).is_some() {
+            return Err(StructureError::Yaml(number, "duplicate key"));
+        }
+    }
+    Ok(Value::Object(map))
+}
+
+// The block under a line, null when nothing is indented under it.
+fn yaml_nested(
+    lines: &mut [YamlLine],
+    at: &mut usize,
+    indent: usize,
+) -> Result<Value, StructureError> {
+    match lines.get(*at) {
+        Some(next) if next.indent > indent => {
+            let indent = next.indent;
+            yaml_node(lines, at, indent)
+        }
+        _ => Ok(Value::Null),
+    }
+}
+
+fn yaml_scalar(s: &str, number: usize) -> Result<Value, StructureError> {
+    let invalid = |message| StructureError::Yaml(number, message);
+    Ok(match s {
+        "[]" => Value::Array(Vec::new()),
+        "{}" => Value::Object(Map::new()),
+        "~" | "null" => Value::Null,
+        s if s.starts_with('"') => {
+            serde_json::from_str(s).map_err(|_| invalid("bad double quoted string"))?
+        }
+        s if s.starts_with('\'') => {
+            let inner = s
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .ok_or_else(|| invalid("unclosed single quoted string"))?;
+            Value::String(inner.replace("''", "'"))
+        }
+        s if s.starts_with(['[', '{', '&', '*', '|', '>']) => {
+            return Err(invalid("only block style YAML is read"))
+        }
+        s => Value::String(s.split(" #").next().unwrap_or(s).trim_end().to_owned()),
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct StructureImport {
+    pub changes: Vec<RecordChange>,
+    // Items of the structure the account has no record for.
+    pub missing: Vec<RcStr>,
+}
+
+// The changes giving the account the structure's folders, and moving each of its records
+// the structure lists into the folder it's listed in. Records are matched by ID, folders
+// by path.
+pub fn import_changes(
+    backup: &Backup,
+    account_name: &str,
+    structure: &InventoryStructure,
+) -> Result<StructureImport, EditError> {
+    let account = backup
+        .accounts
+        .get(&RcStr::new(account_name.to_owned()))
+        .ok_or_else(|| EditError::NoAccount(account_name.to_owned()))?;
+    let mut import = StructureImport::default();
+    let mut created = BTreeSet::new();
+    let mut stack: Vec<(Vec<RcStr>, &StructureFolder)> = structure
+        .roots
+        .iter()
+        .map(|root| (vec![root.name.clone()], root))
+        .collect();
+    while let Some((path, folder)) = stack.pop() {
+        if path.len() > 1 {
+            for change in edit::mkdir_changes(backup, account_name, &path)? {
+                if let RecordChange::Create { file, .. } = &change {
+                    if created.insert(file.clone()) {
+                        import.changes.push(change);
+                    }
+                }
+            }
+        }
+        for item in &folder.items {
+            let Some((file, rec)) = account.records.iter().find(|(_, r)| r.id == item.id) else {
+                import.missing.push(item.id.clone());
+                continue;
+            };
+            if rec.path != path {
+                import
+                    .changes
+                    .push(edit::path_change(account_name, file, &path));
+            }
+        }
+        for sub in &folder.folders {
+            let mut sub_path = path.clone();
+            sub_path.push(sub.name.clone());
+            stack.push((sub_path, sub));
+        }
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{
+        redact::RedactionProfile,
+        testing::{record, TempBackup},
+    };
+    use serde_json::json;
+
+    // An Inventory holding a Trips folder with one item in it.
+    fn trips(name: &str) -> TempBackup {
+        let backup = TempBackup::new(name);
+        let mut folder = record("R-trips", "U-owner", "Inventory");
+        folder["recordType"] = json!("directory");
+        folder["name"] = json!("Trips");
+        backup.write_record("U-owner", &folder);
+        let mut item = record("R-beach", "U-owner", "Inventory\\Trips");
+        item["name"] = json!("Beach");
+        backup.write_record("U-owner", &item);
+        backup
+    }
+
+    #[test]
+    fn redacted_structures_keep_only_ids() {
+        let backup = trips("structure-redact").load();
+        let redactor = Redactor::new(RedactionProfile::Full, backup.accounts.keys().cloned());
+        let mut json = Vec::new();
+        let structures = inventory_structures(&backup, &redactor);
+        write_structures(&structures, StructureFormat::Json, &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        for hidden in ["U-owner", "Trips", "Beach", "Inventory"] {
+            assert!(!json.contains(hidden), "{} in {}", hidden, json);
+        }
+        assert!(json.contains("R-trips") && json.contains("R-beach"));
+    }
+
+    #[test]
+    fn yaml_structures_read_back() {
+        let backup = trips("structure-yaml").load();
+        let structures = inventory_structures(&backup, &Redactor::default());
+        let mut yaml = Vec::new();
+        write_structures(&structures, StructureFormat::Yaml, &mut yaml).unwrap();
+        let mut json = Vec::new();
+        write_structures(&structures, StructureFormat::Json, &mut json).unwrap();
+        for written in [yaml, json] {
+            let read = read_structures(std::str::from_utf8(&written).unwrap()).unwrap();
+            assert_eq!(
+                serde_json::to_value(&read).unwrap(),
+                serde_json::to_value(&structures).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn hand_edited_yaml_reads() {
+        let yaml = "\
+# moved the beach into its own folder
+- account: U-owner
+  roots:
+  - name: Inventory
+    folders:
+      - name: 'Trips ''21'
+        id: \"R-trips\"
+        folders: []
+        items:
+          - id: R-beach   # the photo
+            name: Beach
+            recordType: object
+    items: []
+";
+        let read = read_structures(yaml).unwrap();
+        let trips = &read[0].roots[0].folders[0];
+        assert_eq!(read[0].account.as_str(), "U-owner");
+        assert_eq!(trips.name.as_str(), "Trips '21");
+        assert_eq!(trips.id.as_deref(), Some("R-trips"));
+        assert_eq!(trips.items[0].id.as_str(), "R-beach");
+        assert!(read[0].roots[0].items.is_empty());
+
+        for broken in ["- account: [U-owner]", "- account: U\n    roots: []\n  bad"] {
+            assert!(matches!(
+                read_structures(broken),
+                Err(StructureError::Yaml(..))
+            ));
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use super::queue::{ExportJob, ExportQueue, ExportSummary};
+use crate::store::{
+    backup::{AssetUri, Backup, Error, RecordType, SZBson},
+    naming::file_name,
+    redact::Redactor,
+    RcStr,
+};
+use bson::Bson;
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Each account's inventory as folders under out/<account>, its folders as directories and
+// every other record as a directory of its own holding
+//
+//   manifest.json          the decompressed manifest, as relaxed extended JSON
+//   thumbnail.<ext>
+//   assets/<hash>.<ext>    everything in its neosDBmanifest
+//
+// so the backup can be browsed in a file manager. Assets are copied, unlike asset links.
+pub fn export_inventory_tree(
+    backup: &Backup,
+    out: &Path,
+    redactor: &Redactor,
+    resume: bool,
+) -> Result<ExportSummary, Error> {
+    let mut queue = ExportQueue::open(out, resume)?;
+    let mut manifests = BTreeSet::new();
+    for (account_name, account) in &backup.accounts {
+        let root = out.join(file_name(account_name));
+        let dir_of = |path: &[RcStr]| {
+            let mut dir = root.clone();
+            dir.extend(path.iter().map(|name| file_name(&redactor.text(name))));
+            dir
+        };
+        // Every folder is claimed up front, so an item named like one doesn't take its place.
+        let mut used = BTreeSet::new();
+        for rec in account.records.values() {
+            for end in 1..=rec.path.len() {
+                used.insert(dir_of(&rec.path[..end]));
+            }
+            if rec.record_type == RecordType::Directory {
+                let mut path = rec.path.clone();
+                path.push(rec.name.clone());
+                // Created here, an empty folder has no jobs to create it.
+                fs::create_dir_all(dir_of(&path))?;
+                used.insert(dir_of(&path));
+            }
+        }
+        for rec in account.records.values() {
+            if rec.record_type == RecordType::Directory {
+                continue;
+            }
+            let dir = dir_of(&rec.path);
+            let name = file_name(&redactor.text(&rec.name));
+            // Names aren't unique within a folder, the record ID tells same-named ones apart.
+            let mut record_dir = dir.join(&name);
+            if !used.insert(record_dir.clone()) {
+                record_dir = dir.join(format!("{} ({})", name, file_name(&rec.id)));
+                used.insert(record_dir.clone());
+            }
+
+            let mut job = |asset: &RcStr, path: PathBuf| {
+                queue.push(ExportJob {
+                    account: account_name.clone(),
+                    record: rec.id.clone(),
+                    asset: asset.clone(),
+                    path,
+                })
+            };
+            let mut seen = BTreeSet::new();
+            if let Some(AssetUri::SZBson(asset)) = &rec.asset_uri {
+                seen.insert(asset.0.clone());
+                let path = record_dir.join("manifest.json");
+                manifests.insert(path.clone());
+                job(&asset.0, path);
+            }
+            if let Some(hash) = rec.thumbnail_uri.as_ref().and_then(AssetUri::asset_id) {
+                seen.insert(hash.clone());
+                let ext = extension(backup, hash);
+                job(hash, record_dir.join(format!("thumbnail.{}", ext)));
+            }
+            for asset in &rec.neos_db_manifest {
+                if seen.insert(asset.hash.clone()) {
+                    let ext = extension(backup, &asset.hash);
+                    let path = record_dir
+                        .join("assets")
+                        .join(format!("{}.{}", asset.hash, ext));
+                    job(&asset.hash, path);
+                }
+            }
+        }
+    }
+    queue.run(|job| {
+        if manifests.contains(&job.path) {
+            write_manifest(backup, &job.asset, &job.path).map_err(|e| e.to_string())
+        } else {
+            copy_asset(backup, &job.asset, &job.path).map_err(|e| e.to_string())
+        }
+    })
+}
+
+// Sniffed, since the URL's extension only says how Neos stored it.
+fn extension(backup: &Backup, hash: &str) -> &'static str {
+    backup.sniff_asset(hash).ok().flatten().unwrap_or("bin")
+}
+
+fn write_manifest(backup: &Backup, hash: &RcStr, out: &Path) -> Result<(), Error> {
+    let document = SZBson(hash.clone()).read_document(backup)?;
+    let json = Bson::Document(document).into_relaxed_extjson();
+    let file = io::BufWriter::new(fs::File::create(out)?);
+    serde_json::to_writer_pretty(file, &json).map_err(|e| Error::SerdeJson(e, out.to_owned()))
+}
+
+fn copy_asset(backup: &Backup, hash: &str, out: &Path) -> io::Result<()> {
+    match backup.asset_path(hash) {
+        Some(path) => fs::copy(path, out).map(|_| ()),
+        None => Err(backup.missing_asset(hash)),
+    }
+}
@@ -0,0 +1,298 @@
+use super::queue::{ExportJob, ExportQueue, ExportSummary};
+use crate::store::{
+    backup::{AssetUri, Backup, Error, Manifest, Record, WellKnownAssetKind},
+    features::MissingFeature,
+    naming::{self, NameParts, NamingPolicy},
+    redact::Redactor,
+    RcStr,
+};
+#[cfg(feature = "audio")]
+use ogg::{PacketReader, PacketWriteEndInfo, PacketWriter};
+#[cfg(feature = "audio")]
+use std::io::{Read, Seek};
+use std::{collections::BTreeSet, io, path::Path};
+#[cfg(any(feature = "images", feature = "audio"))]
+use std::{fs, io::Write};
+
+#[derive(thiserror::Error, Debug)]
+pub enum MediaError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Disabled(#[from] MissingFeature),
+    #[cfg(feature = "images")]
+    #[error("Image: {0}")]
+    Image(#[from] image::ImageError),
+    #[cfg(feature = "images")]
+    #[error("Png: {0}")]
+    Png(#[from] png::EncodingError),
+    #[cfg(feature = "audio")]
+    #[error("Ogg: {0}")]
+    Ogg(#[from] ogg::OggReadError),
+    #[error("not an ogg vorbis stream")]
+    NotVorbis,
+}
+
+// Where an exported file came from, embedded so it stays traceable to its record.
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    pub record: RcStr,
+    pub name: RcStr,
+    pub owner: RcStr,
+    pub created: Option<String>,
+    pub hash: RcStr,
+}
+
+impl Provenance {
+    pub fn of(rec: &Record, hash: &RcStr, redactor: &Redactor) -> Self {
+        Self {
+            record: rec.id.clone(),
+            name: redactor.text(&rec.name),
+            owner: redactor.username(&rec.owner_id, &rec.owner_name),
+            created: rec
+                .creation_time
+                .or(rec.last_modification_time)
+                .map(|t| t.to_rfc3339()),
+            hash: hash.clone(),
+        }
+    }
+
+    // PNG keywords and Vorbis field names for each value, using the standard ones where
+    // a standard one fits.
+    #[cfg_attr(not(any(feature = "images", feature = "audio")), allow(dead_code))]
+    fn tags(&self) -> Vec<(&'static str, &'static str, String)> {
+        let mut tags = vec![
+            ("Title", "TITLE", self.name.to_string()),
+            ("Author", "ARTIST", self.owner.to_string()),
+            ("Source", "SOURCE", format!("neosdb:///{}", self.hash)),
+            ("Neos Record", "NEOS_RECORD", self.record.to_string()),
+        ];
+        if let Some(created) = &self.created {
+            tags.push(("Creation Time", "DATE", created.clone()));
+        }
+        tags
+    }
+}
+
+// Every image and audio asset a record references, as PNG or tagged OGG, at the naming
+// policy's media paths under out.
+pub fn export_media(
+    backup: &Backup,
+    out: &Path,
+    redactor: &Redactor,
+    naming: &NamingPolicy,
+    resume: bool,
+) -> Result<ExportSummary, Error> {
+    let mut queue = ExportQueue::open(out, resume)?;
+    let template = naming.media_template();
+    let needs_sites = naming::uses(template, "slot") || naming::uses(template, "component");
+    let mut used = BTreeSet::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let sites = match &rec.asset_uri {
+                Some(AssetUri::SZBson(asset)) if needs_sites => {
+                    let res: Result<Manifest, _> = asset.open(backup);
+                    res.map(|m| naming::asset_sites(&m)).unwrap_or_default()
+                }
+                _ => Default::default(),
+            };
+            let hashes: BTreeSet<RcStr> = rec
+                .neos_db_manifest
+                .iter()
+                .map(|a| a.hash.clone())
+                .chain(
+                    rec.thumbnail_uri
+                        .iter()
+                        .filter_map(|u| u.asset_id().cloned()),
+                )
+                .collect();
+            for hash in hashes {
+                let Some((kind, output)) = media_kinds(backup, &hash) else {
+                    continue;
+                };
+                let mut parts = NameParts::default()
+                    .with("account", account_name)
+                    .with("record", &rec.id)
+                    .with("name", redactor.text(&rec.name))
+                    .with("hash", &hash)
+                    .with("kind", kind)
+                    .with("ext", naming.extension(output));
+                if let Some((slot, component)) = sites.get(&hash) {
+                    parts = parts.with("slot", slot).with("component", component);
+                }
+                let path = naming::render(template, &parts, out);
+                queue.push(ExportJob {
+                    account: account_name.clone(),
+                    record: rec.id.clone(),
+                    path: naming::unique(path, &hash, &mut used),
+                    asset: hash,
+                });
+            }
+        }
+    }
+    queue.run(|job| {
+        let rec = backup
+            .accounts
+            .get(&job.account)
+            .and_then(|a| a.records.get(&job.record))
+            .ok_or_else(|| format!("no record {}", job.record))?;
+        let provenance = Provenance::of(rec, &job.asset, redactor);
+        export_asset(backup, &job.asset, &provenance, &job.path).map_err(|e| e.to_string())
+    })
+}
+
+// The sniffed kind and the kind it's written as. Kinds whose feature is off aren't
+// planned at all, rather than failing one by one.
+fn media_kinds(backup: &Backup, hash: &str) -> Option<(&'static str, &'static str)> {
+    match backup.sniff_asset(hash).ok()?? {
+        kind @ ("webp" | "png" | "jpg" | "gif") if cfg!(feature = "images") => Some((kind, "png")),
+        "ogg" if cfg!(feature = "audio") => Some(("ogg", "ogg")),
+        _ => None,
+    }
+}
+
+pub fn export_asset(
+    backup: &Backup,
+    hash: &str,
+    provenance: &Provenance,
+    out: &Path,
+) -> Result<(), MediaError> {
+    let Some(path) = backup.asset_path(hash) else {
+        return Err(backup.missing_asset(hash).into());
+    };
+    if backup.sniff_asset(hash)? == Some("ogg") {
+        retag_ogg_file(&path, provenance, out)
+    } else {
+        reencode_png(&path, provenance, out)
+    }
+}
+
+#[cfg(feature = "audio")]
+fn retag_ogg_file(path: &Path, provenance: &Provenance, out: &Path) -> Result<(), MediaError> {
+    let ogg = fs::File::open(path)?;
+    tag_ogg(ogg, provenance, io::BufWriter::new(fs::File::create(out)?))
+}
+
+#[cfg(not(feature = "audio"))]
+fn retag_ogg_file(_: &Path, _: &Provenance, _: &Path) -> Result<(), MediaError> {
+    Err(MissingFeature("audio").into())
+}
+
+#[cfg(feature = "images")]
+fn reencode_png(path: &Path, provenance: &Provenance, out: &Path) -> Result<(), MediaError> {
+    let image = image::load_from_memory(&fs::read(path)?)?;
+    write_png(
+        &image,
+        provenance,
+        io::BufWriter::new(fs::File::create(out)?),
+    )
+}
+
+#[cfg(not(feature = "images"))]
+fn reencode_png(_: &Path, _: &Provenance, _: &Path) -> Result<(), MediaError> {
+    Err(MissingFeature("images").into())
+}
+
+#[cfg(feature = "images")]
+pub fn write_png<W: Write>(
+    image: &image::DynamicImage,
+    provenance: &Provenance,
+    out: W,
+) -> Result<(), MediaError> {
+    let image = image.to_rgba8();
+    let mut encoder = png::Encoder::new(out, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, _, value) in provenance.tags() {
+        encoder.add_itxt_chunk(keyword.to_owned(), value)?;
+    }
+    encoder.write_header()?.write_image_data(image.as_raw())?;
+    Ok(())
+}
+
+// Rewrites the Vorbis comment header with the provenance tags, leaving the audio alone.
+#[cfg(feature = "audio")]
+pub fn tag_ogg<R, W>(ogg: R, provenance: &Provenance, out: W) -> Result<(), MediaError>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    let mut reader = PacketReader::new(ogg);
+    let mut writer = PacketWriter::new(out);
+    let mut packets = 0;
+    while let Some(packet) = reader.read_packet()? {
+        let end = if packet.last_in_stream() {
+            PacketWriteEndInfo::EndStream
+        } else if packet.last_in_page() {
+            PacketWriteEndInfo::EndPage
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        packets += 1;
+        let data = if packets == 2 {
+            retag_comments(&packet.data, provenance).ok_or(MediaError::NotVorbis)?
+        } else {
+            packet.data.clone()
+        };
+        writer.write_packet(
+            data.into_boxed_slice(),
+            packet.stream_serial(),
+            end,
+            packet.absgp_page(),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "audio")]
+const COMMENT_MAGIC: &[u8] = b"\x03vorbis";
+
+#[cfg(feature = "audio")]
+fn retag_comments(header: &[u8], provenance: &Provenance) -> Option<Vec<u8>> {
+    let mut rest = header.strip_prefix(COMMENT_MAGIC)?;
+    let vendor_len = read_len(&mut rest)?;
+    let vendor = take(&mut rest, vendor_len)?.to_vec();
+    let count = read_len(&mut rest)?;
+    let mut comments = Vec::new();
+    for _ in 0..count {
+        let len = read_len(&mut rest)?;
+        comments.push(take(&mut rest, len)?.to_vec());
+    }
+
+    let tags = provenance.tags();
+    // Field names compare case-insensitively, ours replace any earlier ones.
+    comments.retain(|c| {
+        let key = c.split(|b| *b == b'=').next().unwrap_or_default();
+        !tags
+            .iter()
+            .any(|(_, name, _)| key.eq_ignore_ascii_case(name.as_bytes()))
+    });
+    comments.extend(
+        tags.iter()
+            .map(|(_, name, value)| format!("{}={}", name, value).into_bytes()),
+    );
+
+    let mut out = COMMENT_MAGIC.to_vec();
+    out.extend((vendor.len() as u32).to_le_bytes());
+    out.extend(vendor);
+    out.extend((comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        out.extend((comment.len() as u32).to_le_bytes());
+        out.extend(comment);
+    }
+    // Framing bit.
+    out.push(1);
+    Some(out)
+}
+
+#[cfg(feature = "audio")]
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    let (head, tail) = (rest.get(..len)?, rest.get(len..)?);
+    *rest = tail;
+    Some(head)
+}
+
+#[cfg(feature = "audio")]
+fn read_len(rest: &mut &[u8]) -> Option<usize> {
+    Some(u32::from_le_bytes(take(rest, 4)?.try_into().ok()?) as usize)
+}
@@ -0,0 +1,251 @@
+use crate::store::{
+    backup::{AssetUri, Backup, Error, Message, MessageContent},
+    naming::file_name,
+    redact::Redactor,
+    render::Zone,
+    report::escape_html,
+    RcStr,
+};
+use std::{collections::BTreeMap, fmt::Write as _, fs, path::Path, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Markdown,
+    Html,
+}
+
+impl FromStr for TranscriptFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "md" | "markdown" => Ok(TranscriptFormat::Markdown),
+            "html" => Ok(TranscriptFormat::Html),
+            _ => Err(format!(
+                "unknown transcript format {:?}, expected md or html",
+                s
+            )),
+        }
+    }
+}
+
+impl TranscriptFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Markdown => "md",
+            TranscriptFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MessagesExport {
+    pub transcripts: u64,
+    pub messages: u64,
+    // Sound and object assets copied next to the transcripts, and those no root has.
+    pub assets: u64,
+    pub missing: u64,
+}
+
+// Writes each conversation as out/<account>/<contact>.<md|html>, oldest message first,
+// with senders named by the contacts in the backup. The ogg of a voice message and the
+// asset and thumbnail of a sent object are copied into out/<account>/assets/ and linked.
+// With redaction on, message content and attachments are left out.
+pub fn export_messages(
+    backup: &Backup,
+    out: &Path,
+    redactor: &Redactor,
+    format: TranscriptFormat,
+    zone: Zone,
+) -> Result<MessagesExport, Error> {
+    // Every account's contacts, since one's own name is only in the others'.
+    let mut names = BTreeMap::new();
+    for account in backup.accounts.values() {
+        for contact in account.contacts.values() {
+            names.insert(&contact.id, &contact.friend_username);
+        }
+    }
+    let name = |id: &RcStr| match names.get(id) {
+        Some(username) => redactor.username(id, username),
+        None => redactor.user_id(id),
+    };
+
+    let mut summary = MessagesExport::default();
+    for (account_name, account) in &backup.accounts {
+        let dir = out.join(file_name(account_name));
+        fs::create_dir_all(&dir)?;
+        for (contact, thread) in &account.messages {
+            let mut thread: Vec<&Message> = thread.iter().collect();
+            thread.sort_by_key(|msg| msg.send_time);
+            let title = format!("{} and {}", name(account_name), name(contact));
+            let mut lines = Vec::with_capacity(thread.len());
+            for msg in thread {
+                let sender = name(msg.sender(account_name, contact));
+                let content = content(backup, msg, redactor, format, &dir, &mut summary)?;
+                lines.push((zone.format(&msg.send_time), sender, content));
+                summary.messages += 1;
+            }
+            let page = match format {
+                TranscriptFormat::Markdown => markdown(&title, &lines),
+                TranscriptFormat::Html => html(&title, &lines),
+            };
+            let file = format!(
+                "{}.{}",
+                file_name(&redactor.user_id(contact)),
+                format.extension()
+            );
+            fs::write(dir.join(file), page)?;
+            summary.transcripts += 1;
+        }
+    }
+    Ok(summary)
+}
+
+// The message as it shows in the transcript, already escaped for the format.
+fn content(
+    backup: &Backup,
+    msg: &Message,
+    redactor: &Redactor,
+    format: TranscriptFormat,
+    dir: &Path,
+    summary: &mut MessagesExport,
+) -> Result<String, Error> {
+    let escape = |text: &str| match format {
+        TranscriptFormat::Markdown => escape_markdown(text),
+        TranscriptFormat::Html => escape_html(text).replace('\n', "<br>"),
+    };
+    if redactor.is_active() {
+        return Ok(escape(&redactor.message(&msg.content)));
+    }
+    let emphasized = |label: &str| match format {
+        TranscriptFormat::Markdown => format!("_{}_", escape(label)),
+        TranscriptFormat::Html => format!("<em>{}</em>", escape(label)),
+    };
+    let (sound, sent) = match msg.parsed_content() {
+        MessageContent::Text(text) | MessageContent::Raw(text) => return Ok(escape(&text)),
+        MessageContent::SessionInvite(session) => {
+            return Ok(emphasized(&format!("session invite: {}", session.name)))
+        }
+        MessageContent::CreditTransfer(transfer) => {
+            return Ok(emphasized(&format!(
+                "credit transfer: {} {}",
+                transfer.amount, transfer.token
+            )))
+        }
+        MessageContent::Sound(sent) => (true, sent),
+        MessageContent::Object(sent) => (false, sent),
+    };
+    let label = match (sound, &sent.name) {
+        (true, _) => "voice message".to_owned(),
+        (false, Some(name)) => name.to_string(),
+        (false, None) => "object".to_owned(),
+    };
+    let label = escape(&label);
+
+    let asset = copy_asset(backup, sent.asset_uri.as_ref(), dir, summary)?;
+    let thumbnail = copy_asset(backup, sent.thumbnail_uri.as_ref(), dir, summary)?;
+    let mut shown = String::new();
+    match format {
+        TranscriptFormat::Markdown => {
+            if let Some(thumbnail) = &thumbnail {
+                write!(shown, "![]({}) ", thumbnail).ok();
+            }
+            match &asset {
+                Some(asset) => write!(shown, "[{}]({})", label, asset).ok(),
+                None => write!(shown, "_{} (missing)_", label).ok(),
+            };
+        }
+        TranscriptFormat::Html => {
+            if let Some(thumbnail) = &thumbnail {
+                write!(shown, "<img src=\"{}\" alt=\"\"> ", thumbnail).ok();
+            }
+            match (&asset, sound) {
+                (Some(asset), true) => {
+                    write!(shown, "<audio controls src=\"{}\"></audio>", asset).ok()
+                }
+                (Some(asset), _) => write!(shown, "<a href=\"{}\">{}</a>", asset, label).ok(),
+                (None, _) => write!(shown, "<em>{} (missing)</em>", label).ok(),
+            };
+        }
+    }
+    Ok(shown)
+}
+
+// Copies the asset into dir/assets once, however many messages share it, returning the
+// path to link it by. None when there's no asset or no root has it.
+fn copy_asset(
+    backup: &Backup,
+    uri: Option<&AssetUri>,
+    dir: &Path,
+    summary: &mut MessagesExport,
+) -> Result<Option<String>, Error> {
+    let Some(uri) = uri else {
+        return Ok(None);
+    };
+    let Some(id) = uri.asset_id() else {
+        return Ok(None);
+    };
+    let Some(source) = backup.asset_path(id) else {
+        summary.missing += 1;
+        return Ok(None);
+    };
+    let link = format!("assets/{}.{}", file_name(id), uri.kind().unwrap_or("bin"));
+    let path = dir.join(&link);
+    if !path.exists() {
+        fs::create_dir_all(dir.join("assets"))?;
+        fs::copy(source, &path)?;
+        summary.assets += 1;
+    }
+    Ok(Some(link))
+}
+
+fn markdown(title: &str, lines: &[(String, RcStr, String)]) -> String {
+    let mut page = format!("# {}\n\n", escape_markdown(title));
+    for (time, sender, content) in lines {
+        // Continuation lines are indented to stay in the list item.
+        let content = content.replace('\n', "\n  ");
+        writeln!(
+            page,
+            "- **{}** {}: {}",
+            time,
+            escape_markdown(sender),
+            content
+        )
+        .ok();
+    }
+    page
+}
+
+fn html(title: &str, lines: &[(String, RcStr, String)]) -> String {
+    let title = escape_html(title);
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+         <style>body {{ font-family: sans-serif; max-width: 50em; margin: auto; }} \
+         time {{ color: #777; }} img {{ max-height: 8em; vertical-align: middle; }}</style>\n\
+         </head>\n<body>\n<h1>{0}</h1>\n",
+        title
+    );
+    for (time, sender, content) in lines {
+        writeln!(
+            page,
+            "<p><time>{}</time> <b>{}</b>: {}</p>",
+            escape_html(time),
+            escape_html(sender),
+            content
+        )
+        .ok();
+    }
+    page + "</body>\n</html>\n"
+}
+
+// Backslashes the characters that would otherwise format the text.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>#|".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
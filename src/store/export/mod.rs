@@ -0,0 +1,41 @@
+pub mod asset_links;
+pub mod bundle;
+pub mod calendar;
+pub mod contact_cards;
+pub mod dashboard;
+pub mod inventory_structure;
+pub mod inventory_tree;
+pub mod media;
+pub mod messages;
+pub mod openmetrics;
+pub mod queue;
+pub mod slot_graph;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod text_corpus;
+pub mod voice;
+
+// vCard and iCalendar text values escape these.
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+// Lines longer than 75 octets continue on the next line after a space, split on char
+// boundaries so multi-byte names survive.
+pub(crate) fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
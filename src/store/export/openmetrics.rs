@@ -0,0 +1,123 @@
+use crate::store::stats::Stats;
+use std::io::{self, Write};
+
+pub fn write_metrics<W>(stats: &Stats, out: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    header(out, "neos_backup_records", "Records in the backup.", None)?;
+    for (kind, count) in &stats.records_by_type {
+        let kind = serde_json::to_value(kind)?;
+        writeln!(
+            out,
+            "neos_backup_records{{type=\"{}\"}} {}",
+            escape(kind.as_str().unwrap_or_default()),
+            count
+        )?;
+    }
+
+    gauge(
+        out,
+        "neos_backup_messages",
+        "Messages in the backup.",
+        None,
+        stats.message_count,
+    )?;
+    if !stats.messages_by_label.is_empty() {
+        header(
+            out,
+            "neos_backup_label_messages",
+            "Messages exchanged with contacts under a user-defined label.",
+            None,
+        )?;
+        for (label, count) in &stats.messages_by_label {
+            writeln!(
+                out,
+                "neos_backup_label_messages{{label=\"{}\"}} {}",
+                escape(label),
+                count
+            )?;
+        }
+    }
+    if !stats.custom_metrics.is_empty() {
+        header(
+            out,
+            "neos_backup_custom",
+            "Metrics defined in the statbox config.",
+            None,
+        )?;
+        for (name, value) in &stats.custom_metrics {
+            writeln!(
+                out,
+                "neos_backup_custom{{name=\"{}\"}} {}",
+                escape(name),
+                value
+            )?;
+        }
+    }
+    gauge(
+        out,
+        "neos_backup_asset_bytes",
+        "Bytes of referenced assets present in the asset store.",
+        Some("bytes"),
+        stats.asset_bytes,
+    )?;
+    gauge(
+        out,
+        "neos_backup_missing_assets",
+        "Referenced assets not present in the asset store.",
+        None,
+        stats.missing_assets.len(),
+    )?;
+    gauge(
+        out,
+        "neos_backup_manifests_scanned",
+        "Manifests successfully parsed.",
+        None,
+        stats.manifests_scanned,
+    )?;
+    gauge(
+        out,
+        "neos_backup_manifest_errors",
+        "Manifests that failed to parse.",
+        None,
+        stats.manifest_errors,
+    )?;
+    if let Some(time) = stats.last_record_time {
+        gauge(
+            out,
+            "neos_backup_last_record_timestamp_seconds",
+            "Most recent record modification time.",
+            Some("seconds"),
+            time.timestamp(),
+        )?;
+    }
+
+    writeln!(out, "# EOF")
+}
+
+fn header<W: Write>(out: &mut W, name: &str, help: &str, unit: Option<&str>) -> io::Result<()> {
+    writeln!(out, "# TYPE {} gauge", name)?;
+    if let Some(unit) = unit {
+        writeln!(out, "# UNIT {} {}", name, unit)?;
+    }
+    writeln!(out, "# HELP {} {}", name, help)
+}
+
+fn gauge<W: Write, V: std::fmt::Display>(
+    out: &mut W,
+    name: &str,
+    help: &str,
+    unit: Option<&str>,
+    value: V,
+) -> io::Result<()> {
+    header(out, name, help, unit)?;
+    writeln!(out, "{} {}", name, value)
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
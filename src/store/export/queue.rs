@@ -0,0 +1,119 @@
+use crate::store::{backup::Error, journal::Journal, RcStr};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Kept in the export directory as one JSON line per finished job, for auditing and so an
+// interrupted export can resume.
+pub const MANIFEST: &str = "export-manifest.jsonl";
+
+// One file an export means to write.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJob {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub asset: RcStr,
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum Outcome {
+    Written { bytes: u64 },
+    Failed { error: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub job: ExportJob,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    pub written: u64,
+    pub bytes: u64,
+    // Written by an earlier run and still on disk.
+    pub skipped: u64,
+    pub failed: Vec<ManifestEntry>,
+}
+
+pub struct ExportQueue {
+    jobs: Vec<ExportJob>,
+    manifest: Journal<ManifestEntry>,
+}
+
+impl ExportQueue {
+    // Without resume the manifest starts over and every job runs again.
+    pub fn open(out: &Path, resume: bool) -> Result<Self, Error> {
+        Ok(Self {
+            jobs: Vec::new(),
+            manifest: Journal::open(out.join(MANIFEST), resume)?,
+        })
+    }
+
+    pub fn push(&mut self, job: ExportJob) {
+        self.jobs.push(job);
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    // Failed jobs from an earlier run are retried, written ones are skipped while their
+    // file is still there at the recorded size.
+    pub fn run<F>(self, mut write: F) -> Result<ExportSummary, Error>
+    where
+        F: FnMut(&ExportJob) -> Result<(), String>,
+    {
+        let Self { jobs, mut manifest } = self;
+        let mut summary = ExportSummary::default();
+        for job in jobs {
+            let key: RcStr = job.path.to_string_lossy().into_owned().into();
+            if let Some(ManifestEntry {
+                outcome: Outcome::Written { bytes },
+                ..
+            }) = manifest.get(&key)
+            {
+                if fs::metadata(&job.path).is_ok_and(|m| m.len() == *bytes) {
+                    summary.skipped += 1;
+                    continue;
+                }
+            }
+
+            if let Some(dir) = job.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let outcome = match write(&job).and_then(|()| {
+                fs::metadata(&job.path)
+                    .map(|m| m.len())
+                    .map_err(|e| e.to_string())
+            }) {
+                Ok(bytes) => {
+                    summary.written += 1;
+                    summary.bytes += bytes;
+                    Outcome::Written { bytes }
+                }
+                Err(error) => {
+                    // Don't leave a partial file that looks like a finished one.
+                    fs::remove_file(&job.path).ok();
+                    Outcome::Failed { error }
+                }
+            };
+            let entry = ManifestEntry { job, outcome };
+            if let Outcome::Failed { .. } = entry.outcome {
+                summary.failed.push(entry.clone());
+            }
+            manifest.insert(key, entry)?;
+        }
+        Ok(summary)
+    }
+}
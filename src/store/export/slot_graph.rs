@@ -0,0 +1,276 @@
+use crate::store::{
+    backup::{Component, FieldValue, Manifest, Slot},
+    redact::{Redact, Redactor},
+    RcStr,
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => Err(format!(
+                "unknown graph format {:?}, expected dot or mermaid",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Child,
+    Component,
+    Reference,
+}
+
+#[derive(Debug)]
+pub struct Node {
+    pub label: String,
+    pub is_slot: bool,
+}
+
+#[derive(Debug)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+    pub label: Option<RcStr>,
+}
+
+#[derive(Debug, Default)]
+pub struct SlotGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    // Every ID in the manifest (slots, components and their fields) to the node owning it.
+    ids: BTreeMap<RcStr, usize>,
+}
+
+impl SlotGraph {
+    pub fn build(manifest: &Manifest) -> Self {
+        let mut graph = Self::default();
+        let mut components = Vec::new();
+        if let Some(slot) = &manifest.object {
+            graph.add_slot(slot, &mut components);
+        }
+        for component in manifest.assets.iter().flatten() {
+            let node = graph.add_component(component);
+            components.push((node, component));
+        }
+        // References can point forward, so they are resolved after every ID is known.
+        for (node, component) in components {
+            for (name, value) in &component.data.fields {
+                let mut targets = Vec::new();
+                graph.collect_references(value, &mut targets);
+                for target in targets {
+                    if target != node {
+                        graph.edges.push(Edge {
+                            from: node,
+                            to: target,
+                            kind: EdgeKind::Reference,
+                            label: Some(name.clone()),
+                        });
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    fn add_slot<'a>(
+        &mut self,
+        slot: &'a Slot,
+        components: &mut Vec<(usize, &'a Component)>,
+    ) -> usize {
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            label: slot
+                .name
+                .data()
+                .as_deref()
+                .unwrap_or("<unnamed>")
+                .to_owned(),
+            is_slot: true,
+        });
+        self.ids.insert(slot.id.clone(), node);
+        for field in [
+            slot.name.id(),
+            slot.tag.id(),
+            slot.active.id(),
+            slot.position.id(),
+            slot.rotation.id(),
+            slot.scale.id(),
+            slot.order_offset.id(),
+        ] {
+            self.ids.insert(field.clone(), node);
+        }
+
+        for component in slot.components.data() {
+            let child = self.add_component(component);
+            components.push((child, component));
+            self.edges.push(Edge {
+                from: node,
+                to: child,
+                kind: EdgeKind::Component,
+                label: None,
+            });
+        }
+        for child in &slot.children {
+            let child = self.add_slot(child, components);
+            self.edges.push(Edge {
+                from: node,
+                to: child,
+                kind: EdgeKind::Child,
+                label: None,
+            });
+        }
+        node
+    }
+
+    fn add_component(&mut self, component: &Component) -> usize {
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            label: component.short_type(),
+            is_slot: false,
+        });
+        for id in [
+            &component.data.id,
+            component.data.update_order.id(),
+            component.data.enabled.id(),
+        ] {
+            self.ids.insert(id.clone(), node);
+        }
+        for value in component.data.fields.values() {
+            if let FieldValue::Dunno(bson::Bson::Document(doc)) = value {
+                if let Ok(id) = doc.get_str("ID") {
+                    self.ids.insert(id.to_owned().into(), node);
+                }
+            }
+        }
+        node
+    }
+
+    fn collect_references(&self, value: &FieldValue, targets: &mut Vec<usize>) {
+        match value {
+            FieldValue::Str(s) => targets.extend(self.ids.get(s.as_str())),
+            FieldValue::Dunno(bson) => self.collect_bson_references(bson, targets),
+            _ => {}
+        }
+    }
+
+    fn collect_bson_references(&self, value: &bson::Bson, targets: &mut Vec<usize>) {
+        match value {
+            bson::Bson::String(s) => targets.extend(self.ids.get(s.as_str())),
+            bson::Bson::Array(values) => {
+                for value in values {
+                    self.collect_bson_references(value, targets);
+                }
+            }
+            bson::Bson::Document(doc) => {
+                // The document's own ID is its identity, not a reference.
+                for (key, value) in doc {
+                    if key != "ID" {
+                        self.collect_bson_references(value, targets);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn write<W: Write>(&self, format: GraphFormat, out: &mut W) -> io::Result<()> {
+        match format {
+            GraphFormat::Dot => self.write_dot(out),
+            GraphFormat::Mermaid => self.write_mermaid(out),
+        }
+    }
+
+    fn write_dot<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "digraph manifest {{")?;
+        writeln!(out, "  rankdir=LR;")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            let shape = if node.is_slot { "folder" } else { "box" };
+            writeln!(
+                out,
+                "  n{} [label=\"{}\", shape={}];",
+                i,
+                dot_escape(&node.label),
+                shape
+            )?;
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Child => "solid",
+                EdgeKind::Component => "dotted",
+                EdgeKind::Reference => "dashed",
+            };
+            write!(out, "  n{} -> n{} [style={}", edge.from, edge.to, style)?;
+            if let Some(label) = &edge.label {
+                write!(out, ", label=\"{}\"", dot_escape(label))?;
+            }
+            writeln!(out, "];")?;
+        }
+        writeln!(out, "}}")
+    }
+
+    fn write_mermaid<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "graph LR")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            let label = mermaid_escape(&node.label);
+            if node.is_slot {
+                writeln!(out, "  n{}[\"{}\"]", i, label)?;
+            } else {
+                writeln!(out, "  n{}([\"{}\"])", i, label)?;
+            }
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                EdgeKind::Child => "-->",
+                EdgeKind::Component => "---",
+                EdgeKind::Reference => "-.->",
+            };
+            match &edge.label {
+                Some(label) => writeln!(
+                    out,
+                    "  n{} {}|\"{}\"| n{}",
+                    edge.from,
+                    arrow,
+                    mermaid_escape(label),
+                    edge.to
+                )?,
+                None => writeln!(out, "  n{} {} n{}", edge.from, arrow, edge.to)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Redact for SlotGraph {
+    // Slot names are user text, component types and field names are not.
+    fn redact(&mut self, r: &Redactor) {
+        for node in self.nodes.iter_mut().filter(|n| n.is_slot) {
+            node.label = r.text(&node.label.clone().into()).to_string();
+        }
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
@@ -0,0 +1,466 @@
+use crate::store::{
+    backup::{self, Account, AssetUri, Backup, Message},
+    lazy::LazyBackup,
+    redact::Redactor,
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Statement, Transaction};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SqliteError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("SQLite: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Backup(#[from] backup::Error),
+    #[error("Serde_json: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+// Each account's copy of a row is kept, keyed by the account folder it came from, since
+// both sides of a conversation and every member of a group export their own. The folder
+// is named by its owner's user ID, and redacted like one.
+const SCHEMA: &str = "
+CREATE TABLE accounts (name TEXT PRIMARY KEY);
+CREATE TABLE records (
+    account TEXT NOT NULL, id TEXT NOT NULL, owner_id TEXT NOT NULL, owner_name TEXT,
+    name TEXT, description TEXT, record_type TEXT NOT NULL, path TEXT NOT NULL,
+    asset_kind TEXT, asset_id TEXT, thumbnail_id TEXT,
+    global_version INTEGER, local_version INTEGER, last_modifying_user_id TEXT,
+    creation_time TEXT, last_modification_time TEXT, first_publish_time TEXT,
+    is_public INTEGER, is_for_patrons INTEGER, visits INTEGER, rating INTEGER,
+    bytes INTEGER NOT NULL,
+    PRIMARY KEY (account, id)
+);
+CREATE TABLE record_tags (account TEXT NOT NULL, record_id TEXT NOT NULL, tag TEXT NOT NULL);
+CREATE TABLE record_assets (
+    account TEXT NOT NULL, record_id TEXT NOT NULL, hash TEXT NOT NULL, bytes INTEGER
+);
+CREATE TABLE contacts (
+    account TEXT NOT NULL, id TEXT NOT NULL, owner_id TEXT NOT NULL, friend_username TEXT,
+    friend_status TEXT, is_accepted INTEGER, latest_message_time TEXT,
+    PRIMARY KEY (account, id)
+);
+CREATE TABLE groups (
+    account TEXT NOT NULL, id TEXT NOT NULL, name TEXT, admin_user_id TEXT,
+    quota_bytes INTEGER, used_bytes INTEGER,
+    PRIMARY KEY (account, id)
+);
+CREATE TABLE group_members (
+    account TEXT NOT NULL, group_id TEXT NOT NULL, id TEXT NOT NULL, owner_id TEXT,
+    quota_bytes INTEGER, used_bytes INTEGER
+);
+CREATE TABLE variable_definitions (
+    account TEXT NOT NULL, definition_owner_id TEXT NOT NULL, subpath TEXT NOT NULL,
+    variable_type TEXT, default_value TEXT
+);
+CREATE TABLE variables (
+    account TEXT NOT NULL, owner_id TEXT NOT NULL, path TEXT NOT NULL, value TEXT
+);
+CREATE INDEX records_id ON records (id);
+CREATE INDEX records_owner_id ON records (owner_id);
+CREATE INDEX record_tags_tag ON record_tags (tag);
+CREATE INDEX record_tags_record ON record_tags (account, record_id);
+CREATE INDEX record_assets_hash ON record_assets (hash);
+CREATE INDEX contacts_id ON contacts (id);
+CREATE INDEX groups_id ON groups (id);
+CREATE INDEX group_members_owner_id ON group_members (owner_id);
+CREATE INDEX variables_owner_id ON variables (owner_id);
+";
+
+// Apart from the rest, since a chunk of a chunked export holds only this.
+const MESSAGES_SCHEMA: &str = "
+CREATE TABLE messages (
+    account TEXT NOT NULL, contact TEXT NOT NULL, id TEXT NOT NULL, owner_id TEXT NOT NULL,
+    recipient_id TEXT NOT NULL, sender_id TEXT NOT NULL, message_type TEXT NOT NULL,
+    content TEXT, send_time TEXT NOT NULL, last_update_time TEXT, read_time TEXT
+);
+CREATE INDEX messages_id ON messages (id);
+CREATE INDEX messages_owner_id ON messages (owner_id);
+CREATE INDEX messages_contact ON messages (account, contact);
+";
+
+const INSERT_MESSAGE: &str =
+    "INSERT INTO messages VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)";
+
+#[derive(Debug, Default)]
+pub struct SqliteExport {
+    pub accounts: u64,
+    pub records: u64,
+    pub messages: u64,
+    pub contacts: u64,
+    pub groups: u64,
+    pub variables: u64,
+}
+
+// Writes the backup into a new SQLite database at out, replacing any there, redacted
+// like the other exports. Times are RFC 3339 text, which sorts and compares as times.
+pub fn export_sqlite(
+    backup: &Backup,
+    out: &Path,
+    redactor: &Redactor,
+) -> Result<SqliteExport, SqliteError> {
+    match fs::remove_file(out) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut db = Connection::open(out)?;
+    db.execute_batch(SCHEMA)?;
+    db.execute_batch(MESSAGES_SCHEMA)?;
+    let tx = db.transaction()?;
+    let mut summary = SqliteExport::default();
+    for (account_name, account) in &backup.accounts {
+        tx.execute(
+            "INSERT INTO accounts VALUES (?1)",
+            [redactor.user_id(account_name).as_str()],
+        )?;
+        summary.accounts += 1;
+        insert_records(&tx, account_name, account, redactor, &mut summary)?;
+        insert_messages(&tx, account_name, account, redactor, &mut summary)?;
+        insert_groups(&tx, account_name, account, redactor, &mut summary)?;
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+// When to start a new file of messages. A chunk ends at whichever limit it reaches first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chunking {
+    pub max_rows: Option<u64>,
+    // Counted from the text written, so files come out somewhat bigger.
+    pub max_bytes: Option<u64>,
+}
+
+impl Chunking {
+    fn is_full(&self, rows: u64, bytes: u64) -> bool {
+        self.max_rows.is_some_and(|max| rows >= max)
+            || self.max_bytes.is_some_and(|max| bytes >= max)
+    }
+}
+
+// A message by where it sits in the export order: account, contact, then file name.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageKey {
+    pub account: RcStr,
+    pub contact: RcStr,
+    pub id: RcStr,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageChunk {
+    // Next to the index, which names it relative to itself.
+    pub file: PathBuf,
+    pub rows: u64,
+    pub bytes: u64,
+    pub first: MessageKey,
+    pub last: MessageKey,
+}
+
+// The index written next to a chunked export: the main database and the chunks holding
+// the messages, in order. Redacted keys are as they were written.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkIndex {
+    pub database: PathBuf,
+    pub messages: u64,
+    pub chunks: Vec<MessageChunk>,
+}
+
+// Like export_sqlite, but with the messages streamed out of the backup a file at a time
+// into numbered databases beside out (backup.messages-0001.sqlite and so on) and listed
+// in out's index (backup.index.json), for backups with more messages than fit in memory.
+// Everything else goes into out, with an empty messages table. The backup passed in is
+// only used for the rest, so it should be loaded with skip_messages. Message files that
+// don't parse are skipped with a warning unless the lazy backup's options are strict.
+pub fn export_sqlite_chunked(
+    backup: &Backup,
+    messages: &LazyBackup,
+    out: &Path,
+    redactor: &Redactor,
+    chunking: Chunking,
+) -> Result<(SqliteExport, ChunkIndex), SqliteError> {
+    let mut summary = export_sqlite(backup, out, redactor)?;
+    let stem = out.file_stem().unwrap_or_default().to_string_lossy();
+    let dir = out.parent().unwrap_or(Path::new(""));
+    let mut index = ChunkIndex {
+        database: PathBuf::from(out.file_name().unwrap_or_default()),
+        ..Default::default()
+    };
+    // Chunks left from a longer earlier export would look like part of this one. Only
+    // those its index lists are removed, whatever else shares their names is left alone.
+    let prefix = format!("{}.messages-", stem);
+    let index_path = dir.join(format!("{}.index.json", stem));
+    match fs::read(&index_path) {
+        Ok(previous) => {
+            let previous: ChunkIndex = serde_json::from_slice(&previous)?;
+            for chunk in previous.chunks {
+                // Just a file name, an index can't point outside its folder.
+                if chunk.file.file_name() != Some(chunk.file.as_os_str()) {
+                    continue;
+                }
+                match fs::remove_file(dir.join(&chunk.file)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+        }
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        Err(_) => {}
+    }
+
+    let mut chunk: Option<(Connection, MessageChunk)> = None;
+    for account_name in messages.account_names()? {
+        let Some(account) = messages.account(&account_name) else {
+            continue;
+        };
+        for entry in account.messages_iter()? {
+            let (contact, msg) = entry?;
+            let key = MessageKey {
+                account: redactor.user_id(&account_name),
+                contact: redactor.user_id(&contact),
+                id: msg.id.clone(),
+            };
+            let (db, current) = match &mut chunk {
+                Some(chunk) => chunk,
+                None => {
+                    let file =
+                        PathBuf::from(format!("{}{:04}.sqlite", prefix, index.chunks.len() + 1));
+                    let db = Connection::open(dir.join(&file))?;
+                    db.execute_batch(MESSAGES_SCHEMA)?;
+                    db.execute_batch("BEGIN")?;
+                    let current = MessageChunk {
+                        file,
+                        rows: 0,
+                        bytes: 0,
+                        first: key.clone(),
+                        last: key.clone(),
+                    };
+                    chunk.insert((db, current))
+                }
+            };
+            let mut insert = db.prepare_cached(INSERT_MESSAGE)?;
+            current.bytes += insert_message(&mut insert, &account_name, &contact, &msg, redactor)?;
+            current.rows += 1;
+            current.last = key;
+            summary.messages += 1;
+            if chunking.is_full(current.rows, current.bytes) {
+                drop(insert);
+                if let Some(full) = chunk.take() {
+                    index.chunks.push(finish_chunk(full)?);
+                }
+            }
+        }
+    }
+    if let Some(last) = chunk {
+        index.chunks.push(finish_chunk(last)?);
+    }
+    index.messages = summary.messages;
+    let file = io::BufWriter::new(fs::File::create(index_path)?);
+    serde_json::to_writer_pretty(file, &index)?;
+    Ok((summary, index))
+}
+
+fn finish_chunk((db, chunk): (Connection, MessageChunk)) -> Result<MessageChunk, SqliteError> {
+    db.execute_batch("COMMIT")?;
+    db.close().map_err(|(_, e)| e)?;
+    Ok(chunk)
+}
+
+fn insert_records(
+    tx: &Transaction,
+    account_name: &RcStr,
+    account: &Account,
+    r: &Redactor,
+    summary: &mut SqliteExport,
+) -> Result<(), SqliteError> {
+    let account_id = r.user_id(account_name);
+    let mut records = tx.prepare(
+        "INSERT INTO records VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13,
+            ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
+    )?;
+    let mut tags = tx.prepare("INSERT INTO record_tags VALUES (?1, ?2, ?3)")?;
+    let mut assets = tx.prepare("INSERT INTO record_assets VALUES (?1, ?2, ?3, ?4)")?;
+    for rec in account.records.values() {
+        let path: Vec<_> = rec.path.iter().map(|s| s.as_str()).collect();
+        let bytes: u64 = rec.neos_db_manifest.iter().map(|a| a.bytes).sum();
+        records.execute(params![
+            account_id.as_str(),
+            rec.id.as_str(),
+            r.user_id(&rec.owner_id).as_str(),
+            r.username(&rec.owner_id, &rec.owner_name).as_str(),
+            r.text(&rec.name).as_str(),
+            rec.description.as_ref().map(|d| r.text(d).to_string()),
+            format!("{:?}", rec.record_type),
+            path.join("\\"),
+            rec.asset_uri.as_ref().and_then(AssetUri::kind),
+            rec.asset_uri
+                .as_ref()
+                .and_then(AssetUri::asset_id)
+                .map(|id| id.as_str()),
+            rec.thumbnail_uri
+                .as_ref()
+                .and_then(AssetUri::asset_id)
+                .map(|id| id.as_str()),
+            rec.global_version,
+            rec.local_version,
+            r.user_id(&rec.last_modifying_user_id).as_str(),
+            rec.creation_time.as_ref().map(time),
+            rec.last_modification_time.as_ref().map(time),
+            rec.first_publish_time.as_ref().map(time),
+            rec.is_public,
+            rec.is_for_patrons,
+            rec.visits,
+            rec.rating,
+            bytes as i64,
+        ])?;
+        for tag in &rec.tags {
+            tags.execute(params![account_id.as_str(), rec.id.as_str(), tag.as_str()])?;
+        }
+        for asset in &rec.neos_db_manifest {
+            assets.execute(params![
+                account_id.as_str(),
+                rec.id.as_str(),
+                asset.hash.as_str(),
+                asset.bytes as i64
+            ])?;
+        }
+        summary.records += 1;
+    }
+    Ok(())
+}
+
+fn insert_messages(
+    tx: &Transaction,
+    account_name: &RcStr,
+    account: &Account,
+    r: &Redactor,
+    summary: &mut SqliteExport,
+) -> Result<(), SqliteError> {
+    let account_id = r.user_id(account_name);
+    let mut contacts = tx.prepare("INSERT INTO contacts VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?;
+    for contact in account.contacts.values() {
+        contacts.execute(params![
+            account_id.as_str(),
+            r.user_id(&contact.id).as_str(),
+            r.user_id(&contact.owner_id).as_str(),
+            r.username(&contact.id, &contact.friend_username).as_str(),
+            RcStr::from(contact.friend_status.clone()).as_str(),
+            contact.is_accepted,
+            contact.latest_message_time.as_ref().map(time),
+        ])?;
+        summary.contacts += 1;
+    }
+
+    let mut messages = tx.prepare(INSERT_MESSAGE)?;
+    for (contact, thread) in &account.messages {
+        for msg in thread {
+            insert_message(&mut messages, account_name, contact, msg, r)?;
+            summary.messages += 1;
+        }
+    }
+    Ok(())
+}
+
+// Returns roughly how many bytes the row took, its text without SQLite's overhead.
+fn insert_message(
+    messages: &mut Statement,
+    account_name: &RcStr,
+    contact: &RcStr,
+    msg: &Message,
+    r: &Redactor,
+) -> Result<u64, SqliteError> {
+    let account_id = r.user_id(account_name);
+    let row = (
+        r.user_id(contact),
+        r.user_id(&msg.owner_id),
+        r.user_id(&msg.recipient_id),
+        r.user_id(msg.sender(account_name, contact)),
+        r.message(&msg.content),
+    );
+    messages.execute(params![
+        account_id.as_str(),
+        row.0.as_str(),
+        msg.id.as_str(),
+        row.1.as_str(),
+        row.2.as_str(),
+        row.3.as_str(),
+        format!("{:?}", msg.message_type),
+        row.4.as_str(),
+        time(&msg.send_time),
+        time(&msg.last_update_time),
+        msg.read_time.as_ref().map(time),
+    ])?;
+    let text = [&row.0, &row.1, &row.2, &row.3, &row.4, &account_id, &msg.id];
+    Ok(text.iter().map(|s| s.len() as u64).sum::<u64>() + 3 * 32)
+}
+
+fn insert_groups(
+    tx: &Transaction,
+    account_name: &RcStr,
+    account: &Account,
+    r: &Redactor,
+    summary: &mut SqliteExport,
+) -> Result<(), SqliteError> {
+    let account_id = r.user_id(account_name);
+    let mut groups = tx.prepare("INSERT INTO groups VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+    for group in account.groups.values() {
+        groups.execute(params![
+            account_id.as_str(),
+            group.id.as_str(),
+            r.text(&group.name).as_str(),
+            r.user_id(&group.admin_user_id).as_str(),
+            group.quota_bytes as i64,
+            group.used_bytes as i64,
+        ])?;
+        summary.groups += 1;
+    }
+    let mut members = tx.prepare("INSERT INTO group_members VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+    for (group_id, group_members) in &account.group_members {
+        for member in group_members.values() {
+            members.execute(params![
+                account_id.as_str(),
+                group_id.as_str(),
+                r.user_id(&member.id).as_str(),
+                r.user_id(&member.owner_id).as_str(),
+                member.quota_bytes,
+                member.used_bytes as i64,
+            ])?;
+        }
+    }
+
+    let mut definitions =
+        tx.prepare("INSERT INTO variable_definitions VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    for def in account.variable_definitions.values() {
+        definitions.execute(params![
+            account_id.as_str(),
+            r.user_id(&def.definition_owner_id).as_str(),
+            def.subpath.as_str(),
+            def.variable_type.as_str(),
+            def.default_value.as_ref().map(|v| v.as_str()),
+        ])?;
+    }
+    let mut variables = tx.prepare("INSERT INTO variables VALUES (?1, ?2, ?3, ?4)")?;
+    for var in account.variables.values() {
+        variables.execute(params![
+            account_id.as_str(),
+            r.user_id(&var.owner_id).as_str(),
+            var.path.as_str(),
+            var.value.as_str(),
+        ])?;
+        summary.variables += 1;
+    }
+    Ok(())
+}
+
+fn time(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339()
+}
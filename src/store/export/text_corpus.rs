@@ -0,0 +1,19 @@
+use crate::store::{
+    redact::{Redact, Redactor},
+    text::TextField,
+};
+use std::io::{self, Write};
+
+// One JSON object per line, so the corpus can be streamed into an indexer.
+pub fn write_corpus<W>(fields: &[TextField], redactor: &Redactor, out: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    for field in fields {
+        let mut field = field.clone();
+        field.redact(redactor);
+        serde_json::to_writer(&mut *out, &field)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
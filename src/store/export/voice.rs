@@ -0,0 +1,114 @@
+use crate::store::{
+    backup::{Backup, Error},
+    redact::{Redact, Redactor},
+    voice::{voice_messages, Transcriber, VoiceMessage},
+    RcStr,
+};
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+pub const TRANSCRIPTS: &str = "transcripts.jsonl";
+
+// A transcribed voice message, one line of transcripts.jsonl for the message index.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    #[serde(flatten)]
+    pub voice: VoiceMessage,
+    pub transcriber: String,
+    pub text: String,
+}
+
+impl Redact for VoiceMessage {
+    fn redact(&mut self, r: &Redactor) {
+        self.contact = r.user_id(&self.contact);
+        self.sender_id = r.user_id(&self.sender_id);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VoiceExport {
+    pub messages: u64,
+    pub extracted: u64,
+    // Voice messages whose asset no root has.
+    pub missing: u64,
+    pub transcribed: u64,
+    // Transcripts kept from an earlier run rather than made again.
+    pub kept: u64,
+    pub failed: Vec<(RcStr, String)>,
+}
+
+// Copies every voice message's ogg to out/<account>/<contact>/<message>.ogg and, given a
+// transcriber, writes its transcript next to it as .txt and a line in transcripts.jsonl.
+// A message that already has a .txt isn't transcribed again. Transcripts are redacted
+// like the rest, before they're written.
+pub fn export_voice(
+    backup: &Backup,
+    out: &Path,
+    redactor: &Redactor,
+    transcriber: Option<&dyn Transcriber>,
+) -> Result<VoiceExport, Error> {
+    let mut summary = VoiceExport::default();
+    fs::create_dir_all(out)?;
+    let mut index = match transcriber {
+        Some(_) => Some(io::BufWriter::new(fs::File::create(out.join(TRANSCRIPTS))?)),
+        None => None,
+    };
+    for mut voice in voice_messages(backup) {
+        summary.messages += 1;
+        let Some(source) = backup.asset_path(&voice.asset) else {
+            summary.missing += 1;
+            continue;
+        };
+        voice.redact(redactor);
+        let audio = audio_path(out, &voice);
+        fs::create_dir_all(audio.parent().unwrap_or(out))?;
+        fs::copy(&source, &audio)?;
+        summary.extracted += 1;
+
+        let (Some(transcriber), Some(index)) = (transcriber, index.as_mut()) else {
+            continue;
+        };
+        let text_path = audio.with_extension("txt");
+        let text = match fs::read_to_string(&text_path) {
+            Ok(text) => {
+                summary.kept += 1;
+                text
+            }
+            Err(_) => match transcriber.transcribe(&audio) {
+                Ok(text) => {
+                    let text = redactor.text(&RcStr::new(text)).to_string();
+                    fs::write(&text_path, &text)?;
+                    summary.transcribed += 1;
+                    text
+                }
+                Err(e) => {
+                    summary.failed.push((voice.message.clone(), e.to_string()));
+                    continue;
+                }
+            },
+        };
+        let transcript = Transcript {
+            voice,
+            transcriber: transcriber.name().to_owned(),
+            text,
+        };
+        serde_json::to_writer(&mut *index, &transcript)
+            .map_err(|e| Error::SerdeJson(e, out.join(TRANSCRIPTS)))?;
+        writeln!(index)?;
+    }
+    if let Some(index) = index.as_mut() {
+        index.flush()?;
+    }
+    Ok(summary)
+}
+
+fn audio_path(out: &Path, voice: &VoiceMessage) -> PathBuf {
+    out.join(voice.account.as_str())
+        .join(voice.contact.as_str())
+        .join(format!("{}.ogg", voice.message))
+}
@@ -0,0 +1,61 @@
+// Optional parts of the crate, each behind a cargo feature of the same name so embedding
+// just the parser doesn't pull in an image stack or an HTTP server.
+pub struct Feature {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub description: &'static str,
+}
+
+pub static FEATURES: &[Feature] = &[
+    Feature {
+        name: "images",
+        enabled: cfg!(feature = "images"),
+        description: "decode and re-encode image assets for previews and media export",
+    },
+    Feature {
+        name: "audio",
+        enabled: cfg!(feature = "audio"),
+        description: "decode and retag ogg assets for previews and media export",
+    },
+    Feature {
+        name: "mesh",
+        enabled: cfg!(feature = "mesh"),
+        description: "the 3D stack, for mesh assets",
+    },
+    Feature {
+        name: "server",
+        enabled: cfg!(feature = "server"),
+        description: "serve assets and previews over HTTP",
+    },
+    Feature {
+        name: "sqlite",
+        enabled: cfg!(feature = "sqlite"),
+        description: "export the backup to a SQLite database",
+    },
+    Feature {
+        name: "transcribe",
+        enabled: cfg!(feature = "transcribe"),
+        description: "run whisper-cli over voice messages for transcripts",
+    },
+    Feature {
+        name: "cloud",
+        enabled: cfg!(feature = "cloud"),
+        description: "talk to the cloud API",
+    },
+];
+
+#[derive(thiserror::Error, Debug, Clone, Copy)]
+#[error("built without the `{0}` feature, rebuild with `--features {0}`")]
+pub struct MissingFeature(pub &'static str);
+
+pub fn enabled(name: &str) -> bool {
+    FEATURES.iter().any(|f| f.name == name && f.enabled)
+}
+
+pub fn require(name: &'static str) -> Result<(), MissingFeature> {
+    if enabled(name) {
+        Ok(())
+    } else {
+        Err(MissingFeature(name))
+    }
+}
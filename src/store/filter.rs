@@ -0,0 +1,54 @@
+use super::{
+    backup::{Backup, Record, RecordType},
+    RcStr,
+};
+use serde::Deserialize;
+
+// Which records a metric or a find covers, every filter given must match.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct RecordFilter {
+    pub record_type: Option<RecordType>,
+    pub tag: Option<String>,
+    // Case-insensitive substring of the record name.
+    pub name_contains: Option<String>,
+    pub public: Option<bool>,
+    // The account folder, a user or group ID.
+    pub account: Option<String>,
+}
+
+impl RecordFilter {
+    pub fn is_empty(&self) -> bool {
+        self.record_type.is_none()
+            && self.tag.is_none()
+            && self.name_contains.is_none()
+            && self.public.is_none()
+            && self.account.is_none()
+    }
+
+    pub fn matches(&self, account: &str, rec: &Record) -> bool {
+        self.record_type
+            .as_ref()
+            .is_none_or(|t| *t == rec.record_type)
+            && self
+                .tag
+                .as_ref()
+                .is_none_or(|tag| rec.tags.iter().any(|t| t.as_str() == tag))
+            && self
+                .name_contains
+                .as_ref()
+                .is_none_or(|needle| rec.name.to_lowercase().contains(&needle.to_lowercase()))
+            && self.public.is_none_or(|p| p == rec.is_public)
+            && self.account.as_ref().is_none_or(|a| a == account)
+    }
+
+    // Matching records with their account, in account then record ID order.
+    pub fn find<'a>(&self, backup: &'a Backup) -> Vec<(&'a RcStr, &'a Record)> {
+        backup
+            .accounts
+            .iter()
+            .flat_map(|(name, account)| account.records.values().map(move |rec| (name, rec)))
+            .filter(|(name, rec)| self.matches(name, rec))
+            .collect()
+    }
+}
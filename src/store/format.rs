@@ -0,0 +1,255 @@
+use super::{backup::Error, warnings::WarningKind};
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+// The tools that have written backups, each with its own quirks in the JSON files.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum FormatVersion {
+    // Wrote timestamps without an offset, and sometimes ones that don't parse at all.
+    NeosEarly,
+    #[default]
+    NeosLate,
+    // Same layout, but resdb:/// URLs and an assetManifest in place of neosDBmanifest.
+    Resonite,
+}
+
+impl fmt::Display for FormatVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FormatVersion::NeosEarly => "early Neos backup tool",
+            FormatVersion::NeosLate => "late Neos backup tool",
+            FormatVersion::Resonite => "Resonite exporter",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Contact,
+    Group,
+    GroupMember,
+    Message,
+    Record,
+    Variable,
+    VariableDefinition,
+}
+
+// Migrations note what they had to drop, for the load's warnings.
+type Migration = fn(&mut Value, &mut Vec<WarningKind>);
+
+// Every per-version tweak, applied in order to the raw JSON before the typed model sees it.
+const MIGRATIONS: &[(FormatVersion, FileKind, Migration)] = &[
+    (
+        FormatVersion::NeosEarly,
+        FileKind::Record,
+        record_timestamps,
+    ),
+    (
+        FormatVersion::NeosEarly,
+        FileKind::Contact,
+        contact_timestamps,
+    ),
+    (
+        FormatVersion::NeosEarly,
+        FileKind::Message,
+        message_timestamps,
+    ),
+    (FormatVersion::Resonite, FileKind::Record, resonite_record),
+];
+
+// Files sampled per folder when detecting, enough to see past a few odd ones.
+const DETECT_SAMPLE: usize = 16;
+
+impl FormatVersion {
+    pub fn detect(root: &Path) -> Result<Self, Error> {
+        let mut version = FormatVersion::NeosLate;
+        for account in root.read_dir()? {
+            let account = account?.path();
+            for folder in ["Records", "Contacts", "Messages"] {
+                let folder = account.join(folder);
+                if !folder.is_dir() {
+                    continue;
+                }
+                for path in sample_files(&folder)? {
+                    let Ok(value) = serde_json::from_slice::<Value>(&fs::read(&path)?) else {
+                        continue;
+                    };
+                    if value.get("assetManifest").is_some() || has_resdb_uri(&value) {
+                        return Ok(FormatVersion::Resonite);
+                    }
+                    if has_bad_timestamp(&value) {
+                        version = FormatVersion::NeosEarly;
+                    }
+                }
+            }
+        }
+        Ok(version)
+    }
+
+    pub fn migrates(self, kind: FileKind) -> bool {
+        MIGRATIONS.iter().any(|(v, k, _)| *v == self && *k == kind)
+    }
+
+    // Returns what the migrations had to drop.
+    pub fn migrate(self, kind: FileKind, value: &mut Value) -> Vec<WarningKind> {
+        let mut dropped = Vec::new();
+        for (_, _, migration) in MIGRATIONS
+            .iter()
+            .filter(|(v, k, _)| *v == self && *k == kind)
+        {
+            migration(value, &mut dropped);
+        }
+        dropped
+    }
+}
+
+// The first few files of a folder, or of the folders in it for messages, which are kept
+// by contact.
+fn sample_files(folder: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    let mut pending = vec![folder.to_owned()];
+    while let Some(dir) = pending.pop() {
+        for entry in dir.read_dir()? {
+            if files.len() == DETECT_SAMPLE {
+                return Ok(files);
+            }
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn has_resdb_uri(value: &Value) -> bool {
+    ["assetUri", "thumbnailUri"].iter().any(|key| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .is_some_and(|uri| uri.starts_with("resdb:///"))
+    })
+}
+
+fn has_bad_timestamp(value: &Value) -> bool {
+    let status = value.get("userStatus").unwrap_or(&Value::Null);
+    [
+        value.get("lastModificationTime"),
+        value.get("latestMessageTime"),
+        value.get("sendTime"),
+        status.get("lastStatusChange"),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(Value::as_str)
+    .any(|time| DateTime::parse_from_rfc3339(time).is_err())
+}
+
+// The timestamps the fix-ups would drop from a file of this kind, for one loaded without
+// them that lenient_time had to drop a timestamp from.
+pub(crate) fn dropped_timestamps(kind: FileKind, value: &mut Value) -> Vec<WarningKind> {
+    let mut dropped = Vec::new();
+    match kind {
+        FileKind::Record => record_timestamps(value, &mut dropped),
+        FileKind::Contact => contact_timestamps(value, &mut dropped),
+        FileKind::Message => message_timestamps(value, &mut dropped),
+        _ => {}
+    }
+    dropped
+}
+
+fn record_timestamps(value: &mut Value, dropped: &mut Vec<WarningKind>) {
+    for key in ["lastModificationTime", "creationTime", "firstPublishTime"] {
+        fix_timestamp(value, key, dropped);
+    }
+}
+
+fn contact_timestamps(value: &mut Value, dropped: &mut Vec<WarningKind>) {
+    fix_timestamp(value, "latestMessageTime", dropped);
+    let Some(status) = value.get_mut("userStatus") else {
+        return;
+    };
+    fix_timestamp(status, "lastStatusChange", dropped);
+    if let Some(session) = status.get_mut("CurrentSession") {
+        session_timestamps(session, dropped);
+    }
+    if let Some(Value::Array(sessions)) = status.get_mut("activeSessions") {
+        for session in sessions {
+            session_timestamps(session, dropped);
+        }
+    }
+}
+
+fn session_timestamps(value: &mut Value, dropped: &mut Vec<WarningKind>) {
+    for key in ["sessionBeginTime", "lastUpdate", "awaySince"] {
+        fix_timestamp(value, key, dropped);
+    }
+}
+
+fn message_timestamps(value: &mut Value, dropped: &mut Vec<WarningKind>) {
+    for key in ["sendTime", "lastUpdateTime", "readTime"] {
+        fix_timestamp(value, key, dropped);
+    }
+}
+
+fn fix_timestamp(value: &mut Value, key: &str, dropped: &mut Vec<WarningKind>) {
+    let Some(Value::String(time)) = value.get_mut(key) else {
+        return;
+    };
+    if DateTime::parse_from_rfc3339(time).is_ok() {
+        return;
+    }
+    let fixed = match parse_timestamp(time) {
+        Some(time) => Value::String(time.to_rfc3339()),
+        None => {
+            if !is_never(time) {
+                dropped.push(WarningKind::DroppedTimestamp {
+                    field: key.to_owned(),
+                    value: time.clone(),
+                });
+            }
+            Value::Null
+        }
+    };
+    value[key] = fixed;
+}
+
+// .NET's default DateTime, which meant "never", so dropping it is no loss.
+pub(crate) fn is_never(time: &str) -> bool {
+    time.starts_with("0001-01-01")
+}
+
+// Offsetless times were written in UTC, anything else unreadable is dropped, as is
+// .NET's default DateTime which stood in for "never".
+pub(crate) fn parse_timestamp(time: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(time) {
+        return Some(time.to_utc());
+    }
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .filter(|naive| naive.year() > 1)
+        .map(|naive| naive.and_utc())
+}
+
+fn resonite_record(value: &mut Value, _: &mut Vec<WarningKind>) {
+    if let Some(object) = value.as_object_mut() {
+        if let Some(manifest) = object.remove("assetManifest") {
+            object.insert("neosDBmanifest".to_owned(), manifest);
+        }
+    }
+    for key in ["assetUri", "thumbnailUri"] {
+        if let Some(Value::String(uri)) = value.get_mut(key) {
+            if let Some(rest) = uri.strip_prefix("resdb:///") {
+                *uri = format!("neosdb:///{}", rest);
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use super::{
+    backup::{AssetUri, Backup, RecordType},
+    RcStr,
+};
+use petgraph::{
+    graph::{DiGraph, NodeIndex},
+    visit::EdgeRef,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Records, contacts and messages belong to one account; assets are shared by all.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum Node {
+    Account { id: RcStr },
+    Record { account: RcStr, id: RcStr },
+    Asset { hash: RcStr },
+    Contact { account: RcStr, id: RcStr },
+    Message { account: RcStr, id: RcStr },
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Edge {
+    // Account to its records, contacts and messages.
+    Owns,
+    // Account to its top level records, and directories to the records inside them.
+    Contains,
+    // Record to its manifest and every asset the manifest uses.
+    References,
+    Thumbnails,
+    // Message to the contact the conversation is with.
+    Messaged,
+}
+
+// The backup's joins as one graph, for running graph algorithms over it.
+#[derive(Debug, Default)]
+pub struct BackupGraph {
+    pub graph: DiGraph<Node, Edge>,
+    index: BTreeMap<Node, NodeIndex>,
+}
+
+impl BackupGraph {
+    pub fn build(backup: &Backup) -> Self {
+        let mut g = Self::default();
+        for (account_name, account) in &backup.accounts {
+            let account_node = g.add(Node::Account {
+                id: account_name.clone(),
+            });
+
+            // Folder path to the directory record that is that folder.
+            let mut folders = BTreeMap::<Vec<RcStr>, NodeIndex>::new();
+            for rec in account.records.values() {
+                let node = g.add(Node::Record {
+                    account: account_name.clone(),
+                    id: rec.id.clone(),
+                });
+                g.graph.add_edge(account_node, node, Edge::Owns);
+                if rec.record_type == RecordType::Directory {
+                    let mut path = rec.path.clone();
+                    path.push(rec.name.clone());
+                    folders.entry(path).or_insert(node);
+                }
+                if let Some(AssetUri::SZBson(asset)) = &rec.asset_uri {
+                    let asset = g.add(Node::Asset {
+                        hash: asset.0.clone(),
+                    });
+                    g.graph.add_edge(node, asset, Edge::References);
+                }
+                for asset in &rec.neos_db_manifest {
+                    let asset = g.add(Node::Asset {
+                        hash: asset.hash.clone(),
+                    });
+                    g.graph.update_edge(node, asset, Edge::References);
+                }
+                if let Some(hash) = rec.thumbnail_uri.as_ref().and_then(AssetUri::asset_id) {
+                    let asset = g.add(Node::Asset { hash: hash.clone() });
+                    g.graph.add_edge(node, asset, Edge::Thumbnails);
+                }
+            }
+            for rec in account.records.values() {
+                let node = g.index[&Node::Record {
+                    account: account_name.clone(),
+                    id: rec.id.clone(),
+                }];
+                // A record in a folder with no directory record has no parent at all.
+                let parent = match rec.path.len() {
+                    0 | 1 => Some(account_node),
+                    _ => folders.get(&rec.path).copied(),
+                };
+                if let Some(parent) = parent {
+                    g.graph.add_edge(parent, node, Edge::Contains);
+                }
+            }
+
+            for contact in account.contacts.keys() {
+                let node = g.add(Node::Contact {
+                    account: account_name.clone(),
+                    id: contact.clone(),
+                });
+                g.graph.add_edge(account_node, node, Edge::Owns);
+            }
+            for (contact, messages) in &account.messages {
+                let contact = g.add(Node::Contact {
+                    account: account_name.clone(),
+                    id: contact.clone(),
+                });
+                for message in messages {
+                    let node = g.add(Node::Message {
+                        account: account_name.clone(),
+                        id: message.id.clone(),
+                    });
+                    g.graph.add_edge(account_node, node, Edge::Owns);
+                    g.graph.add_edge(node, contact, Edge::Messaged);
+                }
+            }
+        }
+        g
+    }
+
+    fn add(&mut self, node: Node) -> NodeIndex {
+        if let Some(index) = self.index.get(&node) {
+            return *index;
+        }
+        let index = self.graph.add_node(node.clone());
+        self.index.insert(node, index);
+        index
+    }
+
+    pub fn node(&self, node: &Node) -> Option<NodeIndex> {
+        self.index.get(node).copied()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeIndex, &Node)> {
+        self.graph
+            .node_indices()
+            .map(move |index| (index, &self.graph[index]))
+    }
+
+    // Targets of a node's outgoing edges of one kind.
+    pub fn neighbors(&self, node: NodeIndex, kind: Edge) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.graph
+            .edges(node)
+            .filter(move |edge| *edge.weight() == kind)
+            .map(|edge| edge.target())
+    }
+
+    // Everything reachable from a node over edges of one kind, the node included.
+    pub fn reachable(&self, from: NodeIndex, kind: Edge) -> BTreeSet<NodeIndex> {
+        let mut seen = BTreeSet::from([from]);
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            for next in self.neighbors(node, kind) {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+}
@@ -0,0 +1,79 @@
+use super::{
+    backup::{Backup, Error, Record},
+    lazy::LazyBackup,
+    RcStr,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+// A record by the account folder it was exported into and its ID, rather than a
+// reference into one loaded Backup. It stays valid across snapshots and reloads, so it
+// can key indices and caches, and writes as account/id in URLs and on the command line.
+// Group records are saved into every member's folder, each copy with its own handle.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordHandle {
+    pub account: RcStr,
+    pub id: RcStr,
+}
+
+impl RecordHandle {
+    pub fn new(account: impl Into<RcStr>, id: impl Into<RcStr>) -> Self {
+        Self {
+            account: account.into(),
+            id: id.into(),
+        }
+    }
+
+    pub fn of(account: &RcStr, rec: &Record) -> Self {
+        Self::new(account.clone(), rec.id.clone())
+    }
+
+    // The record in this backup, None if it has no such account or record.
+    pub fn resolve<'a>(&self, backup: &'a Backup) -> Option<&'a Record> {
+        backup.accounts.get(&self.account)?.records.get(&self.id)
+    }
+
+    // The record read on its own, without loading the rest of the backup.
+    pub fn load(&self, backup: &LazyBackup) -> Result<Option<Record>, Error> {
+        match backup.account(&self.account) {
+            Some(account) => account.record(&self.id),
+            None => Ok(None),
+        }
+    }
+}
+
+impl fmt::Display for RecordHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.account, self.id)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("bad record handle {0:?}, expected account/id")]
+pub struct BadRecordHandle(String);
+
+impl FromStr for RecordHandle {
+    type Err = BadRecordHandle;
+
+    // Neither part may hold a slash or walk out of its folder, since both name files.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = |part: &str| part.is_empty() || part.contains(['/', '\\']) || part == "..";
+        match s.split_once('/') {
+            Some((account, id)) if !bad(account) && !bad(id) => Ok(Self::new(account, id)),
+            _ => Err(BadRecordHandle(s.to_owned())),
+        }
+    }
+}
+
+impl Backup {
+    // A handle for every record, in account then file name order.
+    pub fn record_handles(&self) -> impl Iterator<Item = RecordHandle> + '_ {
+        self.accounts.iter().flat_map(|(name, account)| {
+            account
+                .records
+                .keys()
+                .map(move |id| RecordHandle::new(name.clone(), id.clone()))
+        })
+    }
+}
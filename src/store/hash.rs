@@ -0,0 +1,71 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{fmt, io, str::FromStr};
+
+// Neos names every asset by the SHA-256 of its bytes as stored, in lowercase hex. The
+// extension in a neosdb:/// URL isn't part of it, and a re-encoded asset is a new asset
+// with a new hash.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AssetId(String);
+
+impl AssetId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // neosdb:///<hash>.<ext>, the form records and manifests refer to assets by.
+    pub fn url(&self, ext: Option<&str>) -> String {
+        match ext {
+            Some(ext) => format!("neosdb:///{}.{}", self.0, ext),
+            None => format!("neosdb:///{}", self.0),
+        }
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for AssetId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Ok(AssetId(s.to_ascii_lowercase()))
+        } else {
+            Err(format!("{:?} is not a 64 digit hex asset hash", s))
+        }
+    }
+}
+
+fn finish(hasher: Sha256) -> AssetId {
+    AssetId(
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+    )
+}
+
+pub fn hash_asset<R>(mut reader: R) -> io::Result<AssetId>
+where
+    R: io::Read,
+{
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(finish(hasher)),
+            Ok(n) => hasher.update(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> AssetId {
+    finish(Sha256::new_with_prefix(bytes))
+}
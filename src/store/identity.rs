@@ -0,0 +1,145 @@
+use super::{
+    backup::{Component, Manifest, Slot},
+    RcStr,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Persistent-IDs survive saving a new version or copying an item, unlike the positional
+// slot and component IDs, so they identify the "same" thing across manifests.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityKind {
+    Slot,
+    Component,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Entity {
+    pub kind: EntityKind,
+    pub id: RcStr,
+    // The slot name, or the component type.
+    pub label: RcStr,
+    // Names of the slots above it, root first.
+    pub path: Vec<RcStr>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityMap {
+    pub root: Option<RcStr>,
+    pub entities: BTreeMap<RcStr, Entity>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub persistent_id: RcStr,
+    pub before: Entity,
+    pub after: Entity,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityDiff {
+    pub unchanged: u64,
+    // Renamed or moved to another parent.
+    pub changed: Vec<Change>,
+    pub added: Vec<Entity>,
+    pub removed: Vec<Entity>,
+}
+
+impl IdentityMap {
+    pub fn build(manifest: &Manifest) -> Self {
+        let mut map = Self::default();
+        if let Some(slot) = &manifest.object {
+            map.root = slot.persistent_id.clone();
+            map.add_slot(slot, &mut Vec::new());
+        }
+        for component in manifest.assets.iter().flatten() {
+            map.add_component(component, &[]);
+        }
+        map
+    }
+
+    fn add_slot(&mut self, slot: &Slot, path: &mut Vec<RcStr>) {
+        let name: RcStr = slot
+            .name
+            .data()
+            .clone()
+            .unwrap_or_else(|| RcStr::new(String::new()));
+        if let Some(pid) = &slot.persistent_id {
+            self.entities.insert(
+                pid.clone(),
+                Entity {
+                    kind: EntityKind::Slot,
+                    id: slot.id.clone(),
+                    label: name.clone(),
+                    path: path.clone(),
+                },
+            );
+        }
+        path.push(name);
+        for component in slot.components.data() {
+            self.add_component(component, path);
+        }
+        for child in &slot.children {
+            self.add_slot(child, path);
+        }
+        path.pop();
+    }
+
+    fn add_component(&mut self, component: &Component, path: &[RcStr]) {
+        if let Some(pid) = &component.data.persistent_id {
+            self.entities.insert(
+                pid.clone(),
+                Entity {
+                    kind: EntityKind::Component,
+                    id: component.data.id.clone(),
+                    label: component.cs_type.clone(),
+                    path: path.to_vec(),
+                },
+            );
+        }
+    }
+
+    // Share of either side's entities that the other also has, 1.0 for identical sets.
+    pub fn overlap(&self, other: &IdentityMap) -> f64 {
+        let shared = self
+            .entities
+            .keys()
+            .filter(|pid| other.entities.contains_key(*pid))
+            .count();
+        let total = self.entities.len().max(other.entities.len());
+        if total == 0 {
+            0.0
+        } else {
+            shared as f64 / total as f64
+        }
+    }
+
+    pub fn diff(&self, after: &IdentityMap) -> IdentityDiff {
+        let mut diff = IdentityDiff::default();
+        for (pid, before) in &self.entities {
+            match after.entities.get(pid) {
+                Some(now) if now.label == before.label && now.path == before.path => {
+                    diff.unchanged += 1
+                }
+                Some(now) => diff.changed.push(Change {
+                    persistent_id: pid.clone(),
+                    before: before.clone(),
+                    after: now.clone(),
+                }),
+                None => diff.removed.push(before.clone()),
+            }
+        }
+        diff.added = after
+            .entities
+            .iter()
+            .filter(|(pid, _)| !self.entities.contains_key(*pid))
+            .map(|(_, entity)| entity.clone())
+            .collect();
+        diff
+    }
+}
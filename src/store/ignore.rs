@@ -0,0 +1,67 @@
+use glob::{MatchOptions, Pattern, PatternError};
+
+// Files sync tools and file browsers leave in folders, which would otherwise be parsed
+// as records or taken for account folders. Conflict copies keep the original's extension,
+// so they'd load as a second copy of it.
+pub const DEFAULT_IGNORES: [&str; 11] = [
+    "Thumbs.db",
+    "desktop.ini",
+    ".DS_Store",
+    "._*",
+    ".stfolder",
+    ".stversions",
+    "*.sync-conflict-*",
+    "* (conflicted copy*",
+    "*.tmp",
+    "~*",
+    ".~*",
+];
+
+// Globs matched against the name of every file and folder the loader walks, case
+// insensitively since the backups come from Windows.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self::new(DEFAULT_IGNORES).expect("default ignore patterns parse")
+    }
+}
+
+impl IgnoreRules {
+    pub fn new<I, S>(patterns: I) -> Result<Self, PatternError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::none().with(patterns)
+    }
+
+    // Ignores nothing, loading every file like before there were rules.
+    pub fn none() -> Self {
+        Self {
+            patterns: Vec::new(),
+        }
+    }
+
+    pub fn with<I, S>(mut self, patterns: I) -> Result<Self, PatternError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for pattern in patterns {
+            self.patterns.push(Pattern::new(pattern.as_ref())?);
+        }
+        Ok(self)
+    }
+
+    pub fn is_ignored(&self, name: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        self.patterns.iter().any(|p| p.matches_with(name, options))
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::BTreeSet};
+use std::collections::{btree_map::Entry, BTreeMap};
 
 use super::RcStr;
 
@@ -20,23 +20,55 @@ impl<T: Internable> Internable for Option<T> {
 
 impl<T: Internable> Internable for Vec<T> {
     fn intern(self, i: &mut Interner) -> Self {
-      self.into_iter().map(|v| v.intern(i)).collect()
+        self.into_iter().map(|v| v.intern(i)).collect()
     }
 }
 
+// Counts uses of each string too, so the cache doubles as a duplication census.
 #[derive(Default, Clone)]
 pub struct Interner {
-    intern_cache: BTreeSet<RcStr>,
+    intern_cache: BTreeMap<RcStr, u64>,
 }
 
 impl Interner {
-    fn intern(&mut self, k: RcStr) -> RcStr {
-        self.intern_cache
-            .get(&k)
-            .map(Clone::clone)
-            .unwrap_or_else(|| {
-                self.intern_cache.insert(k.clone());
+    pub fn intern(&mut self, k: RcStr) -> RcStr {
+        match self.intern_cache.entry(k) {
+            Entry::Occupied(mut e) => {
+                *e.get_mut() += 1;
+                e.key().clone()
+            }
+            Entry::Vacant(e) => {
+                let k = e.key().clone();
+                e.insert(1);
                 k
-            })
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &Interner) {
+        for (k, n) in other.iter() {
+            *self.intern_cache.entry(k.clone()).or_default() += n;
+        }
+    }
+
+    pub fn uses(&self, k: &RcStr) -> u64 {
+        self.intern_cache.get(k).copied().unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&RcStr, u64)> {
+        self.intern_cache.iter().map(|(k, n)| (k, *n))
+    }
+
+    pub fn len(&self) -> usize {
+        self.intern_cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intern_cache.is_empty()
+    }
+
+    // Bytes a string table would no longer store, ignoring the references replacing them.
+    pub fn duplicate_bytes(&self) -> u64 {
+        self.iter().map(|(k, n)| (n - 1) * k.len() as u64).sum()
     }
 }
@@ -0,0 +1,265 @@
+use super::{
+    backup::{Account, AssetUri, Record, RecordType},
+    RcStr,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Folder {
+    pub name: RcStr,
+    // The Directory record backing this folder, roots like "Inventory" have none.
+    pub record: Option<RcStr>,
+    pub folders: BTreeMap<RcStr, Folder>,
+    pub items: Vec<RcStr>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Inventory {
+    pub root: Folder,
+    // Link records pointing at a record in the same account, by link record ID.
+    pub links: BTreeMap<RcStr, InventoryLink>,
+    // Directory record ID to the path of the folder it is.
+    pub directories: BTreeMap<RcStr, Vec<RcStr>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryLink {
+    pub name: RcStr,
+    // The folder holding the link.
+    pub folder: Vec<RcStr>,
+    pub target: RcStr,
+}
+
+// A folder link that leads back to the folder holding it or one of its ancestors.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CyclicLink {
+    pub link: RcStr,
+    pub name: RcStr,
+    pub folder: Vec<RcStr>,
+    pub target: Vec<RcStr>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSize {
+    pub path: Vec<RcStr>,
+    pub descendants: u64,
+    pub bytes: u64,
+}
+
+impl Inventory {
+    pub fn build(account: &Account) -> Self {
+        let mut inventory = Self::default();
+        for rec in account.records.values() {
+            let parent = inventory.root.folder_mut(&rec.path);
+            if rec.record_type == RecordType::Directory {
+                let folder = parent.folders.entry(rec.name.clone()).or_default();
+                folder.name = rec.name.clone();
+                folder.record.get_or_insert_with(|| rec.id.clone());
+                let mut path = rec.path.clone();
+                path.push(rec.name.clone());
+                inventory.directories.insert(rec.id.clone(), path);
+            } else {
+                parent.items.push(rec.id.clone());
+            }
+            if let (RecordType::Link, Some(AssetUri::NeosRec(target))) =
+                (&rec.record_type, &rec.asset_uri)
+            {
+                if target.group_id.as_str() == rec.owner_id.as_str() {
+                    inventory.links.insert(
+                        rec.id.clone(),
+                        InventoryLink {
+                            name: rec.name.clone(),
+                            folder: rec.path.clone(),
+                            target: target.asset_id.clone(),
+                        },
+                    );
+                }
+            }
+        }
+        inventory
+    }
+
+    // Where a link into this account's folders leads, if it does.
+    fn link_target(&self, link: &RcStr) -> Option<&Vec<RcStr>> {
+        self.directories.get(&self.links.get(link)?.target)
+    }
+
+    // Links that would send a walker following folder links around in circles. Links to
+    // other accounts can't be followed from here and are never reported.
+    pub fn cyclic_links(&self) -> Vec<CyclicLink> {
+        let mut cyclic = Vec::new();
+        let mut done = BTreeSet::new();
+        let mut on_path = BTreeSet::new();
+        let mut path = Vec::new();
+        for folder in self.root.folders.values() {
+            self.find_cycles(folder, &mut path, &mut on_path, &mut done, &mut cyclic);
+        }
+        cyclic
+    }
+
+    fn find_cycles(
+        &self,
+        folder: &Folder,
+        path: &mut Vec<RcStr>,
+        on_path: &mut BTreeSet<Vec<RcStr>>,
+        done: &mut BTreeSet<Vec<RcStr>>,
+        cyclic: &mut Vec<CyclicLink>,
+    ) {
+        path.push(folder.name.clone());
+        if done.contains(path) {
+            path.pop();
+            return;
+        }
+        on_path.insert(path.clone());
+        for sub in folder.folders.values() {
+            self.find_cycles(sub, path, on_path, done, cyclic);
+        }
+        for id in &folder.items {
+            let Some(target) = self.link_target(id) else {
+                continue;
+            };
+            if on_path.contains(target) {
+                cyclic.push(CyclicLink {
+                    link: id.clone(),
+                    name: self.links[id].name.clone(),
+                    folder: path.clone(),
+                    target: target.clone(),
+                });
+            } else if let Some(next) = self.folder(target) {
+                // Walk the target from its real path, so its own links are judged there.
+                let mut target_path = target[..target.len() - 1].to_vec();
+                self.find_cycles(next, &mut target_path, on_path, done, cyclic);
+            }
+        }
+        on_path.remove(path);
+        done.insert(path.clone());
+        path.pop();
+    }
+
+    // Every record beneath the root with the folder path it was reached through, following
+    // folder links. Each folder is entered at most once, so cycles and diamonds of links
+    // can neither loop nor repeat records.
+    pub fn walk<'a>(&'a self, account: &'a Account) -> Vec<(Vec<RcStr>, &'a Record)> {
+        let mut out = Vec::new();
+        let mut entered = BTreeSet::new();
+        let mut stack: Vec<(Vec<RcStr>, &Folder)> = self
+            .root
+            .folders
+            .values()
+            .map(|f| (vec![f.name.clone()], f))
+            .collect();
+        while let Some((path, folder)) = stack.pop() {
+            if !entered.insert(folder as *const Folder) {
+                continue;
+            }
+            for sub in folder.folders.values() {
+                if let Some(rec) = sub.record.as_ref().and_then(|id| account.records.get(id)) {
+                    out.push((path.clone(), rec));
+                }
+                let mut sub_path = path.clone();
+                sub_path.push(sub.name.clone());
+                stack.push((sub_path, sub));
+            }
+            for id in &folder.items {
+                let Some(rec) = account.records.get(id) else {
+                    continue;
+                };
+                out.push((path.clone(), rec));
+                if let Some(target) = self.link_target(id).and_then(|t| self.folder(t)) {
+                    let mut link_path = path.clone();
+                    link_path.push(rec.name.clone());
+                    stack.push((link_path, target));
+                }
+            }
+        }
+        out
+    }
+
+    pub fn folder(&self, path: &[RcStr]) -> Option<&Folder> {
+        path.iter()
+            .try_fold(&self.root, |folder, name| folder.folders.get(name))
+    }
+
+    // Every folder with its descendant count and deduplicated asset bytes.
+    pub fn folder_sizes(&self, account: &Account) -> Vec<FolderSize> {
+        let mut sizes = Vec::new();
+        for folder in self.root.folders.values() {
+            folder.sizes(account, &mut Vec::new(), &mut sizes);
+        }
+        sizes
+    }
+}
+
+impl Folder {
+    fn folder_mut(&mut self, path: &[RcStr]) -> &mut Folder {
+        path.iter().fold(self, |folder, name| {
+            folder
+                .folders
+                .entry(name.clone())
+                .or_insert_with(|| Folder {
+                    name: name.clone(),
+                    ..Default::default()
+                })
+        })
+    }
+
+    pub fn records<'a>(&'a self, account: &'a Account) -> impl Iterator<Item = &'a Record> + 'a {
+        let mut stack = vec![self];
+        let mut pending = Vec::new();
+        std::iter::from_fn(move || loop {
+            if let Some(id) = pending.pop() {
+                if let Some(rec) = account.records.get(id) {
+                    return Some(rec);
+                }
+                continue;
+            }
+            let folder = stack.pop()?;
+            stack.extend(folder.folders.values());
+            pending.extend(folder.items.iter());
+            pending.extend(folder.folders.values().filter_map(|f| f.record.as_ref()));
+        })
+    }
+
+    // Asset hash to byte size for every asset used beneath this folder.
+    pub fn asset_closure(&self, account: &Account) -> BTreeMap<RcStr, u64> {
+        self.records(account)
+            .flat_map(|rec| rec.neos_db_manifest.iter())
+            .map(|asset| (asset.hash.clone(), asset.bytes))
+            .collect()
+    }
+
+    fn sizes(
+        &self,
+        account: &Account,
+        path: &mut Vec<RcStr>,
+        out: &mut Vec<FolderSize>,
+    ) -> (u64, BTreeMap<RcStr, u64>) {
+        path.push(self.name.clone());
+        let mut descendants = self.items.len() as u64;
+        let mut closure: BTreeMap<RcStr, u64> = self
+            .items
+            .iter()
+            .filter_map(|id| account.records.get(id))
+            .flat_map(|rec| rec.neos_db_manifest.iter())
+            .map(|asset| (asset.hash.clone(), asset.bytes))
+            .collect();
+        for folder in self.folders.values() {
+            let (count, assets) = folder.sizes(account, path, out);
+            descendants += count + u64::from(folder.record.is_some());
+            closure.extend(assets);
+        }
+        out.push(FolderSize {
+            path: path.clone(),
+            descendants,
+            bytes: closure.values().sum(),
+        });
+        path.pop();
+        (descendants, closure)
+    }
+}
@@ -0,0 +1,112 @@
+use super::{backup::Error, RcStr};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+// Remembers per-asset results of a long scan so an interrupted run can pick up where it
+// left off. Each line is one JSON [key, value] pair, a torn last line is ignored.
+pub struct Journal<T> {
+    done: BTreeMap<RcStr, T>,
+    file: Option<(PathBuf, BufWriter<File>)>,
+}
+
+// The journal for a scan kind over a given asset store, in the user cache directory.
+pub fn path_for(kind: &str, assets_dir: &Path) -> Option<PathBuf> {
+    let assets_dir = assets_dir
+        .canonicalize()
+        .unwrap_or_else(|_| assets_dir.to_owned());
+    let hash = assets_dir
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |h, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+        });
+    dirs::cache_dir().map(|d| {
+        d.join("neos-full-statbox")
+            .join(format!("{}-{:016x}.journal", kind, hash))
+    })
+}
+
+impl<T> Journal<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    pub fn disabled() -> Self {
+        Self {
+            done: BTreeMap::new(),
+            file: None,
+        }
+    }
+
+    // Without resume any previous journal is discarded.
+    pub fn open(path: PathBuf, resume: bool) -> Result<Self, Error> {
+        let mut done = BTreeMap::new();
+        if resume && path.is_file() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                if let Ok((key, value)) = serde_json::from_str::<(RcStr, T)>(&line?) {
+                    done.insert(key, value);
+                }
+            }
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)?;
+        // Rewrite what survived so a torn line doesn't corrupt later appends.
+        for entry in &done {
+            serde_json::to_writer(&mut file, &entry).map_err(std::io::Error::from)?;
+            file.write_all(b"\n")?;
+        }
+        Ok(Self {
+            done,
+            file: Some((path, BufWriter::new(file))),
+        })
+    }
+
+    pub fn resumed(&self) -> usize {
+        self.done.len()
+    }
+
+    pub fn get(&self, key: &RcStr) -> Option<&T> {
+        self.done.get(key)
+    }
+
+    pub fn insert(&mut self, key: RcStr, value: T) -> Result<(), Error> {
+        if let Some((_, file)) = &mut self.file {
+            serde_json::to_writer(&mut *file, &(&key, &value)).map_err(std::io::Error::from)?;
+            file.write_all(b"\n")?;
+            file.flush()?;
+        }
+        self.done.insert(key, value);
+        Ok(())
+    }
+
+    pub fn get_or_insert_with<F>(&mut self, key: &RcStr, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> T,
+    {
+        if let Some(value) = self.done.get(key) {
+            return Ok(value.clone());
+        }
+        let value = f();
+        self.insert(key.clone(), value.clone())?;
+        Ok(value)
+    }
+
+    // A completed scan has nothing left to resume.
+    pub fn finish(self) -> Result<(), Error> {
+        if let Some((path, file)) = self.file {
+            drop(file);
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
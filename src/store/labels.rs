@@ -0,0 +1,52 @@
+use super::{backup::Error, RcStr};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+// User-defined contact groups, kept next to the config rather than in the backup:
+//
+//   [labels]
+//   "close friends" = ["U-alice", "U-bob"]
+//   "event staff" = ["U-carol"]
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Labels {
+    pub labels: BTreeMap<RcStr, BTreeSet<RcStr>>,
+}
+
+impl Labels {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("neos-full-statbox").join("labels.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| Error::Config(e, path.to_owned()))
+    }
+
+    // An explicit path must exist, the default location is optional.
+    pub fn load_or_default(path: Option<&Path>) -> Result<Self, Error> {
+        match path {
+            Some(path) => Self::load(path),
+            None => match Self::default_path() {
+                Some(path) if path.is_file() => Self::load(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    pub fn of<'a>(&'a self, user_id: &'a str) -> impl Iterator<Item = &'a RcStr> + 'a {
+        self.labels
+            .iter()
+            .filter(move |(_, users)| users.iter().any(|u| u.as_str() == user_id))
+            .map(|(label, _)| label)
+    }
+
+    pub fn has(&self, user_id: &str, label: &str) -> bool {
+        self.labels
+            .get(&RcStr::new(label.to_owned()))
+            .is_some_and(|users| users.iter().any(|u| u.as_str() == user_id))
+    }
+}
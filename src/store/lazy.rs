@@ -0,0 +1,278 @@
+use super::{
+    backup::{
+        data_entries, from_file, Account, Backup, Contact, Error, FromFile, LoadOptions, Message,
+        Record,
+    },
+    format::FormatVersion,
+    ignore::IgnoreRules,
+    RcStr,
+};
+use std::path::{Path, PathBuf};
+
+// A backup opened having read only the few files that tell its format. Accounts are only
+// looked at when asked for and their files parsed one at a time as they're iterated, so
+// finding one record doesn't cost loading them all.
+#[derive(Debug)]
+pub struct LazyBackup {
+    pub root: PathBuf,
+    pub format: FormatVersion,
+    pub options: LoadOptions,
+}
+
+impl Backup {
+    pub fn open(root: PathBuf) -> Result<LazyBackup, Error> {
+        Self::open_with(root, &LoadOptions::default())
+    }
+
+    pub fn open_with(root: PathBuf, options: &LoadOptions) -> Result<LazyBackup, Error> {
+        Ok(LazyBackup {
+            format: FormatVersion::detect(&root)?,
+            root,
+            options: options.clone(),
+        })
+    }
+}
+
+impl LazyBackup {
+    // The account folders the options let through, by name.
+    pub fn account_names(&self) -> Result<Vec<RcStr>, Error> {
+        let mut names = Vec::new();
+        for dir in self.root.read_dir()? {
+            let dir = dir?;
+            let name = self.options.warnings.name(&dir.file_name(), &dir.path());
+            if dir.file_type()?.is_dir()
+                && name.as_str() != "Assets"
+                && !self.options.ignore.is_ignored(&name)
+                && self.options.wants_account(&name)
+            {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    // The account by folder name, None for one the options leave out like account_names.
+    pub fn account(&self, name: &str) -> Option<LazyAccount<'_>> {
+        if !is_file_name(name)
+            || name == "Assets"
+            || self.options.ignore.is_ignored(name)
+            || !self.options.wants_account(name)
+        {
+            return None;
+        }
+        let root = self.root.join(name);
+        root.is_dir().then(|| LazyAccount {
+            backup: self,
+            name: RcStr::new(name.to_owned()),
+            root,
+        })
+    }
+
+    // Reads everything the options ask for, like Backup::load_with.
+    pub fn load(&self) -> Result<Backup, Error> {
+        Backup::load_with(self.root.clone(), &self.options)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LazyAccount<'a> {
+    backup: &'a LazyBackup,
+    pub name: RcStr,
+    root: PathBuf,
+}
+
+impl<'a> LazyAccount<'a> {
+    // The record saved under this ID, parsed on its own. IDs that would name a file
+    // outside the Records folder are refused.
+    pub fn record(&self, id: &str) -> Result<Option<Record>, Error> {
+        if !is_file_name(id) {
+            return Err(Error::BadRecordId(id.to_owned()));
+        }
+        let path = self.root.join("Records").join(format!("{}.json", id));
+        if !path.is_file() {
+            return Ok(None);
+        }
+        from_file(path, self.backup.format, &self.backup.options.warnings).map(Some)
+    }
+
+    // The account's records by file name, each read when the iterator reaches it and
+    // skipped like messages are if it doesn't parse.
+    pub fn records_iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<(RcStr, Record), Error>> + 'a, Error> {
+        self.files_iter(&self.root.join("Records"))
+    }
+
+    pub fn contacts_iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<(RcStr, Contact), Error>> + 'a, Error> {
+        self.files_iter(&self.root.join("Contacts"))
+    }
+
+    // Every message with the contact it was exchanged with, a contact at a time and a
+    // file at a time within it. Files that don't parse are skipped with a warning unless
+    // the options are strict, as when loading.
+    pub fn messages_iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<(RcStr, Message), Error>> + 'a, Error> {
+        let backup = self.backup;
+        let contacts = sorted_entries(&self.root.join("Messages"), &backup.options.ignore)?;
+        Ok(contacts.into_iter().flat_map(move |dir| {
+            let contact = backup
+                .options
+                .warnings
+                .name(dir.file_name().unwrap_or_default(), &dir);
+            let messages: Box<dyn Iterator<Item = _> + 'a> = match parse_files(backup, &dir) {
+                Ok(messages) => {
+                    Box::new(messages.map(move |msg| msg.map(|(_, msg)| (contact.clone(), msg))))
+                }
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
+            messages
+        }))
+    }
+
+    // Reads the whole account, like Backup::load_with does for each.
+    pub fn load(&self) -> Result<Account, Error> {
+        Account::load(self.root.clone(), self.backup.format, &self.backup.options)
+            .map(|(_, account)| account)
+    }
+
+    fn files_iter<T>(
+        &self,
+        dir: &Path,
+    ) -> Result<impl Iterator<Item = Result<(RcStr, T), Error>> + 'a, Error>
+    where
+        T: FromFile + 'a,
+    {
+        parse_files(self.backup, dir)
+    }
+}
+
+// A folder's files by name, each parsed when the iterator reaches it and skipped, with a
+// warning, if it doesn't parse in a lenient load.
+fn parse_files<'a, T>(
+    backup: &'a LazyBackup,
+    dir: &Path,
+) -> Result<impl Iterator<Item = Result<(RcStr, T), Error>> + 'a, Error>
+where
+    T: FromFile + 'a,
+{
+    let (format, options) = (backup.format, &backup.options);
+    Ok(sorted_entries(dir, &options.ignore)?
+        .into_iter()
+        .filter_map(move |path| {
+            let name = options
+                .warnings
+                .name(path.file_stem().unwrap_or_default(), &path);
+            let item = from_file(path.clone(), format, &options.warnings);
+            options
+                .tolerate(&path, item)
+                .map(|item| item.map(|item| (name, item)))
+                .transpose()
+        }))
+}
+
+// Whether the name, joined onto a folder, stays a file directly in it.
+fn is_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+// A folder's data files in name order, none for a folder the account doesn't have.
+fn sorted_entries(dir: &Path, ignore: &IgnoreRules) -> Result<Vec<PathBuf>, Error> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = data_entries(dir, ignore)?;
+    entries.sort();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn names_stay_in_their_folder() {
+        let root = std::env::temp_dir().join(format!("statbox-lazy-{}", std::process::id()));
+        fs::create_dir_all(root.join("U-test").join("Records")).unwrap();
+        let backup = Backup::open(root.clone());
+        let outcome = backup.map(|backup| {
+            let bad_accounts = ["..", ".", "", "U-test/..", "..\\U-test", "Assets"]
+                .iter()
+                .filter(|name| backup.account(name).is_some())
+                .count();
+            let account = backup.account("U-test").unwrap();
+            let bad_ids: Vec<_> = ["../../U-test/Records/x", "a\\b", "..", ""]
+                .iter()
+                .map(|id| account.record(id).map_err(|e| e.code()))
+                .collect();
+            (bad_accounts, bad_ids, account.record("R-missing").unwrap())
+        });
+        fs::remove_dir_all(&root).unwrap();
+        let (bad_accounts, bad_ids, missing) = outcome.unwrap();
+        assert_eq!(bad_accounts, 0);
+        for id in bad_ids {
+            assert_eq!(id.unwrap_err(), "bad-record-id");
+        }
+        assert!(missing.is_none());
+    }
+
+    // Each message file is only read when the iterator gets to it, so a big conversation
+    // is never held whole.
+    #[test]
+    fn messages_are_read_as_iterated() {
+        let temp = crate::store::testing::TempBackup::new("lazy-messages");
+        let messages = temp.root.join("U-test").join("Messages").join("U-friend");
+        fs::create_dir_all(&messages).unwrap();
+        let write = |id: &str, content: &str| {
+            let message = serde_json::json!({
+                "id": id, "ownerId": "U-test", "recipientId": "U-friend",
+                "messageType": "Text", "content": content,
+                "sendTime": "2021-03-04T05:06:07Z", "lastUpdateTime": "2021-03-04T05:06:07Z",
+            });
+            let path = messages.join(format!("{}.json", id));
+            fs::write(path, serde_json::to_vec(&message).unwrap()).unwrap();
+        };
+        write("M-1", "first");
+        write("M-2", "second");
+
+        let backup = Backup::open(temp.root.clone()).unwrap();
+        let account = backup.account("U-test").unwrap();
+        let mut iter = account.messages_iter().unwrap();
+        let (contact, first) = iter.next().unwrap().unwrap();
+        assert_eq!(
+            (contact.as_str(), first.content.as_str()),
+            ("U-friend", "first")
+        );
+        write("M-2", "rewritten");
+        let (_, second) = iter.next().unwrap().unwrap();
+        assert_eq!(second.content.as_str(), "rewritten");
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn accounts_follow_the_options() {
+        let temp = crate::store::testing::TempBackup::new("lazy-accounts");
+        for account in ["U-in", "U-out", "U-x.sync-conflict-1"] {
+            fs::create_dir_all(temp.root.join(account)).unwrap();
+        }
+        let options = LoadOptions {
+            exclude_accounts: vec!["U-out".to_owned()],
+            ..Default::default()
+        };
+        let backup = Backup::open_with(temp.root.clone(), &options).unwrap();
+        let names = backup.account_names().unwrap();
+        for name in ["U-in", "U-out", "U-x.sync-conflict-1"] {
+            assert_eq!(
+                backup.account(name).is_some(),
+                names.iter().any(|n| n.as_str() == name),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(names, [RcStr::from("U-in")]);
+    }
+}
@@ -0,0 +1,145 @@
+use super::{
+    backup::{self, uncompress_7z},
+    RcStr,
+};
+use bson::{RawBsonRef, RawDocument};
+use std::io::{self, Read, Write};
+use thiserror::Error;
+
+// How much of a manifest opening one will take before giving up on it, so a crafted or
+// corrupt 7zbson can't exhaust memory or the stack. The defaults are well past anything
+// Neos writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManifestLimits {
+    pub max_decompressed_bytes: u64,
+    // How deep slots nest under the manifest's root slot, which counts as 1.
+    pub max_slot_depth: usize,
+    // On every slot together, and the manifest's assets.
+    pub max_components: u64,
+}
+
+impl Default for ManifestLimits {
+    fn default() -> Self {
+        Self {
+            max_decompressed_bytes: 2 << 30,
+            max_slot_depth: 512,
+            max_components: 5_000_000,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum LimitExceeded {
+    #[error("{asset} decompresses to more than {max} bytes")]
+    DecompressedSize { asset: RcStr, max: u64 },
+    #[error("{asset} nests slots more than {max} deep")]
+    SlotDepth { asset: RcStr, max: usize },
+    #[error("{asset} has more than {max} components")]
+    Components { asset: RcStr, max: u64 },
+}
+
+impl ManifestLimits {
+    pub fn unlimited() -> Self {
+        Self {
+            max_decompressed_bytes: u64::MAX,
+            max_slot_depth: usize::MAX,
+            max_components: u64::MAX,
+        }
+    }
+
+    // Stops as soon as the output passes the limit, whatever the header claims.
+    pub(crate) fn decompress<R: Read>(
+        &self,
+        asset: &RcStr,
+        file: R,
+        out: &mut Vec<u8>,
+    ) -> Result<(), backup::Error> {
+        self.decompress_into(asset, file, out)
+    }
+
+    // The same into any writer, for output sent on as it's decompressed.
+    pub(crate) fn decompress_into<R: Read, W: Write>(
+        &self,
+        asset: &RcStr,
+        file: R,
+        out: W,
+    ) -> Result<(), backup::Error> {
+        let mut capped = Capped {
+            out,
+            left: self.max_decompressed_bytes,
+            exceeded: false,
+        };
+        match uncompress_7z(file, &mut capped) {
+            Ok(()) => Ok(()),
+            Err(_) if capped.exceeded => Err(LimitExceeded::DecompressedSize {
+                asset: asset.clone(),
+                max: self.max_decompressed_bytes,
+            }
+            .into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Walks the raw document without recursing, before it's turned into a Document or a
+    // Manifest, both of which recurse once per level. Parts that don't parse are left
+    // for the parse to report.
+    pub fn check(&self, asset: &RcStr, manifest: &RawDocument) -> Result<(), LimitExceeded> {
+        let mut components = 0;
+        if let Some(RawBsonRef::Array(assets)) = manifest.get("Assets").ok().flatten() {
+            components += assets.into_iter().count() as u64;
+        }
+        let mut slots = Vec::new();
+        if let Some(RawBsonRef::Document(root)) = manifest.get("Object").ok().flatten() {
+            slots.push((root, 1));
+        }
+        while let Some((slot, depth)) = slots.pop() {
+            if depth > self.max_slot_depth {
+                return Err(LimitExceeded::SlotDepth {
+                    asset: asset.clone(),
+                    max: self.max_slot_depth,
+                });
+            }
+            if let Some(RawBsonRef::Document(field)) = slot.get("Components").ok().flatten() {
+                if let Some(RawBsonRef::Array(data)) = field.get("Data").ok().flatten() {
+                    components += data.into_iter().count() as u64;
+                }
+            }
+            if components > self.max_components {
+                return Err(LimitExceeded::Components {
+                    asset: asset.clone(),
+                    max: self.max_components,
+                });
+            }
+            if let Some(RawBsonRef::Array(children)) = slot.get("Children").ok().flatten() {
+                for child in children.into_iter().flatten() {
+                    if let RawBsonRef::Document(child) = child {
+                        slots.push((child, depth + 1));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Capped<W> {
+    out: W,
+    left: u64,
+    exceeded: bool,
+}
+
+impl<W: Write> Write for Capped<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() as u64 > self.left {
+            self.exceeded = true;
+            return Err(io::Error::other("decompressed size limit exceeded"));
+        }
+        self.out.write_all(buf)?;
+        self.left -= buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
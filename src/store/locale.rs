@@ -0,0 +1,159 @@
+use super::backup::Error;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock, PoisonError,
+    },
+};
+
+// Translations of the text reports show, keyed by the English text itself, so anything
+// a catalog lacks stays English and English needs no catalog at all:
+//
+//   # neos-full-statbox/locales/ja.toml in the user config directory
+//   "Records" = "レコード"
+//   "{} records" = "{}件のレコード"
+//
+// Placeholders fill in order, a translation numbers them ({1} before {0}) where its word
+// order differs.
+#[derive(Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct Catalog {
+    messages: BTreeMap<String, String>,
+}
+
+// The built-in locale, the text as written.
+pub const DEFAULT_LOCALE: &str = "en";
+
+impl Catalog {
+    pub fn dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("neos-full-statbox").join("locales"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| Error::Config(e, path.to_owned()))
+    }
+
+    // A locale name like "ja", found in the locales directory, or a path to a catalog.
+    pub fn find(locale: &str) -> Result<Self, Error> {
+        if locale == DEFAULT_LOCALE {
+            return Ok(Self::default());
+        }
+        let path = Path::new(locale);
+        let path = if path.extension().is_some() || path.components().count() > 1 {
+            path.to_owned()
+        } else {
+            Self::dir()
+                .unwrap_or_default()
+                .join(locale)
+                .with_extension("toml")
+        };
+        if !path.is_file() {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no catalog for locale {} at {}", locale, path.display()),
+            )));
+        }
+        Self::load(&path)
+    }
+
+    pub fn get<'a>(&'a self, msg: &'a str) -> Option<&'a str> {
+        self.messages.get(msg).map(String::as_str)
+    }
+}
+
+// Process-wide like timings, so render doesn't need a catalog threaded through every
+// function. Set once at startup, before anything renders.
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static UNTRANSLATED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+pub fn install(catalog: Catalog) {
+    let _ = CATALOG.set(catalog);
+}
+
+// Collect every text looked up that the catalog lacks, for translators to fill in.
+pub fn record_untranslated() {
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+pub fn take_untranslated() -> BTreeSet<String> {
+    let mut untranslated = UNTRANSLATED.lock().unwrap_or_else(PoisonError::into_inner);
+    std::mem::take(&mut *untranslated)
+}
+
+pub fn tr(msg: &str) -> &str {
+    match CATALOG.get().and_then(|c| c.get(msg)) {
+        Some(translated) => translated,
+        None => {
+            if RECORDING.load(Ordering::Relaxed) {
+                let mut untranslated = UNTRANSLATED.lock().unwrap_or_else(PoisonError::into_inner);
+                untranslated.insert(msg.to_owned());
+            }
+            msg
+        }
+    }
+}
+
+// tr for text with placeholders, filled from args.
+pub fn trf(msg: &str, args: &[&dyn fmt::Display]) -> String {
+    let template = tr(msg);
+    let mut out = String::with_capacity(template.len());
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out += &rest[..open];
+        let after = &rest[open + 1..];
+        let index = after.find('}').and_then(|close| {
+            let inner = &after[..close];
+            let index = if inner.is_empty() {
+                next
+            } else {
+                inner.parse().ok()?
+            };
+            Some((index, close))
+        });
+        match index.and_then(|(index, close)| Some((args.get(index)?, close))) {
+            Some((arg, close)) => {
+                out += &arg.to_string();
+                next += 1;
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out + rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_fill_from_args() {
+        // No catalog is installed in tests, so the text is its own template.
+        let cases: [(&str, &[&dyn fmt::Display], &str); 9] = [
+            ("{} of {}", &[&1, &"two"], "1 of two"),
+            ("{1} before {0}", &[&"a", &"b"], "b before a"),
+            ("{0} and {0}", &[&7], "7 and 7"),
+            ("no placeholders", &[&1], "no placeholders"),
+            // Missing arguments leave the placeholder as written, extra ones are unused.
+            ("{} and {}", &[&1], "1 and {}"),
+            ("{2}", &[&1], "{2}"),
+            ("{}", &[&1, &2, &3], "1"),
+            // Anything that isn't a placeholder is text.
+            ("{name} {", &[&1], "{name} {"),
+            ("}{}{", &[&"x"], "}x{"),
+        ];
+        for (template, args, expected) in cases {
+            assert_eq!(trf(template, args), expected, "{:?}", template);
+        }
+    }
+}
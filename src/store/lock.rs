@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+// Kept in the backup folder by whatever is writing to it, statbox or a sync tool. It's
+// advisory: nothing stops a writer that doesn't look for it.
+pub const LOCK_FILE: &str = ".sync.lock";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LockInfo {
+    // The program holding the lock, like neos-full-statbox or a sync tool's name.
+    pub holder: String,
+    pub pid: Option<u32>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("the backup was opened read-only")]
+    ReadOnly,
+    #[error(
+        "{holder} holds the lock on the backup{}, remove {} if nothing is writing to it",
+        since.map(|t| format!(" since {}", t)).unwrap_or_default(),
+        path.display()
+    )]
+    Held {
+        holder: String,
+        since: Option<DateTime<Utc>>,
+        path: PathBuf,
+    },
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub fn lock_path(root: &Path) -> PathBuf {
+    root.join(LOCK_FILE)
+}
+
+// Who holds the lock on the backup, if anyone. A lock file that can't be read still
+// counts, as one some other tool wrote.
+pub fn current(root: &Path) -> Option<LockInfo> {
+    let content = fs::read(lock_path(root)).ok()?;
+    Some(serde_json::from_slice(&content).unwrap_or(LockInfo {
+        holder: "an unknown program".to_owned(),
+        pid: None,
+        since: None,
+    }))
+}
+
+// The lock on a backup, released when dropped.
+#[derive(Debug)]
+pub struct BackupLock {
+    path: PathBuf,
+}
+
+impl BackupLock {
+    pub fn acquire(root: &Path) -> Result<Self, LockError> {
+        let path = lock_path(root);
+        let held = |path: PathBuf| {
+            let info = current(root);
+            LockError::Held {
+                holder: info
+                    .as_ref()
+                    .map_or_else(|| "something".to_owned(), |i| i.holder.clone()),
+                since: info.and_then(|i| i.since),
+                path,
+            }
+        };
+        let mut file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(held(path)),
+            Err(e) => return Err(e.into()),
+        };
+        let info = LockInfo {
+            holder: env!("CARGO_PKG_NAME").to_owned(),
+            pid: Some(std::process::id()),
+            since: Some(Utc::now()),
+        };
+        let lock = Self { path };
+        file.write_all(&serde_json::to_vec(&info).map_err(io::Error::from)?)?;
+        Ok(lock)
+    }
+}
+
+impl Drop for BackupLock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
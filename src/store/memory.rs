@@ -0,0 +1,224 @@
+use super::{backup::*, internment::Interner, RcStr};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem::size_of,
+};
+
+// Heap bytes a value owns beyond its own size_of. Estimates, not allocator truth: B-tree
+// overhead is approximated and allocator slack is ignored.
+pub trait HeapSize {
+    fn heap_size(&self, sizer: &mut Sizer) -> usize;
+}
+
+// Counts each shared string once, however many values point at it, and keeps track of
+// how much the sharing saved.
+#[derive(Default)]
+pub struct Sizer {
+    seen: BTreeSet<usize>,
+    pub shared_bytes: usize,
+}
+
+impl HeapSize for RcStr {
+    fn heap_size(&self, sizer: &mut Sizer) -> usize {
+        // The strong and weak counts sit next to the bytes in the Arc's allocation.
+        let size = 2 * size_of::<usize>() + self.len();
+        if sizer.seen.insert(self.as_ptr() as usize) {
+            size
+        } else {
+            sizer.shared_bytes += size;
+            0
+        }
+    }
+}
+
+impl HeapSize for String {
+    fn heap_size(&self, _: &mut Sizer) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self, sizer: &mut Sizer) -> usize {
+        self.as_ref().map_or(0, |v| v.heap_size(sizer))
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self, sizer: &mut Sizer) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(|v| v.heap_size(sizer)).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for BTreeMap<K, V> {
+    fn heap_size(&self, sizer: &mut Sizer) -> usize {
+        // Nodes run about two thirds full.
+        let entries = self.len() * (size_of::<K>() + size_of::<V>()) * 3 / 2;
+        entries
+            + self
+                .iter()
+                .map(|(k, v)| k.heap_size(sizer) + v.heap_size(sizer))
+                .sum::<usize>()
+    }
+}
+
+macro_rules! no_heap {
+    ($($t:ty),*) => {
+        $(impl HeapSize for $t {
+            fn heap_size(&self, _: &mut Sizer) -> usize {
+                0
+            }
+        })*
+    };
+}
+
+no_heap!(bool, i32, i64, u64, DateTime<Utc>, RecordType, MessageType);
+
+macro_rules! heap_fields {
+    ($($t:ident { $($field:ident),* })*) => {
+        $(impl HeapSize for $t {
+            fn heap_size(&self, sizer: &mut Sizer) -> usize {
+                0 $(+ self.$field.heap_size(sizer))*
+            }
+        })*
+    };
+}
+
+heap_fields! {
+    Account {
+        contacts, group_members, groups, messages, records, variable_definitions, variables
+    }
+    Contact {
+        id, owner_id, friend_username, alternate_usernames, friend_status, is_accepted,
+        user_status, latest_message_time, profile
+    }
+    ContactStatus {
+        online_status, last_status_change, current_session_id, current_session_access_level,
+        current_session_hidden, current_hosting, compatibility_hash, neos_version,
+        public_rsa_key, output_device, is_mobile, current_session, active_sessions
+    }
+    RsaKey { exponent, modulus, p, q, dp, dq, inverse_q, d }
+    Session {
+        name, description, corresponding_world_id, tags, session_id, normalized_session_id,
+        host_user_id, host_machine_id, host_username, compatibility_hash, universe_id,
+        neos_version, session_urls, parent_session_ids, nested_session_ids, session_users,
+        thumbnail, access_level
+    }
+    CorrespondingWorldId { record_id, owner_id }
+    SessionUsers { username, user_id }
+    Profile {
+        icon_url, background_url, tagline, description, profile_world_url, showcase_items,
+        token_opt_out
+    }
+    GroupMember { id, owner_id }
+    Group { id, admin_user_id, name }
+    Message { id, owner_id, recipient_id, message_type, content, send_time, last_update_time, read_time }
+    VariableDefinition {
+        definition_owner_id, subpath, variable_type, default_value, read_permissions,
+        write_permissions, list_permissions
+    }
+    Variable { owner_id, path, value }
+    Record {
+        id, owner_id, asset_uri, last_modifying_user_id, last_modifying_machine_id, name,
+        description, owner_name, tags, path, thumbnail_uri, submissions, neos_db_manifest
+    }
+    AssetRef { hash }
+    Submission {
+        id, owner_id, target_record_id, submitted_by_id, submitted_by_name, featured_by_user_id
+    }
+    RecordId { record_id, owner_id }
+}
+
+impl HeapSize for FriendStatus {
+    fn heap_size(&self, sizer: &mut Sizer) -> usize {
+        match self {
+            FriendStatus::Other(s) => s.heap_size(sizer),
+            _ => 0,
+        }
+    }
+}
+
+impl HeapSize for AssetUri {
+    fn heap_size(&self, sizer: &mut Sizer) -> usize {
+        match self {
+            AssetUri::SZBson(SZBson(id)) | AssetUri::Webp(Webp(id)) | AssetUri::Ogg(Ogg(id)) => {
+                id.heap_size(sizer)
+            }
+            AssetUri::Unknown(Unknown { kind, id }) => kind.heap_size(sizer) + id.heap_size(sizer),
+            AssetUri::Detected(Detected { id, .. }) => id.heap_size(sizer),
+            AssetUri::NeosRec(NeosRecAsset { group_id, asset_id }) => {
+                group_id.heap_size(sizer) + asset_id.heap_size(sizer)
+            }
+        }
+    }
+}
+
+// For measuring an interner on its own, next to the model it interned for.
+impl HeapSize for Interner {
+    fn heap_size(&self, sizer: &mut Sizer) -> usize {
+        self.iter().map(|(k, _)| k.heap_size(sizer)).sum::<usize>()
+            + self.len() * (size_of::<RcStr>() + size_of::<u64>()) * 3 / 2
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub subsystem: &'static str,
+    pub items: u64,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    pub subsystems: Vec<Usage>,
+    // What the shared strings would have cost as copies.
+    pub shared_string_bytes: u64,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.subsystems.iter().map(|u| u.bytes).sum()
+    }
+}
+
+// Estimated heap use of a loaded backup, by what holds it.
+pub fn memory_usage(backup: &Backup) -> MemoryUsage {
+    let mut sizer = Sizer::default();
+    let usage = |subsystem| Usage {
+        subsystem,
+        ..Default::default()
+    };
+    let mut records = usage("records");
+    let mut messages = usage("messages");
+    let mut contacts = usage("contacts");
+    let mut groups = usage("groups");
+    let mut variables = usage("variables");
+    for (name, account) in &backup.accounts {
+        records.bytes +=
+            (name.heap_size(&mut sizer) + account.records.heap_size(&mut sizer)) as u64;
+        records.items += account.records.len() as u64;
+        messages.bytes += account.messages.heap_size(&mut sizer) as u64;
+        messages.items += account.messages.values().map(Vec::len).sum::<usize>() as u64;
+        contacts.bytes += account.contacts.heap_size(&mut sizer) as u64;
+        contacts.items += account.contacts.len() as u64;
+        groups.bytes += (account.groups.heap_size(&mut sizer)
+            + account.group_members.heap_size(&mut sizer)) as u64;
+        groups.items += account.groups.len() as u64;
+        variables.bytes += (account.variables.heap_size(&mut sizer)
+            + account.variable_definitions.heap_size(&mut sizer)) as u64;
+        variables.items += account.variables.len() as u64;
+    }
+    let (index_len, index_bytes) = backup.asset_index_size();
+    let asset_index = Usage {
+        subsystem: "asset index",
+        items: index_len as u64,
+        bytes: index_bytes as u64,
+    };
+    MemoryUsage {
+        subsystems: vec![records, messages, contacts, groups, variables, asset_index],
+        shared_string_bytes: sizer.shared_bytes as u64,
+    }
+}
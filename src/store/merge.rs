@@ -0,0 +1,296 @@
+use super::{
+    backup::{is_sidecar, Backup, SIDECARS},
+    format::FormatVersion,
+    ignore::IgnoreRules,
+    RcStr,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MergeError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("nothing to merge")]
+    NoSources,
+    #[error("{} was only loaded in part, merging it would drop the rest", .0.display())]
+    Partial(PathBuf),
+    #[error("{} was written by the {}, the others by the {}", .0.display(), .1, .2)]
+    MixedFormats(PathBuf, FormatVersion, FormatVersion),
+    #[error("{} already has files in it", .0.display())]
+    OutputNotEmpty(PathBuf),
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeSummary {
+    pub sources: u64,
+    pub accounts: u64,
+    pub records: u64,
+    // Record files no source could parse, so with no version to pick by, taken by path
+    // like other files.
+    pub unparsed_records: u64,
+    // Copies of a record left out for a higher version of it in another source.
+    pub superseded_records: u64,
+    // Contacts, messages, groups, variables and anything else in the account folders.
+    pub files: u64,
+    // Files at the same path in more than one source, the later source's kept.
+    pub replaced_files: u64,
+    pub assets: u64,
+    // Copies of an asset another source already supplied.
+    pub duplicate_assets: u64,
+    pub bytes: u64,
+}
+
+// Which file of which source ends up where in the merged backup.
+#[derive(Debug, Default)]
+pub struct MergePlan {
+    // By path in the merged backup, to the file it's copied from.
+    files: BTreeMap<PathBuf, PathBuf>,
+    pub summary: MergeSummary,
+}
+
+// Plans combining several backups into one, sources given oldest first:
+//
+// - each record from the source with its highest (globalVersion, localVersion), later
+//   sources winning ties, along with that copy's sidecars, and record files that didn't
+//   parse anywhere by their path like the rest;
+// - every other file in the account folders by its path, the latest source's copy where
+//   several have one, as messages only gain read and update times;
+// - each asset once, since its file is named by its hash.
+//
+// Files are taken as they are on disk, so fields the model doesn't know survive.
+pub fn plan(sources: &[Backup], ignore: &IgnoreRules) -> Result<MergePlan, MergeError> {
+    let Some(first) = sources.first() else {
+        return Err(MergeError::NoSources);
+    };
+    for source in sources {
+        if source.partial {
+            return Err(MergeError::Partial(source.root.clone()));
+        }
+        if source.format != first.format {
+            return Err(MergeError::MixedFormats(
+                source.root.clone(),
+                source.format,
+                first.format,
+            ));
+        }
+    }
+
+    let mut plan = MergePlan::default();
+    plan.summary.sources = sources.len() as u64;
+
+    // The winning source of each record, by account and file name.
+    let mut records: BTreeMap<_, (usize, (i32, i32))> = BTreeMap::new();
+    for (i, source) in sources.iter().enumerate() {
+        for (account_name, account) in &source.accounts {
+            for (file, rec) in &account.records {
+                let version = (rec.global_version, rec.local_version);
+                match records.get_mut(&(account_name, file)) {
+                    Some(winner) => {
+                        plan.summary.superseded_records += 1;
+                        if version >= winner.1 {
+                            *winner = (i, version);
+                        }
+                    }
+                    None => {
+                        records.insert((account_name, file), (i, version));
+                    }
+                }
+            }
+        }
+    }
+    plan.summary.records = records.len() as u64;
+    for ((account, file), (i, _)) in &records {
+        let folder = Path::new(account.as_str()).join("Records");
+        let from = sources[*i].root.join(&folder);
+        for suffix in [".json"].into_iter().chain(SIDECARS) {
+            let name = format!("{}{}", file, suffix);
+            if from.join(&name).is_file() {
+                plan.files.insert(folder.join(&name), from.join(&name));
+            }
+        }
+    }
+
+    let mut accounts = BTreeSet::new();
+    for source in sources {
+        for account in source.accounts.keys() {
+            accounts.insert(account);
+            let mut pending = vec![PathBuf::from(account.as_str())];
+            while let Some(relative) = pending.pop() {
+                let path = source.root.join(&relative);
+                for entry in path.read_dir()? {
+                    let entry = entry?;
+                    let name = entry.file_name();
+                    let name = name.to_string_lossy();
+                    if ignore.is_ignored(&name) {
+                        continue;
+                    }
+                    let in_records = relative == Path::new(account.as_str()).join("Records");
+                    let relative = relative.join(entry.file_name());
+                    if entry.file_type()?.is_dir() {
+                        pending.push(relative);
+                        continue;
+                    }
+                    if in_records {
+                        // Those that parsed somewhere were picked by version above.
+                        let file = record_file(&name);
+                        if records.contains_key(&(account, &RcStr::from(file))) {
+                            continue;
+                        }
+                        if !is_sidecar(&name) && !plan.files.contains_key(&relative) {
+                            plan.summary.unparsed_records += 1;
+                        }
+                    }
+                    if plan.files.insert(relative, entry.path()).is_some() {
+                        plan.summary.replaced_files += 1;
+                    } else if !in_records {
+                        plan.summary.files += 1;
+                    }
+                }
+            }
+        }
+    }
+    plan.summary.accounts = accounts.len() as u64;
+
+    for source in sources {
+        if source.metadata_only {
+            continue;
+        }
+        for entry in source.assets_dir.read_dir()? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file()
+                || ignore.is_ignored(&entry.file_name().to_string_lossy())
+            {
+                continue;
+            }
+            let relative = Path::new("Assets").join(entry.file_name());
+            if plan.files.contains_key(&relative) {
+                plan.summary.duplicate_assets += 1;
+            } else {
+                plan.summary.assets += 1;
+                plan.files.insert(relative, entry.path());
+            }
+        }
+    }
+
+    for from in plan.files.values() {
+        plan.summary.bytes += from.metadata()?.len();
+    }
+    Ok(plan)
+}
+
+// The record a file in a Records folder belongs to, by the name it's loaded as.
+fn record_file(name: &str) -> &str {
+    SIDECARS
+        .iter()
+        .chain([&".json"])
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name)
+}
+
+impl MergePlan {
+    // Copies the files into out, which has to be missing or empty so nothing already
+    // there is mixed in.
+    pub fn write(&self, out: &Path) -> Result<(), MergeError> {
+        if out.exists() && out.read_dir()?.next().is_some() {
+            return Err(MergeError::OutputNotEmpty(out.to_owned()));
+        }
+        for (relative, from) in &self.files {
+            let to = out.join(relative);
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(from, to)?;
+        }
+        Ok(())
+    }
+}
+
+impl Backup {
+    pub fn merge(
+        sources: &[Backup],
+        out: &Path,
+        ignore: &IgnoreRules,
+    ) -> Result<MergeSummary, MergeError> {
+        let plan = plan(sources, ignore)?;
+        plan.write(out)?;
+        Ok(plan.summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{
+        backup::LoadOptions,
+        testing::{record, TempBackup},
+    };
+
+    fn source(name: &str, versions: &[(&str, i32, i32)]) -> TempBackup {
+        let temp = TempBackup::new(name);
+        for (id, global, local) in versions {
+            let mut rec = record(id, "U-owner", "Inventory");
+            rec["globalVersion"] = (*global).into();
+            rec["localVersion"] = (*local).into();
+            rec["name"] = name.into();
+            temp.write_record("U-owner", &rec);
+        }
+        temp.write_asset(&"a".repeat(64), b"shared");
+        temp
+    }
+
+    #[test]
+    fn merges_keep_the_highest_versions() {
+        let older = source(
+            "merge-older",
+            &[("R-a", 2, 1), ("R-b", 1, 1), ("R-tie", 1, 1)],
+        );
+        let broken = older.root.join("U-owner/Records/R-broken.json");
+        fs::write(&broken, "{").unwrap();
+        let newer = source(
+            "merge-newer",
+            &[("R-a", 1, 5), ("R-b", 1, 2), ("R-tie", 1, 1)],
+        );
+        fs::write(newer.root.join("U-owner/Records/R-b.Storage.json"), "{}").unwrap();
+        let sources = [older.root.clone(), newer.root.clone()]
+            .map(|root| Backup::load_with(root, &LoadOptions::default()).unwrap());
+
+        let out = TempBackup::new("merge-out");
+        fs::remove_dir_all(&out.root).unwrap();
+        let summary = Backup::merge(&sources, &out.root, &IgnoreRules::default()).unwrap();
+        assert_eq!(
+            (
+                summary.records,
+                summary.superseded_records,
+                summary.unparsed_records
+            ),
+            (3, 3, 1)
+        );
+        assert_eq!((summary.assets, summary.duplicate_assets), (1, 1));
+
+        let merged = Backup::load_with(out.root.clone(), &LoadOptions::default()).unwrap();
+        let records = &merged.accounts["U-owner"].records;
+        let picked = |id: &str| {
+            let rec = &records[id];
+            (rec.name.as_str(), rec.global_version, rec.local_version)
+        };
+        // By globalVersion first, then localVersion, the newer source winning ties.
+        assert_eq!(picked("R-a"), ("merge-older", 2, 1));
+        assert_eq!(picked("R-b"), ("merge-newer", 1, 2));
+        assert_eq!(picked("R-tie"), ("merge-newer", 1, 1));
+        assert!(out.root.join("U-owner/Records/R-b.Storage.json").exists());
+        let kept = fs::read(out.root.join("U-owner/Records/R-broken.json")).unwrap();
+        assert_eq!(kept, b"{");
+
+        assert!(matches!(
+            Backup::merge(&sources, &out.root, &IgnoreRules::default()),
+            Err(MergeError::OutputNotEmpty(_))
+        ));
+    }
+}
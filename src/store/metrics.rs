@@ -0,0 +1,49 @@
+use super::{backup::Backup, filter::RecordFilter};
+use serde::Deserialize;
+
+// What a custom metric adds up over the records it matches.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Measure {
+    #[default]
+    Count,
+    // Referenced asset bytes, like the per-type totals.
+    Bytes,
+    Visits,
+}
+
+// A user-defined metric from the config, every filter given must match:
+//
+//   [[metrics]]
+//   name = "tagged world bytes"
+//   measure = "bytes"
+//   record-type = "world"
+//   tag = "favorite"
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetricDef {
+    pub name: String,
+    #[serde(default)]
+    pub measure: Measure,
+    #[serde(flatten)]
+    pub filter: RecordFilter,
+}
+
+impl MetricDef {
+    pub fn evaluate(&self, backup: &Backup) -> u64 {
+        let mut total = 0;
+        for (account_name, account) in &backup.accounts {
+            for rec in account.records.values() {
+                if !self.filter.matches(account_name, rec) {
+                    continue;
+                }
+                total += match self.measure {
+                    Measure::Count => 1,
+                    Measure::Bytes => rec.neos_db_manifest.iter().map(|a| a.bytes).sum(),
+                    Measure::Visits => rec.visits.max(0) as u64,
+                };
+            }
+        }
+        total
+    }
+}
@@ -1,7 +1,65 @@
-use std::rc::Rc;
-
-pub mod internment;
+pub mod aliases;
 pub mod backup;
+pub mod borrowed;
+pub mod canonical;
+pub mod config;
+pub mod conflicts;
+pub mod convert;
 mod de;
+pub mod debug_dump;
+pub mod decode;
+pub mod diff;
+pub mod edit;
+pub mod export;
+pub mod features;
+pub mod filter;
+pub mod format;
+pub mod graph;
+pub mod handle;
+pub mod hash;
+pub mod identity;
+pub mod ignore;
+pub mod internment;
+pub mod inventory;
+pub mod journal;
+pub mod labels;
+pub mod lazy;
+pub mod limits;
+pub mod locale;
+pub mod lock;
+pub mod memory;
+pub mod merge;
+pub mod metrics;
+pub mod naming;
+pub mod orphans;
+pub mod phash;
+pub mod preview;
+mod rc_str;
+pub mod recovery;
+pub mod redact;
+pub mod render;
+pub mod report;
+pub mod sample;
+pub mod schema;
+pub mod screenshot;
+#[cfg(feature = "server")]
+pub mod serve;
+pub mod session_url;
+pub mod sniff;
+pub mod stats;
+#[cfg(test)]
+mod testing;
+pub mod text;
+pub mod timings;
+pub mod validate;
+pub mod verify;
+pub mod voice;
+pub mod warnings;
+pub mod watch;
+
+pub use rc_str::RcStr;
 
-pub type RcStr = Rc<String>;
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<backup::Backup>();
+};
@@ -0,0 +1,232 @@
+use super::{
+    backup::{Component, Manifest, Slot},
+    report::disabled::{collect_strings, hash_of},
+    RcStr,
+};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+pub const MEDIA: &str = "{account}/{record}/{hash}.{ext}";
+pub const ASSET_LINKS: &str = "{account}/{name}/{role}.{ext}";
+
+const PLACEHOLDERS: &[&str] = &[
+    "account",
+    "record",
+    "name",
+    "hash",
+    "kind",
+    "ext",
+    "role",
+    "slot",
+    "component",
+];
+
+// How extracted files are named, shared by every export that writes assets out. Templates
+// are paths relative to the export folder, with {placeholders} filled in per file.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct NamingPolicy {
+    // Asset kind, sniffed or from the URL, to the extension its files get. Kinds not
+    // listed keep their own name as the extension.
+    pub extensions: BTreeMap<String, String>,
+    pub media: Option<String>,
+    pub asset_links: Option<String>,
+}
+
+// The values a template can use for one file. Ones an export doesn't know render as _.
+#[derive(Debug, Default, Clone)]
+pub struct NameParts(BTreeMap<&'static str, String>);
+
+impl NameParts {
+    pub fn with(mut self, key: &'static str, value: impl ToString) -> Self {
+        self.0.insert(key, value.to_string());
+        self
+    }
+}
+
+fn placeholders(template: &str) -> impl Iterator<Item = &str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}').map(|(key, _)| key))
+}
+
+impl NamingPolicy {
+    pub fn extension(&self, kind: &str) -> String {
+        self.extensions
+            .get(kind)
+            .cloned()
+            .unwrap_or_else(|| kind.to_owned())
+    }
+
+    pub fn media_template(&self) -> &str {
+        self.media.as_deref().unwrap_or(MEDIA)
+    }
+
+    pub fn asset_links_template(&self) -> &str {
+        self.asset_links.as_deref().unwrap_or(ASSET_LINKS)
+    }
+
+    // Catches typos before an export has written half its files.
+    pub fn check(&self) -> Result<(), String> {
+        for template in [self.media_template(), self.asset_links_template()] {
+            if let Some(key) = placeholders(template).find(|key| !PLACEHOLDERS.contains(key)) {
+                return Err(format!(
+                    "unknown placeholder {{{}}} in naming template {:?}, expected one of: {}",
+                    key,
+                    template,
+                    PLACEHOLDERS.join(", ")
+                ));
+            }
+            if template.starts_with('/') || template.split('/').any(|s| s == "..") {
+                return Err(format!(
+                    "naming template {:?} leaves the export folder",
+                    template
+                ));
+            }
+        }
+        for ext in self.extensions.values() {
+            if ext.contains(['/', '\\']) {
+                return Err(format!("extension {:?} can't contain a slash", ext));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn uses(template: &str, key: &str) -> bool {
+    placeholders(template).any(|k| k == key)
+}
+
+// The template's path for a file under out. Values are cleaned to one path component each,
+// so only the template's own slashes make folders.
+pub fn render(template: &str, parts: &NameParts, out: &Path) -> PathBuf {
+    let mut path = out.to_path_buf();
+    for segment in template.split('/').filter(|s| !s.is_empty()) {
+        let mut name = String::new();
+        let mut rest = segment;
+        while let Some((before, after)) = rest.split_once('{') {
+            name.push_str(before);
+            match after.split_once('}') {
+                Some((key, after)) => {
+                    name.push_str(&parts.0.get(key).map_or("_".to_owned(), |v| file_name(v)));
+                    rest = after;
+                }
+                None => {
+                    name.push('{');
+                    rest = after;
+                }
+            }
+        }
+        name.push_str(rest);
+        path.push(file_name(&name));
+    }
+    path
+}
+
+// A template without {hash} can give two assets the same path, later ones get the hash
+// prefix added to their file name.
+pub fn unique(path: PathBuf, hash: &str, used: &mut BTreeSet<PathBuf>) -> PathBuf {
+    if used.insert(path.clone()) {
+        return path;
+    }
+    let prefix = hash.get(..12).unwrap_or(hash);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, prefix, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, prefix),
+    };
+    let path = path.with_file_name(name);
+    used.insert(path.clone());
+    path
+}
+
+// Names are free text, keep them to what every filesystem accepts.
+pub fn file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.');
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        "_".to_owned()
+    } else {
+        cleaned.to_owned()
+    }
+}
+
+// Where in a manifest each asset is used, as the slot and the component type loading it.
+// Providers in the asset list count as used on the slot of a component pointing at them.
+pub fn asset_sites(manifest: &Manifest) -> BTreeMap<RcStr, (String, String)> {
+    let mut providers = BTreeMap::new();
+    for provider in manifest.assets.iter().flatten() {
+        let mut strings = Vec::new();
+        for value in provider.data.fields.values() {
+            collect_strings(value, &mut strings);
+        }
+        let hashes: Vec<_> = strings.iter().filter_map(|s| hash_of(s)).collect();
+        providers.insert(provider.data.id.clone(), (provider.short_type(), hashes));
+    }
+    let mut sites = BTreeMap::new();
+    if let Some(slot) = &manifest.object {
+        slot_sites(slot, &providers, &mut sites);
+    }
+    for (component, hashes) in providers.values() {
+        for hash in hashes {
+            sites
+                .entry(hash.clone())
+                .or_insert_with(|| ("Assets".to_owned(), component.clone()));
+        }
+    }
+    sites
+}
+
+fn slot_sites(
+    slot: &Slot,
+    providers: &BTreeMap<RcStr, (String, Vec<RcStr>)>,
+    sites: &mut BTreeMap<RcStr, (String, String)>,
+) {
+    let slot_name = slot.name.data().as_deref().unwrap_or_default().to_owned();
+    for component in slot.components.data() {
+        component_sites(component, &slot_name, providers, sites);
+    }
+    for child in &slot.children {
+        slot_sites(child, providers, sites);
+    }
+}
+
+fn component_sites(
+    component: &Component,
+    slot_name: &str,
+    providers: &BTreeMap<RcStr, (String, Vec<RcStr>)>,
+    sites: &mut BTreeMap<RcStr, (String, String)>,
+) {
+    let mut strings = Vec::new();
+    for value in component.data.fields.values() {
+        collect_strings(value, &mut strings);
+    }
+    for s in &strings {
+        let found: Vec<(RcStr, String)> = match providers.get(s) {
+            Some((provider, hashes)) => hashes
+                .iter()
+                .map(|h| (h.clone(), provider.clone()))
+                .collect(),
+            None => hash_of(s)
+                .map(|h| (h, component.short_type()))
+                .into_iter()
+                .collect(),
+        };
+        for (hash, component) in found {
+            sites
+                .entry(hash)
+                .or_insert_with(|| (slot_name.to_owned(), component));
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use super::{
+    backup::{AssetRef, AssetUri, Backup, MessageContent},
+    hash::AssetId,
+    lock::LockError,
+    RcStr,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PruneError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Lock(#[from] LockError),
+    #[error("the backup was only loaded in part, its orphans may be what the rest of it uses")]
+    Partial,
+    #[error("the backup has no Assets folder to prune")]
+    NoAssets,
+    #[error("quarantine {} is inside the backup, where it would load as an account", .0.display())]
+    QuarantineInBackup(PathBuf),
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedAsset {
+    pub hash: RcStr,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Orphans {
+    // Distinct hashes records and messages reference, whether or not they're present.
+    pub referenced: u64,
+    // Files in the asset folder named by a hash.
+    pub files: u64,
+    pub bytes: u64,
+    // Largest first.
+    pub assets: Vec<OrphanedAsset>,
+}
+
+impl Orphans {
+    pub fn bytes(&self) -> u64 {
+        self.assets.iter().map(|a| a.bytes).sum()
+    }
+}
+
+impl Backup {
+    // Every asset hash a record or a record sent in a message references, through its
+    // asset, its thumbnail or its manifest.
+    pub fn referenced_assets(&self) -> BTreeSet<RcStr> {
+        let mut referenced = BTreeSet::new();
+        let mut add = |asset_uri: &Option<AssetUri>,
+                       thumbnail_uri: &Option<AssetUri>,
+                       manifest: &[AssetRef]| {
+            let uris = [asset_uri, thumbnail_uri].into_iter().flatten();
+            referenced.extend(uris.filter_map(AssetUri::asset_id).cloned());
+            referenced.extend(manifest.iter().map(|a| a.hash.clone()));
+        };
+        for account in self.accounts.values() {
+            for rec in account.records.values() {
+                add(&rec.asset_uri, &rec.thumbnail_uri, &rec.neos_db_manifest);
+            }
+            for msg in account.messages.values().flatten() {
+                if let MessageContent::Object(sent) | MessageContent::Sound(sent) =
+                    msg.parsed_content()
+                {
+                    add(&sent.asset_uri, &sent.thumbnail_uri, &sent.neos_db_manifest);
+                }
+            }
+        }
+        referenced
+    }
+
+    // The files in the backup's own asset folder nothing references. Fallback roots are
+    // other backups' business and left out.
+    pub fn find_orphans(&self) -> io::Result<Orphans> {
+        let referenced = self.referenced_assets();
+        let mut orphans = Orphans {
+            referenced: referenced.len() as u64,
+            ..Default::default()
+        };
+        if self.metadata_only {
+            return Ok(orphans);
+        }
+        for entry in self.assets_dir.read_dir()? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.parse::<AssetId>().is_err() || !entry.file_type()?.is_file() {
+                continue;
+            }
+            let bytes = entry.metadata()?.len();
+            orphans.files += 1;
+            orphans.bytes += bytes;
+            if !referenced.contains(name.as_str()) {
+                orphans.assets.push(OrphanedAsset {
+                    hash: name.into(),
+                    bytes,
+                });
+            }
+        }
+        orphans
+            .assets
+            .sort_by(|a, b| (b.bytes, &a.hash).cmp(&(a.bytes, &b.hash)));
+        Ok(orphans)
+    }
+
+    // Deletes the orphans, or moves them into quarantine when given one, returning what
+    // was (or on a dry run would be) pruned. Refuses on a partly loaded backup, whose
+    // orphans may be what the rest of it uses.
+    pub fn prune(&self, quarantine: Option<&Path>, dry_run: bool) -> Result<Orphans, PruneError> {
+        if self.partial {
+            return Err(PruneError::Partial);
+        }
+        if self.metadata_only {
+            return Err(PruneError::NoAssets);
+        }
+        if let Some(quarantine) = quarantine {
+            if std::path::absolute(quarantine)?.starts_with(std::path::absolute(&self.root)?) {
+                return Err(PruneError::QuarantineInBackup(quarantine.to_owned()));
+            }
+        }
+        let orphans = self.find_orphans()?;
+        if dry_run {
+            return Ok(orphans);
+        }
+        let _lock = self.lock()?;
+        if let Some(quarantine) = quarantine {
+            fs::create_dir_all(quarantine)?;
+        }
+        for orphan in &orphans.assets {
+            let path = self.assets_dir.join(orphan.hash.as_str());
+            match quarantine {
+                Some(quarantine) => move_file(&path, &quarantine.join(orphan.hash.as_str()))?,
+                None => fs::remove_file(&path)?,
+            }
+        }
+        Ok(orphans)
+    }
+}
+
+// A rename, or a copy and delete where the quarantine is on another drive.
+pub(crate) fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
@@ -0,0 +1,83 @@
+#[cfg(not(feature = "images"))]
+use super::features::MissingFeature;
+use super::preview::PreviewError;
+#[cfg(feature = "images")]
+use image::imageops::FilterType;
+use serde::Serialize;
+use std::fmt;
+
+// The image is shrunk to SIZE x SIZE and the lowest LOW x LOW frequencies of its DCT
+// kept, so recompression, resizing and small color shifts barely move the hash.
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+const SIZE: usize = 32;
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+const LOW: usize = 8;
+
+// A 64 bit DCT perceptual hash (pHash). Images that look alike have hashes a few bits
+// apart, however differently their bytes were encoded.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ImageHash(pub u64);
+
+impl ImageHash {
+    // How many of the 64 bits differ.
+    pub fn distance(&self, other: &ImageHash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+impl fmt::Display for ImageHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+#[cfg(feature = "images")]
+pub fn phash(bytes: &[u8]) -> Result<ImageHash, PreviewError> {
+    let image = image::load_from_memory(bytes)?
+        .resize_exact(SIZE as u32, SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = image.pixels().map(|p| f64::from(p.0[0])).collect();
+
+    // The 2D DCT-II is separable: rows first, then the columns of that, only as far as
+    // the low frequencies that are kept.
+    let cosines: Vec<f64> = (0..LOW * SIZE)
+        .map(|i| {
+            let (u, x) = (i / SIZE, i % SIZE);
+            (std::f64::consts::PI * u as f64 * (2 * x + 1) as f64 / (2 * SIZE) as f64).cos()
+        })
+        .collect();
+    let dct = |values: &mut dyn Iterator<Item = f64>, u: usize| -> f64 {
+        values
+            .zip(&cosines[u * SIZE..(u + 1) * SIZE])
+            .map(|(v, c)| v * c)
+            .sum()
+    };
+    let mut rows = vec![0.0; SIZE * LOW];
+    for y in 0..SIZE {
+        for u in 0..LOW {
+            rows[y * LOW + u] = dct(&mut pixels[y * SIZE..(y + 1) * SIZE].iter().copied(), u);
+        }
+    }
+    let mut low = Vec::with_capacity(LOW * LOW);
+    for v in 0..LOW {
+        for u in 0..LOW {
+            low.push(dct(&mut (0..SIZE).map(|y| rows[y * LOW + u]), v));
+        }
+    }
+
+    // The DC term is the average brightness, not structure, so it's left out of the
+    // median the bits are set against.
+    let mut sorted = low[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    let hash = low
+        .iter()
+        .enumerate()
+        .fold(0u64, |hash, (i, &c)| hash | (u64::from(c > median) << i));
+    Ok(ImageHash(hash))
+}
+
+#[cfg(not(feature = "images"))]
+pub fn phash(_: &[u8]) -> Result<ImageHash, PreviewError> {
+    Err(MissingFeature("images").into())
+}
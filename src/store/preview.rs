@@ -0,0 +1,193 @@
+use super::{
+    backup::{AssetStoreUnavailable, Backup},
+    features::MissingFeature,
+};
+#[cfg(feature = "images")]
+use image::{imageops::FilterType, ImageFormat};
+#[cfg(any(feature = "images", feature = "audio"))]
+use std::io::Cursor;
+use std::{fs, io, path::PathBuf, str::FromStr};
+
+// Largest thumbnail edge a caller may ask for, so one request can't balloon memory.
+pub const MAX_SIZE: u32 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewFormat {
+    Png,
+    Wav,
+}
+
+impl FromStr for PreviewFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(PreviewFormat::Png),
+            "wav" => Ok(PreviewFormat::Wav),
+            "mp3" => Err("no mp3 encoder is built in, use wav".to_owned()),
+            _ => Err(format!("unknown preview format {:?}", s)),
+        }
+    }
+}
+
+impl PreviewFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PreviewFormat::Png => "png",
+            PreviewFormat::Wav => "wav",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            PreviewFormat::Png => "image/png",
+            PreviewFormat::Wav => "audio/wav",
+        }
+    }
+
+    // What an asset of a sniffed kind previews as when the caller doesn't say.
+    pub fn for_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "webp" | "png" | "jpg" | "gif" => Some(PreviewFormat::Png),
+            "ogg" => Some(PreviewFormat::Wav),
+            _ => None,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PreviewError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("no asset {0}")]
+    Missing(String),
+    #[error(transparent)]
+    Unavailable(#[from] AssetStoreUnavailable),
+    #[error("can't preview a {0} asset as {1}")]
+    Unsupported(&'static str, &'static str),
+    #[error(transparent)]
+    Disabled(#[from] MissingFeature),
+    #[cfg(feature = "images")]
+    #[error("Image: {0}")]
+    Image(#[from] image::ImageError),
+    #[cfg(feature = "audio")]
+    #[error("Vorbis: {0}")]
+    Vorbis(#[from] lewton::VorbisError),
+    #[cfg(feature = "audio")]
+    #[error("Wav: {0}")]
+    Wav(#[from] hound::Error),
+}
+
+// Converted previews kept on disk by hash, format and size, so each is only made once.
+pub struct PreviewCache {
+    dir: Option<PathBuf>,
+}
+
+impl PreviewCache {
+    pub fn new() -> Self {
+        Self {
+            dir: dirs::cache_dir().map(|d| d.join("neos-full-statbox").join("previews")),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self { dir: None }
+    }
+
+    pub fn get(
+        &self,
+        backup: &Backup,
+        hash: &str,
+        format: PreviewFormat,
+        size: Option<u32>,
+    ) -> Result<Vec<u8>, PreviewError> {
+        let path = self.dir.as_ref().map(|dir| {
+            dir.join(format!(
+                "{}-{}.{}",
+                hash,
+                size.unwrap_or(0),
+                format.extension()
+            ))
+        });
+        if let Some(bytes) = path.as_ref().and_then(|p| fs::read(p).ok()) {
+            return Ok(bytes);
+        }
+        let bytes = convert(backup, hash, format, size)?;
+        // The cache is best effort, a preview that can't be saved is still served.
+        if let Some(path) = path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).ok();
+            }
+            fs::write(path, &bytes).ok();
+        }
+        Ok(bytes)
+    }
+}
+
+impl Default for PreviewCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn convert(
+    backup: &Backup,
+    hash: &str,
+    format: PreviewFormat,
+    size: Option<u32>,
+) -> Result<Vec<u8>, PreviewError> {
+    if !backup.has_asset_store() {
+        return Err(AssetStoreUnavailable.into());
+    }
+    let path = backup
+        .asset_path(hash)
+        .ok_or_else(|| PreviewError::Missing(hash.to_owned()))?;
+    let kind = backup.sniff_asset(hash)?.unwrap_or("unknown");
+    match (PreviewFormat::for_kind(kind), format) {
+        (Some(PreviewFormat::Png), PreviewFormat::Png) => resize_to_png(&fs::read(path)?, size),
+        (Some(PreviewFormat::Wav), PreviewFormat::Wav) => ogg_to_wav(fs::File::open(path)?),
+        _ => Err(PreviewError::Unsupported(kind, format.extension())),
+    }
+}
+
+#[cfg(feature = "images")]
+pub(crate) fn resize_to_png(bytes: &[u8], size: Option<u32>) -> Result<Vec<u8>, PreviewError> {
+    let mut image = image::load_from_memory(bytes)?;
+    if let Some(size) = size {
+        let size = size.clamp(1, MAX_SIZE);
+        image = image.resize(size, size, FilterType::Triangle);
+    }
+    let mut out = Vec::new();
+    image.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "images"))]
+pub(crate) fn resize_to_png(_: &[u8], _: Option<u32>) -> Result<Vec<u8>, PreviewError> {
+    Err(MissingFeature("images").into())
+}
+
+#[cfg(feature = "audio")]
+pub(crate) fn ogg_to_wav<R: io::Read + io::Seek>(ogg: R) -> Result<Vec<u8>, PreviewError> {
+    let mut stream = lewton::inside_ogg::OggStreamReader::new(ogg)?;
+    let spec = hound::WavSpec {
+        channels: stream.ident_hdr.audio_channels as u16,
+        sample_rate: stream.ident_hdr.audio_sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut out = Vec::new();
+    let mut wav = hound::WavWriter::new(Cursor::new(&mut out), spec)?;
+    while let Some(samples) = stream.read_dec_packet_itl()? {
+        for sample in samples {
+            wav.write_sample(sample)?;
+        }
+    }
+    wav.finalize()?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "audio"))]
+pub(crate) fn ogg_to_wav<R: io::Read + io::Seek>(_: R) -> Result<Vec<u8>, PreviewError> {
+    Err(MissingFeature("audio").into())
+}
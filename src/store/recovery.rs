@@ -0,0 +1,215 @@
+use super::{
+    backup::{Backup, Component, Error, Field, Manifest, SZBson, Slot},
+    RcStr,
+};
+use bson::{Bson, Document};
+use serde::{de::DeserializeOwned, Serialize};
+
+// A part of a manifest the typed model couldn't read, and why.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Failure {
+    // Dotted path like Object.Children[2].Components.Data[0], or <document>.
+    pub path: String,
+    pub error: String,
+}
+
+// Whatever could be read out of a manifest, with every unreadable part left out and listed.
+#[derive(Debug, Default)]
+pub struct Recovered {
+    pub manifest: Manifest,
+    pub failures: Vec<Failure>,
+    pub slots: u64,
+    pub components: u64,
+}
+
+impl Recovered {
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl SZBson {
+    // Never fails on a bad manifest, only when the asset can't be read or decompressed.
+    pub fn recover(&self, b: &Backup) -> Result<Recovered, Error> {
+        let raw = self.read_raw(b)?;
+        match bson::RawDocumentBuf::from_bytes(raw).and_then(|doc| doc.to_document()) {
+            Ok(doc) => Ok(recover(&doc)),
+            Err(e) => Ok(Recovered {
+                failures: vec![Failure {
+                    path: "<document>".to_owned(),
+                    error: e.to_string(),
+                }],
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+// Reads a manifest strictly, and when that fails walks it slot by slot and component by
+// component, keeping every piece that reads on its own.
+pub fn recover(doc: &Document) -> Recovered {
+    let mut walk = Walk::default();
+    if let Ok(manifest) = bson::from_document::<Manifest>(doc.clone()) {
+        if let Some(object) = &manifest.object {
+            walk.count(object);
+        }
+        walk.components += manifest.assets.as_ref().map_or(0, |a| a.len() as u64);
+        return walk.finish(manifest);
+    }
+
+    let object = match doc.get("Object") {
+        None | Some(Bson::Null) => None,
+        Some(Bson::Document(slot)) => Some(walk.slot(slot, "Object".to_owned())),
+        Some(other) => {
+            walk.fail(
+                "Object",
+                format!("expected a slot, found {:?}", other.element_type()),
+            );
+            None
+        }
+    };
+    let assets = match doc.get("Assets") {
+        None | Some(Bson::Null) => None,
+        Some(Bson::Array(assets)) => Some(walk.components(assets, "Assets")),
+        Some(other) => {
+            walk.fail(
+                "Assets",
+                format!("expected an array, found {:?}", other.element_type()),
+            );
+            None
+        }
+    };
+    let type_versions = match doc.get("TypeVersions") {
+        Some(versions) => walk.read(versions, "TypeVersions").unwrap_or_default(),
+        None => Default::default(),
+    };
+    walk.finish(Manifest {
+        object,
+        assets,
+        type_versions,
+    })
+}
+
+#[derive(Default)]
+struct Walk {
+    failures: Vec<Failure>,
+    slots: u64,
+    components: u64,
+}
+
+impl Walk {
+    fn finish(self, manifest: Manifest) -> Recovered {
+        Recovered {
+            manifest,
+            failures: self.failures,
+            slots: self.slots,
+            components: self.components,
+        }
+    }
+
+    fn fail(&mut self, path: &str, error: String) {
+        self.failures.push(Failure {
+            path: path.to_owned(),
+            error,
+        });
+    }
+
+    fn read<T: DeserializeOwned>(&mut self, value: &Bson, path: &str) -> Option<T> {
+        match bson::from_bson(value.clone()) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.fail(path, e.to_string());
+                None
+            }
+        }
+    }
+
+    fn count(&mut self, slot: &Slot) {
+        self.slots += 1;
+        self.components += slot.components.data().len() as u64;
+        for child in &slot.children {
+            self.count(child);
+        }
+    }
+
+    // The slot's own fields are read with its children and components taken out, so a
+    // bad component or descendant only costs that one piece.
+    fn slot(&mut self, doc: &Document, path: String) -> Slot {
+        let mut shell = doc.clone();
+        shell.insert("Children", Bson::Array(Vec::new()));
+        let components = match shell.get_document_mut("Components") {
+            Ok(field) => match field.insert("Data", Bson::Array(Vec::new())) {
+                Some(Bson::Array(components)) => components,
+                _ => Vec::new(),
+            },
+            Err(_) => Vec::new(),
+        };
+
+        let mut slot = match bson::from_document::<Slot>(shell) {
+            Ok(slot) => slot,
+            Err(e) => {
+                self.fail(&path, e.to_string());
+                self.salvage(doc, &path)
+            }
+        };
+        self.slots += 1;
+        *slot.components.data_mut() =
+            self.components(&components, &format!("{}.Components.Data", path));
+
+        if let Ok(children) = doc.get_array("Children") {
+            for (i, child) in children.iter().enumerate() {
+                let child_path = format!("{}.Children[{}]", path, i);
+                match child {
+                    Bson::Document(child) => {
+                        let child = self.slot(child, child_path);
+                        slot.children.push(child);
+                    }
+                    other => self.fail(
+                        &child_path,
+                        format!("expected a slot, found {:?}", other.element_type()),
+                    ),
+                }
+            }
+        }
+        slot
+    }
+
+    // What little of a slot whose own fields are broken still reads.
+    fn salvage(&mut self, doc: &Document, path: &str) -> Slot {
+        let text = |key: &str| doc.get_str(key).map(|s| RcStr::new(s.to_owned())).ok();
+        let name = doc
+            .get("Name")
+            .and_then(|name| bson::from_bson::<Field<Option<RcStr>>>(name.clone()).ok());
+        let components = doc
+            .get_document("Components")
+            .ok()
+            .and_then(|field| field.get_str("ID").ok())
+            .map(|id| Field::Value {
+                id: RcStr::new(id.to_owned()),
+                data: Vec::new(),
+            });
+        if text("ID").is_none() {
+            self.fail(&format!("{}.ID", path), "slot has no ID".to_owned());
+        }
+        Slot {
+            id: text("ID").unwrap_or_default(),
+            persistent_id: text("Persistent-ID"),
+            parent_reference: text("ParentReference").unwrap_or_default(),
+            name: name.unwrap_or_default(),
+            components: components.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn components(&mut self, components: &[Bson], path: &str) -> Vec<Component> {
+        let mut read = Vec::new();
+        for (i, component) in components.iter().enumerate() {
+            if let Some(component) = self.read(component, &format!("{}[{}]", path, i)) {
+                read.push(component);
+            }
+        }
+        self.components += read.len() as u64;
+        read
+    }
+}
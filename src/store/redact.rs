@@ -0,0 +1,104 @@
+use super::RcStr;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedactionProfile {
+    #[default]
+    None,
+    // Other people are pseudonymized, the backup owner's own data is kept.
+    ContactsOnly,
+    // Everyone is pseudonymized and free text (names, messages) is removed.
+    Full,
+}
+
+impl FromStr for RedactionProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(RedactionProfile::None),
+            "contacts-only" => Ok(RedactionProfile::ContactsOnly),
+            "full-anonymize" => Ok(RedactionProfile::Full),
+            _ => Err(format!(
+                "unknown redaction profile {:?}, expected none, contacts-only or full-anonymize",
+                s
+            )),
+        }
+    }
+}
+
+// Pseudonyms are stable across runs so redacted exports can still be joined.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    pub profile: RedactionProfile,
+    pub owners: Vec<RcStr>,
+}
+
+impl Redactor {
+    pub fn new<I>(profile: RedactionProfile, owners: I) -> Self
+    where
+        I: IntoIterator<Item = RcStr>,
+    {
+        Self {
+            profile,
+            owners: owners.into_iter().collect(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.profile != RedactionProfile::None
+    }
+
+    fn hides_user(&self, id: &str) -> bool {
+        match self.profile {
+            RedactionProfile::None => false,
+            RedactionProfile::ContactsOnly => !self.owners.iter().any(|o| o.as_str() == id),
+            RedactionProfile::Full => true,
+        }
+    }
+
+    pub fn user_id(&self, id: &RcStr) -> RcStr {
+        if self.hides_user(id) {
+            pseudonym("U-anon-", id)
+        } else {
+            id.clone()
+        }
+    }
+
+    // The owning user's id decides whether their name is hidden.
+    pub fn username(&self, id: &str, name: &RcStr) -> RcStr {
+        if self.hides_user(id) {
+            pseudonym("user-", id)
+        } else {
+            name.clone()
+        }
+    }
+
+    pub fn text(&self, text: &RcStr) -> RcStr {
+        match self.profile {
+            RedactionProfile::Full => "[redacted]".to_owned().into(),
+            _ => text.clone(),
+        }
+    }
+
+    // Message bodies belong to both parties, so any redaction hides them.
+    pub fn message(&self, text: &RcStr) -> RcStr {
+        if self.is_active() {
+            "[redacted]".to_owned().into()
+        } else {
+            text.clone()
+        }
+    }
+}
+
+pub trait Redact {
+    fn redact(&mut self, r: &Redactor);
+}
+
+// FNV-1a, spelled out so pseudonyms don't change between Rust releases.
+fn pseudonym(prefix: &str, id: &str) -> RcStr {
+    let hash = id.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    format!("{}{:08x}", prefix, hash as u32).into()
+}
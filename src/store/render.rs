@@ -0,0 +1,1737 @@
+use super::{
+    aliases::UserNames,
+    backup::Record,
+    conflicts::{ConflictKind, ConflictSide, Resolution, SyncConflict},
+    convert::Converters,
+    diff::{BackupDiff, ContactChangeKind},
+    export::{
+        queue::{ExportSummary, Outcome},
+        voice::VoiceExport,
+    },
+    features::Feature,
+    identity::{Entity, IdentityDiff},
+    inventory::{CyclicLink, FolderSize},
+    locale::{tr, trf},
+    memory::MemoryUsage,
+    merge::MergeSummary,
+    orphans::Orphans,
+    report::{
+        broken_refs::{BrokenRefs, RefRole},
+        compression::Compression,
+        contacts::ContactInteraction,
+        copies::CopyGroup,
+        devices::DeviceStats,
+        disabled::DisabledContent,
+        groups::GroupStorage,
+        headless::HeadlessHost,
+        population::WorldPopulation,
+        relays::SessionRoute,
+        screenshots::{Photo, WorldPhotos},
+        shapes::LeftoverShape,
+        similar_images::{SimilarImages, ThumbnailMatch},
+        stranded::StrandedRecord,
+        strings::StringReport,
+        threads::{StitchedThread, ThreadIssue},
+    },
+    sample::Estimate,
+    session_url::Transport,
+    stats::{Stats, StatsDelta},
+    timings::{Phase, Timing},
+    validate::ValidationReport,
+    verify::{AssetProblem, VerifyReport},
+    warnings::Warning,
+    RcStr,
+};
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
+use comfy_table::{presets, Cell, ContentArrangement, Table};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+};
+
+// Where human-facing output renders timestamps, the data itself stays in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Zone {
+    #[default]
+    Utc,
+    Local,
+    Named(Tz),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utc" | "UTC" => Ok(Zone::Utc),
+            "local" => Ok(Zone::Local),
+            _ => Tz::from_str(s)
+                .map(Zone::Named)
+                .map_err(|_| format!("unknown timezone {:?}", s)),
+        }
+    }
+}
+
+impl Zone {
+    pub fn format(&self, time: &DateTime<Utc>) -> String {
+        const FORMAT: &str = "%Y-%m-%d %H:%M:%S %Z";
+        match self {
+            Zone::Utc => time.format(FORMAT).to_string(),
+            Zone::Local => time
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S %:z")
+                .to_string(),
+            Zone::Named(tz) => time.with_timezone(tz).format(FORMAT).to_string(),
+        }
+    }
+}
+
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+pub fn human_signed_bytes(bytes: i64) -> String {
+    let sign = if bytes < 0 { "-" } else { "+" };
+    format!("{}{}", sign, human_bytes(bytes.unsigned_abs()))
+}
+
+pub fn percent(part: u64, total: u64) -> String {
+    if total == 0 {
+        "-".to_owned()
+    } else {
+        format!("{:.1}%", part as f64 * 100.0 / total as f64)
+    }
+}
+
+pub fn table<I, T>(header: I) -> Table
+where
+    I: IntoIterator<Item = T>,
+    T: Into<Cell>,
+{
+    let mut table = Table::new();
+    table
+        .load_preset(presets::UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(header);
+    table
+}
+
+fn titled(title: &str, table: Table) -> String {
+    format!("{}\n{}\n", title, table)
+}
+
+// Appended to the title of anything scaled up from a sample of the manifests.
+fn estimated(estimate: Option<&Estimate>) -> String {
+    estimate.map_or_else(String::new, |e| {
+        trf(
+            " (estimated from a sample of {} of {} manifests)",
+            &[&e.sampled, &e.total],
+        )
+    })
+}
+
+pub fn stats(stats: &Stats) -> String {
+    let mut out = String::new();
+
+    let total_records: u64 = stats.records_by_type.values().sum();
+    let total_bytes: u64 = stats.bytes_by_type.values().sum();
+    let mut records = table([tr("Type"), tr("Records"), "%", tr("Referenced bytes"), "%"]);
+    for (kind, count) in &stats.records_by_type {
+        let bytes = stats.bytes_by_type.get(kind).copied().unwrap_or_default();
+        records.add_row(vec![
+            format!("{:?}", kind),
+            count.to_string(),
+            percent(*count, total_records),
+            human_bytes(bytes),
+            percent(bytes, total_bytes),
+        ]);
+    }
+    out += &titled(tr("Records"), records);
+
+    let mut totals = table([tr("Total"), tr("Value")]);
+    totals.add_row(vec![
+        tr("Messages").to_owned(),
+        stats.message_count.to_string(),
+    ]);
+    totals.add_row(vec![
+        tr("Asset bytes").to_owned(),
+        human_bytes(stats.asset_bytes),
+    ]);
+    totals.add_row(vec![
+        tr("Missing assets").to_owned(),
+        if stats.asset_store_unavailable {
+            tr("asset store unavailable").to_owned()
+        } else {
+            stats.missing_assets.len().to_string()
+        },
+    ]);
+    if stats.manifests_scanned + stats.manifest_errors > 0 {
+        totals.add_row(vec![
+            tr("Manifests parsed").to_owned(),
+            stats.manifests_scanned.to_string(),
+        ]);
+        totals.add_row(vec![
+            tr("Manifests failed").to_owned(),
+            stats.manifest_errors.to_string(),
+        ]);
+    }
+    out += &titled(tr("Totals"), totals);
+
+    // Only worth a table once there's more than one account to tell apart.
+    if stats.accounts.len() > 1 {
+        let mut accounts = table([
+            tr("Account"),
+            tr("Records"),
+            tr("Asset bytes"),
+            tr("Contacts"),
+            tr("Messages"),
+        ]);
+        for (name, account) in &stats.accounts {
+            accounts.add_row(vec![
+                name.to_string(),
+                account.records_by_type.values().sum::<u64>().to_string(),
+                human_bytes(account.asset_bytes),
+                account.messages_by_contact.len().to_string(),
+                account
+                    .messages_by_contact
+                    .values()
+                    .sum::<u64>()
+                    .to_string(),
+            ]);
+        }
+        out += &titled(tr("Accounts"), accounts);
+    }
+
+    let mut contacts: Vec<_> = stats
+        .accounts
+        .iter()
+        .flat_map(|(name, account)| {
+            account
+                .messages_by_contact
+                .iter()
+                .map(move |(contact, count)| (name, contact, *count))
+        })
+        .collect();
+    if !contacts.is_empty() {
+        contacts.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+        let mut rows = table([tr("Account"), tr("Contact"), tr("Messages"), "%"]);
+        for (name, contact, count) in contacts.into_iter().take(20) {
+            rows.add_row(vec![
+                name.to_string(),
+                contact.to_string(),
+                count.to_string(),
+                percent(count, stats.message_count),
+            ]);
+        }
+        out += &titled(tr("Top contacts by messages"), rows);
+    }
+
+    let storage: Vec<_> = stats
+        .accounts
+        .iter()
+        .flat_map(|(name, account)| account.storage.iter().map(move |quota| (name, quota)))
+        .collect();
+    if !storage.is_empty() {
+        let mut rows = table([
+            tr("Account"),
+            tr("Group"),
+            tr("Used"),
+            tr("Quota"),
+            "%",
+            tr("Account used"),
+        ]);
+        for (name, quota) in storage {
+            rows.add_row(vec![
+                name.to_string(),
+                quota.name.as_ref().unwrap_or(&quota.group).to_string(),
+                human_bytes(quota.used_bytes),
+                human_bytes(quota.quota_bytes),
+                quota
+                    .used_fraction()
+                    .map_or_else(|| "-".to_owned(), |f| format!("{:.1}%", f * 100.0)),
+                quota
+                    .member_used_bytes
+                    .map_or_else(|| "-".to_owned(), human_bytes),
+            ]);
+        }
+        out += &titled(tr("Storage quotas"), rows);
+    }
+
+    if !stats.tags.is_empty() {
+        let mut sorted: Vec<_> = stats.tags.iter().collect();
+        sorted.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        let mut tags = table([tr("Tag"), tr("Records"), "%"]);
+        for (tag, count) in sorted.into_iter().take(20) {
+            tags.add_row(vec![
+                tag.to_string(),
+                count.to_string(),
+                percent(*count, total_records),
+            ]);
+        }
+        out += &titled(tr("Top tags"), tags);
+    }
+
+    if !stats.custom_metrics.is_empty() {
+        let mut metrics = table([tr("Metric"), tr("Value")]);
+        for (name, value) in &stats.custom_metrics {
+            metrics.add_row(vec![name.to_string(), value.to_string()]);
+        }
+        out += &titled(tr("Custom metrics"), metrics);
+    }
+
+    if !stats.messages_by_label.is_empty() {
+        let mut labels = table([tr("Label"), tr("Messages"), "%"]);
+        for (label, count) in &stats.messages_by_label {
+            labels.add_row(vec![
+                label.to_string(),
+                count.to_string(),
+                percent(*count, stats.message_count),
+            ]);
+        }
+        out += &titled(tr("Messages by label"), labels);
+    }
+
+    // Only worth a table once fallback roots filled in something.
+    if stats.assets_by_root.len() > 1 {
+        let mut roots = table([tr("Asset root"), tr("Assets")]);
+        for (root, count) in &stats.assets_by_root {
+            roots.add_row(vec![root.to_string(), count.to_string()]);
+        }
+        out += &titled(tr("Assets by root"), roots);
+    }
+
+    if !stats.asset_kinds.is_empty() {
+        let kind_bytes: u64 = stats.asset_kinds.values().map(|k| k.bytes).sum();
+        let mut kinds = table([
+            tr("Kind"),
+            tr("Assets"),
+            tr("Bytes"),
+            "%",
+            tr("Compression"),
+        ]);
+        for (kind, kind_stats) in &stats.asset_kinds {
+            kinds.add_row(vec![
+                kind.to_string(),
+                kind_stats.count.to_string(),
+                human_bytes(kind_stats.bytes),
+                percent(kind_stats.bytes, kind_bytes),
+                kind_stats
+                    .compression_ratio()
+                    .map_or_else(|| "-".to_owned(), |r| format!("{:.2}x", r)),
+            ]);
+        }
+        out += &titled(tr("Assets by kind"), kinds);
+    }
+
+    if !stats.component_types.is_empty() {
+        let total_components: u64 = stats.component_types.values().sum();
+        let mut sorted: Vec<_> = stats.component_types.iter().collect();
+        sorted.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        let mut components = table([tr("Component"), tr("Uses"), "%"]);
+        for (name, count) in sorted.into_iter().take(20) {
+            components.add_row(vec![
+                name.to_string(),
+                count.to_string(),
+                percent(*count, total_components),
+            ]);
+        }
+        out += &titled(
+            &trf(
+                "Top components{}",
+                &[&estimated(stats.components_estimate.as_ref())],
+            ),
+            components,
+        );
+    }
+
+    out
+}
+
+pub fn delta(delta: &StatsDelta) -> String {
+    let mut changes = table([tr("Change"), tr("Value")]);
+    for (kind, count) in &delta.records_by_type {
+        changes.add_row(vec![
+            trf("{} records", &[&format!("{:?}", kind)]),
+            format!("{:+}", count),
+        ]);
+    }
+    changes.add_row(vec![
+        tr("Messages").to_owned(),
+        format!("{:+}", delta.message_count),
+    ]);
+    changes.add_row(vec![
+        tr("Asset bytes").to_owned(),
+        human_signed_bytes(delta.asset_bytes),
+    ]);
+    for asset in &delta.newly_missing_assets {
+        changes.add_row(vec![tr("Newly missing").to_owned(), asset.to_string()]);
+    }
+    for asset in &delta.recovered_assets {
+        changes.add_row(vec![tr("Recovered").to_owned(), asset.to_string()]);
+    }
+    titled(tr("Changes since baseline"), changes)
+}
+
+pub fn export_summary(summary: &ExportSummary) -> String {
+    let mut out = String::new();
+    if !summary.failed.is_empty() {
+        let mut failed = table([tr("Account"), tr("Record"), tr("Asset"), tr("Error")]);
+        for entry in &summary.failed {
+            let Outcome::Failed { error } = &entry.outcome else {
+                continue;
+            };
+            failed.add_row(vec![
+                entry.job.account.to_string(),
+                entry.job.record.to_string(),
+                entry.job.asset.to_string(),
+                error.clone(),
+            ]);
+        }
+        out += &titled(tr("Failed"), failed);
+    }
+    let mut totals = table([tr("Files"), tr("Count")]);
+    totals.add_row(vec![tr("Written").to_owned(), summary.written.to_string()]);
+    totals.add_row(vec![tr("Bytes").to_owned(), human_bytes(summary.bytes)]);
+    totals.add_row(vec![
+        tr("Already done").to_owned(),
+        summary.skipped.to_string(),
+    ]);
+    totals.add_row(vec![
+        tr("Failed").to_owned(),
+        summary.failed.len().to_string(),
+    ]);
+    out + &titled(tr("Export"), totals)
+}
+
+pub fn converters(converters: &Converters) -> String {
+    let mut t = table([tr("Converter"), tr("From"), tr("To")]);
+    for converter in converters.iter() {
+        t.add_row(vec![
+            converter.name().to_owned(),
+            converter.inputs().join(", "),
+            converter.output().to_owned(),
+        ]);
+    }
+    titled(tr("Converters"), t)
+}
+
+pub fn records(records: &[(&RcStr, &Record)]) -> String {
+    let mut t = table([
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Type"),
+        tr("Bytes"),
+    ]);
+    for (account, rec) in records {
+        t.add_row(vec![
+            account.to_string(),
+            rec.id.to_string(),
+            rec.name.to_string(),
+            format!("{:?}", rec.record_type),
+            human_bytes(rec.neos_db_manifest.iter().map(|a| a.bytes).sum()),
+        ]);
+    }
+    titled(&trf("{} records", &[&records.len()]), t)
+}
+
+pub fn account_records<'a, I>(records: I) -> String
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut records: Vec<_> = records.into_iter().collect();
+    records.sort_by(|a, b| (&a.path, &a.name).cmp(&(&b.path, &b.name)));
+    let mut t = table([
+        tr("Folder"),
+        tr("Name"),
+        tr("Record"),
+        tr("Type"),
+        tr("Bytes"),
+    ]);
+    for rec in &records {
+        let path: Vec<_> = rec.path.iter().map(|s| s.as_str()).collect();
+        t.add_row(vec![
+            path.join("\\"),
+            rec.name.to_string(),
+            rec.id.to_string(),
+            format!("{:?}", rec.record_type),
+            human_bytes(rec.neos_db_manifest.iter().map(|a| a.bytes).sum()),
+        ]);
+    }
+    titled(&trf("{} records", &[&records.len()]), t)
+}
+
+pub fn features(features: &[Feature]) -> String {
+    let mut t = table([tr("Feature"), tr("Built in"), tr("Provides")]);
+    for feature in features {
+        t.add_row(vec![
+            feature.name.to_owned(),
+            tr(if feature.enabled { "yes" } else { "no" }).to_owned(),
+            feature.description.to_owned(),
+        ]);
+    }
+    titled(tr("Features"), t)
+}
+
+pub fn memory(usage: &MemoryUsage) -> String {
+    let total = usage.total();
+    let mut rows = table([tr("Subsystem"), tr("Items"), tr("Estimated"), "%"]);
+    for u in &usage.subsystems {
+        rows.add_row(vec![
+            u.subsystem.to_owned(),
+            u.items.to_string(),
+            human_bytes(u.bytes),
+            percent(u.bytes, total),
+        ]);
+    }
+    let out = titled(tr("Memory"), rows);
+
+    let mut totals = table([tr("Total"), tr("Value")]);
+    totals.add_row(vec![tr("Estimated heap").to_owned(), human_bytes(total)]);
+    totals.add_row(vec![
+        tr("Shared strings save").to_owned(),
+        human_bytes(usage.shared_string_bytes),
+    ]);
+    // Manifests are decoded where they're used and dropped again.
+    totals.add_row(vec![tr("Manifest cache").to_owned(), "none".to_owned()]);
+    out + &titled(tr("Totals"), totals)
+}
+
+pub fn timings(timings: &[(Phase, Timing)]) -> String {
+    let mut t = table([
+        tr("Phase"),
+        tr("Count"),
+        tr("Time"),
+        tr("Bytes"),
+        tr("Throughput"),
+    ]);
+    for (phase, timing) in timings {
+        let secs = timing.time.as_secs_f64();
+        let throughput = match (timing.bytes, secs > 0.0) {
+            (_, false) => String::new(),
+            (0, true) => format!("{:.0}/s", timing.count as f64 / secs),
+            (bytes, true) => format!("{}/s", human_bytes((bytes as f64 / secs) as u64)),
+        };
+        t.add_row(vec![
+            phase.to_string(),
+            timing.count.to_string(),
+            format!("{:.3}s", secs),
+            if timing.bytes == 0 {
+                String::new()
+            } else {
+                human_bytes(timing.bytes)
+            },
+            throughput,
+        ]);
+    }
+    titled(tr("Timings"), t)
+}
+
+pub fn warnings(warnings: &[Warning]) -> String {
+    let mut t = table([tr("Path"), tr("Warning")]);
+    for warning in warnings {
+        t.add_row(vec![
+            warning.path.display().to_string(),
+            warning.kind.to_string(),
+        ]);
+    }
+    titled(&trf("{} warnings", &[&warnings.len()]), t)
+}
+
+pub fn validation(report: &ValidationReport) -> String {
+    let mut rows = table([
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Disallowed component"),
+        tr("Count"),
+    ]);
+    for record in &report.violations {
+        for (component, count) in &record.components {
+            rows.add_row(vec![
+                record.account.to_string(),
+                record.record.to_string(),
+                record.name.to_string(),
+                component.clone(),
+                count.to_string(),
+            ]);
+        }
+    }
+    let verdict = tr(if report.passed() { "PASS" } else { "FAIL" });
+    trf(
+        "{}: {} of {} manifests violate {}, {} unreadable",
+        &[
+            &verdict,
+            &report.violations.len(),
+            &report.records_checked,
+            &report.profile,
+            &report.unreadable.len(),
+        ],
+    ) + &format!("\n{}\n", rows)
+}
+
+pub fn verify(report: &VerifyReport) -> String {
+    let mut out = String::new();
+    if !report.findings.is_empty() {
+        let mut findings = table([
+            tr("Kind"),
+            tr("Code"),
+            tr("Account"),
+            tr("Record"),
+            tr("Asset"),
+            tr("Message"),
+        ]);
+        for finding in &report.findings {
+            findings.add_row(vec![
+                format!("{:?}", finding.kind),
+                finding.error.code.clone(),
+                finding.account.as_deref().unwrap_or_default().to_owned(),
+                finding.record.as_deref().unwrap_or_default().to_owned(),
+                finding.asset.as_deref().unwrap_or_default().to_owned(),
+                finding.error.message.clone(),
+            ]);
+        }
+        out += &titled(tr("Findings"), findings);
+    }
+    if !report.report_cards.is_empty() {
+        let mut cards = table([
+            tr("Account"),
+            tr("Assets present"),
+            tr("Manifests parse"),
+            tr("Corrupted"),
+            tr("Verified now"),
+        ]);
+        for card in &report.report_cards {
+            cards.add_row(vec![
+                card.account.to_string(),
+                format!("{} ({})", percent(card.present, card.assets), card.present),
+                format!("{} ({})", percent(card.parsed, card.manifests), card.parsed),
+                card.corrupted.to_string(),
+                card.verified.to_string(),
+            ]);
+        }
+        out += &titled(tr("Report cards"), cards);
+    }
+    if let Some(integrity) = &report.integrity {
+        if !integrity.issues.is_empty() {
+            let mut issues = table([
+                tr("Asset"),
+                tr("Problem"),
+                tr("Expected"),
+                tr("Actual"),
+                tr("Used by"),
+            ]);
+            for issue in &integrity.issues {
+                let users: Vec<_> = issue
+                    .records
+                    .iter()
+                    .map(|(account, record)| format!("{}/{}", account, record))
+                    .collect();
+                let problem = match &issue.error {
+                    Some(error) => format!("{:?}: {}", issue.problem, error),
+                    None => format!("{:?}", issue.problem),
+                };
+                issues.add_row(vec![
+                    issue.hash.to_string(),
+                    problem,
+                    issue.expected_bytes.map(human_bytes).unwrap_or_default(),
+                    issue.actual_bytes.map(human_bytes).unwrap_or_default(),
+                    users.join(", "),
+                ]);
+            }
+            out += &titled(tr("Asset integrity"), issues);
+        }
+        let mut files = table([tr("Asset files"), tr("Count")]);
+        files.add_row(vec![tr("Hashed").to_owned(), integrity.files.to_string()]);
+        files.add_row(vec![tr("Bytes").to_owned(), human_bytes(integrity.bytes)]);
+        files.add_row(vec![tr("Intact").to_owned(), integrity.ok.to_string()]);
+        for problem in [
+            AssetProblem::Corrupted,
+            AssetProblem::Truncated,
+            AssetProblem::Unreadable,
+            AssetProblem::SizeMismatch,
+            AssetProblem::Missing,
+        ] {
+            files.add_row(vec![
+                format!("{:?}", problem),
+                integrity.count(problem).to_string(),
+            ]);
+        }
+        files.add_row(vec![
+            tr("Unreferenced").to_owned(),
+            integrity.unreferenced.to_string(),
+        ]);
+        files.add_row(vec![
+            tr("Not named by a hash").to_owned(),
+            integrity.skipped.len().to_string(),
+        ]);
+        out += &titled(tr("Asset files"), files);
+    }
+    let mut summary = table([tr("Checked"), tr("Count")]);
+    summary.add_row(vec![
+        tr("Records").to_owned(),
+        report.records_checked.to_string(),
+    ]);
+    summary.add_row(vec![
+        tr("Assets").to_owned(),
+        if report.asset_store_unavailable {
+            tr("asset store unavailable").to_owned()
+        } else {
+            report.assets_checked.to_string()
+        },
+    ]);
+    summary.add_row(vec![
+        tr("Manifests").to_owned(),
+        report.manifests_checked.to_string(),
+    ]);
+    summary.add_row(vec![
+        tr("Findings").to_owned(),
+        report.findings.len().to_string(),
+    ]);
+    out + &titled(tr("Summary"), summary)
+}
+
+pub fn orphans(orphans: &Orphans) -> String {
+    let mut rows = table([tr("Asset"), tr("Bytes"), "%"]);
+    for orphan in &orphans.assets {
+        rows.add_row(vec![
+            orphan.hash.to_string(),
+            human_bytes(orphan.bytes),
+            percent(orphan.bytes, orphans.bytes),
+        ]);
+    }
+    titled(
+        &trf(
+            "{} of {} asset files referenced by nothing, {} of {}",
+            &[
+                &orphans.assets.len(),
+                &orphans.files,
+                &human_bytes(orphans.bytes()),
+                &human_bytes(orphans.bytes),
+            ],
+        ),
+        rows,
+    )
+}
+
+pub fn conflicts(conflicts: &[SyncConflict]) -> String {
+    let side = |side: &ConflictSide| {
+        let name = side.path.file_name().unwrap_or_default().to_string_lossy();
+        match (&side.version, &side.error) {
+            (Some(version), _) => format!("{} ({})", name, version),
+            (None, Some(error)) => format!("{}\n{}", name, error.message),
+            (None, None) => name.into_owned(),
+        }
+    };
+    let mut rows = table([
+        tr("Account"),
+        tr("Kind"),
+        tr("Original"),
+        tr("Conflict copy"),
+        tr("Resolution"),
+    ]);
+    for conflict in conflicts {
+        rows.add_row(vec![
+            conflict.account.to_string(),
+            tr(match conflict.kind {
+                ConflictKind::Record => "Record",
+                ConflictKind::Message => "Message",
+            })
+            .to_owned(),
+            side(&conflict.original),
+            side(&conflict.copy),
+            tr(match conflict.resolution {
+                Resolution::KeepOriginal => "Keep original",
+                Resolution::KeepCopy => "Keep copy",
+                Resolution::Manual => "Review manually",
+            })
+            .to_owned(),
+        ]);
+    }
+    let manual = conflicts
+        .iter()
+        .filter(|c| c.resolution == Resolution::Manual)
+        .count();
+    titled(
+        &trf(
+            "{} sync conflict copies, {} needing manual review",
+            &[&conflicts.len(), &manual],
+        ),
+        rows,
+    )
+}
+
+pub fn folder_sizes(account: &str, sizes: &[FolderSize]) -> String {
+    let total = sizes
+        .iter()
+        .filter(|s| s.path.len() == 1)
+        .map(|s| s.bytes)
+        .sum();
+    let mut folders = table([tr("Folder"), tr("Records"), tr("Bytes"), tr("% of account")]);
+    for size in sizes {
+        let path: Vec<_> = size.path.iter().map(|s| s.as_str()).collect();
+        folders.add_row(vec![
+            path.join("\\"),
+            size.descendants.to_string(),
+            human_bytes(size.bytes),
+            percent(size.bytes, total),
+        ]);
+    }
+    titled(account, folders)
+}
+
+pub fn contacts(contacts: &[ContactInteraction], zone: Zone) -> String {
+    let mut rows = table([
+        tr("Account"),
+        tr("Contact"),
+        tr("Id"),
+        tr("Status"),
+        tr("Last interaction"),
+        tr("Messages"),
+        tr("Labels"),
+        tr("Flags"),
+    ]);
+    for contact in contacts {
+        let status = if contact.is_accepted {
+            tr("accepted").to_owned()
+        } else {
+            trf("pending: {}", &[&format!("{:?}", contact.friend_status)])
+        };
+        let flags: Vec<_> = contact.flags.iter().map(|f| format!("{:?}", f)).collect();
+        rows.add_row(vec![
+            contact.account.to_string(),
+            contact.username.to_string(),
+            contact.contact_id.to_string(),
+            status,
+            contact
+                .last_interaction
+                .map_or_else(|| tr("never").to_owned(), |t| zone.format(&t)),
+            contact.message_count.to_string(),
+            contact
+                .labels
+                .iter()
+                .map(|l| l.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            flags.join(", "),
+        ]);
+    }
+    rows.to_string() + "\n"
+}
+
+pub fn devices(stats: &DeviceStats) -> String {
+    let counts = |title: &str, header: &str, counts: &BTreeMap<RcStr, u64>| {
+        let total = counts.values().sum();
+        let mut rows = table([tr("Device"), tr(header), "%"]);
+        for (device, count) in counts {
+            rows.add_row(vec![
+                device.to_string(),
+                count.to_string(),
+                percent(*count, total),
+            ]);
+        }
+        titled(tr(title), rows)
+    };
+    let mut out = counts(
+        "Contacts by last output device",
+        "Contacts",
+        &stats.contact_devices,
+    );
+    out += &(trf("Mobile contacts: {}", &[&stats.contacts_mobile]) + "\n");
+    out + &counts(
+        "Session users by output device",
+        "Users",
+        &stats.session_user_devices,
+    )
+}
+
+pub fn disabled(records: &[DisabledContent]) -> String {
+    let mut rows = table([
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Inactive slots"),
+        tr("Disabled components"),
+        tr("Disabled bytes"),
+        tr("% of record"),
+    ]);
+    for rec in records {
+        rows.add_row(vec![
+            rec.account.to_string(),
+            rec.record.to_string(),
+            rec.name.to_string(),
+            format!("{} / {}", rec.inactive_slots, rec.slots),
+            format!("{} / {}", rec.disabled_components, rec.components),
+            human_bytes(rec.disabled_bytes),
+            percent(rec.disabled_bytes, rec.total_bytes),
+        ]);
+    }
+    let total: u64 = records.iter().map(|r| r.disabled_bytes).sum();
+    titled(tr("Disabled content"), rows) + &format!("Reclaimable: {}\n", human_bytes(total))
+}
+
+pub fn shapes(shapes: &[LeftoverShape]) -> String {
+    let mut rows = table([
+        tr("Kind"),
+        tr("Shape"),
+        tr("Count"),
+        tr("Records"),
+        tr("Seen in"),
+        tr("Example"),
+    ]);
+    for shape in shapes {
+        let sources: Vec<_> = shape
+            .sources
+            .iter()
+            .take(3)
+            .map(|(source, n)| format!("{}.{} ({})", source.component, source.field, n))
+            .collect();
+        rows.add_row(vec![
+            format!("{:?}", shape.kind),
+            shape.shape.clone(),
+            shape.count.to_string(),
+            shape.records.to_string(),
+            sources.join("\n"),
+            format!("{} {}", shape.example.record, shape.example.path),
+        ]);
+    }
+    titled(tr("Untyped field shapes"), rows)
+}
+
+pub fn strings(report: &StringReport) -> String {
+    let mut rows = table([
+        tr("String"),
+        tr("JSON uses"),
+        tr("BSON uses"),
+        tr("Bytes"),
+        "%",
+    ]);
+    for dupe in &report.top {
+        rows.add_row(vec![
+            dupe.value.to_string(),
+            dupe.json_uses.to_string(),
+            dupe.bson_uses.to_string(),
+            human_bytes(dupe.bytes),
+            percent(dupe.bytes, report.total_bytes),
+        ]);
+    }
+    let out = titled(tr("Most duplicated strings"), rows);
+
+    let mut totals = table([tr("Total"), tr("Value")]);
+    totals.add_row(vec![
+        tr("JSON strings").to_owned(),
+        report.json_strings.to_string(),
+    ]);
+    totals.add_row(vec![
+        tr("BSON strings").to_owned(),
+        trf(
+            "{} in {} manifests",
+            &[&report.bson_strings, &report.manifests_scanned],
+        ),
+    ]);
+    totals.add_row(vec![tr("Distinct").to_owned(), report.distinct.to_string()]);
+    totals.add_row(vec![
+        tr("String bytes").to_owned(),
+        human_bytes(report.total_bytes),
+    ]);
+    totals.add_row(vec![
+        tr("Interned bytes").to_owned(),
+        human_bytes(report.unique_bytes),
+    ]);
+    totals.add_row(vec![
+        tr("Shared manifest table saves").to_owned(),
+        human_bytes(report.manifest_table_savings),
+    ]);
+    out + &titled(
+        &trf("Totals{}", &[&estimated(report.estimate.as_ref())]),
+        totals,
+    )
+}
+
+pub fn copies(groups: &[CopyGroup]) -> String {
+    let mut rows = table([
+        tr("Root Persistent-ID"),
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Versions"),
+    ]);
+    for group in groups {
+        for (i, copy) in group.records.iter().enumerate() {
+            let (root, versions) = if i == 0 {
+                (group.root.to_string(), group.versions.to_string())
+            } else {
+                Default::default()
+            };
+            rows.add_row(vec![
+                root,
+                copy.account.to_string(),
+                copy.record.to_string(),
+                copy.name.to_string(),
+                versions,
+            ]);
+        }
+    }
+    titled(tr("Copies of the same object"), rows)
+}
+
+pub fn link_cycles(cycles: &BTreeMap<RcStr, Vec<CyclicLink>>) -> String {
+    let path = |p: &[RcStr]| p.iter().map(|s| s.as_str()).collect::<Vec<_>>().join("\\");
+    let mut rows = table([
+        tr("Account"),
+        tr("Link"),
+        tr("Name"),
+        tr("In folder"),
+        tr("Points at"),
+    ]);
+    for (account, links) in cycles {
+        for link in links {
+            rows.add_row(vec![
+                account.to_string(),
+                link.link.to_string(),
+                link.name.to_string(),
+                path(&link.folder),
+                path(&link.target),
+            ]);
+        }
+    }
+    titled(tr("Inventory links that form cycles"), rows)
+}
+
+pub fn compression(compression: &Compression) -> String {
+    let mut rows = table([
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("7zbson"),
+        tr("Uncompressed"),
+        tr("Brson"),
+        tr("Savings"),
+    ]);
+    for rec in &compression.records {
+        rows.add_row(vec![
+            rec.account.to_string(),
+            rec.record.to_string(),
+            rec.name.to_string(),
+            human_bytes(rec.sizes.compressed),
+            human_bytes(rec.sizes.uncompressed),
+            human_bytes(rec.sizes.brson),
+            human_signed_bytes(rec.savings),
+        ]);
+    }
+    let totals = &compression.totals;
+    let mut out = titled(
+        &trf(
+            "{} manifests take {} ({} uncompressed), {} as brson: {} saved{}",
+            &[
+                &compression.assets,
+                &human_bytes(totals.compressed),
+                &human_bytes(totals.uncompressed),
+                &human_bytes(totals.brson),
+                &human_signed_bytes(compression.savings),
+                &estimated(compression.estimate.as_ref()),
+            ],
+        ),
+        rows,
+    );
+    if !compression.unreadable.is_empty() {
+        out += &trf(
+            "{} manifests failed to decompress",
+            &[&compression.unreadable.len()],
+        );
+        out += "\n";
+    }
+    out
+}
+
+pub fn thumbnail_matches(matches: &[ThumbnailMatch]) -> String {
+    let mut rows = table([
+        tr("Distance"),
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Thumbnail"),
+    ]);
+    for m in matches {
+        rows.add_row(vec![
+            m.distance.to_string(),
+            m.account.to_string(),
+            m.record.to_string(),
+            m.name.to_string(),
+            m.thumbnail.to_string(),
+        ]);
+    }
+    titled(
+        &trf("{} records with a look-alike thumbnail", &[&matches.len()]),
+        rows,
+    )
+}
+
+pub fn similar_images(similar: &SimilarImages) -> String {
+    let savings = similar.clusters.iter().map(|c| c.savings).sum();
+    let mut rows = table([
+        tr("Cluster"),
+        tr("Asset"),
+        tr("pHash"),
+        tr("Distance"),
+        tr("Bytes"),
+        tr("Records"),
+    ]);
+    for (n, cluster) in similar.clusters.iter().enumerate() {
+        for image in &cluster.images {
+            let records: Vec<_> = image
+                .records
+                .iter()
+                .map(|(account, record)| format!("{}/{}", account, record))
+                .collect();
+            let keep = if image.asset == cluster.keep {
+                tr(" (kept)")
+            } else {
+                ""
+            };
+            rows.add_row(vec![
+                (n + 1).to_string(),
+                format!("{}{}", image.asset, keep),
+                image.phash.to_string(),
+                image.distance.to_string(),
+                human_bytes(image.bytes),
+                records.join(", "),
+            ]);
+        }
+    }
+    let mut out = titled(
+        &trf(
+            "{} clusters of look-alike images among {} hashed, {} to save (threshold {} bits)",
+            &[
+                &similar.clusters.len(),
+                &similar.hashed,
+                &human_bytes(savings),
+                &similar.threshold,
+            ],
+        ),
+        rows,
+    );
+    if !similar.unreadable.is_empty() {
+        out += &trf(
+            "{} image assets failed to decode",
+            &[&similar.unreadable.len()],
+        );
+        out += "\n";
+    }
+    out
+}
+
+pub fn stranded(records: &[StrandedRecord]) -> String {
+    let total = records.iter().map(|r| r.bytes).sum();
+    let mut rows = table([
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Claimed folder"),
+        tr("Bytes"),
+    ]);
+    for rec in records {
+        let path: Vec<_> = rec.path.iter().map(|s| s.as_str()).collect();
+        rows.add_row(vec![
+            rec.account.to_string(),
+            rec.record.to_string(),
+            rec.name.to_string(),
+            path.join("\\"),
+            human_bytes(rec.bytes),
+        ]);
+    }
+    titled(
+        &trf(
+            "{} records unreachable from the inventory, {}",
+            &[&records.len(), &human_bytes(total)],
+        ),
+        rows,
+    )
+}
+
+pub fn broken_refs(broken: &BrokenRefs) -> String {
+    if broken.asset_store_unavailable {
+        return tr("Asset store unavailable, no references checked\n").to_owned();
+    }
+    let mut rows = table([
+        tr("Owner"),
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Folder"),
+        tr("Missing"),
+        tr("Recoverable"),
+    ]);
+    let mut owners = BTreeMap::<(&RcStr, &RcStr), (u64, u64)>::new();
+    for rec in &broken.records {
+        let path: Vec<_> = rec.path.iter().map(|s| s.as_str()).collect();
+        let missing: Vec<_> = rec
+            .missing
+            .iter()
+            .map(|m| {
+                let role = match &m.role {
+                    RefRole::Nested { slot, component } => format!("{} on {}", component, slot),
+                    role => tr(&format!("{:?}", role)).to_owned(),
+                };
+                format!("{} ({})", m.hash, role)
+            })
+            .collect();
+        rows.add_row(vec![
+            format!("{} ({})", rec.owner_name, rec.owner_id),
+            rec.account.to_string(),
+            rec.record.to_string(),
+            rec.name.to_string(),
+            path.join("\\"),
+            missing.join("\n"),
+            tr(if rec.unrecoverable { "no" } else { "partly" }).to_owned(),
+        ]);
+        let owner = owners.entry((&rec.owner_id, &rec.owner_name)).or_default();
+        owner.0 += 1;
+        owner.1 += rec.unrecoverable as u64;
+    }
+    let mut out = titled(
+        &trf(
+            "{} of {} records refer to {} missing assets",
+            &[
+                &broken.records.len(),
+                &broken.records_checked,
+                &broken.missing_assets,
+            ],
+        ),
+        rows,
+    );
+
+    let mut by_owner = table([tr("Owner"), tr("Broken records"), tr("Unrecoverable")]);
+    for ((id, name), (records, unrecoverable)) in owners {
+        by_owner.add_row(vec![
+            format!("{} ({})", name, id),
+            records.to_string(),
+            unrecoverable.to_string(),
+        ]);
+    }
+    out += &titled(tr("By owner"), by_owner);
+    if !broken.unreadable_manifests.is_empty() {
+        out += &trf(
+            "{} manifests failed to open, what they use wasn't checked",
+            &[&broken.unreadable_manifests.len()],
+        );
+        out += "\n";
+    }
+    out
+}
+
+pub fn group_storage(groups: &[GroupStorage]) -> String {
+    let bytes = |b: Option<u64>| b.map(human_bytes).unwrap_or_else(|| "?".to_owned());
+    let mut rows = table([
+        tr("Group"),
+        tr("Name"),
+        tr("Admin"),
+        tr("Records"),
+        tr("In backup"),
+        tr("Used"),
+        tr("Quota"),
+        tr("Unaccounted"),
+        tr("Exported by"),
+    ]);
+    for group in groups {
+        let exported: Vec<_> = group.exported_by.iter().map(|a| a.as_str()).collect();
+        rows.add_row(vec![
+            group.id.to_string(),
+            group
+                .name
+                .as_ref()
+                .map_or(tr("(unknown)").to_owned(), |n| n.to_string()),
+            group
+                .admin_user_id
+                .as_ref()
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            group.records.to_string(),
+            human_bytes(group.bytes),
+            bytes(group.used_bytes),
+            bytes(group.quota_bytes),
+            human_bytes(group.unaccounted_bytes),
+            exported.join(", "),
+        ]);
+    }
+    let mut out = titled(&trf("{} groups", &[&groups.len()]), rows);
+
+    let mut missing = table([tr("Group"), tr("Record")]);
+    let mut count = 0;
+    for group in groups {
+        for record in &group.missing_records {
+            missing.add_row(vec![group.id.to_string(), record.to_string()]);
+            count += 1;
+        }
+    }
+    if count > 0 {
+        out += &titled(
+            &trf("{} linked group records missing from the backup", &[&count]),
+            missing,
+        );
+    }
+    out
+}
+
+pub fn backup_diff(diff: &BackupDiff, zone: Zone) -> String {
+    let version = |global: i32, local: i32| format!("{}.{}", global, local);
+    let mut records = table([
+        tr("Change"),
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Version"),
+    ]);
+    for (change, recs) in [
+        ("added", &diff.added_records),
+        ("removed", &diff.removed_records),
+    ] {
+        for rec in recs {
+            records.add_row(vec![
+                tr(change).to_owned(),
+                rec.account.to_string(),
+                rec.id.to_string(),
+                rec.name.to_string(),
+                version(rec.global_version, rec.local_version),
+            ]);
+        }
+    }
+    for modified in &diff.modified_records {
+        let (before, after) = (&modified.before, &modified.after);
+        let name = if before.name == after.name {
+            after.name.to_string()
+        } else {
+            format!("{} -> {}", before.name, after.name)
+        };
+        records.add_row(vec![
+            tr("modified").to_owned(),
+            after.account.to_string(),
+            after.id.to_string(),
+            name,
+            format!(
+                "{} -> {}",
+                version(before.global_version, before.local_version),
+                version(after.global_version, after.local_version)
+            ),
+        ]);
+    }
+
+    let mut contacts = table([tr("Change"), tr("Account"), tr("Contact"), tr("Fields")]);
+    for contact in &diff.contacts {
+        let (change, fields) = match &contact.kind {
+            ContactChangeKind::Added => ("added", String::new()),
+            ContactChangeKind::Removed => ("removed", String::new()),
+            ContactChangeKind::Changed { fields } => ("changed", fields.join(", ")),
+        };
+        contacts.add_row(vec![
+            tr(change).to_owned(),
+            contact.account.to_string(),
+            format!("{} ({})", contact.username, contact.id),
+            fields,
+        ]);
+    }
+
+    // Per conversation, in the order their first new message was sent.
+    let mut conversations: Vec<(&RcStr, &RcStr, usize, DateTime<Utc>)> = Vec::new();
+    for msg in &diff.new_messages {
+        match conversations
+            .iter_mut()
+            .find(|(account, contact, ..)| **account == msg.account && **contact == msg.contact)
+        {
+            Some((.., count, last)) => {
+                *count += 1;
+                *last = msg.send_time;
+            }
+            None => conversations.push((&msg.account, &msg.contact, 1, msg.send_time)),
+        }
+    }
+    let mut messages = table([
+        tr("Account"),
+        tr("Contact"),
+        tr("New messages"),
+        tr("Latest"),
+    ]);
+    for (account, contact, count, last) in conversations {
+        messages.add_row(vec![
+            account.to_string(),
+            contact.to_string(),
+            count.to_string(),
+            zone.format(&last),
+        ]);
+    }
+
+    let summary = trf(
+        "{} records added, {} removed, {} modified and {} unchanged; {} contacts changed; {} new messages",
+        &[
+            &diff.added_records.len(),
+            &diff.removed_records.len(),
+            &diff.modified_records.len(),
+            &diff.unchanged_records,
+            &diff.contacts.len(),
+            &diff.new_messages.len(),
+        ],
+    );
+    let mut out = String::new();
+    for (title, rows) in [
+        (tr("Records"), records),
+        (tr("Contacts"), contacts),
+        (tr("Messages"), messages),
+    ] {
+        if !rows.is_empty() {
+            out += &titled(title, rows);
+        }
+    }
+    out + &summary + "\n"
+}
+
+pub fn identity_diff(diff: &IdentityDiff) -> String {
+    let path = |e: &Entity| {
+        let path: Vec<_> = e.path.iter().map(|s| s.as_str()).collect();
+        path.join("/")
+    };
+    let mut rows = table([tr("Change"), tr("Kind"), tr("Name or type"), tr("Under")]);
+    for change in &diff.changed {
+        let (before, after) = (&change.before, &change.after);
+        let label = if before.label == after.label {
+            after.label.to_string()
+        } else {
+            format!("{} -> {}", before.label, after.label)
+        };
+        let under = if before.path == after.path {
+            path(after)
+        } else {
+            format!("{} -> {}", path(before), path(after))
+        };
+        rows.add_row(vec![
+            tr("changed").to_owned(),
+            format!("{:?}", after.kind),
+            label,
+            under,
+        ]);
+    }
+    for (change, entities) in [("added", &diff.added), ("removed", &diff.removed)] {
+        for entity in entities {
+            rows.add_row(vec![
+                tr(change).to_owned(),
+                format!("{:?}", entity.kind),
+                entity.label.to_string(),
+                path(entity),
+            ]);
+        }
+    }
+    titled(tr("Changes by Persistent-ID"), rows)
+        + &trf(
+            "{} unchanged, {} changed, {} added, {} removed",
+            &[
+                &diff.unchanged,
+                &diff.changed.len(),
+                &diff.added.len(),
+                &diff.removed.len(),
+            ],
+        )
+        + "\n"
+}
+
+pub fn aliases<'a, I>(users: I) -> String
+where
+    I: IntoIterator<Item = (&'a RcStr, &'a UserNames)>,
+{
+    let mut rows = table([tr("User"), tr("Name"), tr("Former names")]);
+    for (id, names) in users {
+        let current = if names.overridden {
+            trf("{} (override)", &[&names.current])
+        } else {
+            names.current.to_string()
+        };
+        let former: Vec<_> = names.former.iter().map(|n| n.as_str()).collect();
+        rows.add_row(vec![id.to_string(), current, former.join(", ")]);
+    }
+    rows.to_string() + "\n"
+}
+
+// "name (was a, b)" when the user went by other names too.
+fn with_former(current: &RcStr, names: &BTreeSet<RcStr>) -> String {
+    let former: Vec<_> = names
+        .iter()
+        .filter(|n| *n != current)
+        .map(|n| n.as_str())
+        .collect();
+    if former.is_empty() {
+        current.to_string()
+    } else {
+        trf("{} (was {})", &[current, &former.join(", ")])
+    }
+}
+
+pub fn session_routes(routes: &[SessionRoute], zone: Zone) -> String {
+    let mut rows = table([
+        tr("Transport"),
+        tr("Host"),
+        tr("Sessions"),
+        tr("Hosted by you"),
+        tr("First seen"),
+        tr("Last seen"),
+    ]);
+    let mut by_transport = BTreeMap::<Transport, u64>::new();
+    for route in routes {
+        *by_transport.entry(route.transport).or_default() += route.sessions;
+        rows.add_row(vec![
+            format!("{:?}", route.transport),
+            route.host.clone(),
+            route.sessions.to_string(),
+            route.own_sessions.to_string(),
+            zone.format(&route.first_seen),
+            zone.format(&route.last_seen),
+        ]);
+    }
+    let out = titled(tr("Session routes"), rows);
+
+    let mut totals = table([tr("Transport"), tr("Sessions")]);
+    for (transport, sessions) in by_transport {
+        totals.add_row(vec![format!("{:?}", transport), sessions.to_string()]);
+    }
+    out + &titled(tr("By transport"), totals)
+}
+
+pub fn voice_export(summary: &VoiceExport) -> String {
+    let mut rows = table([tr("Voice messages"), tr("Count")]);
+    for (what, count) in [
+        ("Found", summary.messages),
+        ("Extracted", summary.extracted),
+        ("Asset missing", summary.missing),
+        ("Transcribed", summary.transcribed),
+        ("Transcript kept", summary.kept),
+        ("Transcription failed", summary.failed.len() as u64),
+    ] {
+        rows.add_row(vec![tr(what).to_owned(), count.to_string()]);
+    }
+    let mut out = titled(tr("Voice export"), rows);
+    for (message, error) in &summary.failed {
+        out += &format!("{}: {}\n", message, error);
+    }
+    out
+}
+
+pub fn merge_summary(summary: &MergeSummary) -> String {
+    let mut rows = table([tr("Merged"), tr("Count")]);
+    for (what, count) in [
+        ("Sources", summary.sources),
+        ("Accounts", summary.accounts),
+        ("Records", summary.records),
+        ("Unparsed records taken by path", summary.unparsed_records),
+        ("Older record copies left out", summary.superseded_records),
+        ("Other files", summary.files),
+        ("Files replaced by a later source", summary.replaced_files),
+        ("Assets", summary.assets),
+        ("Duplicate assets left out", summary.duplicate_assets),
+    ] {
+        rows.add_row(vec![tr(what).to_owned(), count.to_string()]);
+    }
+    rows.add_row(vec![tr("Size").to_owned(), human_bytes(summary.bytes)]);
+    titled(tr("Merge"), rows)
+}
+
+pub fn photos(photos: &[&Photo], zone: Zone) -> String {
+    let mut rows = table([
+        tr("Account"),
+        tr("Record"),
+        tr("Name"),
+        tr("Taken"),
+        tr("World"),
+        tr("People"),
+    ]);
+    for photo in photos {
+        let meta = &photo.metadata;
+        let people: Vec<_> = meta.users.iter().map(|u| u.as_str()).collect();
+        rows.add_row(vec![
+            photo.account.to_string(),
+            photo.record.to_string(),
+            photo.name.to_string(),
+            meta.taken
+                .as_ref()
+                .map(|t| zone.format(t))
+                .unwrap_or_default(),
+            meta.world_name
+                .as_ref()
+                .map(|name| name.to_string())
+                .unwrap_or_default(),
+            people.join(", "),
+        ]);
+    }
+    titled(&trf("{} photos", &[&photos.len()]), rows)
+}
+
+pub fn photos_by_world(worlds: &[WorldPhotos], zone: Zone) -> String {
+    let time = |t: &Option<DateTime<Utc>>| t.as_ref().map(|t| zone.format(t)).unwrap_or_default();
+    let mut rows = table([
+        tr("World"),
+        tr("Photos"),
+        tr("Days"),
+        tr("First taken"),
+        tr("Last taken"),
+        tr("People"),
+    ]);
+    for world in worlds {
+        let mut people: Vec<_> = world.people.iter().collect();
+        people.sort_by_key(|(_, n)| std::cmp::Reverse(**n));
+        let people: Vec<_> = people
+            .iter()
+            .take(5)
+            .map(|(id, n)| format!("{} ({})", id, n))
+            .collect();
+        rows.add_row(vec![
+            world.world.to_string(),
+            world.photos.len().to_string(),
+            world.days.len().to_string(),
+            time(&world.first_taken),
+            time(&world.last_taken),
+            people.join(", "),
+        ]);
+    }
+    let photos: usize = worlds.iter().map(|w| w.photos.len()).sum();
+    titled(
+        &trf("{} photos in {} worlds", &[&photos, &worlds.len()]),
+        rows,
+    )
+}
+
+pub fn world_population(worlds: &[WorldPopulation], zone: Zone) -> String {
+    let mut rows = table([
+        tr("World"),
+        tr("Sessions"),
+        tr("Hosted by you"),
+        tr("Snapshots"),
+        tr("Peak users"),
+        tr("Avg users"),
+        tr("Peak active"),
+        tr("Avg active"),
+        tr("Max users"),
+        tr("First seen"),
+        tr("Last seen"),
+    ]);
+    for world in worlds {
+        rows.add_row(vec![
+            world.world.to_string(),
+            world.sessions.to_string(),
+            world.hosted_sessions.to_string(),
+            world.snapshots.to_string(),
+            world.peak_users.to_string(),
+            format!("{:.1}", world.average_users),
+            world.peak_active.to_string(),
+            format!("{:.1}", world.average_active),
+            world.max_users.to_string(),
+            zone.format(&world.first_seen),
+            zone.format(&world.last_seen),
+        ]);
+    }
+    titled(tr("World population"), rows)
+}
+
+fn issue(issue: &ThreadIssue) -> String {
+    match issue {
+        ThreadIssue::Missing { from } => trf("missing from {}", &[from]),
+        ThreadIssue::SenderDiffers => tr("sender differs").to_owned(),
+        ThreadIssue::ContentDiffers => tr("content differs").to_owned(),
+    }
+}
+
+pub fn threads(threads: &[StitchedThread], zone: Zone) -> String {
+    let mut rows = table([
+        tr("Between"),
+        tr("Messages"),
+        tr("Only in first"),
+        tr("Only in second"),
+        tr("Disagreements"),
+    ]);
+    let mut issues = table([tr("Between"), tr("Message"), tr("Sent"), tr("Issues")]);
+    for thread in threads {
+        let between = format!("{} / {}", thread.a, thread.b);
+        rows.add_row(vec![
+            between.clone(),
+            thread.messages.len().to_string(),
+            thread.missing_from(&thread.b).to_string(),
+            thread.missing_from(&thread.a).to_string(),
+            thread.disagreements().to_string(),
+        ]);
+        for msg in thread.messages.iter().filter(|m| !m.issues.is_empty()) {
+            let found: Vec<_> = msg.issues.iter().map(issue).collect();
+            issues.add_row(vec![
+                between.clone(),
+                msg.id.to_string(),
+                zone.format(&msg.send_time),
+                found.join(", "),
+            ]);
+        }
+    }
+    titled(tr("Stitched threads"), rows) + &titled(tr("Sync issues"), issues)
+}
+
+pub fn conversation(thread: &StitchedThread, zone: Zone) -> String {
+    let read = |time: Option<&Option<DateTime<Utc>>>| match time {
+        None => tr("no copy").to_owned(),
+        Some(None) => tr("unread").to_owned(),
+        Some(Some(time)) => zone.format(time),
+    };
+    let mut rows = table([
+        tr("Sent").to_owned(),
+        tr("From").to_owned(),
+        tr("Type").to_owned(),
+        tr("Content").to_owned(),
+        trf("Read per {}", &[&thread.a]),
+        trf("Read per {}", &[&thread.b]),
+        tr("Issues").to_owned(),
+    ]);
+    for msg in &thread.messages {
+        let mut content: String = msg.content.chars().take(60).collect();
+        if content.len() < msg.content.len() {
+            content.push('…');
+        }
+        let found: Vec<_> = msg.issues.iter().map(issue).collect();
+        rows.add_row(vec![
+            zone.format(&msg.send_time),
+            msg.sender_id.to_string(),
+            msg.message_type.clone(),
+            content,
+            read(msg.read_receipts.get(&thread.a)),
+            read(msg.read_receipts.get(&thread.b)),
+            found.join(", "),
+        ]);
+    }
+    titled(&trf("{} and {}", &[&thread.a, &thread.b]), rows)
+}
+
+pub fn headless(hosts: &[HeadlessHost], zone: Zone) -> String {
+    let mut rows = table([
+        tr("Host"),
+        tr("Name"),
+        tr("World"),
+        tr("Began"),
+        tr("Uptime"),
+    ]);
+    for host in hosts {
+        let name = with_former(&host.host_name, &host.host_usernames);
+        for session in &host.sessions {
+            let uptime = session.uptime();
+            rows.add_row(vec![
+                host.host_user_id.to_string(),
+                name.clone(),
+                session.name.to_string(),
+                zone.format(&session.begin),
+                format!("{}h{:02}m", uptime.num_hours(), uptime.num_minutes() % 60),
+            ]);
+        }
+    }
+    rows.to_string() + "\n"
+}
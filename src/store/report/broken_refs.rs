@@ -0,0 +1,192 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::{AssetUri, Backup, Manifest, Record, WellKnownAssetKind},
+    naming, render, RcStr,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+// How a record refers to an asset.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum RefRole {
+    // The record's own asset, like its manifest or texture. Without it the item is gone.
+    Asset,
+    Thumbnail,
+    // Listed in the record's neosDBmanifest.
+    Manifest,
+    // Used inside the record's manifest, by a component on a slot.
+    Nested { slot: String, component: String },
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingRef {
+    pub hash: RcStr,
+    pub role: RefRole,
+    pub bytes: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenRecord {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub name: RcStr,
+    pub owner_id: RcStr,
+    pub owner_name: RcStr,
+    pub path: Vec<RcStr>,
+    // Its own asset is missing, so nothing of it can be loaded. Otherwise it loads with
+    // parts missing.
+    pub unrecoverable: bool,
+    pub missing: Vec<MissingRef>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenRefs {
+    pub records_checked: u64,
+    // Manifests opened to find what they use, and those that failed to open.
+    pub manifests_scanned: u64,
+    pub unreadable_manifests: Vec<RcStr>,
+    pub unrecoverable: u64,
+    // Distinct hashes missing, however many records share them.
+    pub missing_assets: u64,
+    // A metadata-only backup, where every reference is broken and none is reported.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub asset_store_unavailable: bool,
+    // By owner, then account, folder and name.
+    pub records: Vec<BrokenRecord>,
+}
+
+// Every record referring to an asset that no asset root has, through its own fields or
+// inside its manifest. Every present manifest is opened, which takes a while.
+pub fn broken_refs(backup: &Backup) -> BrokenRefs {
+    let mut result = BrokenRefs {
+        asset_store_unavailable: !backup.has_asset_store(),
+        ..Default::default()
+    };
+    let records: Vec<_> = backup
+        .accounts
+        .iter()
+        .flat_map(|(name, account)| account.records.values().map(move |rec| (name, rec)))
+        .collect();
+    result.records_checked = records.len() as u64;
+    if result.asset_store_unavailable {
+        return result;
+    }
+
+    let checked: Vec<_> = records
+        .into_par_iter()
+        .map(|(account, rec)| (account, rec, missing_refs(backup, rec)))
+        .collect();
+    let mut missing_assets = BTreeSet::new();
+    for (account, rec, (missing, scanned)) in checked {
+        match scanned {
+            Some(true) => result.manifests_scanned += 1,
+            Some(false) => {
+                result.manifests_scanned += 1;
+                if let Some(id) = rec.asset_uri.as_ref().and_then(AssetUri::asset_id) {
+                    result.unreadable_manifests.push(id.clone());
+                }
+            }
+            None => {}
+        }
+        if missing.is_empty() {
+            continue;
+        }
+        missing_assets.extend(missing.iter().map(|m| m.hash.clone()));
+        let unrecoverable = missing.iter().any(|m| m.role == RefRole::Asset);
+        result.unrecoverable += unrecoverable as u64;
+        result.records.push(BrokenRecord {
+            account: account.clone(),
+            record: rec.id.clone(),
+            name: rec.name.clone(),
+            owner_id: rec.owner_id.clone(),
+            owner_name: rec.owner_name.clone(),
+            path: rec.path.clone(),
+            unrecoverable,
+            missing,
+        });
+    }
+    result.missing_assets = missing_assets.len() as u64;
+    result.unreadable_manifests.sort();
+    result.unreadable_manifests.dedup();
+    result.records.sort_by(|a, b| {
+        (&a.owner_id, &a.account, &a.path, &a.name).cmp(&(
+            &b.owner_id,
+            &b.account,
+            &b.path,
+            &b.name,
+        ))
+    });
+    result
+}
+
+// The record's missing references, each hash once under the first role found for it,
+// and whether its manifest was opened and parsed (None when there was none to open).
+fn missing_refs(backup: &Backup, rec: &Record) -> (Vec<MissingRef>, Option<bool>) {
+    let sizes: BTreeMap<_, _> = rec
+        .neos_db_manifest
+        .iter()
+        .map(|a| (a.hash.clone(), a.bytes))
+        .collect();
+    let mut refs = BTreeMap::<RcStr, RefRole>::new();
+    let own = [
+        (&rec.asset_uri, RefRole::Asset),
+        (&rec.thumbnail_uri, RefRole::Thumbnail),
+    ];
+    for (uri, role) in own {
+        if let Some(id) = uri.as_ref().and_then(AssetUri::asset_id) {
+            refs.entry(id.clone()).or_insert(role);
+        }
+    }
+    for hash in sizes.keys() {
+        refs.entry(hash.clone()).or_insert(RefRole::Manifest);
+    }
+
+    let mut scanned = None;
+    if let Some(AssetUri::SZBson(asset)) = &rec.asset_uri {
+        if backup.has_asset(&asset.0) {
+            let manifest: Result<Manifest, _> = asset.open(backup);
+            scanned = Some(manifest.is_ok());
+            for (hash, (slot, component)) in manifest
+                .map(|m| naming::asset_sites(&m))
+                .unwrap_or_default()
+            {
+                refs.entry(hash)
+                    .or_insert(RefRole::Nested { slot, component });
+            }
+        }
+    }
+
+    let mut missing: Vec<_> = refs
+        .into_iter()
+        .filter(|(hash, _)| !backup.has_asset(hash))
+        .map(|(hash, role)| MissingRef {
+            bytes: sizes.get(&hash).copied(),
+            hash,
+            role,
+        })
+        .collect();
+    missing.sort_by(|a, b| (&a.role, &a.hash).cmp(&(&b.role, &b.hash)));
+    (missing, scanned)
+}
+
+pub struct BrokenRefsReport;
+
+impl Report for BrokenRefsReport {
+    fn name(&self) -> &'static str {
+        "broken-refs"
+    }
+
+    fn title(&self) -> &'static str {
+        "Records referring to missing assets"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let broken = broken_refs(backup);
+        ReportData::new(&broken, render::broken_refs(&broken))
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::{AssetUri, Backup, SZBson},
+    render,
+    sample::Estimate,
+    RcStr,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{collections::BTreeMap, fs};
+
+// Brotli settings the brson estimate is measured at, a middle ground between what the
+// game writes and how long measuring a whole backup takes.
+pub const BROTLI_QUALITY: u32 = 9;
+pub const BROTLI_WINDOW: u32 = 22;
+
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Sizes {
+    // As stored, LZMA compressed.
+    pub compressed: u64,
+    pub uncompressed: u64,
+    // Brotli compressed, as a brson conversion would store it.
+    pub brson: u64,
+}
+
+impl Sizes {
+    pub fn savings(&self) -> i64 {
+        self.compressed as i64 - self.brson as i64
+    }
+
+    fn add(&mut self, other: &Sizes) {
+        self.compressed += other.compressed;
+        self.uncompressed += other.uncompressed;
+        self.brson += other.brson;
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordCompression {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub name: RcStr,
+    pub asset: RcStr,
+    pub sizes: Sizes,
+    pub savings: i64,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Compression {
+    // Each distinct manifest counted once, however many records share it.
+    pub assets: u64,
+    pub totals: Sizes,
+    pub savings: i64,
+    // Set when the totals were scaled up from a sample; the records are only the sample's.
+    pub estimate: Option<Estimate>,
+    // Manifests that failed to decompress.
+    pub unreadable: Vec<RcStr>,
+    // Most savings first.
+    pub records: Vec<RecordCompression>,
+}
+
+// What each record's 7zbson manifest takes now against its decompressed size and a
+// brotli recompression of it, so a brson conversion can be weighed before running it.
+// Every manifest is decompressed and recompressed, which takes a while on big backups.
+pub fn compression(backup: &Backup) -> Compression {
+    let mut users = BTreeMap::<RcStr, Vec<(RcStr, RcStr, RcStr)>>::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let Some(AssetUri::SZBson(asset)) = &rec.asset_uri else {
+                continue;
+            };
+            if backup.manifest_sample.contains(&asset.0) {
+                users.entry(asset.0.clone()).or_default().push((
+                    account_name.clone(),
+                    rec.id.clone(),
+                    rec.name.clone(),
+                ));
+            }
+        }
+    }
+
+    let measured: Vec<_> = users
+        .keys()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|asset| {
+            let path = backup.asset_path(asset)?;
+            Some((
+                asset.clone(),
+                measure(backup, asset, fs::metadata(path).ok()?.len()),
+            ))
+        })
+        .collect();
+
+    let mut result = Compression::default();
+    for (asset, sizes) in measured {
+        let Some(sizes) = sizes else {
+            result.unreadable.push(asset);
+            continue;
+        };
+        result.assets += 1;
+        result.totals.add(&sizes);
+        for (account, record, name) in users.remove(&asset).unwrap_or_default() {
+            result.records.push(RecordCompression {
+                account,
+                record,
+                name,
+                asset: asset.clone(),
+                sizes,
+                savings: sizes.savings(),
+            });
+        }
+    }
+    result.estimate = backup.manifest_sample.estimate();
+    if let Some(estimate) = &result.estimate {
+        result.assets = estimate.extrapolate(result.assets);
+        let totals = &mut result.totals;
+        totals.compressed = estimate.extrapolate(totals.compressed);
+        totals.uncompressed = estimate.extrapolate(totals.uncompressed);
+        totals.brson = estimate.extrapolate(totals.brson);
+    }
+    result.savings = result.totals.savings();
+    result.records.sort_by(|a, b| {
+        (std::cmp::Reverse(a.savings), &a.account, &a.record).cmp(&(
+            std::cmp::Reverse(b.savings),
+            &b.account,
+            &b.record,
+        ))
+    });
+    result
+}
+
+fn measure(backup: &Backup, asset: &RcStr, compressed: u64) -> Option<Sizes> {
+    let raw = SZBson(asset.clone()).read_raw(backup).ok()?;
+    let mut brson = Vec::new();
+    let params = brotli::enc::BrotliEncoderParams {
+        quality: BROTLI_QUALITY as i32,
+        lgwin: BROTLI_WINDOW as i32,
+        ..Default::default()
+    };
+    brotli::BrotliCompress(&mut raw.as_slice(), &mut brson, &params).ok()?;
+    Some(Sizes {
+        compressed,
+        uncompressed: raw.len() as u64,
+        brson: brson.len() as u64,
+    })
+}
+
+pub struct CompressionReport;
+
+impl Report for CompressionReport {
+    fn name(&self) -> &'static str {
+        "compression"
+    }
+
+    fn title(&self) -> &'static str {
+        "Manifest compression and brson savings"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let compression = compression(backup);
+        ReportData::new(&compression, render::compression(&compression))
+    }
+}
@@ -0,0 +1,90 @@
+use crate::store::{
+    aliases::Aliases,
+    backup::{Backup, Contact, FriendStatus},
+    labels::Labels,
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ContactFlag {
+    // is_accepted is set but the status says otherwise.
+    AcceptedWithoutStatus,
+    // The status says accepted but is_accepted was never set.
+    StatusWithoutAccept,
+    Blocked,
+    Ignored,
+    // Messages were exchanged with someone who is not a friend.
+    MessagesWithoutFriendship,
+}
+
+pub fn check_contact(contact: &Contact, message_count: usize) -> Vec<ContactFlag> {
+    let mut flags = Vec::new();
+    let accepted_status = contact.friend_status == FriendStatus::Accepted;
+    if contact.is_accepted && !accepted_status {
+        flags.push(ContactFlag::AcceptedWithoutStatus);
+    }
+    if accepted_status && !contact.is_accepted {
+        flags.push(ContactFlag::StatusWithoutAccept);
+    }
+    match contact.friend_status {
+        FriendStatus::Blocked => flags.push(ContactFlag::Blocked),
+        FriendStatus::Ignored => flags.push(ContactFlag::Ignored),
+        _ => {}
+    }
+    if message_count > 0 && !contact.is_accepted && !accepted_status {
+        flags.push(ContactFlag::MessagesWithoutFriendship);
+    }
+    flags
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactInteraction {
+    pub account: RcStr,
+    pub contact_id: RcStr,
+    pub username: RcStr,
+    pub friend_status: FriendStatus,
+    pub is_accepted: bool,
+    pub flags: Vec<ContactFlag>,
+    pub message_count: usize,
+    pub labels: Vec<RcStr>,
+    pub last_message: Option<DateTime<Utc>>,
+    pub last_status_change: Option<DateTime<Utc>>,
+    pub last_interaction: Option<DateTime<Utc>>,
+}
+
+// Most recent first, contacts with no known interaction last.
+pub fn last_interactions(
+    backup: &Backup,
+    aliases: &Aliases,
+    labels: &Labels,
+) -> Vec<ContactInteraction> {
+    let mut interactions = Vec::new();
+    for (account_name, account) in &backup.accounts {
+        for contact in account.contacts.values() {
+            let messages = account.messages.get(&contact.id);
+            let message_count = messages.map_or(0, Vec::len);
+            let last_sent = messages.and_then(|m| m.iter().map(|m| m.send_time).max());
+            let last_message = contact.latest_message_time.max(last_sent);
+            let last_status_change = contact.user_status.last_status_change;
+            interactions.push(ContactInteraction {
+                account: account_name.clone(),
+                contact_id: contact.id.clone(),
+                username: aliases.display(&contact.id, &contact.friend_username),
+                friend_status: contact.friend_status.clone(),
+                is_accepted: contact.is_accepted,
+                flags: check_contact(contact, message_count),
+                message_count,
+                labels: labels.of(&contact.id).cloned().collect(),
+                last_message,
+                last_status_change,
+                last_interaction: last_message.max(last_status_change),
+            });
+        }
+    }
+    interactions.sort_by_key(|i| std::cmp::Reverse(i.last_interaction));
+    interactions
+}
@@ -0,0 +1,85 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::{AssetUri, Backup, Manifest, WellKnownAssetKind},
+    identity::IdentityMap,
+    render, RcStr,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyOf {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub name: RcStr,
+    pub manifest: RcStr,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyGroup {
+    // Persistent-ID of the root slot every record in the group shares.
+    pub root: RcStr,
+    pub records: Vec<CopyOf>,
+    // Distinct manifests among the copies, more than one means they have diverged.
+    pub versions: u64,
+}
+
+// Records saved from the same object, found by their root slot's Persistent-ID.
+pub fn copies(backup: &Backup) -> Vec<CopyGroup> {
+    let mut roots = BTreeMap::<RcStr, Option<RcStr>>::new();
+    let mut groups = BTreeMap::<RcStr, Vec<CopyOf>>::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let Some(AssetUri::SZBson(asset)) = &rec.asset_uri else {
+                continue;
+            };
+            let root = roots.entry(asset.0.clone()).or_insert_with(|| {
+                let res: Result<Manifest, _> = asset.open(backup);
+                res.ok().and_then(|m| IdentityMap::build(&m).root)
+            });
+            if let Some(root) = root {
+                groups.entry(root.clone()).or_default().push(CopyOf {
+                    account: account_name.clone(),
+                    record: rec.id.clone(),
+                    name: rec.name.clone(),
+                    manifest: asset.0.clone(),
+                });
+            }
+        }
+    }
+
+    let mut out: Vec<_> = groups
+        .into_iter()
+        .filter(|(_, records)| records.len() > 1)
+        .map(|(root, records)| CopyGroup {
+            root,
+            versions: records
+                .iter()
+                .map(|r| &r.manifest)
+                .collect::<BTreeSet<_>>()
+                .len() as u64,
+            records,
+        })
+        .collect();
+    out.sort_by_key(|g| std::cmp::Reverse(g.records.len()));
+    out
+}
+
+pub struct CopiesReport;
+
+impl Report for CopiesReport {
+    fn name(&self) -> &'static str {
+        "copies"
+    }
+
+    fn title(&self) -> &'static str {
+        "Copies of the same object"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let groups = copies(backup);
+        ReportData::new(&groups, render::copies(&groups))
+    }
+}
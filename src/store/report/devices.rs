@@ -0,0 +1,75 @@
+use super::{Report, ReportData};
+use crate::store::{backup::Backup, render, RcStr};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceStats {
+    // From each contact's last known status.
+    pub contact_devices: BTreeMap<RcStr, u64>,
+    pub contacts_mobile: u64,
+    // From users listed in sessions captured in contact statuses, once per session and user.
+    pub session_user_devices: BTreeMap<RcStr, u64>,
+}
+
+// FrooxEngine's OutputDevice enum, as session users report it.
+pub fn output_device_name(device: i32) -> &'static str {
+    match device {
+        0 => "Unknown",
+        1 => "Headless",
+        2 => "Screen",
+        3 => "VR",
+        4 => "Screen360",
+        _ => "Other",
+    }
+}
+
+pub fn device_stats(backup: &Backup) -> DeviceStats {
+    let mut stats = DeviceStats::default();
+    let mut seen_users = BTreeSet::new();
+    for account in backup.accounts.values() {
+        for contact in account.contacts.values() {
+            let status = &contact.user_status;
+            *stats
+                .contact_devices
+                .entry(status.output_device.clone())
+                .or_default() += 1;
+            if status.is_mobile {
+                stats.contacts_mobile += 1;
+            }
+            let sessions = status
+                .current_session
+                .iter()
+                .chain(status.active_sessions.iter().flatten());
+            for session in sessions {
+                for user in &session.session_users {
+                    if seen_users.insert((session.session_id.clone(), user.user_id.clone())) {
+                        *stats
+                            .session_user_devices
+                            .entry(output_device_name(user.output_device).to_owned().into())
+                            .or_default() += 1;
+                    }
+                }
+            }
+        }
+    }
+    stats
+}
+
+pub struct DevicesReport;
+
+impl Report for DevicesReport {
+    fn name(&self) -> &'static str {
+        "devices"
+    }
+
+    fn title(&self) -> &'static str {
+        "Devices"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let stats = device_stats(backup);
+        ReportData::new(&stats, render::devices(&stats))
+    }
+}
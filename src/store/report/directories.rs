@@ -0,0 +1,30 @@
+use super::{Report, ReportData};
+use crate::store::{backup::Backup, inventory::Inventory, render, RcStr};
+use std::collections::BTreeMap;
+
+const TOP: usize = 20;
+
+pub struct DirectoriesReport;
+
+impl Report for DirectoriesReport {
+    fn name(&self) -> &'static str {
+        "directories"
+    }
+
+    fn title(&self) -> &'static str {
+        "Largest inventory folders"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let mut text = String::new();
+        let mut accounts = BTreeMap::<RcStr, _>::new();
+        for (name, account) in &backup.accounts {
+            let mut sizes = Inventory::build(account).folder_sizes(account);
+            sizes.sort_by_key(|size| std::cmp::Reverse(size.bytes));
+            sizes.truncate(TOP);
+            text += &render::folder_sizes(name, &sizes);
+            accounts.insert(name.clone(), sizes);
+        }
+        ReportData::new(&accounts, text)
+    }
+}
@@ -0,0 +1,184 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::{AssetUri, Backup, Component, FieldValue, Manifest, Slot, WellKnownAssetKind},
+    render, RcStr,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+const TOP: usize = 20;
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DisabledContent {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub name: RcStr,
+    pub slots: u64,
+    // Slots that are inactive themselves or sit under an inactive parent.
+    pub inactive_slots: u64,
+    pub components: u64,
+    // Disabled components plus every component on an inactive slot.
+    pub disabled_components: u64,
+    // Asset bytes only reachable from disabled content, an estimate of what pruning frees.
+    pub disabled_bytes: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Default)]
+struct Walk {
+    slots: u64,
+    inactive_slots: u64,
+    components: u64,
+    disabled_components: u64,
+    live_refs: BTreeSet<RcStr>,
+    disabled_refs: BTreeSet<RcStr>,
+}
+
+// Records with any disabled content, the most reclaimable bytes first.
+pub fn disabled_content(backup: &Backup) -> Vec<DisabledContent> {
+    let mut out = Vec::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let Some(AssetUri::SZBson(asset)) = &rec.asset_uri else {
+                continue;
+            };
+            let res: Result<Manifest, _> = asset.open(backup);
+            let Ok(manifest) = res else {
+                continue;
+            };
+            let sizes: BTreeMap<_, _> = rec
+                .neos_db_manifest
+                .iter()
+                .map(|a| (a.hash.clone(), a.bytes))
+                .collect();
+            let walk = walk_manifest(&manifest);
+            if walk.inactive_slots + walk.disabled_components == 0 {
+                continue;
+            }
+            let disabled_bytes = walk
+                .disabled_refs
+                .difference(&walk.live_refs)
+                .filter_map(|hash| sizes.get(hash))
+                .sum();
+            out.push(DisabledContent {
+                account: account_name.clone(),
+                record: rec.id.clone(),
+                name: rec.name.clone(),
+                slots: walk.slots,
+                inactive_slots: walk.inactive_slots,
+                components: walk.components,
+                disabled_components: walk.disabled_components,
+                disabled_bytes,
+                total_bytes: sizes.values().sum(),
+            });
+        }
+    }
+    out.sort_by_key(|d| std::cmp::Reverse((d.disabled_bytes, d.disabled_components)));
+    out
+}
+
+fn walk_manifest(manifest: &Manifest) -> Walk {
+    let mut walk = Walk::default();
+    // Asset providers live outside the slot tree, components point at them by ID.
+    let providers: BTreeMap<&RcStr, BTreeSet<RcStr>> = manifest
+        .assets
+        .iter()
+        .flatten()
+        .map(|provider| (&provider.data.id, asset_hashes(provider)))
+        .collect();
+    let mut live = Vec::new();
+    let mut disabled = Vec::new();
+    if let Some(slot) = &manifest.object {
+        walk.slot(slot, false, &mut live, &mut disabled);
+    }
+    for (refs, strings) in [
+        (&mut walk.live_refs, live),
+        (&mut walk.disabled_refs, disabled),
+    ] {
+        for s in strings {
+            match providers.get(&s) {
+                Some(hashes) => refs.extend(hashes.iter().cloned()),
+                None => refs.extend(hash_of(&s)),
+            }
+        }
+    }
+    walk
+}
+
+impl Walk {
+    fn slot(
+        &mut self,
+        slot: &Slot,
+        parent_inactive: bool,
+        live: &mut Vec<RcStr>,
+        disabled: &mut Vec<RcStr>,
+    ) {
+        let inactive = parent_inactive || !*slot.active.data();
+        self.slots += 1;
+        self.inactive_slots += u64::from(inactive);
+        for component in slot.components.data() {
+            self.components += 1;
+            let off = inactive || !*component.data.enabled.data();
+            self.disabled_components += u64::from(off);
+            let strings = if off { &mut *disabled } else { &mut *live };
+            for value in component.data.fields.values() {
+                collect_strings(value, strings);
+            }
+        }
+        for child in &slot.children {
+            self.slot(child, inactive, live, disabled);
+        }
+    }
+}
+
+fn asset_hashes(provider: &Component) -> BTreeSet<RcStr> {
+    let mut strings = Vec::new();
+    for value in provider.data.fields.values() {
+        collect_strings(value, &mut strings);
+    }
+    strings.iter().filter_map(|s| hash_of(s)).collect()
+}
+
+pub(crate) fn collect_strings(value: &FieldValue, out: &mut Vec<RcStr>) {
+    match value {
+        FieldValue::Str(s) => out.push(s.clone()),
+        FieldValue::Dunno(bson) => collect_bson_strings(bson, out),
+        _ => {}
+    }
+}
+
+fn collect_bson_strings(value: &bson::Bson, out: &mut Vec<RcStr>) {
+    match value {
+        bson::Bson::String(s) => out.push(s.clone().into()),
+        bson::Bson::Array(values) => values.iter().for_each(|v| collect_bson_strings(v, out)),
+        bson::Bson::Document(doc) => doc.values().for_each(|v| collect_bson_strings(v, out)),
+        _ => {}
+    }
+}
+
+// @neosdb:///<hash>.<ext> -> <hash>, manifests prefix URLs with an @.
+pub(crate) fn hash_of(url: &str) -> Option<RcStr> {
+    let url = url.strip_prefix('@').unwrap_or(url);
+    let rest = url.strip_prefix("neosdb:///")?;
+    let hash = rest.split('.').next().unwrap_or(rest);
+    Some(hash.to_owned().into())
+}
+
+pub struct DisabledReport;
+
+impl Report for DisabledReport {
+    fn name(&self) -> &'static str {
+        "disabled"
+    }
+
+    fn title(&self) -> &'static str {
+        "Disabled content"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let mut records = disabled_content(backup);
+        records.truncate(TOP);
+        ReportData::new(&records, render::disabled(&records))
+    }
+}
@@ -0,0 +1,127 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::{AssetUri, Backup},
+    render, RcStr,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupStorage {
+    pub id: RcStr,
+    // None for a group only known from the records it owns, no member exported it.
+    pub name: Option<RcStr>,
+    pub admin_user_id: Option<RcStr>,
+    pub quota_bytes: Option<u64>,
+    // What the cloud said the group uses, against what its records in the backup add up to.
+    pub used_bytes: Option<u64>,
+    pub records: u64,
+    pub bytes: u64,
+    // The account folders the group's records were exported into.
+    pub exported_by: BTreeSet<RcStr>,
+    // Group records something links to that no account exported.
+    pub missing_records: BTreeSet<RcStr>,
+    // The part of used_bytes no record in the backup accounts for.
+    pub unaccounted_bytes: u64,
+}
+
+// Storage per group, from the groups members exported and any record a group owns,
+// wherever it was exported. Each asset counts once per group.
+pub fn group_storage(backup: &Backup) -> Vec<GroupStorage> {
+    let mut groups = BTreeMap::<RcStr, GroupStorage>::new();
+    let blank = |id: &RcStr| GroupStorage {
+        id: id.clone(),
+        name: None,
+        admin_user_id: None,
+        quota_bytes: None,
+        used_bytes: None,
+        records: 0,
+        bytes: 0,
+        exported_by: BTreeSet::new(),
+        missing_records: BTreeSet::new(),
+        unaccounted_bytes: 0,
+    };
+
+    for (id, group) in backup.groups() {
+        let storage = groups.entry(id.clone()).or_insert_with(|| blank(id));
+        storage.name = Some(group.name.clone());
+        storage.admin_user_id = Some(group.admin_user_id.clone());
+        storage.quota_bytes = Some(group.quota_bytes);
+        storage.used_bytes = Some(group.used_bytes);
+    }
+
+    let mut owned = BTreeMap::<RcStr, BTreeSet<RcStr>>::new();
+    let mut assets = BTreeMap::<RcStr, BTreeMap<RcStr, u64>>::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            if !rec.is_group_owned() {
+                continue;
+            }
+            let storage = groups
+                .entry(rec.owner_id.clone())
+                .or_insert_with(|| blank(&rec.owner_id));
+            storage.exported_by.insert(account_name.clone());
+            owned
+                .entry(rec.owner_id.clone())
+                .or_default()
+                .insert(rec.id.clone());
+            let sizes = assets.entry(rec.owner_id.clone()).or_default();
+            for asset in &rec.neos_db_manifest {
+                sizes.insert(asset.hash.clone(), asset.bytes);
+            }
+        }
+    }
+
+    for account in backup.accounts.values() {
+        for rec in account.records.values() {
+            let Some(AssetUri::NeosRec(target)) = &rec.asset_uri else {
+                continue;
+            };
+            if !target.group_id.starts_with("G-") {
+                continue;
+            }
+            let found = owned
+                .get(&target.group_id)
+                .is_some_and(|records| records.contains(&target.asset_id));
+            if !found {
+                groups
+                    .entry(target.group_id.clone())
+                    .or_insert_with(|| blank(&target.group_id))
+                    .missing_records
+                    .insert(target.asset_id.clone());
+            }
+        }
+    }
+
+    let mut groups: Vec<_> = groups
+        .into_values()
+        .map(|mut storage| {
+            storage.records = owned.get(&storage.id).map_or(0, |r| r.len() as u64);
+            storage.bytes = assets.get(&storage.id).map_or(0, |a| a.values().sum());
+            storage.unaccounted_bytes = storage
+                .used_bytes
+                .map_or(0, |used| used.saturating_sub(storage.bytes));
+            storage
+        })
+        .collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.bytes));
+    groups
+}
+
+pub struct GroupsReport;
+
+impl Report for GroupsReport {
+    fn name(&self) -> &'static str {
+        "groups"
+    }
+
+    fn title(&self) -> &'static str {
+        "Group storage"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let groups = group_storage(backup);
+        ReportData::new(&groups, render::group_storage(&groups))
+    }
+}
@@ -0,0 +1,56 @@
+use super::sessions::captured_sessions;
+use crate::store::{aliases::Aliases, backup::Backup, RcStr};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSpan {
+    pub session_id: RcStr,
+    pub name: RcStr,
+    pub begin: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl SessionSpan {
+    pub fn uptime(&self) -> Duration {
+        self.last_seen - self.begin
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadlessHost {
+    pub host_user_id: RcStr,
+    // Current name, host_usernames has every name the sessions were hosted under.
+    pub host_name: RcStr,
+    pub host_usernames: BTreeSet<RcStr>,
+    pub worlds: BTreeSet<RcStr>,
+    pub sessions: Vec<SessionSpan>,
+}
+
+pub fn headless_hosts(backup: &Backup, aliases: &Aliases) -> Vec<HeadlessHost> {
+    let mut hosts = BTreeMap::<RcStr, HeadlessHost>::new();
+    for session in captured_sessions(backup).into_values() {
+        if !session.headless_host {
+            continue;
+        }
+        let host = hosts.entry(session.host_user_id.clone()).or_default();
+        host.host_name = aliases.display(&session.host_user_id, &session.host_username);
+        host.host_user_id = session.host_user_id;
+        host.host_usernames.insert(session.host_username);
+        host.worlds.insert(session.name.clone());
+        host.sessions.push(SessionSpan {
+            session_id: session.session_id,
+            name: session.name,
+            begin: session.session_begin_time,
+            last_seen: session.last_update,
+        });
+    }
+    let mut hosts: Vec<_> = hosts.into_values().collect();
+    for host in &mut hosts {
+        host.sessions.sort_by_key(|s| s.begin);
+    }
+    hosts
+}
@@ -0,0 +1,33 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::Backup,
+    inventory::{CyclicLink, Inventory},
+    render, RcStr,
+};
+use std::collections::BTreeMap;
+
+pub fn link_cycles(backup: &Backup) -> BTreeMap<RcStr, Vec<CyclicLink>> {
+    backup
+        .accounts
+        .iter()
+        .map(|(name, account)| (name.clone(), Inventory::build(account).cyclic_links()))
+        .filter(|(_, links)| !links.is_empty())
+        .collect()
+}
+
+pub struct LinkCyclesReport;
+
+impl Report for LinkCyclesReport {
+    fn name(&self) -> &'static str {
+        "link-cycles"
+    }
+
+    fn title(&self) -> &'static str {
+        "Inventory links that form cycles"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let cycles = link_cycles(backup);
+        ReportData::new(&cycles, render::link_cycles(&cycles))
+    }
+}
@@ -0,0 +1,112 @@
+pub mod broken_refs;
+pub mod compression;
+pub mod contacts;
+pub mod copies;
+pub mod devices;
+pub mod directories;
+pub mod disabled;
+pub mod groups;
+pub mod headless;
+pub mod link_cycles;
+pub mod population;
+pub mod relays;
+pub mod screenshots;
+pub mod sessions;
+pub mod shapes;
+pub mod similar_images;
+pub mod stranded;
+pub mod strings;
+pub mod threads;
+
+use super::{
+    backup::Backup,
+    schema::{self, SCHEMA_VERSION},
+    timings::{self, Phase},
+};
+use serde::Serialize;
+use serde_json::Value;
+
+// A report computed from the backup alone, so `stats --report` and the dashboard can run
+// any of them by name. Adding one is a file with an impl plus an entry in REPORTS.
+pub trait Report: Sync {
+    fn name(&self) -> &'static str;
+    fn title(&self) -> &'static str;
+    fn compute(&self, backup: &Backup) -> ReportData;
+
+    // compute, counted against the report compute phase for --timings.
+    fn run(&self, backup: &Backup) -> ReportData {
+        let mut data = timings::time(Phase::Compute, 0, || self.compute(backup));
+        data.kind = self.name();
+        data
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Html,
+}
+
+#[derive(Debug)]
+pub struct ReportData {
+    pub value: Value,
+    // The report's name once run, for the JSON.
+    pub kind: &'static str,
+    text: String,
+}
+
+impl ReportData {
+    pub fn new<T: Serialize>(data: &T, text: String) -> Self {
+        Self {
+            value: serde_json::to_value(data).unwrap_or(Value::Null),
+            kind: "",
+            text,
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.text.clone(),
+            ReportFormat::Json => schema::to_value(self.kind, &self.value, SCHEMA_VERSION)
+                .and_then(|value| serde_json::to_string_pretty(&value))
+                .unwrap_or_default(),
+            ReportFormat::Html => format!("<pre>{}</pre>", escape_html(&self.text)),
+        }
+    }
+}
+
+pub static REPORTS: &[&dyn Report] = &[
+    &broken_refs::BrokenRefsReport,
+    &compression::CompressionReport,
+    &copies::CopiesReport,
+    &devices::DevicesReport,
+    &directories::DirectoriesReport,
+    &disabled::DisabledReport,
+    &groups::GroupsReport,
+    &link_cycles::LinkCyclesReport,
+    &population::PopulationReport,
+    &relays::RelaysReport,
+    &screenshots::ScreenshotsReport,
+    &shapes::ShapesReport,
+    // Every asset would be unreadable without a decoder.
+    #[cfg(feature = "images")]
+    &similar_images::SimilarImagesReport,
+    &stranded::StrandedReport,
+    &strings::StringsReport,
+    &threads::ThreadsReport,
+];
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+    REPORTS.iter().map(|r| r.name())
+}
+
+pub fn find(name: &str) -> Option<&'static dyn Report> {
+    REPORTS.iter().copied().find(|r| r.name() == name)
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,114 @@
+use super::{
+    sessions::{session_snapshots, SnapshotSource},
+    Report, ReportData,
+};
+use crate::store::{
+    backup::Backup,
+    render::{self, Zone},
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Attendance of one world across the snapshots of sessions an account in the backup
+// hosted or was invited to.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldPopulation {
+    pub world: RcStr,
+    pub sessions: u64,
+    pub hosted_sessions: u64,
+    pub snapshots: u64,
+    // Users listed in the session, present or not.
+    pub peak_users: u64,
+    pub average_users: f64,
+    pub peak_active: u64,
+    pub average_active: f64,
+    // The largest user limit any of the sessions had.
+    pub max_users: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct Tally {
+    sessions: BTreeSet<RcStr>,
+    hosted: BTreeSet<RcStr>,
+    users: Vec<u64>,
+    active: Vec<u64>,
+    max_users: u64,
+    seen: Vec<DateTime<Utc>>,
+}
+
+// Per world name, the most attended first.
+pub fn world_population(backup: &Backup) -> Vec<WorldPopulation> {
+    let snapshots = session_snapshots(backup);
+    let invited: BTreeSet<_> = snapshots
+        .iter()
+        .filter(|(source, _)| *source == SnapshotSource::Invite)
+        .map(|(_, session)| session.session_id.clone())
+        .collect();
+
+    // Status and invite copies of the same moment count once.
+    let mut counted = BTreeSet::new();
+    let mut worlds = BTreeMap::<RcStr, Tally>::new();
+    for (_, session) in &snapshots {
+        let hosted = backup.accounts.contains_key(&session.host_user_id);
+        if !hosted && !invited.contains(&session.session_id) {
+            continue;
+        }
+        if !counted.insert((session.session_id.clone(), session.last_update)) {
+            continue;
+        }
+        let tally = worlds.entry(session.name.clone()).or_default();
+        tally.sessions.insert(session.session_id.clone());
+        if hosted {
+            tally.hosted.insert(session.session_id.clone());
+        }
+        let users = (session.session_users.len() as u64).max(session.joined_users.max(0) as u64);
+        tally.users.push(users);
+        tally.active.push(session.active_users.max(0) as u64);
+        tally.max_users = tally.max_users.max(session.max_users.max(0) as u64);
+        tally
+            .seen
+            .extend([session.session_begin_time, session.last_update]);
+    }
+
+    let average = |counts: &[u64]| counts.iter().sum::<u64>() as f64 / counts.len() as f64;
+    let mut worlds: Vec<_> = worlds
+        .into_iter()
+        .map(|(world, tally)| WorldPopulation {
+            world,
+            sessions: tally.sessions.len() as u64,
+            hosted_sessions: tally.hosted.len() as u64,
+            snapshots: tally.users.len() as u64,
+            peak_users: tally.users.iter().copied().max().unwrap_or_default(),
+            average_users: average(&tally.users),
+            peak_active: tally.active.iter().copied().max().unwrap_or_default(),
+            average_active: average(&tally.active),
+            max_users: tally.max_users,
+            first_seen: tally.seen.iter().copied().min().unwrap_or_default(),
+            last_seen: tally.seen.iter().copied().max().unwrap_or_default(),
+        })
+        .collect();
+    worlds.sort_by_key(|w| std::cmp::Reverse((w.peak_users, w.sessions)));
+    worlds
+}
+
+pub struct PopulationReport;
+
+impl Report for PopulationReport {
+    fn name(&self) -> &'static str {
+        "population"
+    }
+
+    fn title(&self) -> &'static str {
+        "World population"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let worlds = world_population(backup);
+        ReportData::new(&worlds, render::world_population(&worlds, Zone::Utc))
+    }
+}
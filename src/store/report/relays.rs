@@ -0,0 +1,69 @@
+use super::{sessions::captured_sessions, Report, ReportData};
+use crate::store::{
+    backup::Backup,
+    render::{self, Zone},
+    session_url::{SessionUrl, Transport},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Sessions reachable one way, through one relay, address, NAT id or Steam id.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRoute {
+    pub transport: Transport,
+    pub host: String,
+    pub sessions: u64,
+    // Of those, the ones hosted by an account in the backup.
+    pub own_sessions: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+// Every way captured sessions advertised being reachable, the most used first.
+pub fn session_routes(backup: &Backup) -> Vec<SessionRoute> {
+    let mut routes = BTreeMap::<(Transport, String), SessionRoute>::new();
+    for session in captured_sessions(backup).into_values() {
+        let own = backup.accounts.contains_key(&session.host_user_id);
+        for SessionUrl {
+            transport, host, ..
+        } in session.urls()
+        {
+            let route = routes
+                .entry((transport, host.clone()))
+                .or_insert_with(|| SessionRoute {
+                    transport,
+                    host,
+                    sessions: 0,
+                    own_sessions: 0,
+                    first_seen: session.session_begin_time,
+                    last_seen: session.last_update,
+                });
+            route.sessions += 1;
+            route.own_sessions += u64::from(own);
+            route.first_seen = route.first_seen.min(session.session_begin_time);
+            route.last_seen = route.last_seen.max(session.last_update);
+        }
+    }
+    let mut routes: Vec<_> = routes.into_values().collect();
+    routes.sort_by_key(|r| std::cmp::Reverse(r.sessions));
+    routes
+}
+
+pub struct RelaysReport;
+
+impl Report for RelaysReport {
+    fn name(&self) -> &'static str {
+        "relays"
+    }
+
+    fn title(&self) -> &'static str {
+        "Session routes"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let routes = session_routes(backup);
+        ReportData::new(&routes, render::session_routes(&routes, Zone::Utc))
+    }
+}
@@ -0,0 +1,180 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::Backup,
+    redact::{Redact, Redactor},
+    render::{self, Zone},
+    screenshot::PhotoMetadata,
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Photo {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub name: RcStr,
+    pub metadata: PhotoMetadata,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldPhotos {
+    // The world's name from its latest photo, or its URL if no photo named it.
+    pub world: RcStr,
+    pub location_url: Option<RcStr>,
+    pub first_taken: Option<DateTime<Utc>>,
+    pub last_taken: Option<DateTime<Utc>>,
+    // Photos per UTC day, as YYYY-MM-DD.
+    pub days: BTreeMap<String, u64>,
+    // Photos each user was in.
+    pub people: BTreeMap<RcStr, u64>,
+    // Oldest first.
+    pub photos: Vec<Photo>,
+}
+
+// Every photo record across the accounts, oldest first. Each account keeps its own copy
+// of a photo it was sent, so the same one can turn up under several.
+pub fn photos(backup: &Backup) -> Vec<Photo> {
+    let mut photos = Vec::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let Some(metadata) = rec.photo_metadata() else {
+                continue;
+            };
+            photos.push(Photo {
+                account: account_name.clone(),
+                record: rec.id.clone(),
+                name: rec.name.clone(),
+                metadata,
+            });
+        }
+    }
+    photos.sort_by_key(|p| p.metadata.taken);
+    photos
+}
+
+// Photos by the world they were taken in, the most photographed first. Photos with no
+// location go under "(unknown)".
+pub fn photos_by_world(photos: Vec<Photo>) -> Vec<WorldPhotos> {
+    let mut worlds = BTreeMap::<RcStr, WorldPhotos>::new();
+    for photo in photos {
+        let key = photo
+            .metadata
+            .world_key()
+            .cloned()
+            .unwrap_or_else(|| RcStr::new("(unknown)".to_owned()));
+        let world = worlds.entry(key.clone()).or_insert_with(|| WorldPhotos {
+            world: key,
+            location_url: None,
+            first_taken: None,
+            last_taken: None,
+            days: BTreeMap::new(),
+            people: BTreeMap::new(),
+            photos: Vec::new(),
+        });
+        let meta = &photo.metadata;
+        if let Some(name) = &meta.world_name {
+            world.world = name.clone();
+        }
+        world.location_url = meta.location_url.clone().or(world.location_url.take());
+        if let Some(taken) = meta.taken {
+            world.first_taken = Some(world.first_taken.map_or(taken, |t| t.min(taken)));
+            world.last_taken = Some(world.last_taken.map_or(taken, |t| t.max(taken)));
+            *world
+                .days
+                .entry(taken.format("%Y-%m-%d").to_string())
+                .or_default() += 1;
+        }
+        for user in &meta.users {
+            *world.people.entry(user.clone()).or_default() += 1;
+        }
+        world.photos.push(photo);
+    }
+    let mut worlds: Vec<_> = worlds.into_values().collect();
+    worlds.sort_by_key(|w| std::cmp::Reverse(w.photos.len()));
+    worlds
+}
+
+// Which photos each user was in, by position in the list it was built from.
+#[derive(Debug, Default)]
+pub struct PeopleIndex {
+    pub users: BTreeMap<RcStr, Vec<usize>>,
+}
+
+impl PeopleIndex {
+    pub fn build(photos: &[Photo]) -> Self {
+        let mut index = Self::default();
+        for (i, photo) in photos.iter().enumerate() {
+            for user in &photo.metadata.users {
+                index.users.entry(user.clone()).or_default().push(i);
+            }
+        }
+        index
+    }
+
+    // The photos every one of the users was in, in the list's order. No users gives
+    // every photo.
+    pub fn photos_with<'a, S>(&self, photos: &'a [Photo], users: &[S]) -> Vec<&'a Photo>
+    where
+        S: AsRef<str>,
+    {
+        let Some((first, rest)) = users.split_first() else {
+            return photos.iter().collect();
+        };
+        let of = |user: &S| {
+            self.users
+                .get(&RcStr::new(user.as_ref().to_owned()))
+                .map_or(&[][..], |found| found.as_slice())
+        };
+        of(first)
+            .iter()
+            .filter(|i| rest.iter().all(|user| of(user).contains(i)))
+            .map(|&i| &photos[i])
+            .collect()
+    }
+}
+
+impl Redact for Photo {
+    fn redact(&mut self, r: &Redactor) {
+        self.name = r.text(&self.name);
+        let meta = &mut self.metadata;
+        meta.world_name = meta.world_name.as_ref().map(|name| r.text(name));
+        meta.host_user_id = meta.host_user_id.as_ref().map(|id| r.user_id(id));
+        for user in &mut meta.users {
+            *user = r.user_id(user);
+        }
+    }
+}
+
+impl Redact for WorldPhotos {
+    fn redact(&mut self, r: &Redactor) {
+        self.world = r.text(&self.world);
+        self.people = std::mem::take(&mut self.people)
+            .into_iter()
+            .map(|(id, n)| (r.user_id(&id), n))
+            .collect();
+        for photo in &mut self.photos {
+            photo.redact(r);
+        }
+    }
+}
+
+pub struct ScreenshotsReport;
+
+impl Report for ScreenshotsReport {
+    fn name(&self) -> &'static str {
+        "screenshots"
+    }
+
+    fn title(&self) -> &'static str {
+        "Photos by world"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let worlds = photos_by_world(photos(backup));
+        ReportData::new(&worlds, render::photos_by_world(&worlds, Zone::Utc))
+    }
+}
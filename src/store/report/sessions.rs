@@ -0,0 +1,56 @@
+use crate::store::{
+    backup::{Backup, MessageContent, Session},
+    RcStr,
+};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotSource {
+    ContactStatus,
+    Invite,
+}
+
+// Every session snapshot in the backup as found, from contact statuses and session
+// invites. The same session usually turns up several times at different moments.
+pub fn session_snapshots(backup: &Backup) -> Vec<(SnapshotSource, Session)> {
+    let mut snapshots = Vec::new();
+    for account in backup.accounts.values() {
+        for contact in account.contacts.values() {
+            let status = &contact.user_status;
+            for session in status
+                .current_session
+                .iter()
+                .chain(status.active_sessions.iter().flatten())
+            {
+                snapshots.push((SnapshotSource::ContactStatus, session.clone()));
+            }
+        }
+        for msg in account.messages.values().flatten() {
+            if let MessageContent::SessionInvite(session) = msg.parsed_content() {
+                snapshots.push((SnapshotSource::Invite, *session));
+            }
+        }
+    }
+    snapshots
+}
+
+// Every session in the backup keyed by session id, repeated snapshots merged to cover
+// the widest span seen.
+pub fn captured_sessions(backup: &Backup) -> BTreeMap<RcStr, Session> {
+    let mut sessions = BTreeMap::<RcStr, Session>::new();
+    for (_, session) in session_snapshots(backup) {
+        match sessions.get_mut(&session.session_id) {
+            Some(known) => {
+                let begin = known.session_begin_time.min(session.session_begin_time);
+                if session.last_update > known.last_update {
+                    *known = session;
+                }
+                known.session_begin_time = begin;
+            }
+            None => {
+                sessions.insert(session.session_id.clone(), session);
+            }
+        }
+    }
+    sessions
+}
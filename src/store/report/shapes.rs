@@ -0,0 +1,203 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::{AssetUri, Backup, Component, FieldValue, Manifest, Slot, WellKnownAssetKind},
+    render, RcStr,
+};
+use bson::Bson;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+const TOP: usize = 20;
+// Deeper documents are cut off, past this the shape is too specific to group on.
+const MAX_DEPTH: usize = 4;
+// Arrays of scalars up to this long keep their length, the vectors and quaternions the
+// model most needs to tell apart.
+const MAX_SIZED_ARRAY: usize = 4;
+
+// How a field the typed model left as raw BSON is laid out.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum LeftoverKind {
+    // An {ID, Data} field whose Data isn't one of the typed values.
+    Field,
+    // An ID next to named members, DataField::Compound's layout.
+    Compound,
+    Other,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeSource {
+    pub component: String,
+    pub field: RcStr,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeExample {
+    pub account: RcStr,
+    pub record: RcStr,
+    // Slot names from the manifest root down, then the component and field.
+    pub path: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LeftoverShape {
+    pub kind: LeftoverKind,
+    pub shape: String,
+    pub count: u64,
+    pub records: u64,
+    // Where the shape turns up, most common first.
+    pub sources: Vec<(ShapeSource, u64)>,
+    pub example: ShapeExample,
+}
+
+struct Tally {
+    count: u64,
+    records: BTreeSet<RcStr>,
+    sources: BTreeMap<ShapeSource, u64>,
+    example: ShapeExample,
+}
+
+// Every field left untyped across the backup's manifests, grouped by shape. The shapes in
+// the most records come first, as the ones most worth teaching the typed model.
+pub fn leftover_shapes(backup: &Backup) -> Vec<LeftoverShape> {
+    let mut tallies = BTreeMap::<(LeftoverKind, String), Tally>::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let Some(AssetUri::SZBson(asset)) = &rec.asset_uri else {
+                continue;
+            };
+            let res: Result<Manifest, _> = asset.open(backup);
+            let Ok(manifest) = res else {
+                continue;
+            };
+            let mut visit = |path: &[RcStr], component: &Component, field: &RcStr, value: &Bson| {
+                let source = ShapeSource {
+                    component: component.short_type(),
+                    field: field.clone(),
+                };
+                let slots: Vec<&str> = path.iter().map(|name| name.as_str()).collect();
+                let tally = tallies
+                    .entry((kind(value), shape(value, 0)))
+                    .or_insert_with(|| Tally {
+                        count: 0,
+                        records: BTreeSet::new(),
+                        sources: BTreeMap::new(),
+                        example: ShapeExample {
+                            account: account_name.clone(),
+                            record: rec.id.clone(),
+                            path: format!("{}/{}.{}", slots.join("/"), source.component, field),
+                        },
+                    });
+                tally.count += 1;
+                tally.records.insert(rec.id.clone());
+                *tally.sources.entry(source).or_default() += 1;
+            };
+            if let Some(slot) = &manifest.object {
+                walk_slot(slot, &mut Vec::new(), &mut visit);
+            }
+            for provider in manifest.assets.iter().flatten() {
+                walk_component(&["<assets>".to_owned().into()], provider, &mut visit);
+            }
+        }
+    }
+
+    let mut shapes: Vec<_> = tallies
+        .into_iter()
+        .map(|((kind, shape), tally)| {
+            let mut sources: Vec<_> = tally.sources.into_iter().collect();
+            sources.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+            LeftoverShape {
+                kind,
+                shape,
+                count: tally.count,
+                records: tally.records.len() as u64,
+                sources,
+                example: tally.example,
+            }
+        })
+        .collect();
+    shapes.sort_by_key(|s| std::cmp::Reverse((s.records, s.count)));
+    shapes
+}
+
+fn walk_slot<F>(slot: &Slot, path: &mut Vec<RcStr>, visit: &mut F)
+where
+    F: FnMut(&[RcStr], &Component, &RcStr, &Bson),
+{
+    path.push(slot.name.data().clone().unwrap_or_default());
+    for component in slot.components.data() {
+        walk_component(path, component, visit);
+    }
+    for child in &slot.children {
+        walk_slot(child, path, visit);
+    }
+    path.pop();
+}
+
+fn walk_component<F>(path: &[RcStr], component: &Component, visit: &mut F)
+where
+    F: FnMut(&[RcStr], &Component, &RcStr, &Bson),
+{
+    for (field, value) in &component.data.fields {
+        if let FieldValue::Dunno(value) = value {
+            visit(path, component, field, value);
+        }
+    }
+}
+
+fn kind(value: &Bson) -> LeftoverKind {
+    match value {
+        Bson::Document(doc) if doc.contains_key("ID") && doc.contains_key("Data") => {
+            LeftoverKind::Field
+        }
+        Bson::Document(doc) if doc.contains_key("ID") && doc.len() > 1 => LeftoverKind::Compound,
+        _ => LeftoverKind::Other,
+    }
+}
+
+// Like {ID: String, Data: [Double; 3]}, members in document order.
+pub fn shape(value: &Bson, depth: usize) -> String {
+    match value {
+        Bson::Document(_) if depth >= MAX_DEPTH => "{..}".to_owned(),
+        Bson::Document(doc) => {
+            let members: Vec<_> = doc
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, shape(v, depth + 1)))
+                .collect();
+            format!("{{{}}}", members.join(", "))
+        }
+        Bson::Array(items) if items.is_empty() => "[]".to_owned(),
+        Bson::Array(items) => {
+            let shapes: BTreeSet<_> = items.iter().map(|v| shape(v, depth + 1)).collect();
+            let shapes: Vec<_> = shapes.into_iter().collect();
+            match shapes.as_slice() {
+                [one] if items.len() <= MAX_SIZED_ARRAY && !one.starts_with(['{', '[']) => {
+                    format!("[{}; {}]", one, items.len())
+                }
+                _ => format!("[{}]", shapes.join(" | ")),
+            }
+        }
+        other => format!("{:?}", other.element_type()),
+    }
+}
+
+pub struct ShapesReport;
+
+impl Report for ShapesReport {
+    fn name(&self) -> &'static str {
+        "shapes"
+    }
+
+    fn title(&self) -> &'static str {
+        "Untyped field shapes"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let mut shapes = leftover_shapes(backup);
+        shapes.truncate(TOP);
+        ReportData::new(&shapes, render::shapes(&shapes))
+    }
+}
@@ -0,0 +1,229 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::Backup,
+    phash::{phash, ImageHash},
+    render, RcStr,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Hashes at most this many bits apart are taken for the same picture.
+pub const DEFAULT_THRESHOLD: u32 = 4;
+
+const IMAGE_KINDS: [&str; 4] = ["webp", "png", "jpg", "gif"];
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarImage {
+    pub asset: RcStr,
+    pub phash: ImageHash,
+    pub bytes: u64,
+    // Bits away from the copy the cluster keeps.
+    pub distance: u32,
+    // The records using the asset, as (account, record).
+    pub records: Vec<(RcStr, RcStr)>,
+}
+
+// Image assets with different content hashes that look the same, like a texture
+// recompressed on each upload.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageCluster {
+    // The largest copy, taken as the best quality one; the rest could point at it.
+    pub keep: RcStr,
+    pub images: Vec<SimilarImage>,
+    pub bytes: u64,
+    pub savings: u64,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarImages {
+    pub threshold: u32,
+    pub hashed: u64,
+    // Image assets that failed to decode.
+    pub unreadable: Vec<RcStr>,
+    // Most savings first.
+    pub clusters: Vec<ImageCluster>,
+}
+
+// Every image asset a record or thumbnail references, clustered by perceptual hash. A
+// cluster holds images each within threshold bits of another in it.
+pub fn similar_images(backup: &Backup, threshold: u32) -> SimilarImages {
+    let mut users = BTreeMap::<RcStr, Vec<(RcStr, RcStr)>>::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let hashes = rec
+                .neos_db_manifest
+                .iter()
+                .map(|a| &a.hash)
+                .chain(rec.thumbnail_uri.iter().filter_map(|u| u.asset_id()));
+            for hash in hashes {
+                let records = users.entry(hash.clone()).or_default();
+                let user = (account_name.clone(), rec.id.clone());
+                if !records.contains(&user) {
+                    records.push(user);
+                }
+            }
+        }
+    }
+
+    let hashed: Vec<_> = users
+        .keys()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|asset| {
+            let kind = backup.sniff_asset(asset).ok()??;
+            if !IMAGE_KINDS.contains(&kind) {
+                return None;
+            }
+            let path = backup.asset_path(asset)?;
+            let bytes = std::fs::read(&path).ok()?;
+            Some((asset.clone(), bytes.len() as u64, phash(&bytes).ok()))
+        })
+        .collect();
+
+    let mut result = SimilarImages {
+        threshold,
+        ..Default::default()
+    };
+    let mut images = Vec::new();
+    for (asset, bytes, hash) in hashed {
+        match hash {
+            Some(hash) => images.push((asset, bytes, hash)),
+            None => result.unreadable.push(asset),
+        }
+    }
+    result.hashed = images.len() as u64;
+
+    // Union-find over every pair close enough.
+    let mut parent: Vec<usize> = (0..images.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..images.len() {
+        for j in i + 1..images.len() {
+            if images[i].2.distance(&images[j].2) <= threshold {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+    let mut groups = BTreeMap::<usize, Vec<usize>>::new();
+    for i in 0..images.len() {
+        groups.entry(root(&mut parent, i)).or_default().push(i);
+    }
+
+    for members in groups.into_values().filter(|m| m.len() > 1) {
+        let keep = *members
+            .iter()
+            .max_by_key(|&&i| (images[i].1, std::cmp::Reverse(&images[i].0)))
+            .unwrap();
+        let bytes = members.iter().map(|&i| images[i].1).sum();
+        let mut cluster: Vec<_> = members
+            .iter()
+            .map(|&i| {
+                let (asset, bytes, hash) = &images[i];
+                SimilarImage {
+                    asset: asset.clone(),
+                    phash: *hash,
+                    bytes: *bytes,
+                    distance: hash.distance(&images[keep].2),
+                    records: users.remove(asset).unwrap_or_default(),
+                }
+            })
+            .collect();
+        cluster.sort_by_key(|image| (image.distance, std::cmp::Reverse(image.bytes)));
+        result.clusters.push(ImageCluster {
+            keep: images[keep].0.clone(),
+            images: cluster,
+            bytes,
+            savings: bytes - images[keep].1,
+        });
+    }
+    result
+        .clusters
+        .sort_by_key(|c| std::cmp::Reverse(c.savings));
+    result
+}
+
+// Thumbnails are small and often framed differently from a screenshot of the same item,
+// so matching against one needs more slack than clustering assets does.
+pub const IDENTIFY_THRESHOLD: u32 = 10;
+
+// A record whose thumbnail looks like the image asked about.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailMatch {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub name: RcStr,
+    pub thumbnail: RcStr,
+    pub distance: u32,
+}
+
+// The records whose thumbnail is within threshold bits of the hash, closest first.
+pub fn identify(backup: &Backup, hash: ImageHash, threshold: u32) -> Vec<ThumbnailMatch> {
+    let mut thumbnails = BTreeMap::<RcStr, Vec<(RcStr, RcStr, RcStr)>>::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            if let Some(asset) = rec.thumbnail_uri.as_ref().and_then(|u| u.asset_id()) {
+                thumbnails.entry(asset.clone()).or_default().push((
+                    account_name.clone(),
+                    rec.id.clone(),
+                    rec.name.clone(),
+                ));
+            }
+        }
+    }
+
+    let close: Vec<_> = thumbnails
+        .keys()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|asset| {
+            let bytes = std::fs::read(backup.asset_path(asset)?).ok()?;
+            let distance = phash(&bytes).ok()?.distance(&hash);
+            (distance <= threshold).then(|| (asset.clone(), distance))
+        })
+        .collect();
+
+    let mut matches = Vec::new();
+    for (thumbnail, distance) in close {
+        for (account, record, name) in thumbnails.remove(&thumbnail).unwrap_or_default() {
+            matches.push(ThumbnailMatch {
+                account,
+                record,
+                name,
+                thumbnail: thumbnail.clone(),
+                distance,
+            });
+        }
+    }
+    matches.sort_by(|a, b| {
+        (a.distance, &a.account, &a.record).cmp(&(b.distance, &b.account, &b.record))
+    });
+    matches
+}
+
+pub struct SimilarImagesReport;
+
+impl Report for SimilarImagesReport {
+    fn name(&self) -> &'static str {
+        "similar-images"
+    }
+
+    fn title(&self) -> &'static str {
+        "Visually identical images"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let similar = similar_images(backup, DEFAULT_THRESHOLD);
+        ReportData::new(&similar, render::similar_images(&similar))
+    }
+}
@@ -0,0 +1,72 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::Backup,
+    graph::{BackupGraph, Edge, Node},
+    render, RcStr,
+};
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StrandedRecord {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub name: RcStr,
+    // The folder the record claims to be in, which some ancestor of is missing.
+    pub path: Vec<RcStr>,
+    pub bytes: u64,
+}
+
+// Records no chain of directories leads to from the inventory root. The game can't show
+// them, but their assets still take up storage. Largest first.
+pub fn stranded_records(backup: &Backup) -> Vec<StrandedRecord> {
+    let graph = BackupGraph::build(backup);
+    let mut stranded = Vec::new();
+    for (account_name, account) in &backup.accounts {
+        let Some(root) = graph.node(&Node::Account {
+            id: account_name.clone(),
+        }) else {
+            continue;
+        };
+        let reachable = graph.reachable(root, Edge::Contains);
+        for rec in account.records.values() {
+            // Group records hang off the group's inventory, not the member's.
+            if rec.is_group_owned() {
+                continue;
+            }
+            let node = graph.node(&Node::Record {
+                account: account_name.clone(),
+                id: rec.id.clone(),
+            });
+            if node.is_some_and(|node| reachable.contains(&node)) {
+                continue;
+            }
+            stranded.push(StrandedRecord {
+                account: account_name.clone(),
+                record: rec.id.clone(),
+                name: rec.name.clone(),
+                path: rec.path.clone(),
+                bytes: rec.neos_db_manifest.iter().map(|a| a.bytes).sum(),
+            });
+        }
+    }
+    stranded.sort_by_key(|s| std::cmp::Reverse(s.bytes));
+    stranded
+}
+
+pub struct StrandedReport;
+
+impl Report for StrandedReport {
+    fn name(&self) -> &'static str {
+        "stranded"
+    }
+
+    fn title(&self) -> &'static str {
+        "Records unreachable from the inventory"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let records = stranded_records(backup);
+        ReportData::new(&records, render::stranded(&records))
+    }
+}
@@ -0,0 +1,153 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::{AssetUri, Backup},
+    internment::Interner,
+    render,
+    sample::Estimate,
+    RcStr,
+};
+use bson::Bson;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+const TOP: usize = 20;
+
+// What a shared table costs per use in place of the inline string, a u32 index.
+const TABLE_REF_BYTES: u64 = 4;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateString {
+    pub value: RcStr,
+    pub json_uses: u64,
+    pub bson_uses: u64,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StringReport {
+    pub json_strings: u64,
+    pub bson_strings: u64,
+    pub distinct: u64,
+    pub total_bytes: u64,
+    // Bytes left if every distinct string, across JSON and BSON, were stored once.
+    pub unique_bytes: u64,
+    // Estimated manifest bytes saved by one string table shared by all manifests.
+    pub manifest_table_savings: u64,
+    pub manifests_scanned: u64,
+    // Set when the BSON counts and table savings were scaled up from a sample.
+    pub estimate: Option<Estimate>,
+    pub top: Vec<DuplicateString>,
+}
+
+// Keys and values of every record, contact and message file plus every decodable manifest.
+pub fn string_report(backup: &Backup, top: usize) -> StringReport {
+    let mut json = Interner::default();
+    if let Ok(value) = serde_json::to_value(&backup.accounts) {
+        intern_json(&value, &mut json);
+    }
+
+    let mut bson = Interner::default();
+    let mut report = StringReport::default();
+    let mut seen = BTreeSet::new();
+    for account in backup.accounts.values() {
+        for rec in account.records.values() {
+            let Some(AssetUri::SZBson(asset)) = &rec.asset_uri else {
+                continue;
+            };
+            if !seen.insert(asset.0.clone()) || !backup.manifest_sample.contains(&asset.0) {
+                continue;
+            }
+            if let Ok(doc) = asset.read_document(backup) {
+                report.manifests_scanned += 1;
+                intern_document(&doc, &mut bson);
+            }
+        }
+    }
+
+    let mut all = json.clone();
+    all.merge(&bson);
+
+    report.json_strings = json.iter().map(|(_, n)| n).sum();
+    report.bson_strings = bson.iter().map(|(_, n)| n).sum();
+    report.distinct = all.iter().count() as u64;
+    report.total_bytes = all.iter().map(|(k, n)| n * k.len() as u64).sum();
+    report.unique_bytes = report.total_bytes - all.duplicate_bytes();
+    report.manifest_table_savings = bson
+        .iter()
+        .map(|(k, n)| (n * k.len() as u64).saturating_sub(k.len() as u64 + n * TABLE_REF_BYTES))
+        .sum();
+    report.estimate = backup.manifest_sample.estimate();
+    if let Some(estimate) = &report.estimate {
+        report.bson_strings = estimate.extrapolate(report.bson_strings);
+        report.manifest_table_savings = estimate.extrapolate(report.manifest_table_savings);
+    }
+
+    let mut dupes: Vec<_> = all
+        .iter()
+        .filter(|(_, n)| *n > 1)
+        .map(|(k, n)| DuplicateString {
+            value: k.clone(),
+            json_uses: json.uses(k),
+            bson_uses: bson.uses(k),
+            bytes: n * k.len() as u64,
+        })
+        .collect();
+    dupes.sort_by_key(|d| std::cmp::Reverse(d.bytes));
+    dupes.truncate(top);
+    report.top = dupes;
+    report
+}
+
+fn intern_json(value: &Value, i: &mut Interner) {
+    match value {
+        Value::String(s) => {
+            i.intern(s.clone().into());
+        }
+        Value::Array(values) => values.iter().for_each(|v| intern_json(v, i)),
+        Value::Object(map) => {
+            for (k, v) in map {
+                i.intern(k.clone().into());
+                intern_json(v, i);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn intern_document(doc: &bson::Document, i: &mut Interner) {
+    for (k, v) in doc {
+        i.intern(k.clone().into());
+        intern_bson(v, i);
+    }
+}
+
+fn intern_bson(value: &Bson, i: &mut Interner) {
+    match value {
+        Bson::String(s) => {
+            i.intern(s.clone().into());
+        }
+        Bson::Array(values) => values.iter().for_each(|v| intern_bson(v, i)),
+        Bson::Document(doc) => intern_document(doc, i),
+        _ => {}
+    }
+}
+
+pub struct StringsReport;
+
+impl Report for StringsReport {
+    fn name(&self) -> &'static str {
+        "strings"
+    }
+
+    fn title(&self) -> &'static str {
+        "Duplicated strings"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let report = string_report(backup, TOP);
+        ReportData::new(&report, render::strings(&report))
+    }
+}
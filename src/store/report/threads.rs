@@ -0,0 +1,136 @@
+use super::{Report, ReportData};
+use crate::store::{
+    backup::Backup,
+    render::{self, Zone},
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Something the two copies of a message don't agree on.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum ThreadIssue {
+    // Only one side has the message, the other never synced it or lost it.
+    Missing { from: RcStr },
+    SenderDiffers,
+    ContentDiffers,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StitchedMessage {
+    pub id: RcStr,
+    pub sender_id: RcStr,
+    pub message_type: String,
+    pub content: RcStr,
+    pub send_time: DateTime<Utc>,
+    // The accounts holding a copy, with when each copy says it was read.
+    pub read_receipts: BTreeMap<RcStr, Option<DateTime<Utc>>>,
+    pub issues: Vec<ThreadIssue>,
+}
+
+// Both sides of a conversation between two accounts in the backup, as one thread.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StitchedThread {
+    pub a: RcStr,
+    pub b: RcStr,
+    pub messages: Vec<StitchedMessage>,
+}
+
+impl StitchedThread {
+    pub fn missing_from(&self, account: &RcStr) -> usize {
+        let missing = ThreadIssue::Missing {
+            from: account.clone(),
+        };
+        self.messages
+            .iter()
+            .filter(|m| m.issues.contains(&missing))
+            .count()
+    }
+
+    pub fn disagreements(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|m| {
+                m.issues
+                    .iter()
+                    .any(|i| !matches!(i, ThreadIssue::Missing { .. }))
+            })
+            .count()
+    }
+}
+
+// Every pair of accounts in the backup with messages between them, matched up by message
+// id. Messages are in send order.
+pub fn stitched_threads(backup: &Backup) -> Vec<StitchedThread> {
+    let mut pairs = BTreeSet::new();
+    for (name, account) in &backup.accounts {
+        for contact in account.messages.keys() {
+            if contact != name && backup.accounts.contains_key(contact) {
+                pairs.insert((name.min(contact).clone(), name.max(contact).clone()));
+            }
+        }
+    }
+
+    let mut threads = Vec::new();
+    for (a, b) in pairs {
+        let mut messages = BTreeMap::<RcStr, StitchedMessage>::new();
+        for (owner, contact) in [(&a, &b), (&b, &a)] {
+            let copies = backup.accounts[owner].messages.get(contact);
+            for msg in copies.into_iter().flatten() {
+                let sender_id = msg.sender(owner, contact).clone();
+                let stitched = messages
+                    .entry(msg.id.clone())
+                    .or_insert_with(|| StitchedMessage {
+                        id: msg.id.clone(),
+                        sender_id: sender_id.clone(),
+                        message_type: format!("{:?}", msg.message_type),
+                        content: msg.content.clone(),
+                        send_time: msg.send_time,
+                        read_receipts: BTreeMap::new(),
+                        issues: Vec::new(),
+                    });
+                if stitched.sender_id != sender_id {
+                    stitched.issues.push(ThreadIssue::SenderDiffers);
+                }
+                if stitched.content != msg.content {
+                    stitched.issues.push(ThreadIssue::ContentDiffers);
+                }
+                stitched.read_receipts.insert(owner.clone(), msg.read_time);
+            }
+        }
+        let mut messages: Vec<_> = messages.into_values().collect();
+        for msg in &mut messages {
+            for account in [&a, &b] {
+                if !msg.read_receipts.contains_key(account) {
+                    msg.issues.push(ThreadIssue::Missing {
+                        from: account.clone(),
+                    });
+                }
+            }
+        }
+        messages.sort_by(|x, y| (x.send_time, &x.id).cmp(&(y.send_time, &y.id)));
+        threads.push(StitchedThread { a, b, messages });
+    }
+    threads
+}
+
+pub struct ThreadsReport;
+
+impl Report for ThreadsReport {
+    fn name(&self) -> &'static str {
+        "threads"
+    }
+
+    fn title(&self) -> &'static str {
+        "Stitched threads"
+    }
+
+    fn compute(&self, backup: &Backup) -> ReportData {
+        let threads = stitched_threads(backup);
+        ReportData::new(&threads, render::threads(&threads, Zone::Utc))
+    }
+}
@@ -0,0 +1,117 @@
+use super::{
+    backup::{AssetUri, Backup},
+    RcStr,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+// How much of a backup's manifests a scan opens: a percentage like "5%", or a count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sample {
+    Percent(f64),
+    Limit(u64),
+}
+
+impl FromStr for Sample {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(percent) = s.strip_suffix('%') {
+            let percent: f64 = percent
+                .trim()
+                .parse()
+                .map_err(|_| format!("{} is not a percentage", s))?;
+            if !(percent > 0.0 && percent <= 100.0) {
+                return Err(format!("{} is not between 0% and 100%", s));
+            }
+            return Ok(Sample::Percent(percent));
+        }
+        s.parse()
+            .map(Sample::Limit)
+            .map_err(|_| format!("{} is neither a percentage nor a count", s))
+    }
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sample::Percent(percent) => write!(f, "{}%", percent),
+            Sample::Limit(limit) => write!(f, "{}", limit),
+        }
+    }
+}
+
+// The manifests a sampled scan opens. Asset IDs are content hashes, so taking the lowest
+// is as good as a random pick, and it picks the same ones on every run.
+#[derive(Debug, Clone, Default)]
+pub struct ManifestSample {
+    // None when every manifest is scanned.
+    chosen: Option<BTreeSet<RcStr>>,
+    total: u64,
+}
+
+// Set on results extrapolated from a sample, so they're never mistaken for a full count.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Estimate {
+    pub sampled: u64,
+    pub total: u64,
+}
+
+impl ManifestSample {
+    pub fn new(backup: &Backup, sample: Sample) -> Self {
+        let mut all = BTreeSet::new();
+        for account in backup.accounts.values() {
+            for rec in account.records.values() {
+                if let Some(AssetUri::SZBson(asset)) = &rec.asset_uri {
+                    all.insert(asset.0.clone());
+                }
+            }
+        }
+        let total = all.len() as u64;
+        let take = match sample {
+            Sample::Percent(percent) => (total as f64 * percent / 100.0).ceil() as u64,
+            Sample::Limit(limit) => limit,
+        };
+        if take >= total {
+            return Self {
+                chosen: None,
+                total,
+            };
+        }
+        Self {
+            chosen: Some(all.into_iter().take(take as usize).collect()),
+            total,
+        }
+    }
+
+    pub fn contains(&self, asset: &str) -> bool {
+        self.chosen
+            .as_ref()
+            .is_none_or(|chosen| chosen.contains(asset))
+    }
+
+    pub fn estimate(&self) -> Option<Estimate> {
+        self.chosen.as_ref().map(|chosen| Estimate {
+            sampled: chosen.len() as u64,
+            total: self.total,
+        })
+    }
+}
+
+impl Estimate {
+    // A count over the sample scaled up to every manifest.
+    pub fn extrapolate(&self, n: u64) -> u64 {
+        if self.sampled == 0 {
+            return 0;
+        }
+        (n as f64 * self.total as f64 / self.sampled as f64).round() as u64
+    }
+
+    pub fn extrapolate_signed(&self, n: i64) -> i64 {
+        if self.sampled == 0 {
+            return 0;
+        }
+        (n as f64 * self.total as f64 / self.sampled as f64).round() as i64
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+// The version stats and report JSON is written in, which dashboards can pin. Adding a
+// field doesn't change it: readers should ignore fields they don't know, the way Stats
+// reads missing ones as defaults. Renaming or removing one, or changing what it means,
+// bumps it and adds a step to UPGRADES so older files still read.
+//
+// Version 0 is the data on its own, as written before versioning.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Upgrades a file's data from the version at its index plus one to the next, given its
+// kind. Unversioned files need none, their data is already what version 1 wraps.
+type Upgrade = fn(&str, &mut Value);
+const UPGRADES: &[Upgrade] = &[];
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Serde_json: {0} ({1})")]
+    Json(serde_json::Error, PathBuf),
+    #[error("{} is a {found} report, expected {expected}", .path.display())]
+    WrongKind {
+        path: PathBuf,
+        found: String,
+        expected: String,
+    },
+    #[error(
+        "{} was written with report schema {found}, this version reads up to {}",
+        .path.display(), SCHEMA_VERSION
+    )]
+    TooNew { path: PathBuf, found: u32 },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Versioned<T> {
+    pub schema_version: u32,
+    // What the data is, like stats or a report's name.
+    pub kind: String,
+    pub data: T,
+}
+
+// The data as it's written in the given schema version, which is at most SCHEMA_VERSION.
+pub fn to_value<T: Serialize>(kind: &str, data: &T, version: u32) -> serde_json::Result<Value> {
+    let data = serde_json::to_value(data)?;
+    if version == 0 {
+        return Ok(data);
+    }
+    serde_json::to_value(Versioned {
+        schema_version: version,
+        kind: kind.to_owned(),
+        data,
+    })
+}
+
+pub fn write<T: Serialize>(
+    path: &Path,
+    kind: &str,
+    data: &T,
+    version: u32,
+) -> Result<(), SchemaError> {
+    let json = |e| SchemaError::Json(e, path.to_owned());
+    let value = to_value(kind, data, version).map_err(json)?;
+    let mut file = BufWriter::new(fs::File::create(path)?);
+    serde_json::to_writer_pretty(&mut file, &value).map_err(json)?;
+    Ok(file.flush()?)
+}
+
+// Reads a file written in any version up to SCHEMA_VERSION, upgrading it to the current
+// one. Unversioned files are taken to be the kind asked for.
+pub fn read<T: DeserializeOwned>(path: &Path, kind: &str) -> Result<T, SchemaError> {
+    let json = |e| SchemaError::Json(e, path.to_owned());
+    let value: Value = serde_json::from_slice(&fs::read(path)?).map_err(json)?;
+    let is_versioned = value.get("schemaVersion").is_some_and(Value::is_u64);
+    let (version, mut data) = if is_versioned {
+        let versioned: Versioned<Value> = serde_json::from_value(value).map_err(json)?;
+        if versioned.kind != kind {
+            return Err(SchemaError::WrongKind {
+                path: path.to_owned(),
+                found: versioned.kind,
+                expected: kind.to_owned(),
+            });
+        }
+        (versioned.schema_version, versioned.data)
+    } else {
+        (0, value)
+    };
+    if version > SCHEMA_VERSION {
+        return Err(SchemaError::TooNew {
+            path: path.to_owned(),
+            found: version,
+        });
+    }
+    for upgrade in &UPGRADES[version.saturating_sub(1) as usize..] {
+        upgrade(kind, &mut data);
+    }
+    serde_json::from_value(data).map_err(json)
+}
@@ -0,0 +1,83 @@
+use super::{
+    backup::{Record, RecordType},
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// Tags marking a texture record as a photo taken in game rather than an uploaded image.
+const PHOTO_TAGS: [&str; 2] = ["camera_photo", "screenshot"];
+
+// Where and when a photo was taken, and who was there, from the key:value tags the
+// camera wrote on the record.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoMetadata {
+    // From the timestamp tag, falling back on the record's creation time.
+    pub taken: Option<DateTime<Utc>>,
+    pub world_name: Option<RcStr>,
+    // The world record (neosrec:///U-x/R-y) or session the photo was taken in.
+    pub location_url: Option<RcStr>,
+    pub host_user_id: Option<RcStr>,
+    // The users present, in tag order.
+    pub users: Vec<RcStr>,
+}
+
+impl PhotoMetadata {
+    // A saved world's owner and record, when the location is one.
+    pub fn world_record(&self) -> Option<(&str, &str)> {
+        let rest = self.location_url.as_ref()?.strip_prefix("neosrec:///")?;
+        rest.split_once('/')
+    }
+
+    // What to group the photo under: the saved world if it was one, else the world's
+    // name, since session URLs differ every time.
+    pub fn world_key(&self) -> Option<&RcStr> {
+        match self.world_record() {
+            Some(_) => self.location_url.as_ref(),
+            None => self.world_name.as_ref().or(self.location_url.as_ref()),
+        }
+    }
+
+    pub fn has_user(&self, user_id: &str) -> bool {
+        self.users.iter().any(|u| u.as_str() == user_id)
+    }
+}
+
+impl Record {
+    pub fn is_photo(&self) -> bool {
+        self.record_type == RecordType::Texture
+            && self.tags.iter().any(|t| PHOTO_TAGS.contains(&t.as_str()))
+    }
+
+    // The photo's metadata, None for a record that isn't a photo. Unknown tags are
+    // skipped, and a user tagged twice is listed once.
+    pub fn photo_metadata(&self) -> Option<PhotoMetadata> {
+        if !self.is_photo() {
+            return None;
+        }
+        let mut meta = PhotoMetadata::default();
+        for tag in &self.tags {
+            let Some((key, value)) = tag.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let text = || RcStr::new(value.to_owned());
+            match key {
+                "timestamp" => {
+                    meta.taken = DateTime::parse_from_rfc3339(value).ok().map(Into::into)
+                }
+                "location_name" => meta.world_name = Some(text()),
+                "location_url" => meta.location_url = Some(text()),
+                "location_host" => meta.host_user_id = Some(text()),
+                "user" | "present_user" if !meta.has_user(value) => meta.users.push(text()),
+                _ => {}
+            }
+        }
+        meta.taken = meta.taken.or(self.creation_time);
+        Some(meta)
+    }
+}
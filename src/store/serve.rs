@@ -0,0 +1,321 @@
+use super::{
+    backup::{AssetStoreUnavailable, Backup, SZBson},
+    handle::RecordHandle,
+    limits::{LimitExceeded, ManifestLimits},
+    preview::{PreviewCache, PreviewError, PreviewFormat},
+    RcStr,
+};
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    thread,
+};
+use tiny_http::{Header, Request, Response, Server};
+
+// Serves a backup's assets over HTTP:
+//
+//   /asset/<hash>                          the raw asset, honouring Range requests
+//   /asset/<hash>?decompress               a 7zbson asset's BSON, decompressed as it's sent
+//   /preview/<hash>?size=256&format=png    webp/png thumbnails, ogg as wav
+//   /record/<account>/<id>                 the record's JSON
+pub fn serve(backup: &Backup, addr: &str, cache: &PreviewCache) -> io::Result<()> {
+    let server = Server::http(addr).map_err(io::Error::other)?;
+    // Conversions are slow, so a few workers keep one preview from stalling the rest.
+    let workers = thread::available_parallelism().map_or(4, |n| n.get().min(8));
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                for request in server.incoming_requests() {
+                    let range = request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv("Range"))
+                        .map(|h| h.value.to_string());
+                    let reply = route(backup, cache, request.url(), range.as_deref());
+                    // A client hanging up mid-response only loses that response.
+                    respond(request, reply).ok();
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+enum Body {
+    Bytes(Vec<u8>),
+    // Read as it's sent, with its length when that's known up front.
+    Stream(Box<dyn Read + Send>, Option<u64>),
+}
+
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Body,
+}
+
+impl Reply {
+    fn bytes(status: u16, content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            content_type,
+            headers: Vec::new(),
+            body: Body::Bytes(body),
+        }
+    }
+
+    fn error(status: u16, message: String) -> Self {
+        Self::bytes(status, "text/plain", message.into_bytes())
+    }
+
+    fn with_header(mut self, name: &'static str, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+}
+
+fn route(backup: &Backup, cache: &PreviewCache, url: &str, range: Option<&str>) -> Reply {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+    let (route, hash) = match path.trim_start_matches('/').split_once('/') {
+        Some(("record", handle)) => return record(backup, handle),
+        Some((route @ ("asset" | "preview"), hash)) => (route, hash),
+        _ => return Reply::error(404, "not found".to_owned()),
+    };
+    // Hashes only ever hold these, anything else could walk out of the asset directory.
+    if hash.is_empty() || !hash.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return Reply::error(400, format!("bad asset hash {:?}", hash));
+    }
+    if !backup.has_asset_store() {
+        return Reply::error(503, AssetStoreUnavailable.to_string());
+    }
+    if !backup.has_asset(hash) {
+        return Reply::error(404, format!("no asset {}", hash));
+    }
+
+    // Flags like decompress have no value, and are left alone where one is expected.
+    let params = || {
+        query.split('&').map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (pair, None),
+        })
+    };
+    if route == "asset" {
+        let result = if params().any(|(key, _)| key == "decompress") {
+            decompressed(backup, hash)
+        } else {
+            asset(backup, hash, range)
+        };
+        return result.unwrap_or_else(|e| Reply::error(500, e.to_string()));
+    }
+
+    let mut size = None;
+    let mut format = None;
+    for (key, value) in params() {
+        match (key, value) {
+            ("size", Some(value)) => match value.parse() {
+                Ok(value) => size = Some(value),
+                Err(_) => return Reply::error(400, format!("bad size {:?}", value)),
+            },
+            ("format", Some(value)) => match value.parse() {
+                Ok(value) => format = Some(value),
+                Err(e) => return Reply::error(400, e),
+            },
+            _ => {}
+        }
+    }
+    let format = match format {
+        Some(format) => format,
+        None => match backup.sniff_asset(hash) {
+            Ok(kind) => match kind.and_then(PreviewFormat::for_kind) {
+                Some(format) => format,
+                None => return Reply::error(415, format!("no preview for {}", hash)),
+            },
+            Err(e) => return Reply::error(500, e.to_string()),
+        },
+    };
+    match cache.get(backup, hash, format, size) {
+        Ok(bytes) => Reply::bytes(200, format.content_type(), bytes),
+        Err(e @ PreviewError::Unsupported(..)) => Reply::error(415, e.to_string()),
+        Err(e @ PreviewError::Missing(_)) => Reply::error(404, e.to_string()),
+        Err(e @ PreviewError::Unavailable(_)) => Reply::error(503, e.to_string()),
+        Err(e @ PreviewError::Disabled(_)) => Reply::error(501, e.to_string()),
+        Err(e) => Reply::error(500, e.to_string()),
+    }
+}
+
+// Streamed from the file rather than read into memory, since worlds run to hundreds of
+// megabytes, and only the part asked for when there's a Range header.
+fn asset(backup: &Backup, hash: &str, range: Option<&str>) -> io::Result<Reply> {
+    let mut file = backup.open_asset(hash)?;
+    let len = file.metadata()?.len();
+    let (status, start, end) = match byte_range(range, len) {
+        ByteRange::Whole => (200, 0, len),
+        ByteRange::Part(start, end) => (206, start, end + 1),
+        ByteRange::Unsatisfiable => {
+            return Ok(
+                Reply::error(416, format!("asset {} is {} bytes", hash, len))
+                    .with_header("Content-Range", format!("bytes */{}", len)),
+            );
+        }
+    };
+    file.seek(SeekFrom::Start(start))?;
+    let reply = Reply {
+        status,
+        content_type: "application/octet-stream",
+        headers: vec![("Accept-Ranges", "bytes".to_owned())],
+        body: Body::Stream(Box::new(file.take(end - start)), Some(end - start)),
+    };
+    Ok(match status {
+        206 => reply.with_header(
+            "Content-Range",
+            format!("bytes {}-{}/{}", start, end - 1, len),
+        ),
+        _ => reply,
+    })
+}
+
+// Decompressed on a thread of its own into a pipe as the client reads, so the whole BSON
+// is never held. The size the header claims is refused past the backup's limits and caps
+// what's sent, so it's the Content-Length either way. A corrupt asset ends the response
+// early.
+fn decompressed(backup: &Backup, hash: &str) -> io::Result<Reply> {
+    if backup.sniff_asset(hash)? != Some("7zbson") {
+        return Ok(Reply::error(415, format!("{} isn't a 7zbson asset", hash)));
+    }
+    let asset = RcStr::from(hash);
+    let len = SZBson(asset.clone())
+        .uncompressed_size(backup)
+        .map_err(io::Error::other)?;
+    let max = backup.limits.max_decompressed_bytes;
+    if len > max {
+        let e = LimitExceeded::DecompressedSize { asset, max };
+        return Ok(Reply::error(415, e.to_string()));
+    }
+    let limits = ManifestLimits {
+        max_decompressed_bytes: len,
+        ..backup.limits
+    };
+    let file = backup.open_asset(hash)?;
+    let (reader, writer) = io::pipe()?;
+    thread::spawn(move || limits.decompress_into(&asset, file, writer));
+    Ok(Reply {
+        status: 200,
+        content_type: "application/bson",
+        headers: Vec::new(),
+        body: Body::Stream(Box::new(reader), Some(len)),
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    Whole,
+    // The first and last byte, inclusive.
+    Part(u64, u64),
+    Unsatisfiable,
+}
+
+// The one range of bytes a Range header asks for. Anything but a single bytes range,
+// including one that doesn't parse, is answered with the whole asset, as RFC 9110 allows.
+fn byte_range(header: Option<&str>, len: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Whole;
+    };
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Whole;
+    };
+    let (start, end) = if start.is_empty() {
+        // The last n bytes.
+        match end.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) => (len.saturating_sub(n), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Whole,
+        }
+    } else {
+        match (start.parse::<u64>(), end) {
+            (Ok(start), "") => (start, len.saturating_sub(1)),
+            (Ok(start), end) => match end.parse::<u64>() {
+                Ok(end) if start <= end => (start, end.min(len.saturating_sub(1))),
+                _ => return ByteRange::Whole,
+            },
+            (Err(_), _) => return ByteRange::Whole,
+        }
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Part(start, end)
+}
+
+fn record(backup: &Backup, handle: &str) -> Reply {
+    let handle: RecordHandle = match handle.parse() {
+        Ok(handle) => handle,
+        Err(e) => return Reply::error(400, format!("{}", e)),
+    };
+    match handle.resolve(backup).map(serde_json::to_vec) {
+        Some(Ok(json)) => Reply::bytes(200, "application/json", json),
+        Some(Err(e)) => Reply::error(500, e.to_string()),
+        None => Reply::error(404, format!("no record {}", handle)),
+    }
+}
+
+fn respond(request: Request, reply: Reply) -> io::Result<()> {
+    let header = |name: &str, value: &str| {
+        Header::from_bytes(name, value).expect("names and values are valid in headers")
+    };
+    let mut headers = vec![header("Content-Type", reply.content_type)];
+    headers.extend(
+        reply
+            .headers
+            .iter()
+            .map(|(name, value)| header(name, value)),
+    );
+    let response = match reply.body {
+        Body::Bytes(body) => Response::from_data(body).boxed(),
+        Body::Stream(reader, len) => Response::new(
+            reply.status.into(),
+            Vec::new(),
+            reader,
+            len.map(|len| len as usize),
+            None,
+        ),
+    };
+    let response = headers.into_iter().fold(
+        response.with_status_code(reply.status),
+        Response::with_header,
+    );
+    request.respond(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_ranges() {
+        let cases = [
+            (Some("bytes=0-9"), 100, ByteRange::Part(0, 9)),
+            (Some("bytes=10-10"), 100, ByteRange::Part(10, 10)),
+            (Some("bytes=90-"), 100, ByteRange::Part(90, 99)),
+            (Some("bytes=-10"), 100, ByteRange::Part(90, 99)),
+            (Some("bytes=-200"), 100, ByteRange::Part(0, 99)),
+            (Some("bytes=-0"), 100, ByteRange::Unsatisfiable),
+            // The end is clamped to the last byte, the start isn't.
+            (Some("bytes=50-500"), 100, ByteRange::Part(50, 99)),
+            (Some("bytes=100-"), 100, ByteRange::Unsatisfiable),
+            (Some("bytes=150-160"), 100, ByteRange::Unsatisfiable),
+            (Some("bytes=0-"), 0, ByteRange::Unsatisfiable),
+            // Anything but one range is answered with the whole asset.
+            (None, 100, ByteRange::Whole),
+            (Some("bytes=0-1,5-6"), 100, ByteRange::Whole),
+            (Some("items=0-9"), 100, ByteRange::Whole),
+            (Some("bytes=abc"), 100, ByteRange::Whole),
+            (Some("bytes=x-5"), 100, ByteRange::Whole),
+            (Some("bytes=5-x"), 100, ByteRange::Whole),
+            (Some("bytes=9-5"), 100, ByteRange::Whole),
+            (Some("bytes=-x"), 100, ByteRange::Whole),
+        ];
+        for (header, len, expected) in cases {
+            assert_eq!(byte_range(header, len), expected, "{:?} of {}", header, len);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use super::backup::Session;
+use serde::Serialize;
+use std::str::FromStr;
+
+// How a session could be reached, by URL scheme.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum Transport {
+    // lnl://address:port, straight to the host.
+    Direct,
+    // lnl-nat://, punched through NAT with the host's NAT id in place of an address.
+    NatPunchthrough,
+    // lnl-relay://, forwarded through a relay server.
+    Relay,
+    // neos-steam://, over Steam networking with the host's Steam id.
+    Steam,
+    Other,
+}
+
+impl Transport {
+    pub fn for_scheme(scheme: &str) -> Self {
+        match scheme {
+            "lnl" => Transport::Direct,
+            "lnl-nat" => Transport::NatPunchthrough,
+            "lnl-relay" | "neos-relay" => Transport::Relay,
+            "neos-steam" => Transport::Steam,
+            _ => Transport::Other,
+        }
+    }
+}
+
+// One of a session's sessionURLs, like lnl-nat://<nat id>/S-<session id>.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionUrl {
+    pub scheme: String,
+    pub transport: Transport,
+    // An address, relay server, NAT id or Steam id depending on the transport.
+    pub host: String,
+    pub port: Option<u16>,
+    pub path: String,
+}
+
+impl FromStr for SessionUrl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = s
+            .split_once("://")
+            .ok_or_else(|| format!("no scheme in session URL {:?}", s))?;
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        // A bracketed IPv6 address has colons of its own.
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => match port.parse() {
+                Ok(port) => (host, Some(port)),
+                Err(_) => return Err(format!("bad port in session URL {:?}", s)),
+            },
+            _ => (authority, None),
+        };
+        if host.is_empty() {
+            return Err(format!("no host in session URL {:?}", s));
+        }
+        Ok(SessionUrl {
+            scheme: scheme.to_owned(),
+            transport: Transport::for_scheme(scheme),
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+        })
+    }
+}
+
+impl Session {
+    // The session's URLs that parse, malformed ones are left out.
+    pub fn urls(&self) -> impl Iterator<Item = SessionUrl> + '_ {
+        self.session_urls.iter().filter_map(|url| url.parse().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_urls_parse() {
+        let url = |transport, host: &str, port, path: &str| {
+            Some((transport, host.to_owned(), port, path.to_owned()))
+        };
+        let cases = [
+            (
+                "lnl://203.0.113.5:12345/S-abc",
+                url(Transport::Direct, "203.0.113.5", Some(12345), "S-abc"),
+            ),
+            (
+                "lnl-nat://a1b2c3/S-1",
+                url(Transport::NatPunchthrough, "a1b2c3", None, "S-1"),
+            ),
+            (
+                "lnl-relay://relay.example.com:3478/S-1",
+                url(Transport::Relay, "relay.example.com", Some(3478), "S-1"),
+            ),
+            (
+                "neos-relay://relay/S-1",
+                url(Transport::Relay, "relay", None, "S-1"),
+            ),
+            (
+                "neos-steam://76561198000000000/S-1",
+                url(Transport::Steam, "76561198000000000", None, "S-1"),
+            ),
+            (
+                "lnl://[2001:db8::1]:80/S-1",
+                url(Transport::Direct, "[2001:db8::1]", Some(80), "S-1"),
+            ),
+            (
+                "lnl://[2001:db8::1]/S-1",
+                url(Transport::Direct, "[2001:db8::1]", None, "S-1"),
+            ),
+            (
+                "https://example.com",
+                url(Transport::Other, "example.com", None, ""),
+            ),
+            ("S-1", None),
+            ("lnl://host:port/S-1", None),
+            ("lnl://host:99999/S-1", None),
+            ("lnl:///S-1", None),
+            ("lnl://:80/S-1", None),
+        ];
+        for (text, expected) in cases {
+            let parsed = text
+                .parse::<SessionUrl>()
+                .ok()
+                .map(|url| (url.transport, url.host, url.port, url.path));
+            assert_eq!(parsed, expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn schemes_map_to_transports() {
+        let cases = [
+            ("lnl", Transport::Direct),
+            ("lnl-nat", Transport::NatPunchthrough),
+            ("lnl-relay", Transport::Relay),
+            ("neos-relay", Transport::Relay),
+            ("neos-steam", Transport::Steam),
+            ("LNL", Transport::Other),
+            ("", Transport::Other),
+        ];
+        for (scheme, transport) in cases {
+            assert_eq!(Transport::for_scheme(scheme), transport, "{}", scheme);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use super::{
+    backup::{AssetUri, Backup, Detected, Ogg, SZBson, Unknown, Webp},
+    RcStr,
+};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Classifies an asset from its leading bytes, for assets whose URL carries no extension.
+pub const SNIFF_LEN: usize = 12;
+
+pub fn sniff(header: &[u8]) -> Option<&'static str> {
+    let kind = match header {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "webp",
+        [b'O', b'g', b'g', b'S', ..] => "ogg",
+        [0x89, b'P', b'N', b'G', ..] => "png",
+        [0xFF, 0xD8, 0xFF, ..] => "jpg",
+        [b'G', b'I', b'F', b'8', ..] => "gif",
+        [b'f', b'L', b'a', b'C', ..] => "flac",
+        [b'I', b'D', b'3', ..] => "mp3",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "mp4",
+        [0x05, b'M', b'e', b's', b'h', b'X', ..] => "meshx",
+        // lc=3 lp=0 pb=2, the properties byte every 7zbson is written with.
+        [0x5D, ..] => "7zbson",
+        _ => return None,
+    };
+    Some(kind)
+}
+
+impl AssetUri {
+    // With no extension in its URL, what the asset's bytes say it is: its own variant for
+    // 7zbson, webp and ogg, Detected for the rest. Unchanged if it already had a kind, or
+    // its bytes are missing or unrecognized.
+    pub fn detect(self, b: &Backup) -> AssetUri {
+        match self {
+            AssetUri::Unknown(Unknown { kind: None, id }) => match b.sniff_asset(&id) {
+                Ok(Some(kind)) => typed(kind, id),
+                _ => AssetUri::Unknown(Unknown { kind: None, id }),
+            },
+            uri => uri,
+        }
+    }
+}
+
+fn typed(kind: &'static str, id: RcStr) -> AssetUri {
+    match kind {
+        "7zbson" => AssetUri::SZBson(SZBson(id)),
+        "webp" => AssetUri::Webp(Webp(id)),
+        "ogg" => AssetUri::Ogg(Ogg(id)),
+        kind => AssetUri::Detected(Detected { kind, id }),
+    }
+}
+
+impl Backup {
+    // Sniffs every asset a record refers to without an extension, the way detect does,
+    // and gives its URIs the kind found. Returns how many URIs changed.
+    pub fn detect_asset_kinds(&mut self) -> usize {
+        let untyped: BTreeSet<RcStr> = self
+            .accounts
+            .values()
+            .flat_map(|account| account.records.values())
+            .flat_map(|rec| [&rec.asset_uri, &rec.thumbnail_uri])
+            .filter_map(|uri| match uri {
+                Some(AssetUri::Unknown(Unknown { kind: None, id })) => Some(id.clone()),
+                _ => None,
+            })
+            .collect();
+        let kinds: BTreeMap<RcStr, &'static str> = untyped
+            .into_par_iter()
+            .filter_map(|id| Some((id.clone(), self.sniff_asset(&id).ok()??)))
+            .collect();
+
+        let mut changed = 0;
+        for account in self.accounts.values_mut() {
+            for rec in account.records.values_mut() {
+                for uri in [&mut rec.asset_uri, &mut rec.thumbnail_uri] {
+                    let Some(AssetUri::Unknown(Unknown { kind: None, id })) = uri else {
+                        continue;
+                    };
+                    if let Some(kind) = kinds.get(id) {
+                        *uri = Some(typed(kind, id.clone()));
+                        changed += 1;
+                    }
+                }
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_bytes() {
+        let cases: [(&[u8], Option<&str>); 16] = [
+            (b"RIFF\x10\0\0\0WEBPVP8 ", Some("webp")),
+            (b"OggS\0\x02", Some("ogg")),
+            (b"\x89PNG\r\n\x1a\n", Some("png")),
+            (b"\xFF\xD8\xFF\xE0", Some("jpg")),
+            (b"GIF89a", Some("gif")),
+            (b"fLaC\0\0\0\x22", Some("flac")),
+            (b"ID3\x04\0", Some("mp3")),
+            (b"\0\0\0\x20ftypisom", Some("mp4")),
+            (b"\x05MeshX\x01", Some("meshx")),
+            (b"\x5D\0\0\x10\0", Some("7zbson")),
+            // Close, but not the format, or cut short before the magic ends.
+            (b"RIFF\x10\0\0\0WAVEfmt ", None),
+            (b"RIFF\x10\0\0\0WEB", None),
+            (b"\x89PN", None),
+            (b"\xFF\xD8", None),
+            (b"PK\x03\x04", None),
+            (b"", None),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(sniff(header), expected, "{:?}", header);
+        }
+    }
+}
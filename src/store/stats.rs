@@ -0,0 +1,443 @@
+use super::{
+    backup::{
+        AssetUri, Backup, Component, Error, ErrorInfo, LoadOptions, Manifest, RecordType, SZBson,
+        Slot, WellKnownAssetKind,
+    },
+    borrowed::{self, View},
+    journal::Journal,
+    labels::Labels,
+    metrics::MetricDef,
+    sample::Estimate,
+    timings::{self, Phase},
+    warnings::WarningKind,
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Stats {
+    pub records_by_type: BTreeMap<RecordType, u64>,
+    pub bytes_by_type: BTreeMap<RecordType, u64>,
+    pub records_per_month: BTreeMap<RcStr, u64>,
+    pub messages_per_month: BTreeMap<RcStr, u64>,
+    // Messages exchanged with contacts under each user-defined label.
+    pub messages_by_label: BTreeMap<RcStr, u64>,
+    // Metrics defined in the config, by name.
+    pub custom_metrics: BTreeMap<RcStr, u64>,
+    pub component_types: BTreeMap<RcStr, u64>,
+    // Records carrying each tag, across every account.
+    pub tags: BTreeMap<RcStr, u64>,
+    // The same breakdowns for each account folder on its own.
+    pub accounts: BTreeMap<RcStr, AccountStats>,
+    pub asset_kinds: BTreeMap<RcStr, AssetKindStats>,
+    pub message_count: u64,
+    pub asset_bytes: u64,
+    pub missing_assets: BTreeSet<RcStr>,
+    // Nothing to look assets up in, so none are counted missing.
+    pub asset_store_unavailable: bool,
+    // Asset count per asset directory that satisfied it, primary and fallbacks.
+    pub assets_by_root: BTreeMap<RcStr, u64>,
+    pub last_record_time: Option<DateTime<Utc>>,
+    pub manifests_scanned: u64,
+    pub manifest_errors: u64,
+    // Set when component counts were scaled up from a sample of the manifests.
+    pub components_estimate: Option<Estimate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AccountStats {
+    pub records_by_type: BTreeMap<RecordType, u64>,
+    // Assets the account's records reference, each counted once within the account;
+    // one shared with another account counts toward both.
+    pub asset_bytes: u64,
+    pub messages_by_contact: BTreeMap<RcStr, u64>,
+    pub storage: Vec<StorageQuota>,
+}
+
+// A group's storage as the account's export of it recorded it.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StorageQuota {
+    pub group: RcStr,
+    pub name: Option<RcStr>,
+    pub quota_bytes: u64,
+    pub used_bytes: u64,
+    // The account's own share, negative quotas meaning unlimited.
+    pub member_quota_bytes: Option<i64>,
+    pub member_used_bytes: Option<u64>,
+}
+
+impl StorageQuota {
+    pub fn used_fraction(&self) -> Option<f64> {
+        (self.quota_bytes > 0).then(|| self.used_bytes as f64 / self.quota_bytes as f64)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AssetKindStats {
+    pub count: u64,
+    pub bytes: u64,
+    pub uncompressed_bytes: u64,
+}
+
+impl AssetKindStats {
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.uncompressed_bytes > 0 && self.bytes > 0)
+            .then(|| self.uncompressed_bytes as f64 / self.bytes as f64)
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsDelta {
+    pub records_by_type: BTreeMap<RecordType, i64>,
+    pub message_count: i64,
+    pub asset_bytes: i64,
+    pub newly_missing_assets: Vec<RcStr>,
+    pub recovered_assets: Vec<RcStr>,
+}
+
+impl Stats {
+    pub fn compute(backup: &Backup) -> Self {
+        timings::time(Phase::Compute, 0, || Self::tally(backup))
+    }
+
+    fn tally(backup: &Backup) -> Self {
+        let mut stats = Self::default();
+        let mut seen_assets = BTreeSet::new();
+
+        for (account_name, account) in &backup.accounts {
+            let mut account_assets = BTreeSet::new();
+            for rec in account.records.values() {
+                stats.add_record(
+                    account_name,
+                    &rec.record_type,
+                    rec.creation_time,
+                    rec.last_modification_time,
+                    rec.neos_db_manifest.iter().map(|a| a.bytes).sum(),
+                    rec.tags.iter().map(|t| t.as_str()),
+                );
+                for asset in &rec.neos_db_manifest {
+                    if account_assets.insert(&asset.hash) {
+                        stats.account(account_name).asset_bytes += asset.bytes;
+                    }
+                    if seen_assets.insert(asset.hash.clone()) {
+                        stats.add_asset(backup, &asset.hash, asset.bytes);
+                    }
+                }
+            }
+            for (contact, messages) in &account.messages {
+                for msg in messages {
+                    stats.add_message(account_name, contact, Some(msg.send_time));
+                }
+            }
+            for (id, group) in &account.groups {
+                let member = account
+                    .group_members
+                    .get(id)
+                    .and_then(|members| members.values().find(|m| m.id == *account_name));
+                stats.account(account_name).storage.push(StorageQuota {
+                    group: id.clone(),
+                    name: Some(group.name.clone()),
+                    quota_bytes: group.quota_bytes,
+                    used_bytes: group.used_bytes,
+                    member_quota_bytes: member.map(|m| m.quota_bytes),
+                    member_used_bytes: member.map(|m| m.used_bytes),
+                });
+            }
+        }
+
+        stats
+    }
+
+    // The same counts as compute, read straight off disk through borrowed views rather
+    // than loading the backup first. Nothing beyond the asset hashes is kept.
+    pub fn compute_streaming(root: &Path, options: &LoadOptions) -> Result<Self, Error> {
+        let mut assets = Backup::default();
+        assets.assets_dir = root.join("Assets");
+        assets.metadata_only = !assets.assets_dir.is_dir();
+        for dir in &options.asset_roots {
+            assets.add_asset_root(dir.clone());
+        }
+
+        let mut stats = Self::default();
+        let mut seen_assets = BTreeSet::<String>::new();
+        let mut account_assets = BTreeSet::<(String, String)>::new();
+        borrowed::scan(root, options, |account, view| match view {
+            View::Record(rec) => {
+                let manifest = rec.neos_db_manifest.unwrap_or_default();
+                stats.add_record(
+                    account,
+                    &rec.record_type,
+                    rec.creation_time,
+                    rec.last_modification_time,
+                    manifest.iter().map(|a| a.bytes).sum(),
+                    rec.tags.iter().flatten().map(|t| t.as_ref()),
+                );
+                for asset in &manifest {
+                    let key = (account.to_owned(), asset.hash.to_string());
+                    if !account_assets.contains(&key) {
+                        stats.account(account).asset_bytes += asset.bytes;
+                        account_assets.insert(key);
+                    }
+                }
+                for asset in manifest {
+                    if !seen_assets.contains(asset.hash.as_ref()) {
+                        stats.add_asset(&assets, &asset.hash, asset.bytes);
+                        seen_assets.insert(asset.hash.into_owned());
+                    }
+                }
+            }
+            View::Message { contact, message } => {
+                stats.add_message(account, contact, message.send_time)
+            }
+        })?;
+        Ok(stats)
+    }
+
+    fn account(&mut self, name: &str) -> &mut AccountStats {
+        if !self.accounts.contains_key(name) {
+            self.accounts.insert(name.into(), AccountStats::default());
+        }
+        self.accounts.get_mut(name).unwrap()
+    }
+
+    fn add_record<'a>(
+        &mut self,
+        account: &str,
+        record_type: &RecordType,
+        created: Option<DateTime<Utc>>,
+        modified: Option<DateTime<Utc>>,
+        bytes: u64,
+        tags: impl Iterator<Item = &'a str>,
+    ) {
+        *self.records_by_type.entry(record_type.clone()).or_default() += 1;
+        *self
+            .account(account)
+            .records_by_type
+            .entry(record_type.clone())
+            .or_default() += 1;
+        for tag in tags {
+            *self.tags.entry(tag.into()).or_default() += 1;
+        }
+        *self.bytes_by_type.entry(record_type.clone()).or_default() += bytes;
+        if let Some(time) = created.or(modified) {
+            *self.records_per_month.entry(month(&time)).or_default() += 1;
+        }
+        if modified.is_some() {
+            self.last_record_time = self.last_record_time.max(modified);
+        }
+    }
+
+    // Only called once per asset, however many records use it.
+    fn add_asset(&mut self, backup: &Backup, hash: &str, bytes: u64) {
+        if !backup.has_asset_store() {
+            self.asset_store_unavailable = true;
+        } else if let Some(root) = backup.asset_root(hash) {
+            self.asset_bytes += bytes;
+            *self
+                .assets_by_root
+                .entry(root.display().to_string().into())
+                .or_default() += 1;
+        } else {
+            self.missing_assets.insert(RcStr::new(hash.to_owned()));
+        }
+    }
+
+    fn add_message(&mut self, account: &str, contact: &str, send_time: Option<DateTime<Utc>>) {
+        self.message_count += 1;
+        *self
+            .account(account)
+            .messages_by_contact
+            .entry(contact.into())
+            .or_default() += 1;
+        if let Some(time) = send_time {
+            *self.messages_per_month.entry(month(&time)).or_default() += 1;
+        }
+    }
+
+    // A contact under several labels counts toward each of them.
+    pub fn count_labels(&mut self, backup: &Backup, labels: &Labels) {
+        for account in backup.accounts.values() {
+            for (contact, messages) in &account.messages {
+                for label in labels.of(contact) {
+                    *self.messages_by_label.entry(label.clone()).or_default() +=
+                        messages.len() as u64;
+                }
+            }
+        }
+    }
+
+    pub fn evaluate_metrics(&mut self, backup: &Backup, metrics: &[MetricDef]) {
+        for metric in metrics {
+            self.custom_metrics
+                .insert(metric.name.clone().into(), metric.evaluate(backup));
+        }
+    }
+
+    pub fn diff(&self, baseline: &Stats) -> StatsDelta {
+        let mut records_by_type = BTreeMap::new();
+        for kind in self
+            .records_by_type
+            .keys()
+            .chain(baseline.records_by_type.keys())
+        {
+            let now = self.records_by_type.get(kind).copied().unwrap_or_default();
+            let then = baseline
+                .records_by_type
+                .get(kind)
+                .copied()
+                .unwrap_or_default();
+            if now != then {
+                records_by_type.insert(kind.clone(), now as i64 - then as i64);
+            }
+        }
+
+        StatsDelta {
+            records_by_type,
+            message_count: self.message_count as i64 - baseline.message_count as i64,
+            asset_bytes: self.asset_bytes as i64 - baseline.asset_bytes as i64,
+            newly_missing_assets: self
+                .missing_assets
+                .difference(&baseline.missing_assets)
+                .cloned()
+                .collect(),
+            recovered_assets: baseline
+                .missing_assets
+                .difference(&self.missing_assets)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    // Opening every manifest is slow, so component counting is opt-in.
+    pub fn scan_manifests(&mut self, backup: &Backup) {
+        // A disabled journal never touches the disk, so this can't fail.
+        let _ = self.scan_manifests_journaled(backup, &mut Journal::disabled());
+    }
+
+    // Component counts per manifest are journaled so an interrupted scan can resume.
+    pub fn scan_manifests_journaled(
+        &mut self,
+        backup: &Backup,
+        journal: &mut Journal<Option<BTreeMap<RcStr, u64>>>,
+    ) -> Result<(), Error> {
+        for account in backup.accounts.values() {
+            for rec in account.records.values() {
+                if let Some(AssetUri::SZBson(asset)) = &rec.asset_uri {
+                    if !backup.manifest_sample.contains(&asset.0) {
+                        continue;
+                    }
+                    let counts = journal.get_or_insert_with(&asset.0, || {
+                        let res: Result<Manifest, _> = asset.open(backup);
+                        res.map_err(|e| {
+                            let path = backup.asset_path(&asset.0).unwrap_or_default();
+                            let kind = WarningKind::UnreadableManifest {
+                                asset: asset.0.clone(),
+                                error: ErrorInfo::from(&e),
+                            };
+                            backup.warnings.push(&path, kind);
+                        })
+                        .ok()
+                        .map(|manifest| {
+                            let mut counts = BTreeMap::new();
+                            count_manifest(&manifest, &mut counts);
+                            counts
+                        })
+                    })?;
+                    match counts {
+                        Some(counts) => {
+                            self.manifests_scanned += 1;
+                            for (cs_type, count) in counts {
+                                *self.component_types.entry(cs_type).or_default() += count;
+                            }
+                        }
+                        None => self.manifest_errors += 1,
+                    }
+                }
+            }
+        }
+        self.components_estimate = backup.manifest_sample.estimate();
+        if let Some(estimate) = &self.components_estimate {
+            for count in self.component_types.values_mut() {
+                *count = estimate.extrapolate(*count);
+            }
+        }
+        Ok(())
+    }
+
+    // Kinds come from the asset URL, falling back to the file's magic bytes.
+    pub fn scan_assets(&mut self, backup: &Backup) {
+        let mut declared = BTreeMap::<RcStr, Option<RcStr>>::new();
+        for account in backup.accounts.values() {
+            for rec in account.records.values() {
+                for uri in [&rec.asset_uri, &rec.thumbnail_uri].into_iter().flatten() {
+                    if let Some(id) = uri.asset_id() {
+                        let kind = declared.entry(id.clone()).or_default();
+                        if kind.is_none() {
+                            *kind = uri.kind().map(|k| k.to_owned().into());
+                        }
+                    }
+                }
+                for asset in &rec.neos_db_manifest {
+                    declared.entry(asset.hash.clone()).or_default();
+                }
+            }
+        }
+
+        for (id, kind) in declared {
+            let Some(Ok(meta)) = backup.asset_path(id.as_str()).map(fs::metadata) else {
+                continue;
+            };
+            let kind = kind
+                .or_else(|| {
+                    let sniffed = backup.sniff_asset(id.as_str()).ok().flatten();
+                    sniffed.map(|k| k.to_owned().into())
+                })
+                .unwrap_or_else(|| "unknown".to_owned().into());
+            let entry = self.asset_kinds.entry(kind.clone()).or_default();
+            entry.count += 1;
+            entry.bytes += meta.len();
+            if kind.as_str() == "7zbson" {
+                if let Ok(size) = SZBson(id).uncompressed_size(backup) {
+                    entry.uncompressed_bytes += size;
+                }
+            }
+        }
+    }
+}
+
+fn count_manifest(manifest: &Manifest, counts: &mut BTreeMap<RcStr, u64>) {
+    if let Some(slot) = &manifest.object {
+        count_slot(slot, counts);
+    }
+    for component in manifest.assets.iter().flatten() {
+        count_component(component, counts);
+    }
+}
+
+fn count_slot(slot: &Slot, counts: &mut BTreeMap<RcStr, u64>) {
+    for component in slot.components.data() {
+        count_component(component, counts);
+    }
+    for child in &slot.children {
+        count_slot(child, counts);
+    }
+}
+
+fn count_component(component: &Component, counts: &mut BTreeMap<RcStr, u64>) {
+    *counts.entry(component.cs_type.clone()).or_default() += 1;
+}
+
+fn month(time: &DateTime<Utc>) -> RcStr {
+    time.format("%Y-%m").to_string().into()
+}
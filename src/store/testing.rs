@@ -0,0 +1,60 @@
+use super::backup::{Backup, LoadOptions};
+use serde_json::{json, Value};
+use std::{fs, path::PathBuf};
+
+// A backup folder under the temp dir, removed again when dropped. Tests run in parallel,
+// so each names its own.
+pub struct TempBackup {
+    pub root: PathBuf,
+}
+
+impl TempBackup {
+    pub fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("statbox-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&root).ok();
+        fs::create_dir_all(root.join("Assets")).unwrap();
+        Self { root }
+    }
+
+    pub fn write_record(&self, account: &str, record: &Value) {
+        let dir = self.root.join(account).join("Records");
+        fs::create_dir_all(&dir).unwrap();
+        let id = record["id"].as_str().unwrap();
+        fs::write(
+            dir.join(format!("{}.json", id)),
+            serde_json::to_vec(record).unwrap(),
+        )
+        .unwrap();
+    }
+
+    pub fn write_asset(&self, hash: &str, bytes: &[u8]) {
+        fs::write(self.root.join("Assets").join(hash), bytes).unwrap();
+    }
+
+    pub fn load(&self) -> Backup {
+        let options = LoadOptions {
+            strict: true,
+            ..Default::default()
+        };
+        Backup::load_with(self.root.clone(), &options).unwrap()
+    }
+}
+
+impl Drop for TempBackup {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.root).ok();
+    }
+}
+
+// A record as the late format writes it, an object at version 1 unless changed after.
+pub fn record(id: &str, owner: &str, path: &str) -> Value {
+    json!({
+        "id": id, "ownerId": owner, "assetUri": null, "globalVersion": 1,
+        "localVersion": 1, "lastModifyingUserId": owner, "name": id,
+        "recordType": "object", "ownerName": "owner", "tags": null, "path": path,
+        "lastModificationTime": "2021-03-04T05:06:07Z",
+        "creationTime": "2021-03-04T05:06:07Z", "firstPublishTime": null,
+        "isPublic": false, "isForPatrons": false, "visits": 0, "rating": 0,
+        "randomOrder": 0, "submissions": null, "neosDBmanifest": null,
+    })
+}
@@ -0,0 +1,107 @@
+use super::{
+    backup::{AssetUri, Backup, Component, FieldValue, Manifest, Slot, WellKnownAssetKind},
+    redact::{Redact, Redactor},
+    RcStr,
+};
+use bson::Bson;
+use serde::Serialize;
+
+// Components that commonly hold text or links people wrote, by short type name, and the
+// field holding it.
+pub const TEXT_FIELDS: &[(&str, &str)] = &[
+    ("Comment", "Text"),
+    ("TextRenderer", "Text"),
+    ("ReferenceField<Uri>", "Reference"),
+    ("ValueField<Uri>", "Value"),
+    ("StaticBinary", "URL"),
+];
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TextField {
+    pub account: RcStr,
+    pub record: RcStr,
+    // Slot names from the manifest root down to the slot holding the component.
+    pub slot_path: Vec<RcStr>,
+    pub component: String,
+    pub field: &'static str,
+    pub value: RcStr,
+}
+
+// Every non-empty well-known text field in every record's manifest, in inventory order.
+pub fn text_fields(backup: &Backup) -> Vec<TextField> {
+    let mut out = Vec::new();
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let Some(AssetUri::SZBson(asset)) = &rec.asset_uri else {
+                continue;
+            };
+            let res: Result<Manifest, _> = asset.open(backup);
+            let Ok(manifest) = res else {
+                continue;
+            };
+            for (slot_path, component, field, value) in manifest_text(&manifest) {
+                out.push(TextField {
+                    account: account_name.clone(),
+                    record: rec.id.clone(),
+                    slot_path,
+                    component,
+                    field,
+                    value,
+                });
+            }
+        }
+    }
+    out
+}
+
+pub fn manifest_text(manifest: &Manifest) -> Vec<(Vec<RcStr>, String, &'static str, RcStr)> {
+    let mut out = Vec::new();
+    if let Some(slot) = &manifest.object {
+        slot_text(slot, &mut Vec::new(), &mut out);
+    }
+    out
+}
+
+fn slot_text(
+    slot: &Slot,
+    path: &mut Vec<RcStr>,
+    out: &mut Vec<(Vec<RcStr>, String, &'static str, RcStr)>,
+) {
+    path.push(slot.name.data().clone().unwrap_or_default());
+    for component in slot.components.data() {
+        if let Some((component, field, value)) = component_text(component) {
+            out.push((path.clone(), component, field, value));
+        }
+    }
+    for child in &slot.children {
+        slot_text(child, path, out);
+    }
+    path.pop();
+}
+
+pub fn component_text(component: &Component) -> Option<(String, &'static str, RcStr)> {
+    let short_type = component.short_type();
+    let (_, field) = TEXT_FIELDS.iter().find(|(t, _)| *t == short_type)?;
+    let (_, value) = component
+        .data
+        .fields
+        .iter()
+        .find(|(name, _)| name.as_str() == *field)?;
+    let value = match value {
+        FieldValue::Str(s) => s.clone(),
+        // Most fields are stored as an {ID, Data} pair the typed model leaves as BSON.
+        FieldValue::Dunno(Bson::Document(doc)) => doc.get_str("Data").ok()?.to_owned().into(),
+        _ => return None,
+    };
+    (!value.trim().is_empty()).then_some((short_type, *field, value))
+}
+
+impl Redact for TextField {
+    fn redact(&mut self, r: &Redactor) {
+        self.value = r.text(&self.value);
+        for name in &mut self.slot_path {
+            *name = r.text(name);
+        }
+    }
+}
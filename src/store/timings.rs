@@ -0,0 +1,78 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+// Where a command spends its time, collected process-wide so the loader doesn't need a
+// timer threaded through every call. Costs one atomic load per phase while disabled.
+// Report compute includes whatever manifests a report opens, which also count under
+// their own phases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    DirectoryWalk,
+    JsonParse,
+    Decompress,
+    BsonParse,
+    Compute,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Phase::DirectoryWalk => "directory walk",
+            Phase::JsonParse => "JSON parse",
+            Phase::Decompress => "decompress",
+            Phase::BsonParse => "BSON parse",
+            Phase::Compute => "report compute",
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timing {
+    pub time: Duration,
+    pub count: u64,
+    // Input bytes the phase went through, zero where that means nothing.
+    pub bytes: u64,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMINGS: Mutex<BTreeMap<Phase, Timing>> = Mutex::new(BTreeMap::new());
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn record(phase: Phase, time: Duration, count: u64, bytes: u64) {
+    let mut timings = TIMINGS.lock().unwrap_or_else(PoisonError::into_inner);
+    let timing = timings.entry(phase).or_default();
+    timing.time += time;
+    timing.count += count;
+    timing.bytes += bytes;
+}
+
+// Runs f, counting it once against phase along with however many bytes it went through.
+pub fn time<T>(phase: Phase, bytes: u64, f: impl FnOnce() -> T) -> T {
+    if !enabled() {
+        return f();
+    }
+    let start = Instant::now();
+    let out = f();
+    record(phase, start.elapsed(), 1, bytes);
+    out
+}
+
+// Everything recorded so far, in phase order, leaving nothing behind.
+pub fn take() -> Vec<(Phase, Timing)> {
+    let mut timings = TIMINGS.lock().unwrap_or_else(PoisonError::into_inner);
+    std::mem::take(&mut *timings).into_iter().collect()
+}
@@ -0,0 +1,163 @@
+use super::{
+    backup::{AssetUri, Backup, Component, Manifest, Slot, WellKnownAssetKind},
+    RcStr,
+};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::BTreeMap};
+
+// Which component types a manifest may use to pass, by type name without generic
+// arguments. A pattern ending in * matches every name starting with the rest.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ValidationProfile {
+    pub name: String,
+    pub allow: Vec<String>,
+    // Checked after allow, for carving exceptions out of a wildcard.
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+impl ValidationProfile {
+    // Ones usable without any configuration. Plugin and mod components live outside
+    // FrooxEngine, and Resonite dropped LogiX for ProtoFlux.
+    pub fn built_in() -> Vec<Self> {
+        let profile = |name: &str, allow: &[&str], deny: &[&str]| ValidationProfile {
+            name: name.to_owned(),
+            allow: allow.iter().map(|s| s.to_string()).collect(),
+            deny: deny.iter().map(|s| s.to_string()).collect(),
+        };
+        vec![
+            profile("vanilla-neos", &["FrooxEngine.*"], &[]),
+            profile(
+                "resonite-compatible",
+                &["FrooxEngine.*"],
+                &["FrooxEngine.LogiX.*"],
+            ),
+        ]
+    }
+
+    // Configured profiles win over built-in ones of the same name.
+    pub fn find<'a>(configured: &'a [Self], name: &str) -> Option<Cow<'a, Self>> {
+        match configured.iter().find(|p| p.name == name) {
+            Some(profile) => Some(Cow::Borrowed(profile)),
+            None => Self::built_in()
+                .into_iter()
+                .find(|p| p.name == name)
+                .map(Cow::Owned),
+        }
+    }
+
+    pub fn allows(&self, type_name: &str) -> bool {
+        self.allow.iter().any(|p| matches(p, type_name))
+            && !self.deny.iter().any(|p| matches(p, type_name))
+    }
+}
+
+// FrooxEngine.ValueField`1[[System.Single, ...]] -> FrooxEngine.ValueField
+pub fn type_name(component: &Component) -> &str {
+    let cs_type = component.cs_type.as_str();
+    cs_type.split(['`', '[']).next().unwrap_or(cs_type)
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordViolations {
+    pub account: RcStr,
+    pub record: RcStr,
+    pub name: RcStr,
+    // Disallowed type name to how many components of it the manifest has.
+    pub components: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    pub profile: String,
+    pub records_checked: u64,
+    // Manifests that didn't parse, so couldn't be checked either way.
+    pub unreadable: Vec<RcStr>,
+    pub violations: Vec<RecordViolations>,
+}
+
+impl ValidationReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.passed() {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+fn count_slot(slot: &Slot, profile: &ValidationProfile, found: &mut BTreeMap<String, u64>) {
+    count_components(slot.components.data(), profile, found);
+    for child in &slot.children {
+        count_slot(child, profile, found);
+    }
+}
+
+fn count_components(
+    components: &[Component],
+    profile: &ValidationProfile,
+    found: &mut BTreeMap<String, u64>,
+) {
+    for component in components {
+        let name = type_name(component);
+        if !profile.allows(name) {
+            *found.entry(name.to_owned()).or_default() += 1;
+        }
+    }
+}
+
+// Every manifest in the backup against the profile, records with violations in account
+// then record order.
+pub fn validate(backup: &Backup, profile: &ValidationProfile) -> ValidationReport {
+    let mut report = ValidationReport {
+        profile: profile.name.clone(),
+        records_checked: 0,
+        unreadable: Vec::new(),
+        violations: Vec::new(),
+    };
+    for (account_name, account) in &backup.accounts {
+        for rec in account.records.values() {
+            let Some(AssetUri::SZBson(asset)) = &rec.asset_uri else {
+                continue;
+            };
+            let res: Result<Manifest, _> = asset.open(backup);
+            let Ok(manifest) = res else {
+                report.unreadable.push(rec.id.clone());
+                continue;
+            };
+            report.records_checked += 1;
+            let mut found = BTreeMap::new();
+            if let Some(slot) = &manifest.object {
+                count_slot(slot, profile, &mut found);
+            }
+            count_components(
+                manifest.assets.as_deref().unwrap_or_default(),
+                profile,
+                &mut found,
+            );
+            if !found.is_empty() {
+                report.violations.push(RecordViolations {
+                    account: account_name.clone(),
+                    record: rec.id.clone(),
+                    name: rec.name.clone(),
+                    components: found,
+                });
+            }
+        }
+    }
+    report
+}
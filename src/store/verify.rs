@@ -0,0 +1,615 @@
+use super::{
+    backup::{AssetUri, Backup, Error, ErrorCategory, ErrorInfo, Manifest, WellKnownAssetKind},
+    hash::{hash_asset, hash_bytes, AssetId},
+    journal::Journal,
+    limits::ManifestLimits,
+    RcStr,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+// Ordered by severity, the most severe finding decides the exit code.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum FindingKind {
+    MissingAsset,
+    UnparseableManifest,
+    CorruptAsset,
+    LoadFailed,
+}
+
+impl FindingKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FindingKind::MissingAsset => 3,
+            FindingKind::UnparseableManifest => 4,
+            FindingKind::CorruptAsset => 6,
+            FindingKind::LoadFailed => 5,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub account: Option<RcStr>,
+    pub record: Option<RcStr>,
+    pub asset: Option<RcStr>,
+    #[serde(flatten)]
+    pub error: ErrorInfo,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub records_checked: u64,
+    pub assets_checked: u64,
+    pub manifests_checked: u64,
+    // A metadata-only backup, where only the records themselves could be checked.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub asset_store_unavailable: bool,
+    pub findings: Vec<Finding>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub report_cards: Vec<ReportCard>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<AssetIntegrity>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AssetStatus {
+    Ok,
+    // The content doesn't hash to the asset's name.
+    HashMismatch,
+    Undecompressable,
+    Unparseable,
+}
+
+// Cached per hash between deep runs, rechecked if the file's length changes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetCheck {
+    pub len: u64,
+    pub status: AssetStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportCard {
+    pub account: RcStr,
+    pub assets: u64,
+    pub present: u64,
+    pub corrupted: u64,
+    pub manifests: u64,
+    pub parsed: u64,
+    // Assets verified this run rather than taken from the cache, shared ones count once.
+    pub verified: u64,
+}
+
+impl VerifyReport {
+    pub fn load_failed(e: &Error) -> Self {
+        Self {
+            findings: vec![Finding {
+                kind: FindingKind::LoadFailed,
+                account: None,
+                record: None,
+                asset: None,
+                error: e.into(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    pub fn check(backup: &Backup) -> Self {
+        // A disabled journal never touches the disk, so this can't fail.
+        Self::check_journaled(backup, &mut Journal::disabled()).unwrap_or_default()
+    }
+
+    // Manifest parse results are journaled so an interrupted check can resume, the
+    // cheap existence checks are always redone.
+    pub fn check_journaled(
+        backup: &Backup,
+        journal: &mut Journal<Option<ErrorInfo>>,
+    ) -> Result<Self, Error> {
+        let mut report = Self {
+            asset_store_unavailable: !backup.has_asset_store(),
+            ..Default::default()
+        };
+        let mut seen_assets = BTreeSet::new();
+
+        for (account_name, account) in &backup.accounts {
+            for rec in account.records.values() {
+                report.records_checked += 1;
+                if report.asset_store_unavailable {
+                    continue;
+                }
+                for asset in &rec.neos_db_manifest {
+                    if !seen_assets.insert(asset.hash.clone()) {
+                        continue;
+                    }
+                    report.assets_checked += 1;
+                    if !backup.has_asset(asset.hash.as_str()) {
+                        report.findings.push(Finding {
+                            kind: FindingKind::MissingAsset,
+                            account: Some(account_name.clone()),
+                            record: Some(rec.id.clone()),
+                            asset: Some(asset.hash.clone()),
+                            error: ErrorInfo::new(
+                                ErrorCategory::MissingAsset,
+                                "asset-missing",
+                                "asset is not present in the asset store",
+                            ),
+                        });
+                    }
+                }
+                if let Some(AssetUri::SZBson(asset)) = &rec.asset_uri {
+                    if backup.has_asset(asset.0.as_str()) {
+                        report.manifests_checked += 1;
+                        let error = journal.get_or_insert_with(&asset.0, || {
+                            let res: Result<Manifest, _> = asset.open(backup);
+                            res.err().map(|e| ErrorInfo::from(&e))
+                        })?;
+                        if let Some(error) = error {
+                            report.findings.push(Finding {
+                                kind: FindingKind::UnparseableManifest,
+                                account: Some(account_name.clone()),
+                                record: Some(rec.id.clone()),
+                                asset: Some(asset.0.clone()),
+                                error,
+                            });
+                        }
+                    } else if seen_assets.insert(asset.0.clone()) {
+                        report.findings.push(Finding {
+                            kind: FindingKind::MissingAsset,
+                            account: Some(account_name.clone()),
+                            record: Some(rec.id.clone()),
+                            asset: Some(asset.0.clone()),
+                            error: ErrorInfo::new(
+                                ErrorCategory::MissingAsset,
+                                "asset-missing",
+                                "manifest is not present in the asset store",
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    // Hashes every present asset, fully decompresses and parses manifests, and grades each
+    // account. Results already in the cache are reused, the rest are checked in parallel.
+    pub fn deep_check(
+        &mut self,
+        backup: &Backup,
+        cache: &mut Journal<AssetCheck>,
+    ) -> Result<(), Error> {
+        let mut accounts = Vec::new();
+        let mut pending = BTreeMap::new();
+        for (account_name, account) in &backup.accounts {
+            let mut assets = BTreeMap::<RcStr, bool>::new();
+            for rec in account.records.values() {
+                for asset in &rec.neos_db_manifest {
+                    assets.entry(asset.hash.clone()).or_default();
+                }
+                for uri in [&rec.asset_uri, &rec.thumbnail_uri].into_iter().flatten() {
+                    if let Some(id) = uri.asset_id() {
+                        *assets.entry(id.clone()).or_default() |=
+                            matches!(uri, AssetUri::SZBson(_));
+                    }
+                }
+            }
+            for (hash, is_manifest) in &assets {
+                let Some(path) = backup.asset_path(hash) else {
+                    continue;
+                };
+                let len = fs::metadata(&path)?.len();
+                if cache.get(hash).is_none_or(|c| c.len != len) {
+                    pending.insert(hash.clone(), (path, *is_manifest));
+                }
+            }
+            accounts.push((account_name, assets));
+        }
+
+        let jobs: Vec<_> = pending
+            .iter()
+            .map(|(hash, (path, is_manifest))| (hash.to_string(), path.clone(), *is_manifest))
+            .collect();
+        let checked: Vec<_> = jobs
+            .into_par_iter()
+            .map(|(hash, path, is_manifest)| {
+                let check = check_asset(&hash, &path, is_manifest, &backup.limits);
+                (hash, check)
+            })
+            .collect();
+        for (hash, check) in checked {
+            cache.insert(hash.into(), check?)?;
+        }
+
+        for (account_name, assets) in accounts {
+            let mut card = ReportCard {
+                account: account_name.clone(),
+                assets: assets.len() as u64,
+                ..Default::default()
+            };
+            for (hash, is_manifest) in assets {
+                card.manifests += u64::from(is_manifest);
+                let Some(check) = cache.get(&hash) else {
+                    continue;
+                };
+                card.present += 1;
+                card.verified += u64::from(pending.contains_key(&hash));
+                match check.status {
+                    AssetStatus::Ok => card.parsed += u64::from(is_manifest),
+                    AssetStatus::Unparseable => {}
+                    AssetStatus::HashMismatch | AssetStatus::Undecompressable => {
+                        card.corrupted += 1;
+                        let code = match check.status {
+                            AssetStatus::HashMismatch => "asset-hash-mismatch",
+                            _ => "lzma-corrupt",
+                        };
+                        self.findings.push(Finding {
+                            kind: FindingKind::CorruptAsset,
+                            account: Some(account_name.clone()),
+                            record: None,
+                            asset: Some(hash.clone()),
+                            error: ErrorInfo::new(
+                                ErrorCategory::Corruption,
+                                code,
+                                check.message.clone().unwrap_or_default(),
+                            ),
+                        });
+                    }
+                }
+            }
+            self.report_cards.push(card);
+        }
+        Ok(())
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.findings
+            .iter()
+            .map(|f| f.kind)
+            .max()
+            .map_or(0, FindingKind::exit_code)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum AssetProblem {
+    // Named for content it doesn't have, at the size manifests expect.
+    Corrupted,
+    // Shorter than manifests expect and not hashing to its name, like a cut off download.
+    Truncated,
+    // Couldn't be read to hash, so whether it's intact isn't known.
+    Unreadable,
+    // Hashes to its name, but manifests list it at another size.
+    SizeMismatch,
+    // Referenced by a record, but in no asset root.
+    Missing,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIssue {
+    pub hash: RcStr,
+    pub problem: AssetProblem,
+    pub expected_bytes: Option<u64>,
+    pub actual_bytes: Option<u64>,
+    pub actual_hash: Option<String>,
+    // The records referencing it, as (account, record).
+    pub records: Vec<(RcStr, RcStr)>,
+    // Why an unreadable file couldn't be read.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIntegrity {
+    pub files: u64,
+    pub bytes: u64,
+    pub ok: u64,
+    // Files no record references, hashed all the same.
+    pub unreferenced: u64,
+    // Files in the asset folder that aren't named by a hash, left alone.
+    pub skipped: Vec<RcStr>,
+    // Most severe first.
+    pub issues: Vec<AssetIssue>,
+}
+
+impl AssetProblem {
+    pub fn code(self) -> &'static str {
+        match self {
+            AssetProblem::Corrupted => "asset-hash-mismatch",
+            AssetProblem::Truncated => "asset-truncated",
+            AssetProblem::Unreadable => "asset-unreadable",
+            AssetProblem::SizeMismatch => "asset-size-mismatch",
+            AssetProblem::Missing => "asset-missing",
+        }
+    }
+}
+
+impl AssetIntegrity {
+    pub fn count(&self, problem: AssetProblem) -> u64 {
+        self.issues.iter().filter(|i| i.problem == problem).count() as u64
+    }
+}
+
+// How far verify_assets has got, passed to its callback from the hashing threads.
+#[derive(Debug, Clone, Copy)]
+pub struct AssetProgress {
+    pub done: u64,
+    pub total: u64,
+    pub bytes: u64,
+}
+
+#[derive(Default)]
+struct Expected {
+    bytes: Option<u64>,
+    records: Vec<(RcStr, RcStr)>,
+}
+
+impl Backup {
+    pub fn verify_assets(&self) -> Result<AssetIntegrity, Error> {
+        self.verify_assets_with(|_| {})
+    }
+
+    // Hashes every file in the asset folder against its name and the sizes the records'
+    // manifests give it, in parallel, and lists referenced assets no root has. A file that
+    // can't be read is listed as unreadable rather than ending the check.
+    pub fn verify_assets_with<F>(&self, progress: F) -> Result<AssetIntegrity, Error>
+    where
+        F: Fn(AssetProgress) + Sync,
+    {
+        let mut expected = BTreeMap::<RcStr, Expected>::new();
+        for (account_name, account) in &self.accounts {
+            for rec in account.records.values() {
+                let user = (account_name.clone(), rec.id.clone());
+                let sized = rec
+                    .neos_db_manifest
+                    .iter()
+                    .map(|a| (&a.hash, Some(a.bytes)));
+                let referenced = [&rec.asset_uri, &rec.thumbnail_uri]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(AssetUri::asset_id)
+                    .map(|id| (id, None));
+                for (hash, bytes) in sized.chain(referenced) {
+                    let entry = expected.entry(hash.clone()).or_default();
+                    entry.bytes = entry.bytes.or(bytes);
+                    if !entry.records.contains(&user) {
+                        entry.records.push(user.clone());
+                    }
+                }
+            }
+        }
+
+        let mut integrity = AssetIntegrity::default();
+        let mut files = Vec::new();
+        if self.assets_dir.is_dir() {
+            for entry in self.assets_dir.read_dir()? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                match name.parse::<AssetId>() {
+                    Ok(_) if entry.file_type()?.is_file() => files.push((name, entry.path())),
+                    _ => integrity.skipped.push(name.into()),
+                }
+            }
+        }
+
+        let total = files.len() as u64;
+        let (done, bytes) = (AtomicU64::new(0), AtomicU64::new(0));
+        let hashed: Vec<_> = files
+            .into_par_iter()
+            .map(|(name, path)| {
+                let read = fs::metadata(&path)
+                    .and_then(|meta| Ok((meta.len(), hash_asset(fs::File::open(&path)?)?)));
+                let len = read.as_ref().map_or(0, |(len, _)| *len);
+                progress(AssetProgress {
+                    done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                    bytes: bytes.fetch_add(len, Ordering::Relaxed) + len,
+                });
+                (name, read)
+            })
+            .collect();
+
+        for (name, read) in hashed {
+            let (expected_bytes, records) = match expected.remove(name.as_str()) {
+                Some(e) => (e.bytes, e.records),
+                None => {
+                    integrity.unreferenced += 1;
+                    (None, Vec::new())
+                }
+            };
+            let (len, actual) = match read {
+                Ok(read) => read,
+                Err(e) => {
+                    integrity.issues.push(AssetIssue {
+                        hash: name.into(),
+                        problem: AssetProblem::Unreadable,
+                        expected_bytes,
+                        actual_bytes: None,
+                        actual_hash: None,
+                        records,
+                        error: Some(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+            integrity.files += 1;
+            integrity.bytes += len;
+            let matches = actual.as_str().eq_ignore_ascii_case(&name);
+            let problem = match (matches, expected_bytes) {
+                (true, Some(want)) if want != len => AssetProblem::SizeMismatch,
+                (true, _) => {
+                    integrity.ok += 1;
+                    continue;
+                }
+                (false, Some(want)) if len < want => AssetProblem::Truncated,
+                (false, _) => AssetProblem::Corrupted,
+            };
+            integrity.issues.push(AssetIssue {
+                hash: name.into(),
+                problem,
+                expected_bytes,
+                actual_bytes: Some(len),
+                actual_hash: (!matches).then(|| actual.to_string()),
+                records,
+                error: None,
+            });
+        }
+
+        // Whatever is left wasn't in the asset folder; a fallback root may still have it.
+        for (hash, e) in expected {
+            if !self.has_asset(&hash) {
+                integrity.issues.push(AssetIssue {
+                    hash,
+                    problem: AssetProblem::Missing,
+                    expected_bytes: e.bytes,
+                    actual_bytes: None,
+                    actual_hash: None,
+                    records: e.records,
+                    error: None,
+                });
+            }
+        }
+        integrity
+            .issues
+            .sort_by(|a, b| (a.problem, &a.hash).cmp(&(b.problem, &b.hash)));
+        Ok(integrity)
+    }
+}
+
+impl VerifyReport {
+    // Damaged files become corrupt asset findings; missing ones the cheap check already
+    // reports.
+    pub fn add_integrity(&mut self, integrity: AssetIntegrity) {
+        for issue in &integrity.issues {
+            if issue.problem == AssetProblem::Missing {
+                continue;
+            }
+            let (account, record) = issue.records.first().cloned().unzip();
+            self.findings.push(Finding {
+                kind: FindingKind::CorruptAsset,
+                account,
+                record,
+                asset: Some(issue.hash.clone()),
+                error: ErrorInfo::new(
+                    ErrorCategory::Corruption,
+                    issue.problem.code(),
+                    issue
+                        .error
+                        .clone()
+                        .unwrap_or_else(|| format!("{:?}", issue.problem)),
+                ),
+            });
+        }
+        self.integrity = Some(integrity);
+    }
+}
+
+fn check_asset(
+    hash: &str,
+    path: &Path,
+    is_manifest: bool,
+    limits: &ManifestLimits,
+) -> Result<AssetCheck, io::Error> {
+    let content = fs::read(path)?;
+    let check = |status, message: Option<String>| AssetCheck {
+        len: content.len() as u64,
+        status,
+        message,
+    };
+    let actual = hash_bytes(&content);
+    if !actual.as_str().eq_ignore_ascii_case(hash) {
+        return Ok(check(
+            AssetStatus::HashMismatch,
+            Some(format!("content hashes to {}", actual)),
+        ));
+    }
+    if !is_manifest {
+        return Ok(check(AssetStatus::Ok, None));
+    }
+    let asset = RcStr::from(hash);
+    let mut raw = Vec::new();
+    match limits.decompress(&asset, content.as_slice(), &mut raw) {
+        Ok(()) => {}
+        Err(e @ Error::Lzma(_)) => {
+            return Ok(check(AssetStatus::Undecompressable, Some(e.to_string())))
+        }
+        // Past the limits, which says nothing about whether it's intact.
+        Err(e) => return Ok(check(AssetStatus::Unparseable, Some(e.to_string()))),
+    }
+    let parsed = bson::RawDocumentBuf::from_bytes(raw)
+        .map_err(|e| e.to_string())
+        .and_then(|doc| match limits.check(&asset, &doc) {
+            Ok(()) => Ok(doc),
+            Err(e) => Err(e.to_string()),
+        })
+        .and_then(|doc| doc.to_document().map_err(|e| e.to_string()))
+        .and_then(|doc| bson::from_document::<Manifest>(doc).map_err(|e| e.to_string()));
+    Ok(match parsed {
+        Ok(_) => check(AssetStatus::Ok, None),
+        Err(e) => check(AssetStatus::Unparseable, Some(e)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::testing::{record, TempBackup};
+    use serde_json::json;
+
+    #[test]
+    fn asset_integrity() {
+        let temp = TempBackup::new("verify-assets");
+        let intended = |content: &[u8]| (hash_bytes(content).to_string(), content.len());
+        let good = intended(b"an intact asset");
+        temp.write_asset(&good.0, b"an intact asset");
+        let corrupted = intended(b"original");
+        temp.write_asset(&corrupted.0, b"tampered");
+        let truncated = intended(b"a download that was cut off");
+        temp.write_asset(&truncated.0, b"a download");
+        let missing = intended(b"never downloaded");
+        let mut rec = record("R-1", "U-owner", "Inventory");
+        rec["neosDBmanifest"] = [&good, &corrupted, &truncated, &missing]
+            .iter()
+            .map(|(hash, bytes)| json!({ "hash": hash, "bytes": bytes }))
+            .collect();
+        temp.write_record("U-owner", &rec);
+
+        let integrity = temp.load().verify_assets().unwrap();
+        assert_eq!((integrity.files, integrity.ok), (3, 1));
+        let problems: Vec<_> = integrity
+            .issues
+            .iter()
+            .map(|issue| (issue.hash.to_string(), issue.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                (corrupted.0, AssetProblem::Corrupted),
+                (truncated.0, AssetProblem::Truncated),
+                (missing.0, AssetProblem::Missing),
+            ]
+        );
+        let owner = ("U-owner".into(), "R-1".into());
+        assert!(integrity
+            .issues
+            .iter()
+            .all(|i| i.records == [owner.clone()]));
+        assert_eq!(integrity.issues[1].actual_bytes, Some(10));
+    }
+}
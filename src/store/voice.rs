@@ -0,0 +1,131 @@
+use super::{
+    backup::{Backup, MessageContent},
+    features::MissingFeature,
+    RcStr,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "transcribe")]
+use std::process;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+// A voice message: a Sound message whose content is the record of an ogg asset.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceMessage {
+    pub account: RcStr,
+    pub contact: RcStr,
+    pub message: RcStr,
+    pub sender_id: RcStr,
+    pub send_time: DateTime<Utc>,
+    pub asset: RcStr,
+}
+
+// Every voice message of every account, in account, contact and send order. Both sides
+// of a conversation in the backup keep a copy.
+pub fn voice_messages(backup: &Backup) -> Vec<VoiceMessage> {
+    let mut voice = Vec::new();
+    for (account_name, account) in &backup.accounts {
+        for (contact, messages) in &account.messages {
+            for msg in messages {
+                let MessageContent::Sound(content) = msg.parsed_content() else {
+                    continue;
+                };
+                let Some(asset) = content.asset_uri.as_ref().and_then(|u| u.asset_id()) else {
+                    continue;
+                };
+                voice.push(VoiceMessage {
+                    account: account_name.clone(),
+                    contact: contact.clone(),
+                    message: msg.id.clone(),
+                    sender_id: msg.sender(account_name, contact).clone(),
+                    send_time: msg.send_time,
+                    asset: asset.clone(),
+                });
+            }
+        }
+    }
+    voice.sort_by(|a, b| {
+        (&a.account, &a.contact, a.send_time).cmp(&(&b.account, &b.contact, b.send_time))
+    });
+    voice
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TranscribeError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("{0} failed: {1}")]
+    Command(String, String),
+}
+
+// Speech to text over one extracted voice message. Crates embedding this one can bring
+// their own, the built-in one runs whisper-cli.
+pub trait Transcriber: Send + Sync {
+    fn name(&self) -> &str;
+    fn transcribe(&self, audio: &Path) -> Result<String, TranscribeError>;
+}
+
+// whisper.cpp's command line, from the config:
+//
+//   [whisper]
+//   model = "/models/ggml-base.bin"
+//   language = "auto"
+//
+// It's handed the ogg as extracted, so it needs a build that decodes ogg; otherwise point
+// program at a wrapper that converts first.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct WhisperCli {
+    #[serde(default = "WhisperCli::default_program")]
+    pub program: String,
+    pub model: PathBuf,
+    pub language: Option<String>,
+}
+
+impl WhisperCli {
+    fn default_program() -> String {
+        "whisper-cli".to_owned()
+    }
+
+    // The config as a transcriber, in a build that can run it.
+    pub fn transcriber(&self) -> Result<&dyn Transcriber, MissingFeature> {
+        #[cfg(feature = "transcribe")]
+        return Ok(self);
+        #[cfg(not(feature = "transcribe"))]
+        Err(MissingFeature("transcribe"))
+    }
+}
+
+#[cfg(feature = "transcribe")]
+impl Transcriber for WhisperCli {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn transcribe(&self, audio: &Path) -> Result<String, TranscribeError> {
+        let mut args: Vec<PathBuf> = vec!["--no-timestamps".into(), "--no-prints".into()];
+        args.extend(["--model".into(), self.model.clone()]);
+        if let Some(language) = &self.language {
+            args.extend(["--language".into(), language.into()]);
+        }
+        args.extend(["--file".into(), audio.to_owned()]);
+        let result = process::Command::new(&self.program).args(args).output()?;
+        if !result.status.success() {
+            return Err(TranscribeError::Command(
+                self.program.clone(),
+                String::from_utf8_lossy(&result.stderr).trim().to_owned(),
+            ));
+        }
+        let text = String::from_utf8_lossy(&result.stdout);
+        let lines: Vec<_> = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+        Ok(lines.join(" "))
+    }
+}
@@ -0,0 +1,119 @@
+use super::{backup::ErrorInfo, RcStr};
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+// Something loading or a scan tolerated and carried on past, unlike an Error which stops
+// it. Each leaves the backup a little different from what's on disk.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum WarningKind {
+    // A field the model doesn't have, dropped when the file was read.
+    UnknownField { field: String },
+    // A file or folder name that isn't valid UTF-8, read with replacement characters.
+    LossyFileName { name: String },
+    // A timestamp that doesn't parse, read as missing.
+    DroppedTimestamp { field: String, value: String },
+    // A manifest a scan couldn't read and left out of its totals.
+    UnreadableManifest { asset: RcStr, error: ErrorInfo },
+    // A file that doesn't parse, left out of a lenient load.
+    SkippedFile { error: ErrorInfo },
+    // A folder in an account this version doesn't know, left out.
+    UnknownFolder,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub kind: WarningKind,
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WarningKind::UnknownField { field } => write!(f, "unknown field {}", field),
+            WarningKind::LossyFileName { name } => write!(f, "name isn't UTF-8, read as {}", name),
+            WarningKind::DroppedTimestamp { field, value } => {
+                write!(f, "{} {:?} isn't a time, dropped", field, value)
+            }
+            WarningKind::UnreadableManifest { asset, error } => {
+                write!(f, "manifest {} skipped: {}", asset, error.message)
+            }
+            WarningKind::SkippedFile { error } => write!(f, "skipped: {}", error.message),
+            WarningKind::UnknownFolder => write!(f, "unknown folder, skipped"),
+        }
+    }
+}
+
+// Where loading and scans put their warnings. Clones share one list, so the sink in the
+// LoadOptions a backup was loaded with is the one in the Backup.
+#[derive(Debug, Clone, Default)]
+pub struct Warnings {
+    found: Arc<Mutex<Vec<Warning>>>,
+    unknown_fields: bool,
+}
+
+impl Warnings {
+    // Also compare every file's fields against the model, which makes loading slower.
+    pub fn with_unknown_fields(mut self) -> Self {
+        self.unknown_fields = true;
+        self
+    }
+
+    pub fn checks_unknown_fields(&self) -> bool {
+        self.unknown_fields
+    }
+
+    pub fn push(&self, path: &Path, kind: WarningKind) {
+        let warning = Warning {
+            path: path.to_owned(),
+            kind,
+        };
+        self.list().push(warning);
+    }
+
+    pub fn len(&self) -> usize {
+        self.list().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list().is_empty()
+    }
+
+    // Those pushed after the first n, in the order they were.
+    pub fn since(&self, n: usize) -> Vec<Warning> {
+        self.list().iter().skip(n).cloned().collect()
+    }
+
+    // Everything so far, in path order, leaving the sink empty.
+    pub fn take(&self) -> Vec<Warning> {
+        let mut warnings = std::mem::take(&mut *self.list());
+        warnings.sort_by(|a, b| a.path.cmp(&b.path));
+        warnings
+    }
+
+    // The name as a string, warning when it had to be converted lossily.
+    pub fn name(&self, name: &OsStr, path: &Path) -> RcStr {
+        let lossy = name.to_string_lossy();
+        if let Cow::Owned(_) = lossy {
+            self.push(
+                path,
+                WarningKind::LossyFileName {
+                    name: lossy.to_string(),
+                },
+            );
+        }
+        lossy.into_owned().into()
+    }
+
+    fn list(&self) -> std::sync::MutexGuard<'_, Vec<Warning>> {
+        self.found.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
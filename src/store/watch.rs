@@ -0,0 +1,72 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+// Modification times of a backup's folders. Adding, removing or replacing a file bumps
+// its folder's time, so comparing two of these finds what changed without reading files.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    assets: Option<SystemTime>,
+    folders: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    None,
+    // Only the Assets folder, which just needs the asset index forgotten.
+    Assets,
+    // Records, contacts, messages or accounts, which needs a reload.
+    Backup,
+}
+
+impl Fingerprint {
+    pub fn of(root: &Path) -> io::Result<Self> {
+        let mut fingerprint = Self::default();
+        fingerprint.folders.insert(root.to_owned(), modified(root));
+        for account in root.read_dir()? {
+            let account = account?.path();
+            if account.file_name().is_some_and(|name| name == "Assets") {
+                fingerprint.assets = modified(&account);
+                continue;
+            }
+            fingerprint
+                .folders
+                .insert(account.clone(), modified(&account));
+            if let Ok(folders) = account.read_dir() {
+                for folder in folders {
+                    let folder = folder?.path();
+                    fingerprint
+                        .folders
+                        .insert(folder.clone(), modified(&folder));
+                    // Messages are one more level down, in a folder per contact.
+                    if folder.file_name().is_some_and(|name| name == "Messages") {
+                        for contact in folder.read_dir()? {
+                            let contact = contact?.path();
+                            fingerprint
+                                .folders
+                                .insert(contact.clone(), modified(&contact));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(fingerprint)
+    }
+
+    pub fn change_since(&self, earlier: &Fingerprint) -> Change {
+        if self.folders != earlier.folders {
+            Change::Backup
+        } else if self.assets != earlier.assets {
+            Change::Assets
+        } else {
+            Change::None
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}